@@ -0,0 +1,133 @@
+//! Throughput of the scan pipeline in records/sec.
+//!
+//! Run with `cargo bench`; compare against a saved baseline with
+//! `cargo bench -- --save-baseline main` and `cargo bench -- --baseline main`.
+
+use battleship::core::accumulator::{CellCounts, CountAccumulator};
+use battleship::core::bitslice::BitsliceBatch;
+use battleship::core::filter::{filter_and_count_with_options, QueryOptions, ScanMode};
+use battleship::core::reader::{DeltaDecodingReader, RawRecordReader};
+use battleship::core::writer::DeltaEncodingWriter;
+use battleship::generator::{enumerate_boards, Fleet};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+
+/// Number of records in the synthetic dataset.
+const RECORDS: usize = 1 << 20;
+
+/// Boards of a small fleet, repeated until there are `RECORDS` of them. Real datasets are
+/// sorted the same way (enumeration order), so deltas compress similarly.
+fn synthetic_boards() -> Vec<u128> {
+    let mut boards = Vec::new();
+    enumerate_boards(&Fleet::new(&[(3, 2), (4, 1)]), |state| boards.push(state.hit_mask().raw_value()));
+    boards.iter().copied().cycle().take(RECORDS).collect()
+}
+
+fn raw_bytes(boards: &[u128]) -> Vec<u8> {
+    boards.iter().flat_map(|board| board.to_le_bytes()).collect()
+}
+
+fn delta_bytes(boards: &[u128]) -> Vec<u8> {
+    let mut writer = DeltaEncodingWriter::new(Vec::new());
+    writer.write_all_records(boards.iter().copied()).unwrap();
+    writer.into_inner().unwrap()
+}
+
+/// A query that matches a fair share of boards, like the first shots of a game.
+const HIT_MASK: u128 = 1 << 40;
+const MISS_MASK: u128 = 1 << 10;
+
+fn options(mode: ScanMode) -> QueryOptions {
+    QueryOptions { mode, ..QueryOptions::default() }
+}
+
+fn bench_readers(c: &mut Criterion) {
+    let boards = synthetic_boards();
+    let raw = raw_bytes(&boards);
+    let delta = delta_bytes(&boards);
+    let zstd = zstd::encode_all(delta.as_slice(), 3).unwrap();
+    let streaming = options(ScanMode::Streaming);
+
+    let mut group = c.benchmark_group("scan");
+    group.throughput(Throughput::Elements(RECORDS as u64));
+    group.sample_size(20);
+
+    group.bench_function("raw", |b| {
+        b.iter(|| filter_and_count_with_options(RawRecordReader::new(raw.as_slice()), HIT_MASK, MISS_MASK, &streaming).unwrap())
+    });
+    group.bench_function("delta", |b| {
+        b.iter(|| filter_and_count_with_options(DeltaDecodingReader::new(delta.as_slice()), HIT_MASK, MISS_MASK, &streaming).unwrap())
+    });
+    group.bench_function("zstd", |b| {
+        b.iter(|| {
+            let decoder = zstd::stream::Decoder::new(zstd.as_slice()).unwrap();
+            filter_and_count_with_options(DeltaDecodingReader::new(decoder), HIT_MASK, MISS_MASK, &streaming).unwrap()
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_counting(c: &mut Criterion) {
+    let boards = synthetic_boards();
+
+    let mut group = c.benchmark_group("count");
+    group.throughput(Throughput::Elements(RECORDS as u64));
+    group.sample_size(20);
+
+    // The per-cell kernel on its own, with every board matching
+    group.bench_function("scalar", |b| {
+        b.iter_batched(
+            CellCounts::new,
+            |mut counts| {
+                for &board in &boards {
+                    counts.visit(black_box(board));
+                }
+                counts.finish()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    // The same on one thread, transposing 64 boards at a time and counting from the lanes
+    group.bench_function("bitsliced-kernel", |b| {
+        b.iter_batched(
+            CellCounts::new,
+            |mut counts| {
+                for records in boards.chunks(BitsliceBatch::CAPACITY) {
+                    let batch = BitsliceBatch::new(black_box(records));
+                    counts.visit_batch(&batch, batch.filled());
+                }
+                counts.finish()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    let modes = [("streaming", ScanMode::Streaming), ("chunked-rayon", ScanMode::Chunked), ("bitsliced", ScanMode::Bitsliced)];
+    for (name, mode) in modes {
+        let options = options(mode);
+        group.bench_function(name, |b| {
+            b.iter(|| filter_and_count_with_options(boards.iter().copied().map(Ok), 0, 0, &options).unwrap())
+        });
+    }
+
+    group.finish();
+
+    // Every mode against a query that rejects most boards, where bitsliced filtering skips
+    // counting whole batches
+    let mut group = c.benchmark_group("query");
+    group.throughput(Throughput::Elements(RECORDS as u64));
+    group.sample_size(20);
+
+    for (name, mode) in modes {
+        let options = options(mode);
+        group.bench_function(name, |b| {
+            b.iter(|| filter_and_count_with_options(boards.iter().copied().map(Ok), HIT_MASK, MISS_MASK, &options).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_readers, bench_counting);
+criterion_main!(benches);
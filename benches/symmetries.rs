@@ -0,0 +1,59 @@
+//! Cost of the symmetry checks the generator runs on every board it enumerates, and of the
+//! transforms symmetry expansion applies to every matched record.
+//!
+//! Run with `cargo bench --bench symmetries`.
+
+use battleship::generator::symmetries::{apply_symmetry, generate_symmetries, is_canonical, Sym};
+use battleship::generator::{enumerate_boards, Fleet};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+/// Boards of a small fleet, in enumeration order like the generator sees them.
+fn boards() -> Vec<u128> {
+    let mut boards = Vec::new();
+    enumerate_boards(&Fleet::new(&[(3, 2), (4, 1)]), |state| boards.push(state.hit_mask().raw_value()));
+    boards
+}
+
+fn bench_is_canonical(c: &mut Criterion) {
+    let boards = boards();
+
+    let mut group = c.benchmark_group("is_canonical");
+    group.throughput(Throughput::Elements(boards.len() as u64));
+    group.sample_size(20);
+
+    group.bench_function("early_exit", |b| {
+        b.iter(|| boards.iter().filter(|&&board| is_canonical(black_box(board))).count())
+    });
+
+    // What is_canonical used to do: build every variant, then compare with the smallest
+    group.bench_function("all_variants", |b| {
+        b.iter(|| {
+            boards.iter()
+                .filter(|&&board| board == generate_symmetries(black_box(board)).into_iter().min().unwrap())
+                .count()
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_apply_symmetry(c: &mut Criterion) {
+    let boards = boards();
+
+    let mut group = c.benchmark_group("apply_symmetry");
+    group.throughput(Throughput::Elements(boards.len() as u64 * Sym::ALL.len() as u64));
+    group.sample_size(20);
+
+    group.bench_function("tables", |b| {
+        b.iter(|| {
+            boards.iter()
+                .map(|&board| Sym::ALL.iter().fold(0, |acc, &sym| acc ^ apply_symmetry(black_box(board), sym)))
+                .fold(0, |acc, variants| acc ^ variants)
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_is_canonical, bench_apply_symmetry);
+criterion_main!(benches);
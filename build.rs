@@ -0,0 +1,10 @@
+fn main() {
+    // Generates the gRPC service from proto/battleship.proto, with a bundled protoc so a system
+    // install isn't needed
+    #[cfg(feature = "grpc")]
+    {
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("bundled protoc");
+        std::env::set_var("PROTOC", protoc);
+        tonic_build::compile_protos("proto/battleship.proto").expect("compile proto/battleship.proto");
+    }
+}
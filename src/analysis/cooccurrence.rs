@@ -0,0 +1,27 @@
+use crate::core::accumulator::PairCorrelations;
+use crate::core::filter::{filter_and_accumulate, QueryOptions, ScanMode};
+use std::io;
+
+/// How often each pair of cells has a ship on the same 9x9 board, reading records one at a time.
+///
+/// `matrix[a][b]` is the number of boards with ships in both `a` and `b`, so the matrix is
+/// symmetric and its diagonal holds the per-cell ship counts. Comparing `matrix[a][b]` with
+/// `matrix[a][a] * matrix[b][b] / boards` shows which cells' ships go together.
+pub fn cooccurrence<I>(reader: I) -> io::Result<[[u64; 81]; 81]>
+where
+    I: IntoIterator<Item = io::Result<u128>>,
+{
+    let options = QueryOptions { mode: ScanMode::Streaming, ..QueryOptions::default() };
+    filter_and_accumulate(reader, 0, 0, &options, PairCorrelations::new).map(|pairs| *pairs)
+}
+
+/// Same as [`cooccurrence`], counting batches of records on every rayon thread. Each board
+/// costs one update per pair of its ship cells, so this pays off whenever decoding is cheaper
+/// than counting, as it is for boards with many ships.
+pub fn cooccurrence_parallel<I>(reader: I) -> io::Result<[[u64; 81]; 81]>
+where
+    I: IntoIterator<Item = io::Result<u128>>,
+{
+    let options = QueryOptions { mode: ScanMode::Chunked, ..QueryOptions::default() };
+    filter_and_accumulate(reader, 0, 0, &options, PairCorrelations::new).map(|pairs| *pairs)
+}
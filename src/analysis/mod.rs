@@ -0,0 +1,7 @@
+pub mod cooccurrence;
+pub mod ships;
+pub mod validity;
+
+pub use cooccurrence::{cooccurrence, cooccurrence_parallel};
+pub use ships::{decompose_ships, decompose_ships_with_geometry, Ship};
+pub use validity::{is_valid_board, is_valid_board_with_geometry};
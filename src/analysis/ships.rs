@@ -0,0 +1,101 @@
+use crate::core::geometry::BoardGeometry;
+use crate::generator::board_mask::BoardMask;
+use crate::generator::point::{Direction, Point};
+
+/// A group of ship cells on a board, as found by [`decompose_ships`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ship {
+    /// The group's first cell in row-major order: the top or left end of a straight ship.
+    pub start: Point,
+    /// Number of cells in the group.
+    pub length: usize,
+    /// Which way the ship runs, or `None` for a single cell or a group that isn't a straight
+    /// line, such as two ships touching.
+    pub direction: Option<Direction>,
+    /// Cells in the group.
+    pub cells: u128,
+}
+
+impl Ship {
+    /// Whether the cells form one straight, unbroken line.
+    pub fn is_straight(&self) -> bool {
+        self.direction.is_some() || self.length == 1
+    }
+}
+
+/// Splits a 9x9 board into its ships, see [`decompose_ships_with_geometry`].
+pub fn decompose_ships(board: u128) -> Vec<Ship> {
+    decompose_ships_with_geometry(board, BoardGeometry::STANDARD)
+}
+
+/// Splits a board into groups of ship cells that touch, including diagonally, in the order of
+/// their first cells. Ships on a valid board never touch, so each group is one ship; groups that
+/// aren't straight show where a board breaks that rule. Bits beyond the board are ignored.
+pub fn decompose_ships_with_geometry(board: u128, geometry: BoardGeometry) -> Vec<Ship> {
+    let edges = Edges::new(geometry);
+    let mut remaining = board & geometry.cell_mask();
+    let mut ships = Vec::new();
+
+    while remaining != 0 {
+        // Grow the group from its first cell until it stops gaining cells
+        let mut cells = remaining & remaining.wrapping_neg();
+        loop {
+            let grown = edges.neighbourhood(cells) & remaining;
+            if grown == cells {
+                break;
+            }
+            cells = grown;
+        }
+
+        remaining &= !cells;
+        ships.push(ship_of(cells, geometry));
+    }
+
+    ships
+}
+
+/// The first and last columns of a board, which cells mustn't spread past.
+struct Edges {
+    geometry: BoardGeometry,
+    first_column: u128,
+    last_column: u128,
+}
+
+impl Edges {
+    fn new(geometry: BoardGeometry) -> Self {
+        let width = geometry.width() as i32;
+        Self {
+            geometry,
+            first_column: BoardMask::column_with_geometry(0, geometry).raw_value(),
+            last_column: BoardMask::column_with_geometry(width - 1, geometry).raw_value(),
+        }
+    }
+
+    /// `cells` and every cell next to one of them, including diagonally.
+    fn neighbourhood(&self, cells: u128) -> u128 {
+        // Spread along rows without wrapping around the board's edges, then along columns
+        let row = cells | ((cells & !self.last_column) << 1) | ((cells & !self.first_column) >> 1);
+        let width = self.geometry.width();
+        (row | (row << width) | (row >> width)) & self.geometry.cell_mask()
+    }
+}
+
+fn ship_of(cells: u128, geometry: BoardGeometry) -> Ship {
+    let first = cells.trailing_zeros() as usize;
+    let start = geometry.point_of(first);
+    let length = cells.count_ones() as usize;
+
+    let horizontal = start.x as usize + length <= geometry.width() && cells == (u128::MAX >> (128 - length)) << first;
+    let vertical = start.y as usize + length <= geometry.height()
+        && (0..length).fold(0, |line, i| line | 1u128 << (first + i * geometry.width())) == cells;
+
+    let direction = match length {
+        1 => None,
+        _ if horizontal => Some(Direction::Horizontal),
+        _ if vertical => Some(Direction::Vertical),
+        _ => None,
+    };
+
+    Ship { start, length, direction, cells }
+}
@@ -0,0 +1,15 @@
+use crate::core::geometry::BoardGeometry;
+use crate::generator::fleet::Fleet;
+use crate::generator::verify::verify_board;
+
+/// Whether a 9x9 board holds exactly the ships in `fleet`, see [`is_valid_board_with_geometry`].
+pub fn is_valid_board(board: u128, fleet: &Fleet) -> bool {
+    is_valid_board_with_geometry(board, BoardGeometry::STANDARD, fleet)
+}
+
+/// Whether `board` holds exactly the ships in `fleet`, each straight and clear of every other
+/// ship, even diagonally, with no cells beyond the board. Use
+/// [`verify_board`] to find out why a board isn't valid.
+pub fn is_valid_board_with_geometry(board: u128, geometry: BoardGeometry, fleet: &Fleet) -> bool {
+    verify_board(board, geometry, fleet).is_ok()
+}
@@ -0,0 +1,58 @@
+use battleship::core::canonicalize::canonicalize_records;
+use battleship::core::geometry::BoardGeometry;
+use battleship::core::header::DatasetHeader;
+use battleship::core::reader::open_records;
+use clap::{Args, ValueHint};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct CanonicalizeArgs {
+    /// Input dataset (raw or delta-encoded, optionally compressed). Use "-" to read from stdin.
+    #[arg(short, long, default_value = "-", value_hint = ValueHint::FilePath)]
+    input: String,
+
+    /// Output file for the raw canonical records. Use "-" to write to stdout.
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Maximum number of distinct boards to deduplicate in memory before spilling to temporary files
+    #[arg(long, default_value_t = 50_000_000, value_parser = clap::value_parser!(u64).range(1..))]
+    memory_records: u64,
+
+    /// Directory for temporary bucket files (defaults to the system temp directory)
+    #[arg(long)]
+    temp_dir: Option<PathBuf>,
+
+    /// Board size of the records when the dataset has no header
+    #[arg(long, value_name = "WxH", default_value_t = BoardGeometry::STANDARD)]
+    board: BoardGeometry,
+}
+
+pub fn run(args: CanonicalizeArgs) -> io::Result<()> {
+    let (header, records) = open_records(&args.input)?;
+    let geometry = header.as_ref().map_or(args.board, |header| header.geometry);
+
+    let mut writer: Box<dyn Write> = if args.output == "-" {
+        Box::new(BufWriter::new(io::stdout().lock()))
+    } else {
+        Box::new(BufWriter::new(File::create(&args.output)?))
+    };
+
+    // The output is raw and has fewer records than the input
+    if let Some(header) = header {
+        DatasetHeader { delta_encoded: false, restart_interval: None, record_count: None, ..header }.write_to(&mut writer)?;
+    }
+
+    let temp_dir = args.temp_dir.unwrap_or_else(std::env::temp_dir);
+    let stats = canonicalize_records(records, &mut writer, geometry, args.memory_records as usize, &temp_dir)?;
+
+    eprintln!(
+        "Read {} records, wrote {} canonical boards ({:.2}x reduction)",
+        stats.records,
+        stats.canonical,
+        stats.reduction_ratio(),
+    );
+    Ok(())
+}
@@ -0,0 +1,17 @@
+use clap::Args;
+use clap_complete::Shell;
+use std::io;
+
+#[derive(Args)]
+pub struct CompletionsArgs {
+    /// Shell to write completions for
+    shell: Shell,
+}
+
+/// Writes completions for `command`, the full command-line interface, to stdout. Source them from
+/// the shell's startup file, e.g. `source <(battleship completions bash)`.
+pub fn run(args: CompletionsArgs, mut command: clap::Command) -> io::Result<()> {
+    let name = command.get_name().to_string();
+    clap_complete::generate(args.shell, &mut command, name, &mut io::stdout());
+    Ok(())
+}
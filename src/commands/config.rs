@@ -0,0 +1,157 @@
+use battleship::generator::Fleet;
+use clap::Arg;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Config file read from the working directory when --config isn't given.
+pub const DEFAULT_CONFIG: &str = "battleship.toml";
+
+/// Commands with a --fleet flag.
+const FLEET_COMMANDS: [&str; 6] = ["encode", "estimate", "generate", "merge", "split", "verify"];
+
+/// Defaults for command-line flags, read from a TOML file like
+///
+/// ```toml
+/// dataset = "data/boards.zst"  # filter --file
+/// output = "bin"               # filter --output
+/// threads = 4                  # filter --threads
+/// fleet = "3:5,4:3"            # --fleet of generate, verify, estimate, ...
+/// ```
+///
+/// Every key is optional. Flags given on the command line override the file.
+#[derive(Debug, Default)]
+pub struct Config {
+    pub dataset: Option<String>,
+    pub output: Option<String>,
+    pub threads: Option<u64>,
+    pub fleet: Option<Fleet>,
+}
+
+impl Config {
+    /// Reads the config file named by a --config flag in `args`, or ./battleship.toml if there's
+    /// one. The file is found before the command line is parsed, since it changes the defaults
+    /// the parser uses.
+    pub fn load_for_args(args: &[OsString]) -> io::Result<Self> {
+        let flag = args.iter().enumerate().find_map(|(i, arg)| match arg.to_str()?.strip_prefix("--config") {
+            Some("") => Some(args.get(i + 1).map(PathBuf::from)),
+            Some(value) => value.strip_prefix('=').map(|value| Some(PathBuf::from(value))),
+            None => None,
+        });
+
+        match flag {
+            Some(Some(path)) => Self::load(&path),
+            // Let clap report the missing value
+            Some(None) => Ok(Self::default()),
+            None if Path::new(DEFAULT_CONFIG).is_file() => Self::load(Path::new(DEFAULT_CONFIG)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+        Self::parse(&text).map_err(|message| io::Error::new(io::ErrorKind::InvalidInput, format!("{}: {}", path.display(), message)))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let table: toml::Table = text.parse().map_err(|e: toml::de::Error| e.message().to_string())?;
+        let mut config = Self::default();
+
+        for (key, value) in table {
+            let string = || value.as_str().map(str::to_string).ok_or_else(|| format!("`{}` must be a string", key));
+            match key.as_str() {
+                "dataset" => config.dataset = Some(string()?),
+                "output" => {
+                    let output = string()?;
+                    if output == "msgpack" && !cfg!(feature = "msgpack") {
+                        return Err("`output` \"msgpack\" needs a build with the msgpack feature".to_string());
+                    }
+                    if !["csv", "bin", "msgpack"].contains(&output.as_str()) {
+                        return Err(format!("`output` must be \"csv\", \"bin\" or \"msgpack\", not \"{}\"", output));
+                    }
+                    config.output = Some(output);
+                }
+                "threads" => match value.as_integer() {
+                    Some(threads) if threads > 0 => config.threads = Some(threads as u64),
+                    _ => return Err("`threads` must be a positive integer".to_string()),
+                },
+                "fleet" => config.fleet = Some(string()?.parse::<Fleet>().map_err(|e| format!("`fleet`: {}", e))?),
+                _ => return Err(format!("unknown key `{}`; expected dataset, output, threads or fleet", key)),
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Adds the --config flag to `command`, and makes the configured values the defaults of the
+    /// flags they stand for, so the command line still overrides them and `--help` shows them.
+    pub fn apply(&self, command: clap::Command) -> clap::Command {
+        let mut command = command.arg(
+            Arg::new("config")
+                .long("config")
+                .global(true)
+                .value_name("PATH")
+                .help(format!("Read default flag values from this TOML file instead of ./{}", DEFAULT_CONFIG)),
+        );
+
+        if let Some(dataset) = &self.dataset {
+            command = command.mut_subcommand("filter", |filter| filter.mut_arg("file", |arg| arg.required(false).default_value(dataset.clone())));
+        }
+        if let Some(output) = &self.output {
+            command = command.mut_subcommand("filter", |filter| filter.mut_arg("output", |arg| arg.default_value(output.clone())));
+        }
+        if let Some(threads) = self.threads {
+            command = command.mut_subcommand("filter", |filter| filter.mut_arg("threads", |arg| arg.default_value(threads.to_string())));
+        }
+        if let Some(fleet) = &self.fleet {
+            for name in FLEET_COMMANDS {
+                command = command.mut_subcommand(name, |subcommand| subcommand.mut_arg("fleet", |arg| arg.default_value(fleet.to_string())));
+            }
+        }
+        command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_parse() {
+        let config = Config::parse("dataset = \"data/boards.zst\"\noutput = \"bin\"\nthreads = 4\nfleet = \"3:5,4:3\"\n").unwrap();
+        assert_eq!(config.dataset.as_deref(), Some("data/boards.zst"));
+        assert_eq!(config.output.as_deref(), Some("bin"));
+        assert_eq!(config.threads, Some(4));
+        assert_eq!(config.fleet, Some(Fleet::standard()));
+
+        assert!(Config::parse("").unwrap().dataset.is_none());
+        assert!(Config::parse("thread = 4").unwrap_err().contains("unknown key `thread`"));
+        assert!(Config::parse("threads = 0").is_err());
+        assert!(Config::parse("output = \"json\"").is_err());
+        assert_eq!(Config::parse("output = \"msgpack\"").is_ok(), cfg!(feature = "msgpack"));
+        assert!(Config::parse("dataset = 3").is_err());
+        assert!(Config::parse("fleet = \"9:1\"").is_err());
+    }
+
+    #[test]
+    fn test_config_overridden_by_flags() {
+        let config = Config { dataset: Some("config.zst".to_string()), threads: Some(2), ..Config::default() };
+        let command = config.apply(clap::Command::new("battleship").subcommand(
+            clap::Command::new("filter")
+                .arg(Arg::new("file").long("file").required(true))
+                .arg(Arg::new("output").long("output"))
+                .arg(Arg::new("threads").long("threads")),
+        ));
+
+        let matches = command.clone().get_matches_from(["battleship", "filter"]);
+        let filter = matches.subcommand_matches("filter").unwrap();
+        assert_eq!(filter.get_one::<String>("file").unwrap(), "config.zst");
+        assert_eq!(filter.get_one::<String>("threads").unwrap(), "2");
+
+        let matches = command.get_matches_from(["battleship", "filter", "--file", "flag.zst", "--threads", "8"]);
+        let filter = matches.subcommand_matches("filter").unwrap();
+        assert_eq!(filter.get_one::<String>("file").unwrap(), "flag.zst");
+        assert_eq!(filter.get_one::<String>("threads").unwrap(), "8");
+    }
+}
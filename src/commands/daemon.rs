@@ -0,0 +1,85 @@
+use battleship::core::protocol::serve_connection;
+use battleship::core::session::Session;
+use clap::{Args, ValueHint};
+use std::io::{self, Read, Write};
+use std::net::TcpListener;
+use std::time::Instant;
+
+#[derive(Args)]
+pub struct DaemonArgs {
+    /// Path to the board data file (delta-encoded or raw, optionally compressed)
+    #[arg(short, long, value_hint = ValueHint::FilePath)]
+    file: String,
+
+    /// Index of the data file; matching chunks are decoded on each request instead of loading
+    /// every record into memory
+    #[arg(long)]
+    index: Option<String>,
+
+    /// TCP address to listen on
+    #[arg(long, default_value = "127.0.0.1:7878")]
+    listen: String,
+
+    /// Listen on a unix socket at this path instead of TCP, replacing a socket left there by an
+    /// earlier daemon
+    #[cfg(unix)]
+    #[arg(long, value_name = "PATH", value_hint = ValueHint::FilePath, conflicts_with = "listen")]
+    socket: Option<std::path::PathBuf>,
+}
+
+pub fn run(args: DaemonArgs) -> io::Result<()> {
+    let start = Instant::now();
+    let session = match &args.index {
+        Some(index) => Session::open_indexed(&args.file, index)?,
+        None => Session::open(&args.file)?,
+    };
+    eprintln!("Loaded {} in {:.2?}", args.file, start.elapsed());
+
+    #[cfg(unix)]
+    if let Some(path) = &args.socket {
+        let listener = bind_socket(path)?;
+        eprintln!("Listening on {}", path.display());
+        return serve(&session, listener.incoming());
+    }
+
+    let listener = TcpListener::bind(&args.listen)?;
+    eprintln!("Listening on {}", listener.local_addr()?);
+    serve(&session, listener.incoming())
+}
+
+/// Answers each connection on its own thread, so a slow client doesn't hold up the others.
+fn serve<S, I>(session: &Session, connections: I) -> io::Result<()>
+where
+    S: Read + Write + Send,
+    I: Iterator<Item = io::Result<S>>,
+{
+    std::thread::scope(|scope| {
+        for stream in connections {
+            // A connection that fails to open only affects that client
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("Failed to accept a connection: {}", e);
+                    continue;
+                }
+            };
+            scope.spawn(move || {
+                if let Err(e) = serve_connection(session, stream) {
+                    eprintln!("Connection failed: {}", e);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(unix)]
+fn bind_socket(path: &std::path::Path) -> io::Result<std::os::unix::net::UnixListener> {
+    use std::os::unix::fs::FileTypeExt;
+
+    // Binding fails when the path exists, as it does after a daemon that didn't shut down cleanly
+    if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        std::fs::remove_file(path)?;
+    }
+    std::os::unix::net::UnixListener::bind(path)
+}
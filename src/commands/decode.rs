@@ -0,0 +1,63 @@
+use battleship::core::header::DatasetHeader;
+use battleship::core::reader::{open_records_with_threads, record_range, RecordEncoding};
+use clap::{Args, ValueHint};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+#[derive(Args)]
+pub struct DecodeArgs {
+    /// Input dataset (delta-encoded or raw, optionally compressed). Use "-" to read from stdin.
+    #[arg(short, long, default_value = "-", value_hint = ValueHint::FilePath)]
+    input: String,
+
+    /// Output file for the raw records. Use "-" to write to stdout.
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Number of zstd frames to decompress at once when the input has one per chunk
+    #[arg(long, default_value_t = 1)]
+    threads: usize,
+
+    /// Write the original headerless format even if the input has a header
+    #[arg(long)]
+    no_header: bool,
+
+    /// Leave out the first N records
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    skip: u64,
+
+    /// Write at most M records, after any skipped by --skip
+    #[arg(long, value_name = "M")]
+    limit: Option<u64>,
+}
+
+pub fn run(args: DecodeArgs) -> io::Result<()> {
+    let (header, records) = open_records_with_threads(&args.input, args.threads.max(1))?;
+
+    if records.encoding() == RecordEncoding::Raw {
+        eprintln!("Input is already raw; copying records unchanged");
+    }
+
+    let mut writer: Box<dyn Write> = if args.output == "-" {
+        Box::new(BufWriter::new(io::stdout().lock()))
+    } else {
+        Box::new(BufWriter::new(File::create(&args.output)?))
+    };
+
+    // A range of records has fewer than the header says
+    let ranged = args.skip > 0 || args.limit.is_some();
+    if let Some(header) = header.filter(|_| !args.no_header) {
+        let record_count = header.record_count.filter(|_| !ranged);
+        DatasetHeader { delta_encoded: false, restart_interval: None, record_count, ..header }.write_to(&mut writer)?;
+    }
+
+    let mut count = 0u64;
+    for record in record_range(records, args.skip, args.limit) {
+        writer.write_all(&record?.to_le_bytes())?;
+        count += 1;
+    }
+    writer.flush()?;
+
+    eprintln!("Decoded {} records", count);
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use battleship::core::duplicates::{find_duplicates, DuplicateStats};
+use battleship::core::geometry::BoardGeometry;
+use battleship::core::reader::open_records;
+use clap::{Args, ValueHint};
+use std::io;
+
+use super::exit;
+
+#[derive(Args)]
+pub struct DuplicatesArgs {
+    /// Dataset to scan (raw or delta-encoded, optionally compressed). Use "-" to read from stdin.
+    #[arg(short, long, default_value = "-", value_hint = ValueHint::FilePath)]
+    input: String,
+
+    /// Number of example duplicates to print of each kind
+    #[arg(long, default_value_t = 10)]
+    examples: usize,
+
+    /// Board size of the records when the dataset has no header
+    #[arg(long, value_name = "WxH", default_value_t = BoardGeometry::STANDARD)]
+    board: BoardGeometry,
+}
+
+pub fn run(args: DuplicatesArgs) -> io::Result<()> {
+    let (header, records) = open_records(&args.input)?;
+    let geometry = header.map_or(args.board, |header| header.geometry);
+
+    let report = find_duplicates(records, geometry, args.examples)?;
+
+    println!("Records: {}", report.records);
+    print_stats("Exact", &report.exact);
+    print_stats("Canonical", &report.canonical);
+
+    // Let scripts fail a pipeline on a bad merge
+    if report.has_duplicates() {
+        std::process::exit(exit::NO_MATCHES.into());
+    }
+    Ok(())
+}
+
+fn print_stats(kind: &str, stats: &DuplicateStats) {
+    println!("{} duplicates: {} ({} distinct boards)", kind, stats.duplicates, stats.distinct);
+    for duplicate in &stats.examples {
+        println!(
+            "  0x{:032x}: record {} (offset {}) repeats record {}",
+            duplicate.board,
+            duplicate.repeat,
+            duplicate.repeat_offset(),
+            duplicate.first,
+        );
+    }
+}
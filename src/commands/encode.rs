@@ -0,0 +1,112 @@
+use battleship::core::encoder::{encode_indexed, zstd_writer, EncodeOptions, ZstdOptions};
+use battleship::core::geometry::BoardGeometry;
+use battleship::core::header::{read_optional_header, DatasetHeader};
+use battleship::generator::Fleet;
+use clap::Args;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use super::exit;
+
+#[derive(Args)]
+pub struct EncodeArgs {
+    /// Maximum number of records per chunk
+    #[arg(long, default_value_t = EncodeOptions::default().chunk_size as u64, value_parser = clap::value_parser!(u64).range(1..))]
+    chunk_size: u64,
+
+    /// Compress each chunk as a separate zstd frame at this level
+    #[arg(long, value_name = "LEVEL", num_args = 0..=1, default_missing_value = "3")]
+    zstd_frames: Option<i32>,
+
+    /// Compress the whole output as one zstd stream at this level. The header stays
+    /// uncompressed in front of it.
+    #[arg(long, value_name = "LEVEL", conflicts_with_all = ["zstd_frames", "index"])]
+    compress: Option<i32>,
+
+    /// Number of zstd worker threads for --compress (requires the `zstdmt` feature)
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "compress")]
+    compress_threads: u32,
+
+    /// Restart the delta chain at each chunk so chunks can be decoded independently.
+    /// The output must then be decoded chunk by chunk, starting at each chunk's offset.
+    #[arg(long)]
+    restart_delta: bool,
+
+    /// Write an index of chunk offsets, record counts and masks to this file
+    #[arg(long)]
+    index: Option<PathBuf>,
+
+    /// Board size recorded in the output header when the input has no header
+    #[arg(long, value_name = "WxH", default_value_t = BoardGeometry::STANDARD)]
+    board: BoardGeometry,
+
+    /// Fleet recorded in the output header when the input has no header
+    #[arg(long, default_value_t = Fleet::standard())]
+    fleet: Fleet,
+
+    /// Write the original headerless format
+    #[arg(long, conflicts_with_all = ["board", "fleet"])]
+    no_header: bool,
+}
+
+pub fn run(args: EncodeArgs) -> io::Result<()> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+
+    // Raw input from the generator carries a header describing the boards
+    let (input_header, mut reader) = read_optional_header(stdin.lock())?;
+    let mut writer = stdout.lock();
+
+    let header = match input_header {
+        Some(header) if header.delta_encoded => {
+            eprintln!("Input is already delta-encoded");
+            std::process::exit(exit::INVALID_INPUT.into());
+        }
+        Some(header) => Some(header),
+        None => Some(DatasetHeader::new(args.board, args.fleet, true)),
+    };
+
+    let options = EncodeOptions {
+        chunk_size: args.chunk_size as usize,
+        restart_delta: args.restart_delta,
+        zstd_frames: args.zstd_frames,
+        header: if args.no_header { None } else { header },
+    };
+
+    let result = match args.compress {
+        Some(level) => {
+            // Keep the header readable without decompressing, as with per-chunk frames
+            if let Some(header) = options.output_header() {
+                header.write_to(&mut writer)?;
+            }
+            let zstd = ZstdOptions { level, threads: args.compress_threads };
+            let mut encoder = zstd_writer(&mut writer, &zstd)?;
+            let result = encode_indexed(&mut reader, &mut encoder, &EncodeOptions { header: None, ..options });
+            encoder.finish()?;
+            result
+        }
+        None => encode_indexed(&mut reader, &mut writer, &options),
+    };
+
+    let index = match result {
+        Ok(index) => index,
+        Err(e) => {
+            eprintln!("Error processing chunk: {}", e);
+            return Err(e);
+        }
+    };
+
+    writer.flush()?;
+    if args.compress.is_some() {
+        eprintln!("Encoded {} records in {} chunks", index.record_count(), index.chunks.len());
+    } else {
+        eprintln!("Encoded {} records in {} chunks ({} bytes)", index.record_count(), index.chunks.len(), index.data_len);
+    }
+
+    if let Some(path) = args.index {
+        index.save(&path)?;
+        eprintln!("Wrote index to {}", path.display());
+    }
+
+    Ok(())
+}
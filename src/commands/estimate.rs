@@ -0,0 +1,79 @@
+use battleship::core::geometry::BoardGeometry;
+use battleship::generator::Fleet;
+use battleship::solver::exact::exact_counts_with_geometry;
+use battleship::solver::monte_carlo::estimate_probabilities_with_geometry;
+use clap::Args;
+use std::io;
+use std::time::Instant;
+
+use super::parse_mask;
+
+#[derive(Args)]
+pub struct EstimateArgs {
+    /// Hit cells as a list like A3,B3,C3 (row letter, column number) or a hex mask like 0xabcdef
+    #[arg(long, value_name = "CELLS|HEX", default_value = "0")]
+    hit: String,
+
+    /// Miss cells as a list like D7,E2 or a hex mask
+    #[arg(short, long, value_name = "CELLS|HEX", default_value = "0")]
+    miss: String,
+
+    /// Number of random boards to draw
+    #[arg(short, long, default_value_t = 100_000)]
+    samples: u64,
+
+    /// Seed for the random boards; the same seed always gives the same estimate
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Enumerate every consistent board for exact probabilities instead of sampling. Fast once
+    /// a few hits and misses are known; with none it takes as long as generating the dataset.
+    #[arg(long, conflicts_with_all = ["samples", "seed"])]
+    exact: bool,
+
+    /// Fleet placed on every board
+    #[arg(long, default_value_t = Fleet::standard())]
+    fleet: Fleet,
+
+    /// Board dimensions (e.g., 9x9 or 10x10)
+    #[arg(long, value_name = "WxH", default_value_t = BoardGeometry::STANDARD)]
+    board: BoardGeometry,
+}
+
+pub fn run(args: EstimateArgs) -> io::Result<()> {
+    let hit_mask = parse_mask("--hit", &args.hit, args.board)?;
+    let miss_mask = parse_mask("--miss", &args.miss, args.board)?;
+    args.fleet.check_fits(args.board).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    let width = args.board.width();
+    let start = Instant::now();
+
+    if args.exact {
+        let (counts, matched) = exact_counts_with_geometry(args.board, hit_mask, miss_mask, &args.fleet);
+        eprintln!("Matching boards: {} in {:.2?}", matched, start.elapsed());
+
+        for row in counts.chunks(width) {
+            for (x, &count) in row.iter().enumerate() {
+                let probability = if matched == 0 { 0.0 } else { count as f64 / matched as f64 };
+                print!("{:.6}{}", probability, if x < width - 1 { "," } else { "" });
+            }
+            println!();
+        }
+        return Ok(());
+    }
+
+    let estimate = estimate_probabilities_with_geometry(args.board, hit_mask, miss_mask, &args.fleet, args.samples, args.seed);
+    eprintln!("Consistent boards: {} of {} samples in {:.2?}", estimate.boards, estimate.samples, start.elapsed());
+
+    if estimate.boards == 0 {
+        eprintln!("No sampled board matches these shots; try more samples or check the masks");
+    }
+
+    for row in estimate.probabilities.chunks(width) {
+        for (x, probability) in row.iter().enumerate() {
+            print!("{:.6}{}", probability, if x < width - 1 { "," } else { "" });
+        }
+        println!();
+    }
+    Ok(())
+}
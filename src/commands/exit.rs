@@ -0,0 +1,33 @@
+//! Process exit codes, so shell pipelines can branch on the result of a command without parsing
+//! its output. Commands that succeed exit with 0, unless one of these applies.
+
+use std::io;
+use std::process::ExitCode;
+
+/// The command succeeded, but a query matched no boards. Commands that check a dataset (like
+/// `verify` or `duplicates`) also exit with this when the check fails.
+pub const NO_MATCHES: u8 = 1;
+
+/// The query or the arguments are invalid, like a cell outside the board or a hit that's also a
+/// miss. Clap exits with the same code for arguments it rejects itself.
+pub const INVALID_INPUT: u8 = 2;
+
+/// An input file doesn't exist.
+pub const NOT_FOUND: u8 = 3;
+
+/// An input couldn't be decoded: it's truncated, corrupt, or built for a different board.
+pub const DECODE_ERROR: u8 = 4;
+
+/// Any other failure, like a permission or network error.
+pub const FAILURE: u8 = 5;
+
+/// The exit code for a command that failed with `error`.
+pub fn for_error(error: &io::Error) -> ExitCode {
+    let code = match error.kind() {
+        io::ErrorKind::InvalidInput => INVALID_INPUT,
+        io::ErrorKind::NotFound => NOT_FOUND,
+        io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => DECODE_ERROR,
+        _ => FAILURE,
+    };
+    ExitCode::from(code)
+}
@@ -0,0 +1,72 @@
+use battleship::core::filter::{PopcountRange, QueryOptions};
+use battleship::core::geometry::BoardGeometry;
+use battleship::core::parquet::write_matches_parquet;
+use battleship::core::query::Query;
+use battleship::core::reader::open_records_with_threads;
+use clap::{Args, ValueHint};
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::time::Instant;
+
+use super::parse_mask;
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// Path to the board data file (delta-encoded or raw, optionally compressed). Use "-" to read from stdin.
+    #[arg(short, long, value_hint = ValueHint::FilePath)]
+    file: String,
+
+    /// Parquet file to write, with a "board" column and a boolean column per cell (A1, A2, ...)
+    #[arg(short, long)]
+    output: String,
+
+    /// Only export boards with ships on these cells, as a list like A3,B3,C3 or a hex mask
+    #[arg(long, value_name = "CELLS|HEX", default_value = "0")]
+    hit: String,
+
+    /// Only export boards without ships on these cells, as a list like D7,E2 or a hex mask
+    #[arg(short, long, value_name = "CELLS|HEX", default_value = "0")]
+    miss: String,
+
+    /// Treat records as canonical boards and export every board in each symmetry orbit
+    #[arg(long)]
+    expand_symmetries: bool,
+
+    /// Only export records with this many ship cells (e.g., 27, or a range like 20-30)
+    #[arg(long, value_name = "N|MIN-MAX")]
+    popcount: Option<PopcountRange>,
+
+    /// Board dimensions of the records in the data file (e.g., 9x9 or 10x10)
+    #[arg(long, value_name = "WxH", default_value_t = BoardGeometry::STANDARD)]
+    board: BoardGeometry,
+
+    /// Decompress this many zstd frames at once when the data file has one frame per chunk
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    decompress_threads: usize,
+}
+
+pub fn run(args: ExportArgs) -> io::Result<()> {
+    let hit_mask = parse_mask("--hit", &args.hit, args.board)?;
+    let miss_mask = parse_mask("--miss", &args.miss, args.board)?;
+    let query = Query::compile(hit_mask, miss_mask, args.board)?;
+
+    let options = QueryOptions {
+        expand_symmetries: args.expand_symmetries,
+        popcount: args.popcount,
+        geometry: args.board,
+        ..QueryOptions::default()
+    };
+
+    let (header, reader) = open_records_with_threads(&args.file, args.decompress_threads)?;
+    if let Some(header) = header {
+        query.check_dataset(header.geometry, &args.file)?;
+    }
+    let reader = query.guard(reader, &args.file);
+
+    let start = Instant::now();
+    let writer = BufWriter::new(File::create(&args.output)?);
+    let written = write_matches_parquet(reader, query.hit_mask(), query.miss_mask(), &options, writer)?;
+
+    eprintln!("Exported {} boards to {} in {:.2?}", written, args.output, start.elapsed());
+    Ok(())
+}
@@ -0,0 +1,666 @@
+use battleship::core::accumulator::{CountAccumulator, GeometryCellCounts};
+use battleship::core::cache::{CacheKey, QueryCache};
+use battleship::core::filter::{filter_and_accumulate_indexed, filter_and_count_cells, filter_and_write, for_each_matching_with_options, matching_boards, sample_matching_with_options, PopcountRange, QueryOptions};
+use battleship::core::encoder::EncodeOptions;
+use battleship::core::header::{read_optional_header, DatasetHeader, HEADER_LEN};
+use battleship::core::index::DatasetIndex;
+use battleship::core::partial::PartialResult;
+use battleship::core::geometry::{BoardGeometry, RECORD_SIZE};
+use battleship::core::heatmap::{render_heatmap_with_geometry, ColorMode};
+use battleship::core::query::Query;
+use battleship::core::reader::{detect_compression, is_remote, open_records_with_threads, read_records_from, record_range, RawRecordReader};
+use battleship::core::rollup::RollupTable;
+use battleship::generator::Fleet;
+use battleship::solver::recommend::{recommend_shot_with_geometry, top_shots_with_geometry};
+use clap::{Args, ValueEnum, ValueHint};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::exit;
+use super::progress::Progress;
+use super::{expand_files, parse_mask, read_state};
+
+#[derive(Args)]
+pub struct FilterArgs {
+    /// Path to the board data file (raw 16-byte masks, optionally zstd compressed). Use "-" to read from stdin,
+    /// or an http(s):// or s3:// URL with the `remote` feature. Repeat it, or use a wildcard in the file name
+    /// like data/shards/*.zst, to add up the counts of several files.
+    #[arg(short, long, required = true, value_hint = ValueHint::FilePath)]
+    file: Vec<String>,
+
+    /// Hit cells as a list like A3,B3,C3 (row letter, column number) or a hex mask like 0xabcdef
+    #[arg(long, value_name = "CELLS|HEX", required_unless_present = "state")]
+    hit: Option<String>,
+
+    /// Miss cells as a list like D7,E2 or a hex mask
+    #[arg(short, long, value_name = "CELLS|HEX", required_unless_present = "state")]
+    miss: Option<String>,
+
+    /// Read the hits and misses from a file instead: either a board drawn as text, with X for
+    /// hits and • for misses, or a JSON object like {"hit": "A3,B3", "miss": ["D7", "E2"]}
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["hit", "miss"])]
+    state: Option<PathBuf>,
+
+    /// Render the counts as a colored terminal heatmap instead of CSV
+    #[arg(long, value_name = "COLORS", num_args = 0..=1, default_missing_value = "256")]
+    heatmap: Option<HeatmapColors>,
+
+    /// Print the fraction of matched boards with a ship in each cell instead of raw counts
+    #[arg(long, conflicts_with = "heatmap")]
+    probabilities: bool,
+
+    /// Print the recommended next shot (the most likely unshot cell) as a cell like "D4" instead
+    /// of the grid, ready to pass back in --hit or --miss
+    #[arg(long, conflicts_with_all = ["heatmap", "probabilities"])]
+    recommend: bool,
+
+    /// Print the K unshot cells with the highest counts as "cell,count,probability" lines, best first
+    #[arg(long, value_name = "K", conflicts_with_all = ["heatmap", "probabilities", "recommend"])]
+    top: Option<usize>,
+
+    /// Format of the counts: CSV, or bin for the count of each cell as a little-endian u32, in
+    /// row-major order, followed by the number of matched boards as a little-endian u64. With the
+    /// `msgpack` feature, msgpack writes a map like {"counts": [...], "matched": 112}.
+    #[arg(long, value_name = "FORMAT", default_value = "csv", conflicts_with_all = ["heatmap", "probabilities", "recommend", "top", "sample"])]
+    output: OutputFormat,
+
+    /// Write the counts to this file instead of stdout
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["heatmap", "probabilities", "recommend", "top", "sample"])]
+    output_file: Option<PathBuf>,
+
+    /// Stream the matching boards (or the first N) to stdout as raw 16-byte records while
+    /// counting them, to pipe them into another command like encode. The counts are then only
+    /// written with --output-file.
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        conflicts_with_all = ["heatmap", "probabilities", "recommend", "top", "sample", "write", "rollup", "cache", "index"]
+    )]
+    emit_matches: Option<Option<u64>>,
+
+    /// Treat records as canonical boards and count every board in each symmetry orbit
+    #[arg(long)]
+    expand_symmetries: bool,
+
+    /// Only count records with this many ship cells (e.g., 27, or a range like 20-30)
+    #[arg(long, value_name = "N|MIN-MAX")]
+    popcount: Option<PopcountRange>,
+
+    /// Board dimensions of the records in the data file (e.g., 9x9 or 10x10)
+    #[arg(long, value_name = "WxH", default_value_t = BoardGeometry::STANDARD)]
+    board: BoardGeometry,
+
+    /// Chunk index written by the encoder; only chunks that may contain matches are decoded
+    #[arg(long)]
+    index: Option<String>,
+
+    /// Check each chunk against its checksum in the index before counting it, failing with the
+    /// chunk number if it's corrupt
+    #[arg(long, requires = "index")]
+    strict: bool,
+
+    /// Rollup table written by the rollup command. Queries with at most one shot are read from it
+    /// instead of scanning, and queries it shows can't match anything skip the scan.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["sample", "write"])]
+    rollup: Option<PathBuf>,
+
+    /// Ignore the first N records of the data file. With --index, decoding starts at the chunk
+    /// holding record N.
+    #[arg(long, value_name = "N", default_value_t = 0, conflicts_with = "strict")]
+    skip: u64,
+
+    /// Read at most M records, after any skipped by --skip
+    #[arg(long, value_name = "M", conflicts_with = "strict")]
+    limit: Option<u64>,
+
+    /// Print this many uniformly random matching boards as hex masks instead of counting
+    #[arg(long, value_name = "N", conflicts_with_all = ["index", "heatmap", "probabilities", "recommend"])]
+    sample: Option<usize>,
+
+    /// Seed for --sample; the same seed always picks the same boards
+    #[arg(long, default_value_t = 0, requires = "sample")]
+    seed: u64,
+
+    /// Write the matching boards to this file as a new delta-encoded, zstd-compressed dataset,
+    /// then count from it. Later queries against the file only read the boards that matched.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["index", "sample"])]
+    write: Option<String>,
+
+    /// Fleet recorded in the header written by --write when the data file has no header
+    #[arg(long, default_value_t = Fleet::standard(), requires = "write")]
+    fleet: Fleet,
+
+    /// Reuse counts from earlier identical queries, stored in this directory (default
+    /// ~/.cache/battleship). Has no effect when reading from stdin or a URL.
+    #[arg(long, value_name = "DIR", num_args = 0..=1, conflicts_with_all = ["sample"])]
+    cache: Option<Option<PathBuf>>,
+
+    /// Also write the counts to this file as a partial result, to be added to the counts of other
+    /// shards with the merge-partials command
+    #[arg(long, value_name = "PATH", conflicts_with = "sample")]
+    emit_partial: Option<PathBuf>,
+
+    /// Decompress this many zstd frames at once when the data file has one frame per chunk
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    decompress_threads: usize,
+
+    /// Filter and count on this many threads instead of one per CPU, leaving the rest for other
+    /// work
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    threads: Option<u64>,
+
+    /// Show a progress bar on stderr with the records read, their rate and, when the data file's
+    /// header gives its record count, the time left
+    #[arg(long, conflicts_with = "index")]
+    progress: bool,
+
+    /// Write progress to stderr as newline-delimited JSON events, about one a second, like
+    /// {"records":65536,"matched":112,"elapsed":1.024,"done":false}, for tools that track long scans
+    #[arg(long, conflicts_with = "index")]
+    progress_json: bool,
+
+    /// Keep running after printing the counts, and print them again whenever the data file
+    /// changes, e.g. while the generator is still writing it. Boards appended to a raw dataset
+    /// are counted on their own and added to the earlier counts; other changes recount the file.
+    #[arg(
+        long,
+        conflicts_with_all = ["sample", "write", "index", "rollup", "cache", "emit_matches", "emit_partial", "skip", "limit", "progress", "progress_json"]
+    )]
+    watch: bool,
+
+    /// Filter and count on the GPU, falling back to the CPU when there's none or with
+    /// --expand-symmetries
+    #[cfg(feature = "gpu")]
+    #[arg(long, conflicts_with = "index")]
+    gpu: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    /// One line of comma-separated counts per row of the board
+    Csv,
+    /// Little-endian binary, for native tools that read the counts directly
+    Bin,
+    /// MessagePack map of the counts and the number of matched boards, for 9x9 boards
+    #[cfg(feature = "msgpack")]
+    Msgpack,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum HeatmapColors {
+    /// xterm 256-color palette
+    #[value(name = "256")]
+    Ansi256,
+    /// 24-bit RGB colors
+    Truecolor,
+}
+
+pub fn run(args: FilterArgs) -> std::io::Result<()> {
+    let matched = filter(args)?;
+
+    // Let scripts branch on whether the query matched anything
+    if matched == 0 {
+        std::io::stdout().flush()?;
+        std::process::exit(exit::NO_MATCHES.into());
+    }
+    Ok(())
+}
+
+/// Runs the query and prints its results, returning the number of matched boards.
+fn filter(mut args: FilterArgs) -> std::io::Result<u64> {
+    let files = expand_files(&args.file)?;
+    if files.len() > 1 && args.index.is_some() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--index describes a single data file, but --file names several",
+        ));
+    }
+    if files.len() > 1 && has_range(&args) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--skip and --limit count records of a single data file, but --file names several",
+        ));
+    }
+    #[cfg(feature = "msgpack")]
+    if matches!(args.output, OutputFormat::Msgpack) && args.board != BoardGeometry::STANDARD {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("--output msgpack writes results for 9x9 boards, not {}", args.board),
+        ));
+    }
+    if files.len() > 1 && args.rollup.is_some() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--rollup describes a single data file, but --file names several",
+        ));
+    }
+
+    let (hit_mask, miss_mask) = match &args.state {
+        Some(path) => read_state(path, args.board)?,
+        None => (
+            parse_mask("--hit", args.hit.as_deref().unwrap_or_default(), args.board)?,
+            parse_mask("--miss", args.miss.as_deref().unwrap_or_default(), args.board)?,
+        ),
+    };
+    let query = Query::compile(hit_mask, miss_mask, args.board)?;
+    let progress = Progress::new(args.progress, args.progress_json);
+
+    let options = QueryOptions {
+        expand_symmetries: args.expand_symmetries,
+        popcount: args.popcount,
+        geometry: args.board,
+        strict: args.strict,
+        threads: args.threads.map(|threads| threads as usize),
+        ..QueryOptions::default()
+    };
+
+    if args.watch {
+        return watch(&args, &files, &query, &options);
+    }
+
+    if let Some(n) = args.sample {
+        let (_, reader) = open_inputs(&files, &args, &query, &options, &progress)?;
+        let boards = sample_matching_with_options(reader, query.hit_mask(), query.miss_mask(), n, args.seed, &options)?;
+        progress.finish();
+
+        eprintln!("Sampled boards: {}", boards.len());
+        for board in &boards {
+            println!("{:#034x}", board);
+        }
+        return Ok(boards.len() as u64);
+    }
+
+    if let Some(path) = &args.write {
+        let (header, reader) = open_inputs(&files, &args, &query, &options, &progress)?;
+
+        let encode_options = EncodeOptions {
+            zstd_frames: Some(3),
+            header: Some(header.unwrap_or_else(|| DatasetHeader::new(args.board, args.fleet.clone(), true))),
+            ..EncodeOptions::default()
+        };
+        let mut writer = BufWriter::new(File::create(path)?);
+        let index = filter_and_write(reader, query.hit_mask(), query.miss_mask(), &options, &mut writer, &encode_options)?;
+
+        // The header came from the input, so fill in the number of boards that were written
+        let mut file = writer.into_inner().map_err(|e| e.into_error())?;
+        let header = DatasetHeader { record_count: Some(index.record_count()), ..encode_options.output_header().unwrap() };
+        file.seek(SeekFrom::Start(0))?;
+        header.write_to(&mut file)?;
+        eprintln!("Wrote {} matching boards to {}", index.record_count(), path);
+    }
+
+    // Partial results describe the query as given, even when it's counted from a narrowed dataset
+    let partial_options = options;
+
+    // Count from the narrowed dataset, whose boards are already filtered and expanded. Its
+    // records were tracked as they were written, so reading them back isn't.
+    let (files, options, progress) = match &args.write {
+        Some(path) => {
+            progress.finish();
+            (vec![path.clone()], QueryOptions { expand_symmetries: false, ..options }, Progress::new(false, false))
+        }
+        None => (files, options, progress),
+    };
+    if args.write.is_some() {
+        // --skip and --limit already picked the boards that were written
+        args.skip = 0;
+        args.limit = None;
+    }
+
+    let mut emitter = args.emit_matches.map(|limit| MatchEmitter { writer: BufWriter::new(std::io::stdout()), remaining: limit });
+
+    // Each file is counted (and cached) on its own, then the counts are added up
+    let mut counts = vec![0u32; args.board.cells()];
+    let mut matched = 0;
+    for file in &files {
+        let (file_counts, file_matched) = count_file(&args, file, &query, &options, &progress, emitter.as_mut())?;
+        for (count, file_count) in counts.iter_mut().zip(file_counts) {
+            *count += file_count;
+        }
+        matched += file_matched;
+    }
+    progress.finish();
+    if let Some(emitter) = &mut emitter {
+        emitter.writer.flush()?;
+    }
+
+    eprintln!("Matched boards: {}", matched);
+
+    if let Some(path) = &args.emit_partial {
+        PartialResult::new(query.hit_mask(), query.miss_mask(), &partial_options, &counts, matched).save(path)?;
+        eprintln!("Wrote partial counts to {}", path.display());
+    }
+
+    print_counts(&args, &query, &counts, matched, emitter.is_some())?;
+    Ok(matched)
+}
+
+/// How often --watch checks the data file for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Prints the counts of the query's matches in the format the flags ask for. `emitting` is set
+/// when stdout holds the matching boards, so the counts are only written to --output-file.
+fn print_counts(args: &FilterArgs, query: &Query, counts: &[u32], matched: u64, emitting: bool) -> std::io::Result<()> {
+    let width = args.board.width();
+
+    if let Some(colors) = args.heatmap {
+        let mode = match colors {
+            HeatmapColors::Ansi256 => ColorMode::Ansi256,
+            HeatmapColors::Truecolor => ColorMode::TrueColor,
+        };
+        print!("{}", render_heatmap_with_geometry(counts, args.board, mode));
+        return Ok(());
+    }
+
+    if args.recommend {
+        match recommend_shot_with_geometry(counts, args.board, query.hit_mask() | query.miss_mask()) {
+            Some(point) => println!("{}", point),
+            None => eprintln!("Every cell has already been shot"),
+        }
+        return Ok(());
+    }
+
+    if let Some(k) = args.top {
+        for (point, count) in top_shots_with_geometry(counts, args.board, query.hit_mask() | query.miss_mask(), k) {
+            let probability = if matched == 0 { 0.0 } else { count as f64 / matched as f64 };
+            println!("{},{},{:.6}", point, count, probability);
+        }
+        return Ok(());
+    }
+
+    if args.probabilities {
+        for row in counts.chunks(width) {
+            for (x, &count) in row.iter().enumerate() {
+                let probability = if matched == 0 { 0.0 } else { count as f64 / matched as f64 };
+                print!("{:.6}{}", probability, if x < width - 1 { "," } else { "" });
+            }
+            println!();
+        }
+        return Ok(());
+    }
+
+    let mut output: Box<dyn Write> = match &args.output_file {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        // Stdout holds the matching boards
+        None if emitting => return Ok(()),
+        None => Box::new(std::io::stdout().lock()),
+    };
+
+    match args.output {
+        OutputFormat::Bin => {
+            for count in counts {
+                output.write_all(&count.to_le_bytes())?;
+            }
+            output.write_all(&matched.to_le_bytes())?;
+        }
+        #[cfg(feature = "msgpack")]
+        OutputFormat::Msgpack => {
+            let counts = counts.try_into().expect("9x9 board, checked before counting");
+            let result = battleship::core::filter::FilterResult::new(counts, matched);
+            rmp_serde::encode::write_named(&mut output, &result).map_err(std::io::Error::other)?;
+        }
+        // Print the grid of counts
+        OutputFormat::Csv => {
+            for row in counts.chunks(width) {
+                for (x, count) in row.iter().enumerate() {
+                    write!(output, "{}{}", count, if x < width - 1 { "," } else { "" })?;
+                }
+                writeln!(output)?;
+            }
+        }
+    }
+    output.flush()
+}
+
+/// Counts the matches in `files`, which must be a single local file, and prints them, then checks
+/// the file for changes every [`WATCH_INTERVAL`] and prints the counts again when they change.
+/// Only returns on an error.
+fn watch(args: &FilterArgs, files: &[String], query: &Query, options: &QueryOptions) -> std::io::Result<u64> {
+    let file = match files {
+        [file] if file != "-" && !is_remote(file) => file,
+        _ => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "--watch needs a single local data file to watch",
+            ))
+        }
+    };
+
+    let progress = Progress::new(false, false);
+    let mut counts = vec![0u32; args.board.cells()];
+    let mut matched = 0;
+    // Where the boards not yet counted start, when the file is a raw dataset that's appended to
+    let mut counted = None;
+    let mut last_seen = None;
+    let mut printed = None;
+
+    loop {
+        let metadata = std::fs::metadata(file)?;
+        let seen = (metadata.len(), metadata.modified()?);
+
+        if last_seen != Some(seen) {
+            last_seen = Some(seen);
+
+            match appendable_records(file, query)? {
+                Some(first_record) => {
+                    // A file that shrank was rewritten, e.g. by a generator resuming from a checkpoint
+                    let start = match counted {
+                        Some(offset) if offset <= seen.0 => offset,
+                        _ => {
+                            counts.fill(0);
+                            matched = 0;
+                            first_record
+                        }
+                    };
+                    // Stop short of a board that's only partly written
+                    let len = (seen.0.saturating_sub(start) / RECORD_SIZE as u64) * RECORD_SIZE as u64;
+
+                    let mut reader = File::open(file)?;
+                    reader.seek(SeekFrom::Start(start))?;
+                    let records = RawRecordReader::new(BufReader::new(reader.take(len)));
+                    let (new_counts, new_matched) =
+                        filter_and_count_cells(query.guard(records, file), query.hit_mask(), query.miss_mask(), options)?;
+                    for (count, new_count) in counts.iter_mut().zip(new_counts) {
+                        *count += new_count;
+                    }
+                    matched += new_matched;
+                    counted = Some(start + len);
+                }
+                None => {
+                    (counts, matched) = count_file(args, file, query, options, &progress, None)?;
+                    counted = None;
+                }
+            }
+
+            if printed.as_ref() != Some(&(counts.clone(), matched)) {
+                eprintln!("Matched boards: {}", matched);
+                print_counts(args, query, &counts, matched, false)?;
+                std::io::stdout().flush()?;
+                printed = Some((counts.clone(), matched));
+            }
+        }
+
+        std::thread::sleep(WATCH_INTERVAL);
+    }
+}
+
+/// Where the records of `file` start, if it's a raw, uncompressed dataset with a header, like the
+/// generator writes, whose new boards can be read on their own as it grows.
+fn appendable_records(file: &str, query: &Query) -> std::io::Result<Option<u64>> {
+    let (header, mut records) = read_optional_header(BufReader::new(File::open(file)?))?;
+    let header = match header {
+        Some(header) if !header.delta_encoded => header,
+        _ => return Ok(None),
+    };
+    query.check_dataset(header.geometry, file)?;
+
+    let mut prefix = Vec::new();
+    records.by_ref().take(8).read_to_end(&mut prefix)?;
+    Ok(detect_compression(&prefix).is_none().then_some(HEADER_LEN as u64))
+}
+
+/// Counts the matches in one data file, answering from the rollup table or reusing cached counts
+/// when `--rollup` or `--cache` is given.
+fn count_file(
+    args: &FilterArgs,
+    file: &str,
+    query: &Query,
+    options: &QueryOptions,
+    progress: &Progress,
+    emitter: Option<&mut MatchEmitter>,
+) -> std::io::Result<(Vec<u32>, u64)> {
+    // Stdin and remote datasets can't be fingerprinted, so they're never cached, and neither are
+    // ranges of records
+    let cache = match &args.cache {
+        Some(dir) if file != "-" && !is_remote(file) && !has_range(args) => {
+            let cache = QueryCache::new(dir.clone().unwrap_or_else(QueryCache::default_dir))?;
+            let key = CacheKey::new(file, query.hit_mask(), query.miss_mask(), options)?;
+            Some((cache, key))
+        }
+        _ => None,
+    };
+
+    if let Some(path) = args.rollup.as_ref().filter(|_| !has_range(args)) {
+        let table = RollupTable::load(path)?;
+        if table.answers(options) {
+            if let Some((counts, matched)) = table.lookup(query.hit_mask(), query.miss_mask()) {
+                eprintln!("Using the rollup table for {}", file);
+                return Ok((counts.to_vec(), matched));
+            }
+            if table.upper_bounds(query.hit_mask(), query.miss_mask()).1 == 0 {
+                eprintln!("The rollup table shows no boards in {} can match", file);
+                return Ok((vec![0; options.geometry.cells()], 0));
+            }
+        }
+    }
+
+    if let Some((cache, key)) = &cache {
+        if let Some(result) = cache.get(key)? {
+            eprintln!("Using cached counts for {}", file);
+            return Ok(result);
+        }
+    }
+
+    let result = count_matches(args, file, query, options, progress, emitter)?;
+    if let Some((cache, key)) = &cache {
+        cache.put(key, &result.0, result.1)?;
+    }
+    Ok(result)
+}
+
+/// Opens every data file, checking each against the query, and chains their records in order.
+/// Returns the first file's header.
+fn open_inputs(files: &[String], args: &FilterArgs, query: &Query, options: &QueryOptions, progress: &Progress) -> std::io::Result<(Option<DatasetHeader>, impl Iterator<Item = std::io::Result<u128>>)> {
+    let mut first_header = None;
+    let mut readers = Vec::with_capacity(files.len());
+
+    for (i, file) in files.iter().enumerate() {
+        let (header, reader) = open_records_with_threads(file, args.decompress_threads)?;
+        if let Some(header) = &header {
+            query.check_dataset(header.geometry, file)?;
+        }
+        progress.add_records(records_read(args, header.as_ref()));
+        if i == 0 {
+            first_header = header;
+        }
+        readers.push(query.guard(reader, file));
+    }
+
+    let records = record_range(readers.into_iter().flatten(), args.skip, args.limit);
+    Ok((first_header, track(records, query, options, progress)))
+}
+
+/// Counts records into `progress` as they're read, along with their matches of the query.
+fn track<I>(records: I, query: &Query, options: &QueryOptions, progress: &Progress) -> impl Iterator<Item = std::io::Result<u128>>
+where
+    I: IntoIterator<Item = std::io::Result<u128>>,
+{
+    let (hit_mask, miss_mask, options) = (query.hit_mask(), query.miss_mask(), *options);
+    progress.track(records, move |record| matching_boards(record, hit_mask, miss_mask, &options))
+}
+
+/// How many records of a data file with this header are read, after --skip and --limit, if the
+/// header says how many it holds.
+fn records_read(args: &FilterArgs, header: Option<&DatasetHeader>) -> Option<u64> {
+    let count = header?.record_count?.saturating_sub(args.skip);
+    Some(args.limit.map_or(count, |limit| count.min(limit)))
+}
+
+/// Whether only a range of records is read, with --skip or --limit.
+fn has_range(args: &FilterArgs) -> bool {
+    args.skip > 0 || args.limit.is_some()
+}
+
+fn count_matches(
+    args: &FilterArgs,
+    file: &str,
+    query: &Query,
+    options: &QueryOptions,
+    progress: &Progress,
+    emitter: Option<&mut MatchEmitter>,
+) -> std::io::Result<(Vec<u32>, u64)> {
+    match &args.index {
+        Some(index_path) if has_range(args) => {
+            let index = DatasetIndex::load(index_path)?;
+            let records = record_range(read_records_from(file, &index, args.skip)?, 0, args.limit);
+            filter_and_count_cells(query.guard(records, file), query.hit_mask(), query.miss_mask(), options)
+        }
+        Some(index_path) => {
+            let index = DatasetIndex::load(index_path)?;
+            let geometry = args.board;
+            filter_and_accumulate_indexed(file, &index, query.hit_mask(), query.miss_mask(), options, || GeometryCellCounts::new(geometry))
+        }
+        None => {
+            let (header, reader) = open_records_with_threads(file, args.decompress_threads)?;
+            if let Some(header) = &header {
+                query.check_dataset(header.geometry, file)?;
+            }
+            progress.add_records(records_read(args, header.as_ref()));
+            let reader = query.guard(track(record_range(reader, args.skip, args.limit), query, options, progress), file);
+            if let Some(emitter) = emitter {
+                return emitter.count_and_emit(reader, query, options);
+            }
+            #[cfg(feature = "gpu")]
+            if args.gpu {
+                return battleship::core::filter::filter_and_count_gpu(reader, query.hit_mask(), query.miss_mask(), options);
+            }
+            filter_and_count_cells(reader, query.hit_mask(), query.miss_mask(), options)
+        }
+    }
+}
+
+/// Writes the matching boards to stdout as raw 16-byte records as they're counted, for
+/// --emit-matches.
+struct MatchEmitter {
+    writer: BufWriter<std::io::Stdout>,
+    /// Boards still to write, when --emit-matches was given a limit.
+    remaining: Option<u64>,
+}
+
+impl MatchEmitter {
+    fn count_and_emit<I>(&mut self, records: I, query: &Query, options: &QueryOptions) -> std::io::Result<(Vec<u32>, u64)>
+    where
+        I: IntoIterator<Item = std::io::Result<u128>>,
+    {
+        let mut counts = GeometryCellCounts::new(options.geometry);
+        let mut written = Ok(());
+
+        // Matches are visited in dataset order, so they're written in that order too
+        for_each_matching_with_options(records, query.hit_mask(), query.miss_mask(), options, |board| {
+            counts.visit(board);
+            if written.is_ok() && self.remaining != Some(0) {
+                written = self.writer.write_all(&board.to_le_bytes());
+                if let Some(remaining) = &mut self.remaining {
+                    *remaining -= 1;
+                }
+            }
+        })?;
+
+        written?;
+        Ok(counts.finish())
+    }
+}
@@ -0,0 +1,417 @@
+use battleship::generator::board_mask::BoardMask;
+use battleship::core::geometry::{BoardGeometry, RECORD_SIZE};
+use battleship::core::header::{DatasetHeader, HEADER_LEN};
+use battleship::generator::board_state::BoardState;
+use battleship::generator::enumerate::{count_of_canonical_endings, count_of_valid_endings, EndingCounter, for_each_subtree_in_order, split_search, SUBTREES_PER_THREAD};
+use battleship::generator::point::{Direction, Point};
+use battleship::generator::Fleet;
+use clap::Args;
+use indicatif::{ProgressBar, ProgressDrawTarget};
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::progress::style;
+
+#[derive(Args)]
+pub struct GenerateArgs {
+    /// Output file for the raw (not delta-encoded) board records
+    #[arg(short, long, required_unless_present_any = ["resume", "count_only"])]
+    output: Option<PathBuf>,
+
+    /// Only write canonical boards, one per symmetry class, pruning symmetric duplicates during
+    /// the search (the default; overrides an earlier --all)
+    #[arg(long, overrides_with = "all")]
+    canonical_only: bool,
+
+    /// Write every valid board instead of only canonical ones (overrides an earlier
+    /// --canonical-only)
+    #[arg(long, overrides_with = "canonical_only")]
+    all: bool,
+
+    /// Ships to place, as LENGTH:COUNT pairs with lengths from 2 to 8 (e.g., 2:1,3:2,4:1,5:1)
+    #[arg(long, default_value_t = Fleet::standard())]
+    fleet: Fleet,
+
+    /// Only count the valid boards for the fleet, without writing anything or checking for
+    /// canonical boards
+    #[arg(long, conflicts_with_all = ["output", "canonical_only", "all", "progress", "resume", "checkpoint"])]
+    count_only: bool,
+
+    /// Show a progress bar on stderr with the share of the search done, its rate and the time left
+    #[arg(long)]
+    progress: bool,
+
+    /// Resume an interrupted run from this checkpoint file; the output, fleet and board
+    /// selection are taken from the checkpoint
+    #[arg(long, value_name = "CHECKPOINT", conflicts_with_all = ["output", "canonical_only", "all", "fleet"])]
+    resume: Option<PathBuf>,
+
+    /// Where to save checkpoints (defaults to the output path with ".checkpoint" appended)
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+
+    /// Seconds between checkpoints
+    #[arg(long, default_value_t = 60)]
+    checkpoint_interval: u64,
+}
+
+pub fn run(args: GenerateArgs) -> io::Result<()> {
+    args.fleet.check_fits(BoardGeometry::STANDARD).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    if args.count_only {
+        let start = BoardState::with_fleet(BoardGeometry::STANDARD, &args.fleet);
+        let total = time(|| EndingCounter::new().count(&start));
+        println!("Total Valid: {}", total);
+        return Ok(());
+    }
+
+    let interval = Duration::from_secs(args.checkpoint_interval);
+
+    let (checkpoint, checkpoint_path) = match args.resume {
+        Some(path) => {
+            let checkpoint = Checkpoint::load(&path)?;
+            println!("Resuming from {} ({} subtrees done)", path.display(), checkpoint.completed_subtrees);
+            (checkpoint, args.checkpoint.unwrap_or(path))
+        }
+        None => {
+            // Required unless --resume is present
+            let output = args.output.unwrap();
+            let checkpoint_path = args.checkpoint.unwrap_or_else(|| default_checkpoint_path(&output));
+            let split_target = rayon::current_num_threads() * SUBTREES_PER_THREAD;
+            // The last of --canonical-only and --all wins, so only --all needs checking
+            (Checkpoint::new(output, args.fleet, !args.all, split_target), checkpoint_path)
+        }
+    };
+
+    write_all_valid_boards(checkpoint, &checkpoint_path, interval, args.progress)
+}
+
+fn default_checkpoint_path(output: &Path) -> PathBuf {
+    let mut path = output.as_os_str().to_owned();
+    path.push(".checkpoint");
+    PathBuf::from(path)
+}
+
+fn time<F, R>(action: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let start = Instant::now();
+    println!("Starting: {:?}", std::time::SystemTime::now());
+    let result = action();
+    println!("Done: {:?} (took {:?})", std::time::SystemTime::now(), start.elapsed());
+    result
+}
+
+#[allow(dead_code)]
+fn print_sample_placed_ship() {
+    let mut board = BoardState::EMPTY;
+    let placed = board.place_ship(3, Point::new(1, 0), Direction::Horizontal);
+    assert!(placed);
+    println!("{}", board.debug_description());
+}
+
+#[allow(dead_code)]
+fn print_all_possible_ships() {
+    print_all_possible_ships_inner(3, Direction::Horizontal);
+    print_all_possible_ships_inner(3, Direction::Vertical);
+
+    print_all_possible_ships_inner(4, Direction::Horizontal);
+    print_all_possible_ships_inner(4, Direction::Vertical);
+}
+
+fn print_all_possible_ships_inner(length: i32, direction: Direction) {
+    println!("Length: {}, Direction: {}", length, match direction {
+        Direction::Horizontal => "horizontal",
+        Direction::Vertical => "vertical",
+    });
+
+    for i in 0..81 {
+        let point = BoardMask::point_of(i);
+
+        let mut board = BoardState::EMPTY;
+        board.place_ship(length, point, direction);
+
+        if board.hit_mask() != BoardMask::FULL {
+            println!("{}", board.debug_description());
+            println!("");
+        }
+    }
+}
+
+fn write_all_valid_boards(mut checkpoint: Checkpoint, checkpoint_path: &Path, interval: Duration, progress: bool) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).write(true).truncate(false).open(&checkpoint.output)?;
+
+    // Drop anything written after the last checkpoint; it will be regenerated
+    file.set_len(checkpoint.bytes_written)?;
+    file.seek(SeekFrom::End(0))?;
+
+    // The record count is filled in once the run is complete
+    let mut header = DatasetHeader::new(BoardGeometry::STANDARD, checkpoint.fleet.clone(), false);
+    if checkpoint.bytes_written == 0 {
+        header.write_to(&mut file)?;
+        checkpoint.bytes_written = HEADER_LEN as u64;
+    }
+
+    let mut writer = BufWriter::new(file);
+
+    let mut last_checkpoint = Instant::now();
+
+    // The split only depends on the target, so a resumed run sees the same subtrees in the same order
+    let start = BoardState::with_fleet(BoardGeometry::STANDARD, &checkpoint.fleet);
+    let frontier = split_search(&start, checkpoint.split_target);
+    let canonical_only = checkpoint.canonical_only;
+    let remaining = &frontier[checkpoint.completed_subtrees.min(frontier.len())..];
+
+    // Progress is measured in valid boards searched, which the counter finds in well under a
+    // second, so it's exact even when only canonical boards are written
+    let mut counter = EndingCounter::new();
+    let subtree_sizes: Vec<u64> = frontier.iter().map(|root| counter.count(root)).collect();
+    let total: u64 = subtree_sizes.iter().sum();
+    let searched: u64 = subtree_sizes[..frontier.len() - remaining.len()].iter().sum();
+    let bar = if progress {
+        ProgressBar::with_draw_target(Some(total), ProgressDrawTarget::stderr())
+            .with_position(searched)
+            .with_style(style("{spinner} [{elapsed_precise}] {wide_bar} {percent}% of {human_len} valid boards ({rate}, {eta} left)"))
+    } else {
+        ProgressBar::hidden()
+    };
+    // A resumed run's time left only goes by how fast this run has gone so far
+    bar.reset_eta();
+
+    time(|| {
+        // Each subtree buffers its boards; buffers are written in search order
+        for_each_subtree_in_order(remaining, |root| {
+            let mut data = Vec::new();
+            let count = if canonical_only {
+                count_of_canonical_endings(root, &mut |board| data.extend_from_slice(&board.to_le_bytes()))
+            } else {
+                count_of_valid_endings(root, &mut |board| data.extend_from_slice(&board.hit_mask().raw_value().to_le_bytes()))
+            };
+            (count, data)
+        }, |(count, data)| -> io::Result<()> {
+            writer.write_all(&data)?;
+            bar.inc(subtree_sizes[checkpoint.completed_subtrees]);
+            checkpoint.completed_subtrees += 1;
+            checkpoint.bytes_written += data.len() as u64;
+            checkpoint.valid_count += count as u64;
+
+            if last_checkpoint.elapsed() >= interval {
+                writer.flush()?;
+                checkpoint.save(checkpoint_path)?;
+                last_checkpoint = Instant::now();
+            }
+
+            Ok(())
+        })
+    })?;
+    bar.finish();
+
+    let mut file = writer.into_inner().map_err(|e| e.into_error())?;
+    header.record_count = Some((checkpoint.bytes_written - HEADER_LEN as u64) / RECORD_SIZE as u64);
+    file.seek(SeekFrom::Start(0))?;
+    header.write_to(&mut file)?;
+
+    println!("Total {}: {}", if canonical_only { "Canonical" } else { "Valid" }, checkpoint.valid_count);
+
+    // The run is complete, so there's nothing left to resume
+    if checkpoint_path.exists() {
+        fs::remove_file(checkpoint_path)?;
+    }
+
+    Ok(())
+}
+
+/// Progress of a generator run, saved periodically so an interrupted run can be resumed.
+///
+/// Subtrees are written to the output strictly in search order, so progress is fully
+/// described by how many subtrees are done and how many bytes they produced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Checkpoint {
+    output: PathBuf,
+    fleet: Fleet,
+    canonical_only: bool,
+    /// Number of subtrees the search was split into. Resumed runs must split the same way.
+    split_target: usize,
+    completed_subtrees: usize,
+    bytes_written: u64,
+    /// Boards written so far: every valid board, or only canonical ones with `canonical_only`.
+    valid_count: u64,
+}
+
+// Version 2 searches for canonical boards in a different order, so subtrees don't line up with version 1
+const CHECKPOINT_HEADER: &str = "battleship-generator-checkpoint 2";
+
+impl Checkpoint {
+    fn new(output: PathBuf, fleet: Fleet, canonical_only: bool, split_target: usize) -> Self {
+        Self { output, fleet, canonical_only, split_target, completed_subtrees: 0, bytes_written: 0, valid_count: 0 }
+    }
+
+    fn to_text(&self) -> String {
+        format!(
+            "{}\noutput={}\nfleet={}\ncanonical_only={}\nsplit_target={}\ncompleted_subtrees={}\nbytes_written={}\nvalid_count={}\n",
+            CHECKPOINT_HEADER,
+            self.output.display(),
+            self.fleet,
+            self.canonical_only,
+            self.split_target,
+            self.completed_subtrees,
+            self.bytes_written,
+            self.valid_count,
+        )
+    }
+
+    fn from_text(text: &str) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let mut lines = text.lines();
+        if lines.next() != Some(CHECKPOINT_HEADER) {
+            return Err(invalid("Not a generator checkpoint".to_string()));
+        }
+
+        let mut checkpoint = Checkpoint::new(PathBuf::new(), Fleet::standard(), true, 0);
+        let mut seen = 0;
+
+        for line in lines {
+            let (key, value) = line.split_once('=')
+                .ok_or_else(|| invalid(format!("Malformed checkpoint line '{}'", line)))?;
+            let number = || value.parse::<u64>().map_err(|_| invalid(format!("Invalid value for {}: '{}'", key, value)));
+
+            match key {
+                "output" => checkpoint.output = PathBuf::from(value),
+                "fleet" => checkpoint.fleet = value.parse().map_err(invalid)?,
+                "canonical_only" => checkpoint.canonical_only = value.parse().map_err(|_| invalid(format!("Invalid value for {}: '{}'", key, value)))?,
+                "split_target" => checkpoint.split_target = number()? as usize,
+                "completed_subtrees" => checkpoint.completed_subtrees = number()? as usize,
+                "bytes_written" => checkpoint.bytes_written = number()?,
+                "valid_count" => checkpoint.valid_count = number()?,
+                _ => return Err(invalid(format!("Unknown checkpoint field '{}'", key))),
+            }
+            seen += 1;
+        }
+
+        if seen != 7 {
+            return Err(invalid("Checkpoint is missing fields".to_string()));
+        }
+
+        Ok(checkpoint)
+    }
+
+    fn load(path: &Path) -> io::Result<Self> {
+        Self::from_text(&fs::read_to_string(path)?)
+    }
+
+    /// Writes the checkpoint to a temporary file first so a crash mid-write never leaves a
+    /// truncated checkpoint behind.
+    fn save(&self, path: &Path) -> io::Result<()> {
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+
+        fs::write(&temp, self.to_text())?;
+        fs::rename(&temp, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use battleship::generator::board_mask::BoardMask;
+    use battleship::generator::board_state::{BoardState, CellState};
+    use battleship::generator::point::{Direction, Point};
+    use battleship::generator::symmetries::is_canonical;
+
+    #[test]
+    fn test_point_operations() {
+        let p1 = Point::new(1, 2);
+        let p2 = Point::new(3, 4);
+        let sum = p1 + p2;
+        assert_eq!(sum.x, 4);
+        assert_eq!(sum.y, 6);
+
+        let diff = p2 - p1;
+        assert_eq!(diff.x, 2);
+        assert_eq!(diff.y, 2);
+    }
+
+    #[test]
+    fn test_board_mask_basic() {
+        let mut mask = BoardMask::EMPTY;
+        let point = Point::new(4, 4);
+
+        assert!(!mask.get(point));
+        mask.set(point, true);
+        assert!(mask.get(point));
+
+        mask.set(point, false);
+        assert!(!mask.get(point));
+    }
+
+    #[test]
+    fn test_board_state_ship_placement() {
+        let mut board = BoardState::EMPTY;
+        let point = Point::new(1, 2);
+
+        // Should be able to place a 3-length ship
+        assert!(board.place_ship(3, point, Direction::Horizontal));
+
+        // Should be in hit state
+        assert_eq!(board.get(point), CellState::Hit);
+        assert_eq!(board.get(Point::new(2, 2)), CellState::Hit);
+        assert_eq!(board.get(Point::new(3, 2)), CellState::Hit);
+
+        // Should have miss outline
+        assert_eq!(board.get(Point::new(0, 1)), CellState::Miss);
+        assert_eq!(board.get(Point::new(4, 3)), CellState::Miss);
+    }
+
+    #[test]
+    fn test_board_mask_index_conversion() {
+        let point = Point::new(4, 5);
+        let index = BoardMask::index_of(point);
+        let converted_back = BoardMask::point_of(index);
+
+        assert_eq!(point.x, converted_back.x);
+        assert_eq!(point.y, converted_back.y);
+    }
+
+    #[test]
+    fn test_board_selection_flags() {
+        #[derive(clap::Parser)]
+        struct Cli {
+            #[command(flatten)]
+            args: GenerateArgs,
+        }
+
+        let all = |flags: &[&str]| {
+            let argv = ["generate", "-o", "boards.bin"].iter().chain(flags);
+            <Cli as clap::Parser>::try_parse_from(argv).unwrap().args.all
+        };
+
+        assert!(!all(&[]));
+        assert!(all(&["--all"]));
+        assert!(!all(&["--all", "--canonical-only"]));
+        assert!(all(&["--canonical-only", "--all"]));
+    }
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let mut checkpoint = Checkpoint::new(PathBuf::from("/tmp/boards.bin"), Fleet::new(&[(3, 4), (4, 2)]), false, 256);
+        checkpoint.completed_subtrees = 17;
+        checkpoint.bytes_written = 4096;
+        checkpoint.valid_count = 1234;
+
+        assert_eq!(Checkpoint::from_text(&checkpoint.to_text()).unwrap(), checkpoint);
+        assert!(Checkpoint::from_text("not a checkpoint").is_err());
+        assert!(Checkpoint::from_text(&format!("{}\noutput=x\n", CHECKPOINT_HEADER)).is_err());
+        assert_eq!(default_checkpoint_path(Path::new("out/latest.bin")), PathBuf::from("out/latest.bin.checkpoint"));
+    }
+
+    #[test]
+    fn test_symmetry_detection() {
+        // Test that a symmetric board is detected as canonical
+        let board: u128 = 0b101_000_101; // Simple symmetric pattern
+        assert!(is_canonical(board));
+    }
+}
@@ -0,0 +1,132 @@
+use battleship::core::geometry::BoardGeometry;
+use battleship::core::header::{read_optional_header, HEADER_LEN};
+use battleship::core::index::DatasetIndex;
+use battleship::core::reader::{detect_compression, open_records, zstd_frame_ranges, RecordEncoding};
+use battleship::generator::board_mask::BoardMask;
+use battleship::generator::board_state::{BoardState, CellState, RenderOptions};
+use clap::Args;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct InspectArgs {
+    /// Dataset to describe (raw or delta-encoded, optionally compressed)
+    file: PathBuf,
+
+    /// Index written by the encoder with --index, to report chunk stats from
+    #[arg(long)]
+    index: Option<PathBuf>,
+
+    /// Board size of the records when the dataset has no header
+    #[arg(long, value_name = "WxH", default_value_t = BoardGeometry::STANDARD)]
+    board: BoardGeometry,
+}
+
+pub fn run(args: InspectArgs) -> io::Result<()> {
+    let file = File::open(&args.file)?;
+    let file_len = file.metadata()?.len();
+    println!("File: {} ({} bytes)", args.file.display(), file_len);
+
+    let (outer_header, rest) = read_optional_header(file)?;
+    let mut prefix = Vec::new();
+    rest.take(6).read_to_end(&mut prefix)?;
+
+    match detect_compression(&prefix) {
+        Some("zstd") => {
+            let start = if outer_header.is_some() { HEADER_LEN as u64 } else { 0 };
+            match zstd_frame_ranges(&mut File::open(&args.file)?, start)? {
+                Some(frames) => println!("Compression: zstd, {} frame(s)", frames.len()),
+                None => println!("Compression: zstd"),
+            }
+        }
+        Some(format) => println!("Compression: {}", format),
+        None => println!("Compression: none"),
+    }
+
+    let (header, records) = open_records(&args.file)?;
+    let geometry = header.as_ref().map_or(args.board, |header| header.geometry);
+    match &header {
+        Some(header) => println!("Header: {}", header),
+        None => println!("Header: none"),
+    }
+
+    let encoding = match records.encoding() {
+        RecordEncoding::Delta => "delta",
+        RecordEncoding::Raw => "raw",
+    };
+    let source = if header.is_some() { "from header" } else { "detected" };
+    println!("Encoding: {} ({})", encoding, source);
+
+    let mut count = 0u64;
+    let mut first = None;
+    let mut last = None;
+    let mut popcounts = BTreeMap::new();
+    for record in records {
+        let record = record?;
+        count += 1;
+        first.get_or_insert(record);
+        last = Some(record);
+        *popcounts.entry(record.count_ones()).or_insert(0u64) += 1;
+    }
+
+    println!("Records: {}", count);
+    if let (Some(first), Some(last)) = (first, last) {
+        println!("First record: {}", describe_record(first, geometry));
+        println!("{}", render_record(first, geometry));
+        println!("Last record: {}", describe_record(last, geometry));
+        println!("{}", render_record(last, geometry));
+    }
+
+    println!("Popcount histogram:");
+    for (popcount, records) in &popcounts {
+        println!("  {:3} cells: {}", popcount, records);
+    }
+
+    if let Some(path) = &args.index {
+        print_chunk_stats(&DatasetIndex::load(path)?, count);
+    }
+    Ok(())
+}
+
+fn describe_record(record: u128, geometry: BoardGeometry) -> String {
+    let cells: Vec<String> = BoardMask::with_geometry(record, geometry)
+        .iter_points()
+        .map(|point| point.to_string())
+        .collect();
+    format!("{:#034x} [{}]", record, cells.join(" "))
+}
+
+fn render_record(record: u128, geometry: BoardGeometry) -> String {
+    let mut board = BoardState::empty(geometry);
+    for point in BoardMask::with_geometry(record, geometry).iter_points() {
+        board.set(point, CellState::Hit);
+    }
+    board.render(RenderOptions { labels: true, compact: true, ..RenderOptions::default() })
+}
+
+fn print_chunk_stats(index: &DatasetIndex, records: u64) {
+    println!("Chunks: {}", index.chunks.len());
+    println!("  Restarted deltas: {}", if index.restart_delta { "yes" } else { "no" });
+    println!("  zstd frame per chunk: {}", if index.zstd_frames { "yes" } else { "no" });
+    println!("  Encoded bytes: {}", index.data_len);
+
+    let indexed = index.record_count();
+    if indexed != records {
+        println!("  Warning: index covers {} records but the dataset has {}", indexed, records);
+    }
+
+    let counts = index.chunks.iter().map(|chunk| chunk.count);
+    if let (Some(min), Some(max)) = (counts.clone().min(), counts.max()) {
+        println!("  Records per chunk: min {}, max {}, mean {:.1}", min, max, indexed as f64 / index.chunks.len() as f64);
+    }
+
+    let sizes = (0..index.chunks.len()).map(|chunk| {
+        let range = index.chunk_range(chunk);
+        range.end - range.start
+    });
+    if let (Some(min), Some(max)) = (sizes.clone().min(), sizes.max()) {
+        println!("  Bytes per chunk: min {}, max {}", min, max);
+    }
+}
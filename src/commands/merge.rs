@@ -0,0 +1,118 @@
+use battleship::core::encoder::{encode_records, EncodeOptions};
+use battleship::core::geometry::BoardGeometry;
+use battleship::core::header::DatasetHeader;
+use battleship::core::merge::{merge_headers, merge_records, MergeOptions};
+use battleship::core::reader::open_records;
+use battleship::generator::Fleet;
+use clap::{Args, ValueHint};
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use super::exit;
+
+#[derive(Args)]
+pub struct MergeArgs {
+    /// Input datasets (raw or delta-encoded, optionally compressed)
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    inputs: Vec<String>,
+
+    /// Output file. Use "-" to write to stdout.
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Sort boards numerically (loads every board into memory)
+    #[arg(long)]
+    sort: bool,
+
+    /// Remove duplicate boards; implies --sort
+    #[arg(long)]
+    dedup: bool,
+
+    /// Replace each board with its canonical form first, so --dedup also removes symmetric boards
+    #[arg(long)]
+    canonicalize: bool,
+
+    /// Maximum number of records per chunk
+    #[arg(long, default_value_t = EncodeOptions::default().chunk_size as u64, value_parser = clap::value_parser!(u64).range(1..))]
+    chunk_size: u64,
+
+    /// Compress each chunk as a separate zstd frame at this level
+    #[arg(long, value_name = "LEVEL", num_args = 0..=1, default_missing_value = "3")]
+    zstd_frames: Option<i32>,
+
+    /// Restart the delta chain at each chunk so chunks can be decoded independently
+    #[arg(long)]
+    restart_delta: bool,
+
+    /// Write an index of chunk offsets, record counts and masks to this file
+    #[arg(long)]
+    index: Option<PathBuf>,
+
+    /// Board size recorded in the output header when no input has a header
+    #[arg(long, value_name = "WxH", default_value_t = BoardGeometry::STANDARD)]
+    board: BoardGeometry,
+
+    /// Fleet recorded in the output header when no input has a header
+    #[arg(long, default_value_t = Fleet::standard())]
+    fleet: Fleet,
+}
+
+pub fn run(args: MergeArgs) -> io::Result<()> {
+    let (headers, inputs): (Vec<_>, Vec<_>) = args.inputs
+        .iter()
+        .map(open_records)
+        .collect::<io::Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+
+    let header = match merge_headers(&headers) {
+        Ok(Some(header)) => DatasetHeader { record_count: None, ..header },
+        Ok(None) => DatasetHeader::new(args.board, args.fleet, true),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(exit::INVALID_INPUT.into());
+        }
+    };
+
+    let merge_options = MergeOptions {
+        sort: args.sort,
+        dedup: args.dedup,
+        canonicalize: args.canonicalize,
+        geometry: header.geometry,
+    };
+    let records = merge_records(inputs, &merge_options)?;
+
+    let options = EncodeOptions {
+        chunk_size: args.chunk_size as usize,
+        restart_delta: args.restart_delta,
+        zstd_frames: args.zstd_frames,
+        header: Some(header),
+    };
+
+    let index = if args.output == "-" {
+        let mut writer = BufWriter::new(io::stdout().lock());
+        let index = encode_records(records, &mut writer, &options)?;
+        writer.flush()?;
+        index
+    } else {
+        let mut writer = BufWriter::new(File::create(&args.output)?);
+        let index = encode_records(records, &mut writer, &options)?;
+
+        // Fill in the record count now that it's known
+        let mut file = writer.into_inner().map_err(|e| e.into_error())?;
+        let header = DatasetHeader { record_count: Some(index.record_count()), ..options.output_header().unwrap() };
+        file.seek(SeekFrom::Start(0))?;
+        header.write_to(&mut file)?;
+        index
+    };
+
+    eprintln!("Merged {} records in {} chunks ({} bytes)", index.record_count(), index.chunks.len(), index.data_len);
+
+    if let Some(path) = args.index {
+        index.save(&path)?;
+        eprintln!("Wrote index to {}", path.display());
+    }
+
+    Ok(())
+}
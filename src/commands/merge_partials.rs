@@ -0,0 +1,58 @@
+use battleship::core::partial::PartialResult;
+use clap::Args;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct MergePartialsArgs {
+    /// Partial results written by `filter --emit-partial`, all for the same query
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Also write the merged counts as a partial result, so they can be merged again
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Print the fraction of matched boards with a ship in each cell instead of raw counts
+    #[arg(long)]
+    probabilities: bool,
+}
+
+pub fn run(args: MergePartialsArgs) -> io::Result<()> {
+    let mut merged: Option<PartialResult> = None;
+
+    for path in &args.inputs {
+        let partial = PartialResult::load(path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+        match &mut merged {
+            Some(merged) => merged
+                .merge(&partial)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?,
+            None => merged = Some(partial),
+        }
+    }
+    let merged = merged.expect("clap requires at least one input");
+
+    eprintln!("Merged {} partial results", args.inputs.len());
+    eprintln!("Matched boards: {}", merged.matched);
+
+    if let Some(path) = &args.output {
+        merged.save(path)?;
+        eprintln!("Wrote merged counts to {}", path.display());
+    }
+
+    let width = merged.geometry.width();
+    for row in merged.counts.chunks(width) {
+        for (x, &count) in row.iter().enumerate() {
+            let separator = if x < width - 1 { "," } else { "" };
+            if args.probabilities {
+                let probability = if merged.matched == 0 { 0.0 } else { count as f64 / merged.matched as f64 };
+                print!("{:.6}{}", probability, separator);
+            } else {
+                print!("{}{}", count, separator);
+            }
+        }
+        println!();
+    }
+    Ok(())
+}
@@ -0,0 +1,302 @@
+use battleship::core::geometry::BoardGeometry;
+use battleship::generator::board_mask::BoardMask;
+use battleship::generator::board_state::BoardState;
+use std::path::Path;
+
+pub mod canonicalize;
+pub mod completions;
+pub mod config;
+pub mod daemon;
+pub mod decode;
+pub mod duplicates;
+pub mod encode;
+pub mod estimate;
+pub mod exit;
+#[cfg(feature = "parquet")]
+pub mod export;
+pub mod filter;
+pub mod generate;
+pub mod inspect;
+pub mod merge;
+pub mod merge_partials;
+pub mod play;
+pub mod progress;
+pub mod rollup;
+#[cfg(feature = "server")]
+pub mod serve;
+#[cfg(feature = "grpc")]
+pub mod serve_grpc;
+pub mod shuffle;
+pub mod sort;
+pub mod split;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod symmetries;
+pub mod verify;
+
+/// Parses a mask given as a cell list (e.g., "A3,B3,C3") or as hex. Hex with a 0x prefix is
+/// always read as hex. Without it, values that read as cells on the board with uppercase row
+/// letters (like "A3") are cells, and anything else that's valid hex (like "a1" or "A1000") is
+/// hex, as it was before cell lists were accepted.
+pub fn parse_mask(flag: &str, value: &str, geometry: BoardGeometry) -> std::io::Result<u128> {
+    let invalid = |message: String| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{}: {}", flag, message));
+
+    if let Some(hex) = value.strip_prefix("0x") {
+        return u128::from_str_radix(hex, 16).map_err(|e| invalid(format!("invalid hex mask '{}': {}", value, e)));
+    }
+
+    let cells = BoardMask::from_coordinates_with_geometry(value, geometry);
+    let mut tokens = value.split(|c: char| c == ',' || c.is_whitespace()).filter(|c| !c.is_empty());
+    if cells.is_ok() && tokens.all(|cell| cell.starts_with(|c: char| c.is_ascii_uppercase())) {
+        return cells.map(|mask| mask.raw_value()).map_err(invalid);
+    }
+
+    if let Ok(mask) = u128::from_str_radix(value, 16) {
+        return Ok(mask);
+    }
+
+    // Not hex either, like a lowercase list "a3,b3" or a cell that's off the board
+    cells.map(|mask| mask.raw_value()).map_err(invalid)
+}
+
+/// Reads the hit and miss masks of a position from `path`: either a JSON object with `hit` and
+/// `miss` cells, each a string [`parse_mask`] accepts or an array of cell names, or a board drawn
+/// as text, as [`BoardState::from_ascii`] reads it, which must be of `geometry`.
+pub fn read_state(path: &Path, geometry: BoardGeometry) -> std::io::Result<(u128, u128)> {
+    let invalid = |message: String| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("--state {}: {}", path.display(), message))
+    };
+    let text = std::fs::read_to_string(path)?;
+
+    if !text.trim_start().starts_with('{') {
+        let board = BoardState::from_ascii(&text).map_err(invalid)?;
+        if board.geometry() != geometry {
+            return Err(invalid(format!("board is {}, but the records are {}", board.geometry(), geometry)));
+        }
+        return Ok((board.hit_mask().raw_value(), board.miss_mask().raw_value()));
+    }
+
+    let (mut hit, mut miss) = (0, 0);
+    for (key, cells) in parse_state_json(&text).map_err(invalid)? {
+        let mask = parse_mask(&format!("--state {} \"{}\"", path.display(), key), &cells, geometry)?;
+        match key.as_str() {
+            "hit" => hit |= mask,
+            "miss" => miss |= mask,
+            other => return Err(invalid(format!("unknown key \"{}\", expected \"hit\" or \"miss\"", other))),
+        }
+    }
+    Ok((hit, miss))
+}
+
+/// Expands the `--file` arguments into the data files to read, in order.
+///
+/// `*` and `?` in a path's file name match any run of characters and any single character, and
+/// are expanded to the matching files in sorted order, e.g. `data/shards/*.zst`. Other paths,
+/// stdin ("-") and URLs are passed through unchanged.
+pub fn expand_files(patterns: &[String]) -> std::io::Result<Vec<String>> {
+    let mut files = Vec::new();
+
+    for pattern in patterns {
+        let is_pattern = pattern.contains(['*', '?']);
+        if !is_pattern || pattern == "-" || battleship::core::reader::is_remote(pattern) {
+            files.push(pattern.clone());
+            continue;
+        }
+
+        let path = Path::new(pattern);
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+        if dir.to_string_lossy().contains(['*', '?']) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("--file {}: wildcards are only supported in the file name", pattern),
+            ));
+        }
+
+        let mut matches = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() && matches_wildcard(&name, &entry.file_name().to_string_lossy()) {
+                matches.push(path.with_file_name(entry.file_name()).to_string_lossy().into_owned());
+            }
+        }
+
+        if matches.is_empty() {
+            return Err(std::io::Error::new(std::io::ErrorKind::NotFound, format!("--file {}: no files match", pattern)));
+        }
+        matches.sort();
+        files.extend(matches);
+    }
+
+    Ok(files)
+}
+
+/// Whether `name` matches `pattern`, where `*` matches any run of characters and `?` any one.
+fn matches_wildcard(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    // Where the last `*` was, and the name position it's currently matched up to
+    let mut star = None;
+    let (mut p, mut n) = (0, 0);
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the last `*` swallow one more character and retry from there
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Reads a flat JSON object whose values are strings or arrays of strings, joining the strings of
+/// an array with commas. Cell names and hex masks never need escapes, so those aren't supported.
+fn parse_state_json(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut json = JsonCursor { chars: text.chars().peekable() };
+    let mut entries = Vec::new();
+
+    json.expect('{')?;
+    while !json.next_is('}') {
+        if !entries.is_empty() {
+            json.expect(',')?;
+        }
+        let key = json.string()?;
+        json.expect(':')?;
+
+        let value = if json.next_is('[') {
+            json.expect('[')?;
+            let mut cells = Vec::new();
+            while !json.next_is(']') {
+                if !cells.is_empty() {
+                    json.expect(',')?;
+                }
+                cells.push(json.string()?);
+            }
+            json.expect(']')?;
+            cells.join(",")
+        } else {
+            json.string()?
+        };
+        entries.push((key, value));
+    }
+
+    Ok(entries)
+}
+
+struct JsonCursor<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl JsonCursor<'_> {
+    /// Whether the next character, after any whitespace, is `c`.
+    fn next_is(&mut self, c: char) -> bool {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+        self.chars.peek() == Some(&c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.next_is(expected);
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!("expected '{}' but found '{}'", expected, c)),
+            None => Err(format!("expected '{}' but the file ended", expected)),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut value = String::new();
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(value),
+                Some(c) => value.push(c),
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_state_from_json_and_ascii() {
+        let dir = std::env::temp_dir().join(format!("battleship-state-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let json = dir.join("state.json");
+        std::fs::write(&json, r#"{ "hit": "A1, A2", "miss": ["B3", "C1"] }"#).unwrap();
+        let geometry = BoardGeometry::new(3, 3);
+        assert_eq!(read_state(&json, geometry).unwrap(), (0b11, 1 << 5 | 1 << 6));
+
+        let ascii = dir.join("state.txt");
+        std::fs::write(&ascii, "XX·\n··•\n•··\n").unwrap();
+        assert_eq!(read_state(&ascii, geometry).unwrap(), (0b11, 1 << 5 | 1 << 6));
+        assert!(read_state(&ascii, BoardGeometry::STANDARD).is_err());
+
+        std::fs::write(&json, r#"{"hits": "A1"}"#).unwrap();
+        assert!(read_state(&json, geometry).is_err());
+        std::fs::write(&json, r#"{"hit": "A1""#).unwrap();
+        assert!(read_state(&json, geometry).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_mask_cells_and_hex() {
+        let geometry = BoardGeometry::STANDARD;
+        assert_eq!(parse_mask("--hit", "A1,A3", geometry).unwrap(), 0b101);
+        assert_eq!(parse_mask("--hit", "A1 B1", geometry).unwrap(), 1 | 1 << 9);
+        assert_eq!(parse_mask("--hit", "0xa1", geometry).unwrap(), 0xa1);
+        assert_eq!(parse_mask("--hit", "", geometry).unwrap(), 0);
+
+        // Bare hex keeps its meaning, even when it could be read as cells
+        assert_eq!(parse_mask("--hit", "a1", geometry).unwrap(), 0xa1);
+        assert_eq!(parse_mask("--hit", "b5", geometry).unwrap(), 0xb5);
+        assert_eq!(parse_mask("--hit", "a1000", geometry).unwrap(), 0xa1000);
+        assert_eq!(parse_mask("--hit", "A1000", geometry).unwrap(), 0xa1000);
+        assert_eq!(parse_mask("--hit", "abcdef", geometry).unwrap(), 0xabcdef);
+
+        assert_eq!(parse_mask("--hit", "a1,a3", geometry).unwrap(), 0b101);
+        assert!(parse_mask("--hit", "Z99", geometry).unwrap_err().to_string().contains("outside"));
+        assert!(parse_mask("--hit", "nonsense", geometry).is_err());
+    }
+
+    #[test]
+    fn test_expand_files() {
+        let dir = std::env::temp_dir().join(format!("battleship-shards-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["shard-02.zst", "shard-01.zst", "shard-10.zst", "notes.txt"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+
+        let shard = |name: &str| dir.join(name).to_string_lossy().into_owned();
+        let pattern = dir.join("shard-0?.zst").to_string_lossy().into_owned();
+        assert_eq!(expand_files(&[pattern]).unwrap(), [shard("shard-01.zst"), shard("shard-02.zst")]);
+
+        let pattern = dir.join("*.zst").to_string_lossy().into_owned();
+        let files = expand_files(&[pattern, "-".to_string()]).unwrap();
+        assert_eq!(files, [shard("shard-01.zst"), shard("shard-02.zst"), shard("shard-10.zst"), "-".to_string()]);
+
+        assert!(expand_files(&[dir.join("*.bin").to_string_lossy().into_owned()]).is_err());
+        assert!(matches_wildcard("a*b*c", "aXbYbc"));
+        assert!(!matches_wildcard("a*b", "aXbY"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,162 @@
+use battleship::core::dataset::Dataset;
+use battleship::core::heatmap::{render_heatmap, ColorMode};
+use battleship::generator::board_mask::BoardMask;
+use battleship::generator::board_state::{BoardState, CellState, RenderOptions};
+use battleship::generator::point::Point;
+use battleship::solver::game_state::{GameState, ShotResult};
+use battleship::solver::strategy::{Entropy, HuntTarget, MaxProbability, Random, Strategy};
+use clap::{Args, ValueEnum, ValueHint};
+use std::io::{self, BufRead, Write};
+use std::time::Instant;
+
+#[derive(Args)]
+pub struct PlayArgs {
+    /// Path to the board data file (raw 16-byte masks, optionally zstd compressed)
+    #[arg(short, long, value_hint = ValueHint::FilePath)]
+    file: String,
+
+    /// Use 24-bit colors for the heatmap instead of the 256-color palette
+    #[arg(long)]
+    truecolor: bool,
+
+    /// How to pick the recommended shot
+    #[arg(long, value_enum, default_value_t = StrategyName::MaxProbability)]
+    strategy: StrategyName,
+
+    /// Seed for the random and hunt-target strategies
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum StrategyName {
+    /// The cell most likely to hold a ship
+    MaxProbability,
+    /// The cell whose result is least predictable
+    Entropy,
+    /// Target unsunk hits, otherwise hunt on a lattice
+    HuntTarget,
+    /// Any unknown cell
+    Random,
+}
+
+const HELP: &str = "Commands:
+  <cell> hit     record a hit, e.g. \"B5 hit\" (row letter A-I, column number 1-9)
+  <cell> miss    record a miss, e.g. \"C7 miss\"
+  <cell> sunk    record a hit that sank a ship
+  undo           forget the most recent shot
+  reset          start a new game
+  help           show this message
+  quit           exit";
+
+pub fn run(args: PlayArgs) -> io::Result<()> {
+    let mode = if args.truecolor { ColorMode::TrueColor } else { ColorMode::Ansi256 };
+
+    eprintln!("Loading {}...", args.file);
+    let start = Instant::now();
+    let dataset = Dataset::load(&args.file)?;
+    eprintln!("Loaded {} boards in {:.2?}", dataset.len(), start.elapsed());
+
+    let mut strategy: Box<dyn Strategy + '_> = match args.strategy {
+        StrategyName::MaxProbability => Box::new(MaxProbability::new(&dataset)),
+        StrategyName::Entropy => Box::new(Entropy::new(&dataset)),
+        StrategyName::HuntTarget => Box::new(HuntTarget::new(args.seed)),
+        StrategyName::Random => Box::new(Random::new(args.seed)),
+    };
+    let strategy = strategy.as_mut();
+
+    let mut game = GameState::new();
+
+    print_status(&dataset, &game, mode, strategy);
+    println!("{}", HELP);
+
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => continue,
+            ["quit"] | ["exit"] => break,
+            ["help"] => println!("{}", HELP),
+            ["reset"] => {
+                game = GameState::new();
+                print_status(&dataset, &game, mode, strategy);
+            }
+            ["undo"] => {
+                match game.undo() {
+                    Some(shot) => println!("Removed shot at {}", shot.point),
+                    None => println!("Nothing to undo"),
+                }
+                print_status(&dataset, &game, mode, strategy);
+            }
+            [cell, result] => {
+                let point = match cell.parse::<Point>() {
+                    Ok(point) if BoardMask::contains(point) => point,
+                    _ => {
+                        println!("Invalid cell '{}'", cell);
+                        continue;
+                    }
+                };
+
+                let result = match result.to_ascii_lowercase().as_str() {
+                    "hit" => ShotResult::Hit,
+                    "miss" => ShotResult::Miss,
+                    "sunk" => ShotResult::Sunk,
+                    _ => {
+                        println!("Unknown result '{}', expected hit, miss or sunk", result);
+                        continue;
+                    }
+                };
+
+                if let Err(error) = game.record_shot(point, result) {
+                    println!("Can't record shot: {}", error);
+                    continue;
+                }
+
+                print_status(&dataset, &game, mode, strategy);
+            }
+            _ => println!("Unrecognized command. Type \"help\" for usage."),
+        }
+    }
+
+    Ok(())
+}
+
+fn print_status(dataset: &Dataset, game: &GameState, mode: ColorMode, strategy: &mut dyn Strategy) {
+    let (hit_mask, miss_mask) = game.to_masks();
+    let result = dataset.filter_and_count(hit_mask, miss_mask);
+
+    let mut shots = BoardState::EMPTY;
+    for point in BoardMask::new(hit_mask).iter_points() {
+        shots.set(point, CellState::Hit);
+    }
+    for point in BoardMask::new(miss_mask).iter_points() {
+        shots.set(point, CellState::Miss);
+    }
+    println!("{}", shots.render(RenderOptions { color: true, labels: true, compact: false }));
+
+    print!("{}", render_heatmap(&result.counts, mode));
+    println!("Matching boards: {}", result.matched);
+
+    if result.matched == 0 {
+        println!("No boards match these shots; check for a mistyped result.");
+        return;
+    }
+
+    let sunk = game.sunk_ships().len();
+    if sunk > 0 {
+        println!("Ships sunk: {}", sunk);
+    }
+
+    match strategy.next_shot(game) {
+        Some(point) => println!("Recommended shot: {}", point),
+        None => println!("Every cell has been shot."),
+    }
+}
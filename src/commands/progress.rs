@@ -0,0 +1,156 @@
+use indicatif::{HumanCount, ProgressBar, ProgressDrawTarget, ProgressState, ProgressStyle};
+use std::cell::Cell;
+use std::fmt::Write;
+use std::io;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// Records counted before the progress is updated, so updating it costs nothing per record.
+const BATCH: u64 = 1 << 16;
+
+/// Time between JSON progress events.
+const EVENT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Progress of a scan, shown as a bar on stderr, as newline-delimited JSON events on stderr, or
+/// both. Clones share the same progress, so records of several inputs add up.
+#[derive(Clone)]
+pub struct Progress {
+    bar: ProgressBar,
+    events: Option<Rc<Events>>,
+}
+
+/// What JSON events report, and when the last one was written.
+struct Events {
+    started: Instant,
+    last: Cell<Instant>,
+    records: Cell<u64>,
+    matched: Cell<u64>,
+}
+
+impl Progress {
+    /// Shows a bar when `bar` is set, whose record counts are added with
+    /// [`add_records`](Self::add_records) as each input is opened, and writes a JSON event like
+    /// `{"records":65536,"matched":112,"elapsed":1.024,"done":false}` about once a second when
+    /// `json` is set. Like any indicatif bar, the bar isn't drawn when stderr isn't a terminal.
+    pub fn new(bar: bool, json: bool) -> Self {
+        let bar = if bar {
+            let bar = ProgressBar::with_draw_target(Some(0), ProgressDrawTarget::stderr());
+            bar.set_style(style("{spinner} [{elapsed_precise}] {wide_bar} {human_pos}/{human_len} records ({rate}, {eta} left)"));
+            bar
+        } else {
+            ProgressBar::hidden()
+        };
+
+        let events = json.then(|| {
+            let now = Instant::now();
+            Rc::new(Events { started: now, last: Cell::new(now), records: Cell::new(0), matched: Cell::new(0) })
+        });
+
+        Self { bar, events }
+    }
+
+    /// Adds an input's `records` to the length of the bar, or switches it to a spinner without a
+    /// time left when the count isn't known (e.g., for a dataset without a header).
+    pub fn add_records(&self, records: Option<u64>) {
+        match records {
+            Some(records) => self.bar.inc_length(records),
+            None => self.bar.set_style(style("{spinner} [{elapsed_precise}] {human_pos} records ({rate})")),
+        }
+    }
+
+    /// Counts records into the progress as they're read. `matches` gives the number of matches a
+    /// record adds, and is only called when JSON events are written.
+    pub fn track<I, F>(&self, records: I, matches: F) -> ProgressRecords<I::IntoIter, F>
+    where
+        I: IntoIterator<Item = io::Result<u128>>,
+        F: Fn(u128) -> u64,
+    {
+        ProgressRecords { records: records.into_iter(), matches, progress: self.clone(), pending: 0, pending_matched: 0 }
+    }
+
+    /// Leaves the bar showing the final counts and writes the last JSON event.
+    pub fn finish(&self) {
+        self.bar.finish();
+        if let Some(events) = &self.events {
+            events.write(true);
+        }
+    }
+
+    fn add(&self, records: u64, matched: u64) {
+        self.bar.inc(records);
+        if let Some(events) = &self.events {
+            events.records.set(events.records.get() + records);
+            events.matched.set(events.matched.get() + matched);
+            if events.last.get().elapsed() >= EVENT_INTERVAL {
+                events.write(false);
+            }
+        }
+    }
+}
+
+impl Events {
+    fn write(&self, done: bool) {
+        self.last.set(Instant::now());
+        eprintln!(
+            "{{\"records\":{},\"matched\":{},\"elapsed\":{:.3},\"done\":{}}}",
+            self.records.get(),
+            self.matched.get(),
+            self.started.elapsed().as_secs_f64(),
+            done
+        );
+    }
+}
+
+/// A bar style from `template`, which can show the rate as a whole number of items a second
+/// with `{rate}`.
+pub fn style(template: &str) -> ProgressStyle {
+    ProgressStyle::with_template(template)
+        .expect("valid template")
+        .with_key("rate", |state: &ProgressState, w: &mut dyn Write| {
+            let _ = write!(w, "{}/s", HumanCount(state.per_sec() as u64));
+        })
+}
+
+/// Iterator returned by [`Progress::track`].
+pub struct ProgressRecords<I, F> {
+    records: I,
+    matches: F,
+    progress: Progress,
+    pending: u64,
+    pending_matched: u64,
+}
+
+impl<I, F> ProgressRecords<I, F> {
+    fn flush(&mut self) {
+        self.progress.add(self.pending, self.pending_matched);
+        self.pending = 0;
+        self.pending_matched = 0;
+    }
+}
+
+impl<I: Iterator<Item = io::Result<u128>>, F: Fn(u128) -> u64> Iterator for ProgressRecords<I, F> {
+    type Item = io::Result<u128>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = self.records.next();
+
+        match &record {
+            Some(Ok(board)) if self.progress.events.is_some() => self.pending_matched += (self.matches)(*board),
+            _ => {}
+        }
+        if record.is_some() {
+            self.pending += 1;
+        }
+        if self.pending == BATCH || (record.is_none() && self.pending > 0) {
+            self.flush();
+        }
+
+        record
+    }
+}
+
+impl<I, F> Drop for ProgressRecords<I, F> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
@@ -0,0 +1,41 @@
+use battleship::core::filter::QueryOptions;
+use battleship::core::geometry::BoardGeometry;
+use battleship::core::query::Query;
+use battleship::core::reader::open_records;
+use battleship::core::rollup::RollupTable;
+use clap::{Args, ValueHint};
+use std::io;
+use std::path::PathBuf;
+use std::time::Instant;
+
+#[derive(Args)]
+pub struct RollupArgs {
+    /// Dataset to summarize (raw or delta-encoded, optionally compressed). Use "-" to read from stdin.
+    #[arg(short, long, default_value = "-", value_hint = ValueHint::FilePath)]
+    input: String,
+
+    /// Where to write the table, for use with `filter --rollup`
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Treat records as canonical boards and count every board in each symmetry orbit
+    #[arg(long)]
+    expand_symmetries: bool,
+}
+
+pub fn run(args: RollupArgs) -> io::Result<()> {
+    let start = Instant::now();
+
+    let (header, records) = open_records(&args.input)?;
+    let query = Query::compile(0, 0, BoardGeometry::STANDARD)?;
+    if let Some(header) = header {
+        query.check_dataset(header.geometry, &args.input)?;
+    }
+
+    let options = QueryOptions { expand_symmetries: args.expand_symmetries, ..QueryOptions::default() };
+    let table = RollupTable::build(query.guard(records, &args.input), &options)?;
+    table.save(&args.output)?;
+
+    eprintln!("Wrote the rollup table of {} boards to {} in {:.2?}", table.total, args.output.display(), start.elapsed());
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use battleship::core::filter::FilterResult;
+use battleship::core::geometry::BoardGeometry;
+use battleship::core::query::Query;
+use battleship::core::session::Session;
+use battleship::solver::recommend::top_shots;
+use clap::{Args, ValueHint};
+use std::io;
+use std::time::Instant;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use super::parse_mask;
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Path to the board data file (delta-encoded or raw, optionally compressed)
+    #[arg(short, long, value_hint = ValueHint::FilePath)]
+    file: String,
+
+    /// Index of the data file; matching chunks are decoded on each request instead of loading
+    /// every record into memory
+    #[arg(long)]
+    index: Option<String>,
+
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: String,
+}
+
+pub fn run(args: ServeArgs) -> io::Result<()> {
+    let start = Instant::now();
+    let session = match &args.index {
+        Some(index) => Session::open_indexed(&args.file, index)?,
+        None => Session::open(&args.file)?,
+    };
+    eprintln!("Loaded {} in {:.2?}", args.file, start.elapsed());
+
+    let server = Server::http(&args.listen).map_err(io::Error::other)?;
+    eprintln!("Listening on http://{}", args.listen);
+
+    for request in server.incoming_requests() {
+        let (status, body) = handle(&session, request.method(), request.url());
+        respond(request, status, body);
+    }
+
+    Ok(())
+}
+
+fn respond(request: Request, status: u16, body: String) {
+    let content_type = Header::from_bytes("Content-Type", "application/json").expect("valid header");
+    let response = Response::from_string(body).with_status_code(status).with_header(content_type);
+
+    // The client hanging up early only affects that client
+    if let Err(e) = request.respond(response) {
+        eprintln!("Failed to send response: {}", e);
+    }
+}
+
+/// Routes a request, returning the status code and JSON body of the response.
+fn handle(session: &Session, method: &Method, url: &str) -> (u16, String) {
+    if *method != Method::Get {
+        return (405, error_json("only GET is supported"));
+    }
+
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let run_query = || -> io::Result<(FilterResult, u128)> {
+        let query = parse_query(query)?;
+        let result = session.query(&query)?;
+        Ok((result, query.hit_mask() | query.miss_mask()))
+    };
+
+    let body = match path {
+        "/heatmap" => run_query().map(|(result, _)| heatmap_json(&result)),
+        "/recommend" => run_query().map(|(result, shot)| recommend_json(&result, shot)),
+        _ => return (404, error_json(&format!("no such endpoint '{}'", path))),
+    };
+
+    match body {
+        Ok(body) => (200, body),
+        Err(e) if e.kind() == io::ErrorKind::InvalidInput => (400, error_json(&e.to_string())),
+        Err(e) => (500, error_json(&e.to_string())),
+    }
+}
+
+/// Reads the `hit` and `miss` parameters of a query string. Either may be left out when there
+/// are no shots of that kind.
+fn parse_query(query: &str) -> io::Result<Query> {
+    let mut hit_mask = 0;
+    let mut miss_mask = 0;
+
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(value)?;
+        match key {
+            "hit" => hit_mask = parse_mask("hit", &value, BoardGeometry::STANDARD)?,
+            "miss" => miss_mask = parse_mask("miss", &value, BoardGeometry::STANDARD)?,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("unknown parameter '{}'", key))),
+        }
+    }
+
+    Ok(Query::compile(hit_mask, miss_mask, BoardGeometry::STANDARD)?)
+}
+
+/// Decodes `%XX` escapes and `+` (for the commas and spaces of cell lists).
+fn percent_decode(value: &str) -> io::Result<String> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("invalid escape in '{}'", value));
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = bytes.get(i + 1..i + 3).ok_or_else(invalid)?;
+                let hex = std::str::from_utf8(hex).map_err(|_| invalid())?;
+                decoded.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+                i += 3;
+            }
+            b'+' => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded).map_err(|_| invalid())
+}
+
+fn heatmap_json(result: &FilterResult) -> String {
+    let counts = result.counts.iter().map(|count| count.to_string()).collect::<Vec<_>>().join(",");
+    let probabilities = result.probabilities().iter().map(|p| format!("{:.6}", p)).collect::<Vec<_>>().join(",");
+    format!("{{\"matched\":{},\"counts\":[{}],\"probabilities\":[{}]}}", result.matched, counts, probabilities)
+}
+
+/// The most likely unshot cell, or `null` when no boards match or every cell has been shot.
+fn recommend_json(result: &FilterResult, already_shot: u128) -> String {
+    let best = top_shots(&result.counts, already_shot, 1).into_iter().next().filter(|_| result.matched > 0);
+
+    let shot = match best {
+        Some((point, count)) => format!(
+            "{{\"cell\":\"{}\",\"x\":{},\"y\":{},\"count\":{},\"probability\":{:.6}}}",
+            point,
+            point.x,
+            point.y,
+            count,
+            count as f64 / result.matched as f64,
+        ),
+        None => "null".to_string(),
+    };
+
+    format!("{{\"matched\":{},\"shot\":{}}}", result.matched, shot)
+}
+
+fn error_json(message: &str) -> String {
+    let escaped = message.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("{{\"error\":\"{}\"}}", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use battleship::core::dataset::Dataset;
+
+    #[test]
+    fn test_serve_routes() {
+        let session = Session::Decoded(Dataset::new(vec![0b011, 0b110, 0b1_0000]));
+
+        let (status, body) = handle(&session, &Method::Get, "/heatmap?hit=A2&miss=");
+        assert_eq!(status, 200);
+        assert!(body.starts_with("{\"matched\":2,\"counts\":[1,2,1,0,"), "{}", body);
+
+        let (status, body) = handle(&session, &Method::Get, "/recommend?hit=A2&miss=A3");
+        assert_eq!(status, 200);
+        assert_eq!(body, "{\"matched\":1,\"shot\":{\"cell\":\"A1\",\"x\":0,\"y\":0,\"count\":1,\"probability\":1.000000}}");
+
+        // Cell lists with an encoded comma
+        let (status, body) = handle(&session, &Method::Get, "/heatmap?hit=A1%2CA2");
+        assert_eq!(status, 200);
+        assert!(body.starts_with("{\"matched\":1,"), "{}", body);
+
+        assert_eq!(handle(&session, &Method::Get, "/heatmap?hit=A1&miss=A1").0, 400);
+        assert_eq!(handle(&session, &Method::Get, "/heatmap?hit=Z99").0, 400);
+        assert_eq!(handle(&session, &Method::Get, "/heatmap?board=10x10").0, 400);
+        assert_eq!(handle(&session, &Method::Get, "/nowhere").0, 404);
+        assert_eq!(handle(&session, &Method::Post, "/heatmap").0, 405);
+    }
+}
@@ -0,0 +1,35 @@
+use battleship::core::grpc::serve;
+use battleship::core::session::Session;
+use clap::{Args, ValueHint};
+use std::io;
+use std::net::SocketAddr;
+use std::time::Instant;
+
+#[derive(Args)]
+pub struct ServeGrpcArgs {
+    /// Path to the board data file (delta-encoded or raw, optionally compressed)
+    #[arg(short, long, value_hint = ValueHint::FilePath)]
+    file: String,
+
+    /// Index of the data file; matching chunks are decoded on each request instead of loading
+    /// every record into memory
+    #[arg(long)]
+    index: Option<String>,
+
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:50051")]
+    listen: SocketAddr,
+}
+
+pub fn run(args: ServeGrpcArgs) -> io::Result<()> {
+    let start = Instant::now();
+    let session = match &args.index {
+        Some(index) => Session::open_indexed(&args.file, index)?,
+        None => Session::open(&args.file)?,
+    };
+    eprintln!("Loaded {} in {:.2?}", args.file, start.elapsed());
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    eprintln!("Serving gRPC on {}", args.listen);
+    runtime.block_on(serve(session, args.listen)).map_err(io::Error::other)
+}
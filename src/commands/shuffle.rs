@@ -0,0 +1,63 @@
+use battleship::core::header::read_optional_header;
+use battleship::core::shuffle::shuffle_records;
+use clap::{Args, ValueHint};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+
+use super::exit;
+
+#[derive(Args)]
+pub struct ShuffleArgs {
+    /// Input file of raw records. Use "-" to read from stdin.
+    #[arg(short, long, default_value = "-", value_hint = ValueHint::FilePath)]
+    input: String,
+
+    /// Output file for the shuffled records. Use "-" to write to stdout.
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Seed for the permutation; the same seed always produces the same order
+    #[arg(short, long, default_value_t = 0)]
+    seed: u64,
+
+    /// Maximum number of records to shuffle in memory before spilling to temporary files
+    #[arg(long, default_value_t = 50_000_000, value_parser = clap::value_parser!(u64).range(1..))]
+    memory_records: u64,
+
+    /// Directory for temporary bucket files (defaults to the system temp directory)
+    #[arg(long)]
+    temp_dir: Option<PathBuf>,
+}
+
+pub fn run(args: ShuffleArgs) -> io::Result<()> {
+    let reader: Box<dyn Read> = if args.input == "-" {
+        Box::new(BufReader::new(io::stdin().lock()))
+    } else {
+        Box::new(BufReader::new(File::open(&args.input)?))
+    };
+
+    // Checked before the output is created, so a rejected run leaves it alone
+    let (header, mut reader) = read_optional_header(reader)?;
+    if header.as_ref().is_some_and(|header| header.delta_encoded) {
+        eprintln!("Input is delta-encoded; shuffle the generator's raw output before encoding it");
+        std::process::exit(exit::INVALID_INPUT.into());
+    }
+
+    let mut writer: Box<dyn Write> = if args.output == "-" {
+        Box::new(BufWriter::new(io::stdout().lock()))
+    } else {
+        Box::new(BufWriter::new(File::create(&args.output)?))
+    };
+
+    // A header from the generator is passed through unchanged
+    if let Some(header) = header {
+        header.write_to(&mut writer)?;
+    }
+
+    let temp_dir = args.temp_dir.unwrap_or_else(std::env::temp_dir);
+    let count = shuffle_records(&mut reader, &mut writer, args.seed, args.memory_records as usize, &temp_dir)?;
+
+    eprintln!("Shuffled {} records", count);
+    Ok(())
+}
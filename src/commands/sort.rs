@@ -0,0 +1,47 @@
+use battleship::core::header::DatasetHeader;
+use battleship::core::reader::open_records;
+use battleship::core::sort::sort_records;
+use clap::{Args, ValueHint};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct SortArgs {
+    /// Input dataset (raw or delta-encoded, optionally compressed). Use "-" to read from stdin.
+    #[arg(short, long, default_value = "-", value_hint = ValueHint::FilePath)]
+    input: String,
+
+    /// Output file for the sorted raw records. Use "-" to write to stdout.
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Maximum number of records to sort in memory before spilling sorted runs to temporary files
+    #[arg(long, default_value_t = 50_000_000, value_parser = clap::value_parser!(u64).range(1..))]
+    memory_records: u64,
+
+    /// Directory for temporary run files (defaults to the system temp directory)
+    #[arg(long)]
+    temp_dir: Option<PathBuf>,
+}
+
+pub fn run(args: SortArgs) -> io::Result<()> {
+    let (header, records) = open_records(&args.input)?;
+
+    let mut writer: Box<dyn Write> = if args.output == "-" {
+        Box::new(BufWriter::new(io::stdout().lock()))
+    } else {
+        Box::new(BufWriter::new(File::create(&args.output)?))
+    };
+
+    // The output is raw, whatever the input's encoding
+    if let Some(header) = header {
+        DatasetHeader { delta_encoded: false, restart_interval: None, ..header }.write_to(&mut writer)?;
+    }
+
+    let temp_dir = args.temp_dir.unwrap_or_else(std::env::temp_dir);
+    let count = sort_records(records, &mut writer, args.memory_records as usize, &temp_dir)?;
+
+    eprintln!("Sorted {} records", count);
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use battleship::core::geometry::BoardGeometry;
+use battleship::core::header::DatasetHeader;
+use battleship::core::reader::open_records;
+use battleship::core::split::{split_records, SplitMode};
+use battleship::generator::Fleet;
+use clap::{Args, ValueEnum, ValueHint};
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom};
+
+use super::exit;
+
+#[derive(Args)]
+pub struct SplitArgs {
+    /// Input dataset (raw or delta-encoded, optionally compressed). Use "-" to read from stdin.
+    #[arg(short, long, default_value = "-", value_hint = ValueHint::FilePath)]
+    input: String,
+
+    /// Number of shards to write
+    #[arg(short = 'n', long, value_parser = clap::value_parser!(u64).range(1..))]
+    shards: u64,
+
+    /// Shard files are written to PREFIX-000.bin, PREFIX-001.bin, ...
+    #[arg(short, long)]
+    prefix: String,
+
+    /// How records are assigned to shards
+    #[arg(long, value_enum, default_value_t = Mode::RoundRobin)]
+    mode: Mode,
+
+    /// Board size recorded in the shard headers when the input has no header
+    #[arg(long, value_name = "WxH", default_value_t = BoardGeometry::STANDARD)]
+    board: BoardGeometry,
+
+    /// Fleet recorded in the shard headers when the input has no header
+    #[arg(long, default_value_t = Fleet::standard())]
+    fleet: Fleet,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Mode {
+    /// Deal records to shards in turn
+    RoundRobin,
+    /// Give each shard one contiguous range of records
+    Ranges,
+}
+
+pub fn run(args: SplitArgs) -> io::Result<()> {
+    let (input_header, records) = open_records(&args.input)?;
+
+    let mode = match args.mode {
+        Mode::RoundRobin => SplitMode::RoundRobin,
+        Mode::Ranges => {
+            let total = match input_header.as_ref().and_then(|header| header.record_count) {
+                Some(total) => total,
+                None if args.input == "-" => {
+                    eprintln!("--mode ranges needs a record count; stdin input must have a header with one");
+                    std::process::exit(exit::INVALID_INPUT.into());
+                }
+                // Count in a first pass, since the header doesn't say
+                None => open_records(&args.input)?.1.try_fold(0u64, |count, record| record.map(|_| count + 1))?,
+            };
+            SplitMode::Ranges { total }
+        }
+    };
+
+    let header = match input_header {
+        Some(header) => DatasetHeader { delta_encoded: true, record_count: None, restart_interval: None, ..header },
+        None => DatasetHeader::new(args.board, args.fleet, true),
+    };
+
+    let paths: Vec<String> = (0..args.shards).map(|i| format!("{}-{:03}.bin", args.prefix, i)).collect();
+    let mut writers = paths
+        .iter()
+        .map(|path| {
+            let mut writer = BufWriter::new(File::create(path)?);
+            header.write_to(&mut writer)?;
+            Ok(writer)
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let counts = split_records(records, &mut writers, mode)?;
+
+    // Fill in each shard's record count now that it's known
+    for ((writer, count), path) in writers.into_iter().zip(&counts).zip(&paths) {
+        let mut file = writer.into_inner().map_err(|e| e.into_error())?;
+        file.seek(SeekFrom::Start(0))?;
+        DatasetHeader { record_count: Some(*count), ..header.clone() }.write_to(&mut file)?;
+        eprintln!("Wrote {} records to {}", count, path);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use battleship::core::filter::{PopcountRange, QueryOptions};
+use battleship::core::geometry::BoardGeometry;
+use battleship::core::query::Query;
+use battleship::core::reader::open_records_with_threads;
+use battleship::core::sqlite::SqliteStore;
+use clap::{Args, Subcommand};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::time::Instant;
+
+use super::parse_mask;
+
+#[derive(Args)]
+pub struct SqliteArgs {
+    #[command(subcommand)]
+    action: SqliteAction,
+}
+
+#[derive(Subcommand)]
+enum SqliteAction {
+    /// Add the records of a dataset to a database, creating it if needed
+    Import {
+        /// Input dataset (delta-encoded or raw, optionally compressed). Use "-" to read from stdin.
+        #[arg(short, long, default_value = "-")]
+        input: String,
+
+        /// Database file
+        #[arg(short, long)]
+        database: String,
+
+        /// Board dimensions of the records (e.g., 9x9 or 10x10); a header in the input takes precedence
+        #[arg(long, value_name = "WxH", default_value_t = BoardGeometry::STANDARD)]
+        board: BoardGeometry,
+    },
+    /// Write the boards of a database as raw 16-byte records
+    Export {
+        /// Database file
+        #[arg(short, long)]
+        database: String,
+
+        /// Output file for the raw records. Use "-" to write to stdout.
+        #[arg(short, long, default_value = "-")]
+        output: String,
+    },
+    /// Count ship hit frequencies over the boards of a database matching a query
+    Filter {
+        /// Database file
+        #[arg(short, long)]
+        database: String,
+
+        /// Hit cells as a list like A3,B3,C3 (row letter, column number) or a hex mask like 0xabcdef
+        #[arg(long, value_name = "CELLS|HEX")]
+        hit: String,
+
+        /// Miss cells as a list like D7,E2 or a hex mask
+        #[arg(short, long, value_name = "CELLS|HEX")]
+        miss: String,
+
+        /// Treat boards as canonical and count every board in each symmetry orbit
+        #[arg(long)]
+        expand_symmetries: bool,
+
+        /// Only count boards with this many ship cells (e.g., 27, or a range like 20-30)
+        #[arg(long, value_name = "N|MIN-MAX")]
+        popcount: Option<PopcountRange>,
+    },
+}
+
+pub fn run(args: SqliteArgs) -> io::Result<()> {
+    let start = Instant::now();
+
+    match args.action {
+        SqliteAction::Import { input, database, board } => {
+            let (header, records) = open_records_with_threads(&input, 1)?;
+            let geometry = header.map_or(board, |header| header.geometry);
+            let query = Query::compile(0, 0, geometry)?;
+
+            let mut store = SqliteStore::create(&database, geometry)?;
+            let imported = store.import(query.guard(records, &input))?;
+            eprintln!("Imported {} boards into {} in {:.2?}", imported, database, start.elapsed());
+        }
+
+        SqliteAction::Export { database, output } => {
+            let store = SqliteStore::open(&database)?;
+            let mut writer: Box<dyn Write> = if output == "-" {
+                Box::new(BufWriter::new(io::stdout().lock()))
+            } else {
+                Box::new(BufWriter::new(File::create(&output)?))
+            };
+
+            let exported = store.export(&mut writer)?;
+            writer.flush()?;
+            eprintln!("Exported {} boards in {:.2?}", exported, start.elapsed());
+        }
+
+        SqliteAction::Filter { database, hit, miss, expand_symmetries, popcount } => {
+            let store = SqliteStore::open(&database)?;
+            let geometry = store.geometry();
+            let query = Query::compile(parse_mask("--hit", &hit, geometry)?, parse_mask("--miss", &miss, geometry)?, geometry)?;
+            let options = QueryOptions { expand_symmetries, popcount, geometry, ..QueryOptions::default() };
+
+            let (counts, matched) = store.filter_and_count(query.hit_mask(), query.miss_mask(), &options)?;
+            eprintln!("Matched boards: {} in {:.2?}", matched, start.elapsed());
+
+            let width = geometry.width();
+            for row in counts.chunks(width) {
+                for (x, count) in row.iter().enumerate() {
+                    print!("{}{}", count, if x < width - 1 { "," } else { "" });
+                }
+                println!();
+            }
+        }
+    }
+
+    Ok(())
+}
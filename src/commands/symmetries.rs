@@ -0,0 +1,47 @@
+use battleship::core::accumulator::{SymmetryClasses, SymmetryStats};
+use battleship::core::filter::{filter_and_accumulate, QueryOptions};
+use battleship::core::geometry::BoardGeometry;
+use battleship::core::reader::open_records;
+use clap::{Args, ValueHint};
+use std::io;
+
+use super::exit;
+
+#[derive(Args)]
+pub struct SymmetriesArgs {
+    /// Dataset to scan (raw or delta-encoded, optionally compressed). Use "-" to read from stdin.
+    #[arg(short, long, default_value = "-", value_hint = ValueHint::FilePath)]
+    input: String,
+
+    /// Board size of the records when the dataset has no header
+    #[arg(long, value_name = "WxH", default_value_t = BoardGeometry::STANDARD)]
+    board: BoardGeometry,
+
+    /// Exit with status 1 unless every record is canonical
+    #[arg(long)]
+    require_canonical: bool,
+}
+
+pub fn run(args: SymmetriesArgs) -> io::Result<()> {
+    let (header, records) = open_records(&args.input)?;
+    let geometry = header.map_or(args.board, |header| header.geometry);
+
+    let options = QueryOptions { geometry, ..QueryOptions::default() };
+    let stats = filter_and_accumulate(records, 0, 0, &options, || SymmetryClasses::new(geometry))?;
+
+    print_stats(&stats);
+
+    if args.require_canonical && !stats.all_canonical() {
+        std::process::exit(exit::NO_MATCHES.into());
+    }
+    Ok(())
+}
+
+fn print_stats(stats: &SymmetryStats) {
+    println!("Records: {}", stats.records);
+    for (size, &count) in stats.orbit_sizes.iter().enumerate().filter(|(size, _)| size.is_power_of_two()) {
+        println!("Orbit size {}: {}", size, count);
+    }
+    println!("Canonical records: {} of {}", stats.canonical, stats.records);
+    println!("Boards covered by canonical records: {}", stats.expanded_boards);
+}
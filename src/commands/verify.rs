@@ -0,0 +1,61 @@
+use battleship::core::geometry::{BoardGeometry, RECORD_SIZE};
+use battleship::core::reader::open_records;
+use battleship::generator::verify::verify_board;
+use battleship::generator::Fleet;
+use clap::{Args, ValueHint};
+use std::io;
+
+use super::exit;
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// Dataset to check (raw or delta-encoded, optionally compressed). Use "-" to read from stdin.
+    #[arg(short, long, default_value = "-", value_hint = ValueHint::FilePath)]
+    input: String,
+
+    /// Board size of the records when the dataset has no header
+    #[arg(long, value_name = "WxH", default_value_t = BoardGeometry::STANDARD)]
+    board: BoardGeometry,
+
+    /// Fleet every record should contain when the dataset has no header
+    #[arg(long, default_value_t = Fleet::standard())]
+    fleet: Fleet,
+}
+
+pub fn run(args: VerifyArgs) -> io::Result<()> {
+    let (header, records) = open_records(&args.input)?;
+    let (geometry, fleet) = header.as_ref().map_or((args.board, args.fleet), |header| (header.geometry, header.fleet.clone()));
+
+    let mut count = 0u64;
+    for record in records {
+        let board = match record {
+            Ok(board) => board,
+            Err(e) => fail(&format!("Error after record {}: {}", count, e)),
+        };
+
+        if let Err(reason) = verify_board(board, geometry, &fleet) {
+            fail(&format!(
+                "Record {} (offset {}) is invalid: {} (0x{:032x})",
+                count,
+                count * RECORD_SIZE as u64,
+                reason,
+                board,
+            ));
+        }
+        count += 1;
+    }
+
+    if let Some(expected) = header.and_then(|header| header.record_count) {
+        if expected != count {
+            fail(&format!("Header says {} records, but the dataset has {}", expected, count));
+        }
+    }
+
+    println!("All {} records are valid {} boards with fleet {}", count, geometry, fleet);
+    Ok(())
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(exit::NO_MATCHES.into());
+}
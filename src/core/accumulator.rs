@@ -0,0 +1,342 @@
+use crate::core::bitslice::BitsliceBatch;
+use crate::core::geometry::BoardGeometry;
+use crate::generator::symmetries::distinct_symmetries_with_geometry;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// An analysis that consumes matched boards during a filter scan.
+///
+/// The filter pipeline creates one accumulator per parallel worker, calls [`visit`](Self::visit)
+/// for every matched board, combines the workers with [`merge`](Self::merge), and finally
+/// calls [`finish`](Self::finish) to produce the result.
+pub trait CountAccumulator: Send {
+    type Output;
+
+    /// Records a single matched board.
+    fn visit(&mut self, board: u128);
+
+    /// Records the boards of `batch` whose bits are set in `matches`, during a
+    /// [`ScanMode::Bitsliced`](crate::core::filter::ScanMode::Bitsliced) scan. The default visits
+    /// them one by one; accumulators that can count straight from the lanes override it.
+    fn visit_batch(&mut self, batch: &BitsliceBatch, matches: u64) {
+        for board in batch.matched_records(matches) {
+            self.visit(board);
+        }
+    }
+
+    /// Folds another accumulator's state into this one.
+    fn merge(&mut self, other: Self);
+
+    /// Consumes the accumulator and returns its result.
+    fn finish(self) -> Self::Output;
+}
+
+/// Per-cell ship counts and the total number of matched boards.
+#[derive(Debug, Clone)]
+pub struct CellCounts {
+    counts: [u32; 81],
+    matched: u64,
+}
+
+impl CellCounts {
+    pub fn new() -> Self {
+        Self { counts: [0; 81], matched: 0 }
+    }
+}
+
+impl Default for CellCounts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CountAccumulator for CellCounts {
+    type Output = ([u32; 81], u64);
+
+    fn visit(&mut self, board: u128) {
+        self.matched += 1;
+
+        // Count hits per cell (only consider bits 0-80 for 81-cell board)
+        let mut mask = board & ((1u128 << 81) - 1);
+        while mask != 0 {
+            let bit = mask.trailing_zeros() as usize;
+            self.counts[bit] += 1;
+            mask &= mask - 1; // Faster way to clear lowest set bit
+        }
+    }
+
+    fn visit_batch(&mut self, batch: &BitsliceBatch, matches: u64) {
+        self.matched += matches.count_ones() as u64;
+        for (cell, count) in self.counts.iter_mut().enumerate() {
+            *count += (batch.lane(cell) & matches).count_ones();
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        for (count, other_count) in self.counts.iter_mut().zip(other.counts) {
+            *count += other_count;
+        }
+        self.matched += other.matched;
+    }
+
+    fn finish(self) -> Self::Output {
+        (self.counts, self.matched)
+    }
+}
+
+/// Per-cell ship counts for a board of any geometry, and the total number of matched boards.
+///
+/// [`CellCounts`] is the fixed-size equivalent for the standard 9x9 board.
+#[derive(Debug, Clone)]
+pub struct GeometryCellCounts {
+    geometry: BoardGeometry,
+    counts: Vec<u32>,
+    matched: u64,
+}
+
+impl GeometryCellCounts {
+    pub fn new(geometry: BoardGeometry) -> Self {
+        Self { geometry, counts: vec![0; geometry.cells()], matched: 0 }
+    }
+}
+
+impl CountAccumulator for GeometryCellCounts {
+    type Output = (Vec<u32>, u64);
+
+    fn visit(&mut self, board: u128) {
+        self.matched += 1;
+
+        let mut mask = board & self.geometry.cell_mask();
+        while mask != 0 {
+            let bit = mask.trailing_zeros() as usize;
+            self.counts[bit] += 1;
+            mask &= mask - 1;
+        }
+    }
+
+    fn visit_batch(&mut self, batch: &BitsliceBatch, matches: u64) {
+        self.matched += matches.count_ones() as u64;
+        for (cell, count) in self.counts.iter_mut().enumerate() {
+            *count += (batch.lane(cell) & matches).count_ones();
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        for (count, other_count) in self.counts.iter_mut().zip(other.counts) {
+            *count += other_count;
+        }
+        self.matched += other.matched;
+    }
+
+    fn finish(self) -> Self::Output {
+        (self.counts, self.matched)
+    }
+}
+
+/// Counts matched boards without looking at their cells.
+#[derive(Debug, Clone, Default)]
+pub struct MatchCount {
+    matched: u64,
+}
+
+impl MatchCount {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CountAccumulator for MatchCount {
+    type Output = u64;
+
+    fn visit(&mut self, _board: u128) {
+        self.matched += 1;
+    }
+
+    fn visit_batch(&mut self, _batch: &BitsliceBatch, matches: u64) {
+        self.matched += matches.count_ones() as u64;
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.matched += other.matched;
+    }
+
+    fn finish(self) -> Self::Output {
+        self.matched
+    }
+}
+
+/// How the matched boards are spread over symmetry orbits, from [`SymmetryClasses`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SymmetryStats {
+    pub records: u64,
+    /// Records that are the canonical (smallest) board of their orbit.
+    pub canonical: u64,
+    /// Number of records whose orbit has each size, indexed by size. Only sizes 1, 2, 4 and 8
+    /// can occur (1, 2 and 4 on rectangular boards).
+    pub orbit_sizes: [u64; 9],
+    /// Total size of the orbits of the canonical records, i.e. how many boards a
+    /// canonical-only dataset expands to.
+    pub expanded_boards: u64,
+}
+
+impl SymmetryStats {
+    /// True if every record is canonical, as in a dataset written by the generator's default
+    /// canonical-only mode.
+    pub fn all_canonical(&self) -> bool {
+        self.canonical == self.records
+    }
+}
+
+/// Classifies matched boards by the size of their symmetry orbit (the number of distinct
+/// boards among their rotations and reflections).
+#[derive(Debug, Clone)]
+pub struct SymmetryClasses {
+    geometry: BoardGeometry,
+    stats: SymmetryStats,
+}
+
+impl SymmetryClasses {
+    pub fn new(geometry: BoardGeometry) -> Self {
+        Self { geometry, stats: SymmetryStats::default() }
+    }
+}
+
+impl CountAccumulator for SymmetryClasses {
+    type Output = SymmetryStats;
+
+    fn visit(&mut self, board: u128) {
+        let mut orbit = distinct_symmetries_with_geometry(board, self.geometry);
+        let smallest = orbit.next();
+        let size = 1 + orbit.count();
+
+        self.stats.records += 1;
+        self.stats.orbit_sizes[size] += 1;
+        if smallest == Some(board) {
+            self.stats.canonical += 1;
+            self.stats.expanded_boards += size as u64;
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.stats.records += other.stats.records;
+        self.stats.canonical += other.stats.canonical;
+        self.stats.expanded_boards += other.stats.expanded_boards;
+        for (count, other_count) in self.stats.orbit_sizes.iter_mut().zip(other.stats.orbit_sizes) {
+            *count += other_count;
+        }
+    }
+
+    fn finish(self) -> Self::Output {
+        self.stats
+    }
+}
+
+/// Counts how often each pair of cells holds a ship on the same board.
+///
+/// The output is a symmetric 81×81 matrix whose diagonal holds the per-cell counts.
+#[derive(Debug, Clone)]
+pub struct PairCorrelations {
+    pairs: Box<[[u64; 81]; 81]>,
+}
+
+impl PairCorrelations {
+    pub fn new() -> Self {
+        Self { pairs: Box::new([[0; 81]; 81]) }
+    }
+}
+
+impl Default for PairCorrelations {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CountAccumulator for PairCorrelations {
+    type Output = Box<[[u64; 81]; 81]>;
+
+    fn visit(&mut self, board: u128) {
+        let mut outer = board & ((1u128 << 81) - 1);
+        while outer != 0 {
+            let i = outer.trailing_zeros() as usize;
+
+            let mut inner = outer;
+            while inner != 0 {
+                let j = inner.trailing_zeros() as usize;
+                self.pairs[i][j] += 1;
+                if i != j {
+                    self.pairs[j][i] += 1;
+                }
+                inner &= inner - 1;
+            }
+
+            outer &= outer - 1;
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        for (row, other_row) in self.pairs.iter_mut().zip(other.pairs.iter()) {
+            for (count, other_count) in row.iter_mut().zip(other_row) {
+                *count += other_count;
+            }
+        }
+    }
+
+    fn finish(self) -> Self::Output {
+        self.pairs
+    }
+}
+
+/// Keeps the `k` matched boards with the highest score.
+///
+/// Ties are broken by preferring the numerically smaller board, so results are deterministic
+/// regardless of how the scan was parallelized.
+#[derive(Debug, Clone)]
+pub struct TopKBoards<F> {
+    k: usize,
+    score: F,
+    heap: BinaryHeap<Reverse<(u64, Reverse<u128>)>>,
+}
+
+impl<F: Fn(u128) -> u64> TopKBoards<F> {
+    pub fn new(k: usize, score: F) -> Self {
+        Self { k, score, heap: BinaryHeap::with_capacity(k + 1) }
+    }
+
+    fn push(&mut self, entry: (u64, Reverse<u128>)) {
+        if self.k == 0 {
+            return;
+        }
+
+        if self.heap.len() < self.k {
+            self.heap.push(Reverse(entry));
+        } else if let Some(Reverse(lowest)) = self.heap.peek() {
+            if entry > *lowest {
+                self.heap.pop();
+                self.heap.push(Reverse(entry));
+            }
+        }
+    }
+}
+
+impl<F: Fn(u128) -> u64 + Send> CountAccumulator for TopKBoards<F> {
+    /// `(score, board)` pairs sorted from highest to lowest score.
+    type Output = Vec<(u64, u128)>;
+
+    fn visit(&mut self, board: u128) {
+        let score = (self.score)(board);
+        self.push((score, Reverse(board)));
+    }
+
+    fn merge(&mut self, other: Self) {
+        for Reverse(entry) in other.heap {
+            self.push(entry);
+        }
+    }
+
+    fn finish(self) -> Self::Output {
+        let mut entries: Vec<_> = self.heap.into_iter().map(|Reverse(entry)| entry).collect();
+        entries.sort_by(|a, b| b.cmp(a));
+        entries.into_iter().map(|(score, Reverse(board))| (score, board)).collect()
+    }
+}
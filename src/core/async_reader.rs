@@ -0,0 +1,160 @@
+use crate::core::geometry::RECORD_SIZE;
+use crate::core::header::{DatasetHeader, HEADER_LEN, HEADER_MAGIC};
+use crate::core::reader::{detect_compression, detect_encoding, DeltaChain, RecordEncoding, DETECTION_SAMPLE_RECORDS};
+use async_compression::tokio::bufread::ZstdDecoder;
+use std::io::{self, Cursor};
+use std::path::Path;
+use std::pin::Pin;
+use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
+
+/// A boxed async byte stream, the async counterpart of `Box<dyn Read>`.
+type AsyncInput = Pin<Box<dyn AsyncRead + Send>>;
+
+/// Reads the boards of a dataset from an async byte stream, e.g. a socket or an HTTP body,
+/// without blocking a thread while waiting for data.
+///
+/// Datasets are read the same way as [`crate::core::reader::open_records`]: the header is parsed
+/// if there is one, zstd compressed data is decompressed, and records are decoded as the header
+/// describes or as detected. Only zstd compression is supported.
+pub struct AsyncBoardReader {
+    input: AsyncInput,
+    header: Option<DatasetHeader>,
+    /// `None` for raw records.
+    chain: Option<DeltaChain>,
+}
+
+impl AsyncBoardReader {
+    /// Opens a dataset file, or stdin for "-".
+    pub async fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        if path.to_string_lossy() == "-" {
+            Self::new(tokio::io::stdin()).await
+        } else {
+            Self::new(tokio::fs::File::open(path).await?).await
+        }
+    }
+
+    /// Starts reading a dataset from `input`, reading as far as needed to parse its header and
+    /// detect its encoding.
+    pub async fn new<R: AsyncRead + Send + 'static>(input: R) -> io::Result<Self> {
+        let (header, rest) = read_optional_header(Box::pin(input)).await?;
+        let decompressed = decompress(rest).await?;
+
+        // The header ends up inside the compressed stream when a whole file is compressed with the zstd tool
+        let (header, mut decompressed) = match header {
+            Some(header) => (Some(header), decompressed),
+            None => read_optional_header(decompressed).await?,
+        };
+
+        let encoding = match &header {
+            Some(header) if !header.delta_encoded => RecordEncoding::Raw,
+            Some(_) => RecordEncoding::Delta,
+            None => {
+                let sample = read_prefix(&mut decompressed, DETECTION_SAMPLE_RECORDS * RECORD_SIZE).await?;
+                let records: Vec<u128> = sample.chunks_exact(RECORD_SIZE)
+                    .map(|bytes| u128::from_le_bytes(bytes.try_into().unwrap()))
+                    .collect();
+                let encoding = detect_encoding(&records).unwrap_or(RecordEncoding::Delta);
+
+                // Put the sampled bytes back in front of the rest of the stream
+                decompressed = Box::pin(Cursor::new(sample).chain(decompressed));
+                encoding
+            }
+        };
+
+        let chain = match encoding {
+            RecordEncoding::Raw => None,
+            RecordEncoding::Delta => Some(DeltaChain::new(header.as_ref().and_then(|h| h.restart_interval).unwrap_or(0))),
+        };
+
+        let input = Box::pin(BufReader::with_capacity(128 * 1024, decompressed));
+        Ok(Self { input, header, chain })
+    }
+
+    /// The dataset's header, if it has one.
+    pub fn header(&self) -> Option<&DatasetHeader> {
+        self.header.as_ref()
+    }
+
+    /// How the records are being decoded, either as the header says or as detected.
+    pub fn encoding(&self) -> RecordEncoding {
+        match self.chain {
+            Some(_) => RecordEncoding::Delta,
+            None => RecordEncoding::Raw,
+        }
+    }
+
+    /// Reads the next board, returning `None` at the end of the dataset.
+    ///
+    /// A stream that ends partway through a record is reported as an error, since that usually
+    /// means the file was truncated.
+    pub async fn next_board(&mut self) -> Option<io::Result<u128>> {
+        let mut buf = [0u8; RECORD_SIZE];
+        let mut filled = 0;
+
+        while filled < RECORD_SIZE {
+            match self.input.read(&mut buf[filled..]).await {
+                Ok(0) if filled == 0 => return None,
+                Ok(0) => {
+                    return Some(Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!("stream ends {} bytes into a {}-byte record; the file may be truncated", filled, RECORD_SIZE),
+                    )));
+                }
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        let record = u128::from_le_bytes(buf);
+        Some(Ok(match &mut self.chain {
+            Some(chain) => chain.decode(record),
+            None => record,
+        }))
+    }
+}
+
+/// Decompresses `input` if it starts with the zstd magic number, passing it through otherwise.
+async fn decompress(mut input: AsyncInput) -> io::Result<AsyncInput> {
+    let prefix = read_prefix(&mut input, 6).await?;
+    let format = detect_compression(&prefix);
+
+    // Prepend the magic bytes we consumed
+    let chained = Cursor::new(prefix).chain(input);
+
+    match format {
+        Some("zstd") => {
+            let mut decoder = ZstdDecoder::new(BufReader::new(chained));
+            decoder.multiple_members(true);
+            Ok(Box::pin(decoder))
+        }
+        Some(format) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("input is {} compressed; async reading only supports zstd", format),
+        )),
+        None => Ok(Box::pin(chained)),
+    }
+}
+
+/// Async counterpart of [`crate::core::header::read_optional_header`].
+async fn read_optional_header(mut input: AsyncInput) -> io::Result<(Option<DatasetHeader>, AsyncInput)> {
+    let prefix = read_prefix(&mut input, HEADER_MAGIC.len()).await?;
+
+    if prefix != HEADER_MAGIC {
+        return Ok((None, Box::pin(Cursor::new(prefix).chain(input))));
+    }
+
+    let mut bytes = [0u8; HEADER_LEN];
+    bytes[..HEADER_MAGIC.len()].copy_from_slice(&prefix);
+    input.read_exact(&mut bytes[HEADER_MAGIC.len()..]).await?;
+
+    Ok((Some(DatasetHeader::from_bytes(&bytes)?), input))
+}
+
+/// Reads up to `len` bytes, stopping early only at the end of the stream.
+async fn read_prefix(input: &mut AsyncInput, len: usize) -> io::Result<Vec<u8>> {
+    let mut prefix = Vec::with_capacity(len);
+    input.take(len as u64).read_to_end(&mut prefix).await?;
+    Ok(prefix)
+}
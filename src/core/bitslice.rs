@@ -0,0 +1,111 @@
+/// Up to 64 records stored bitsliced: lane `c` holds cell `c` of every record, one bit per
+/// record, so a query tests all of them with one AND per shot instead of one per record.
+///
+/// Bit `k` of every lane belongs to `records()[k]`. Lanes are built with two 64×64 bit-matrix
+/// transposes (one per half of the 128-bit records), which costs a few operations per record.
+#[derive(Debug, Clone)]
+pub struct BitsliceBatch {
+    records: [u128; 64],
+    len: usize,
+    lanes: [u64; 128],
+}
+
+impl BitsliceBatch {
+    /// Records per batch, one per bit of a lane.
+    pub const CAPACITY: usize = 64;
+
+    /// Transposes `records`, which must hold at most [`Self::CAPACITY`] boards.
+    pub fn new(records: &[u128]) -> Self {
+        assert!(records.len() <= Self::CAPACITY, "a batch holds at most 64 records, not {}", records.len());
+
+        let mut batch = Self { records: [0; 64], len: records.len(), lanes: [0; 128] };
+        batch.records[..records.len()].copy_from_slice(records);
+
+        let (low, high) = batch.lanes.split_at_mut(64);
+        for (k, &record) in records.iter().enumerate() {
+            low[k] = record as u64;
+            high[k] = (record >> 64) as u64;
+        }
+        transpose64(low.try_into().unwrap());
+        transpose64(high.try_into().unwrap());
+
+        batch
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn records(&self) -> &[u128] {
+        &self.records[..self.len]
+    }
+
+    /// Which records have a ship in `cell`, as a bit per record.
+    pub fn lane(&self, cell: usize) -> u64 {
+        self.lanes[cell]
+    }
+
+    /// A bit for every record in the batch.
+    pub fn filled(&self) -> u64 {
+        if self.len == 64 { u64::MAX } else { (1 << self.len) - 1 }
+    }
+
+    /// Which records have ships in every cell of `hit_mask` and none in `miss_mask`, as a bit
+    /// per record.
+    pub fn matching(&self, hit_mask: u128, miss_mask: u128) -> u64 {
+        let mut matches = self.filled();
+
+        let mut hits = hit_mask;
+        while hits != 0 && matches != 0 {
+            matches &= self.lanes[hits.trailing_zeros() as usize];
+            hits &= hits - 1;
+        }
+
+        let mut misses = miss_mask;
+        while misses != 0 && matches != 0 {
+            matches &= !self.lanes[misses.trailing_zeros() as usize];
+            misses &= misses - 1;
+        }
+
+        matches
+    }
+
+    /// The records whose bits are set in `matches`, in batch order.
+    pub fn matched_records(&self, matches: u64) -> impl Iterator<Item = u128> + '_ {
+        let mut remaining = matches & self.filled();
+        std::iter::from_fn(move || {
+            if remaining == 0 {
+                return None;
+            }
+            let k = remaining.trailing_zeros() as usize;
+            remaining &= remaining - 1;
+            Some(self.records[k])
+        })
+    }
+}
+
+/// Transposes a 64×64 bit matrix in place: bit `j` of row `i` becomes bit `i` of row `j`.
+///
+/// Swaps ever smaller blocks across the diagonal, from 32×32 down to 1×1 (Hacker's Delight
+/// 7-3), so it takes 6 passes of 32 swaps instead of 4096 single-bit moves.
+fn transpose64(rows: &mut [u64; 64]) {
+    let mut width = 32;
+    let mut mask: u64 = 0x0000_0000_FFFF_FFFF;
+
+    while width != 0 {
+        let mut k = 0;
+        while k < 64 {
+            let swap = ((rows[k] >> width) ^ rows[k + width]) & mask;
+            rows[k + width] ^= swap;
+            rows[k] ^= swap << width;
+            k = (k + width + 1) & !width;
+        }
+
+        width >>= 1;
+        mask ^= mask << width;
+    }
+}
@@ -0,0 +1,199 @@
+use crate::core::filter::QueryOptions;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Identifies a cache entry file.
+const CACHE_MAGIC: [u8; 4] = *b"BSQC";
+
+/// Version of the entry layout written by [`QueryCache::put`].
+pub const CACHE_VERSION: u16 = 1;
+
+/// Bytes read from each end of a dataset when fingerprinting it.
+const FINGERPRINT_SAMPLE: u64 = 64 * 1024;
+
+/// What a cached result was computed from: a dataset, a query, and the options that change
+/// its result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// Fingerprint of the dataset file (see [`CacheKey::new`]).
+    pub dataset: u64,
+    pub hit_mask: u128,
+    pub miss_mask: u128,
+    /// Hash of the query options that affect the counts.
+    pub options: u64,
+}
+
+impl CacheKey {
+    /// Builds the key of a query against the dataset at `path`.
+    ///
+    /// Hashing a multi-gigabyte dataset would cost as much as the query, so the dataset is
+    /// fingerprinted by its length, modification time, and the bytes at its start and end
+    /// (which hold the header and the last records). Replacing a file with a different one
+    /// changes the fingerprint.
+    pub fn new<P: AsRef<Path>>(path: P, hit_mask: u128, miss_mask: u128, options: &QueryOptions) -> io::Result<Self> {
+        Ok(Self { dataset: fingerprint(path.as_ref())?, hit_mask, miss_mask, options: hash_options(options) })
+    }
+
+    fn file_name(&self) -> String {
+        format!("{:016x}-{:032x}-{:032x}-{:016x}.counts", self.dataset, self.hit_mask, self.miss_mask, self.options)
+    }
+}
+
+/// An on-disk cache of per-cell counts and matched totals, so repeating a query (as
+/// interactive play does after an undo) doesn't rescan the dataset.
+///
+/// Each entry is its own small file in the cache directory: the magic `BSQC`, a u16 version,
+/// a u16 cell count, the u64 number of matched boards, then a u32 count per cell, all
+/// little-endian. Entries never go stale since the key changes with the dataset; delete the
+/// directory to reclaim space.
+#[derive(Debug, Clone)]
+pub struct QueryCache {
+    dir: PathBuf,
+}
+
+impl QueryCache {
+    /// Opens the cache in `dir`, creating the directory if needed.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// `$XDG_CACHE_HOME/battleship`, falling back to `~/.cache/battleship` and then to the
+    /// system temp directory.
+    pub fn default_dir() -> PathBuf {
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+            .unwrap_or_else(std::env::temp_dir);
+        base.join("battleship")
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the cached counts and matched total for `key`, or `None` if there aren't any.
+    pub fn get(&self, key: &CacheKey) -> io::Result<Option<(Vec<u32>, u64)>> {
+        let file = match File::open(self.dir.join(key.file_name())) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        read_entry(&mut BufReader::new(file)).map(Some)
+    }
+
+    /// Stores the counts and matched total for `key`.
+    ///
+    /// The entry is written to a temporary file and renamed into place, so a concurrent
+    /// reader never sees a partial entry.
+    pub fn put(&self, key: &CacheKey, counts: &[u32], matched: u64) -> io::Result<()> {
+        let path = self.dir.join(key.file_name());
+        let temp = path.with_extension(format!("tmp{}", std::process::id()));
+
+        let mut writer = BufWriter::new(File::create(&temp)?);
+        writer.write_all(&CACHE_MAGIC)?;
+        writer.write_all(&CACHE_VERSION.to_le_bytes())?;
+        writer.write_all(&(counts.len() as u16).to_le_bytes())?;
+        writer.write_all(&matched.to_le_bytes())?;
+        for count in counts {
+            writer.write_all(&count.to_le_bytes())?;
+        }
+        writer.flush()?;
+        drop(writer);
+
+        fs::rename(&temp, &path)
+    }
+}
+
+fn read_entry<R: Read>(reader: &mut R) -> io::Result<(Vec<u32>, u64)> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != CACHE_MAGIC {
+        return Err(invalid("Not a query cache entry"));
+    }
+
+    let version = u16::from_le_bytes(read_array(reader)?);
+    if version != CACHE_VERSION {
+        return Err(invalid(&format!("Unsupported query cache version {}", version)));
+    }
+
+    let cells = u16::from_le_bytes(read_array(reader)?);
+    let matched = u64::from_le_bytes(read_array(reader)?);
+    let counts = (0..cells)
+        .map(|_| read_array(reader).map(u32::from_le_bytes))
+        .collect::<io::Result<_>>()?;
+
+    Ok((counts, matched))
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut buffer = [0u8; N];
+    reader.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+fn fingerprint(path: &Path) -> io::Result<u64> {
+    let mut file = File::open(path)?;
+    let metadata = file.metadata()?;
+    let len = metadata.len();
+    let modified = metadata.modified()?.duration_since(UNIX_EPOCH).unwrap_or_default();
+
+    let mut hash = Fnv1a::new();
+    hash.write(&len.to_le_bytes());
+    hash.write(&modified.as_secs().to_le_bytes());
+    hash.write(&modified.subsec_nanos().to_le_bytes());
+
+    let mut sample = Vec::new();
+    Read::by_ref(&mut file).take(FINGERPRINT_SAMPLE).read_to_end(&mut sample)?;
+    if len > FINGERPRINT_SAMPLE {
+        file.seek(SeekFrom::Start(len.saturating_sub(FINGERPRINT_SAMPLE).max(FINGERPRINT_SAMPLE)))?;
+        file.read_to_end(&mut sample)?;
+    }
+    hash.write(&sample);
+
+    Ok(hash.finish())
+}
+
+/// Hash of the query options that change a query's counts. Stable across builds, since it's
+/// stored on disk.
+pub(crate) fn hash_options(options: &QueryOptions) -> u64 {
+    let mut hash = Fnv1a::new();
+    hash.write(&[options.expand_symmetries as u8]);
+    match options.popcount {
+        Some(range) => {
+            hash.write(&[1]);
+            hash.write(&range.min.to_le_bytes());
+            hash.write(&range.max.to_le_bytes());
+        }
+        None => hash.write(&[0]),
+    }
+    hash.write(&(options.geometry.width() as u64).to_le_bytes());
+    hash.write(&(options.geometry.height() as u64).to_le_bytes());
+    hash.finish()
+}
+
+/// 64-bit FNV-1a. Keys are stored on disk, so the hash must not change between builds the way
+/// `std`'s `DefaultHasher` may.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xCBF2_9CE4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
@@ -0,0 +1,116 @@
+use crate::core::geometry::BoardGeometry;
+use crate::core::random::SplitMix64;
+use crate::core::reader::read_record;
+use crate::core::shuffle::TempBucket;
+use crate::generator::symmetries::canonicalize_with_geometry;
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Number of temporary buckets used when the distinct boards don't fit in memory.
+const BUCKET_COUNT: usize = 64;
+
+/// Result of [`canonicalize_records`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CanonicalizeStats {
+    /// Records read.
+    pub records: u64,
+    /// Distinct canonical boards written.
+    pub canonical: u64,
+}
+
+impl CanonicalizeStats {
+    /// How many records were read per board written, e.g. close to 8 for a full 9x9 dataset.
+    pub fn reduction_ratio(&self) -> f64 {
+        if self.canonical == 0 { 0.0 } else { self.records as f64 / self.canonical as f64 }
+    }
+}
+
+/// Maps every record to its canonical board (the smallest of its symmetric variants) and writes
+/// each distinct canonical board once, raw, to `writer`.
+///
+/// Up to `max_records_in_memory` distinct boards are deduplicated in a hash set, in the order
+/// they first appear. Beyond that, every board is scattered into temporary buckets under
+/// `temp_dir` by hash, so copies of a board share a bucket, and each bucket is deduplicated on
+/// its own the same way.
+pub fn canonicalize_records<I, W>(
+    records: I,
+    writer: &mut W,
+    geometry: BoardGeometry,
+    max_records_in_memory: usize,
+    temp_dir: &Path,
+) -> io::Result<CanonicalizeStats>
+where
+    I: IntoIterator<Item = io::Result<u128>>,
+    W: Write,
+{
+    assert!(max_records_in_memory > 0, "Must allow at least one record in memory");
+
+    let mut records_read = 0u64;
+    let mut canonical = records.into_iter().map(|record| {
+        records_read += 1;
+        record.map(|board| canonicalize_with_geometry(board, geometry))
+    });
+
+    let canonical = dedup_records(&mut canonical, writer, max_records_in_memory, temp_dir, 0)?;
+    writer.flush()?;
+    Ok(CanonicalizeStats { records: records_read, canonical })
+}
+
+/// Writes each distinct record once, returning the number written. `level` picks the hash that
+/// spreads records over buckets, so a bucket's records don't all land in one bucket again.
+fn dedup_records<W: Write>(
+    records: &mut dyn Iterator<Item = io::Result<u128>>,
+    writer: &mut W,
+    max_records_in_memory: usize,
+    temp_dir: &Path,
+    level: u64,
+) -> io::Result<u64> {
+    let mut seen = HashSet::new();
+    let mut distinct: Vec<u128> = Vec::new();
+
+    // The first distinct board that doesn't fit in memory, if any
+    let overflow = loop {
+        let Some(record) = records.next() else {
+            for record in &distinct {
+                writer.write_all(&record.to_le_bytes())?;
+            }
+            return Ok(distinct.len() as u64);
+        };
+
+        let record = record?;
+        if seen.contains(&record) {
+            continue;
+        }
+        if distinct.len() == max_records_in_memory {
+            break record;
+        }
+        seen.insert(record);
+        distinct.push(record);
+    };
+
+    // Too many distinct boards for memory: partition everything by hash
+    drop(seen);
+    let mut buckets = (0..BUCKET_COUNT)
+        .map(|_| TempBucket::create(temp_dir))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    for record in distinct.drain(..).chain([overflow]).map(Ok).chain(records) {
+        let record = record?;
+        buckets[bucket_of(record, level)].write(record)?;
+    }
+
+    let mut total = 0u64;
+    for bucket in buckets {
+        let mut reader = bucket.into_reader()?;
+        let mut bucket_records = std::iter::from_fn(|| read_record(&mut reader).transpose());
+        total += dedup_records(&mut bucket_records, writer, max_records_in_memory, temp_dir, level + 1)?;
+    }
+
+    Ok(total)
+}
+
+fn bucket_of(record: u128, level: u64) -> usize {
+    let key = record as u64 ^ (record >> 64) as u64 ^ SplitMix64::new(level).next_u64();
+    (SplitMix64::new(key).next_u64() % BUCKET_COUNT as u64) as usize
+}
@@ -0,0 +1,133 @@
+use std::io::{self, Write};
+
+const PRIME_1: u64 = 0x9E37_79B1_85EB_CA87;
+const PRIME_2: u64 = 0xC2B2_AE3D_27D4_EB4F;
+const PRIME_3: u64 = 0x1656_67B1_9E37_79F9;
+const PRIME_4: u64 = 0x85EB_CA77_C2B2_AE63;
+const PRIME_5: u64 = 0x27D4_EB2F_1656_67C5;
+
+/// The 64-bit xxHash (XXH64) of `bytes`.
+pub fn xxh64(bytes: &[u8], seed: u64) -> u64 {
+    let mut hasher = Xxh64::new(seed);
+    hasher.update(bytes);
+    hasher.finish()
+}
+
+/// Computes XXH64 over data written in pieces, giving the same hash as [`xxh64`] of all of it.
+///
+/// It is also a [`Write`] sink, so data can be hashed as it's copied elsewhere.
+#[derive(Debug, Clone)]
+pub struct Xxh64 {
+    seed: u64,
+    lanes: [u64; 4],
+    /// Bytes not yet folded into the lanes, which take 32 at a time.
+    pending: [u8; 32],
+    pending_len: usize,
+    total_len: u64,
+}
+
+impl Xxh64 {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            lanes: [
+                seed.wrapping_add(PRIME_1).wrapping_add(PRIME_2),
+                seed.wrapping_add(PRIME_2),
+                seed,
+                seed.wrapping_sub(PRIME_1),
+            ],
+            pending: [0; 32],
+            pending_len: 0,
+            total_len: 0,
+        }
+    }
+
+    pub fn update(&mut self, mut bytes: &[u8]) {
+        self.total_len += bytes.len() as u64;
+
+        if self.pending_len > 0 {
+            let take = bytes.len().min(32 - self.pending_len);
+            self.pending[self.pending_len..self.pending_len + take].copy_from_slice(&bytes[..take]);
+            self.pending_len += take;
+            bytes = &bytes[take..];
+
+            if self.pending_len < 32 {
+                return;
+            }
+            let stripe = self.pending;
+            self.consume_stripe(&stripe);
+            self.pending_len = 0;
+        }
+
+        let mut stripes = bytes.chunks_exact(32);
+        for stripe in &mut stripes {
+            self.consume_stripe(stripe);
+        }
+
+        let rest = stripes.remainder();
+        self.pending[..rest.len()].copy_from_slice(rest);
+        self.pending_len = rest.len();
+    }
+
+    /// The hash of everything written so far.
+    pub fn finish(&self) -> u64 {
+        let mut hash = if self.total_len >= 32 {
+            let [v1, v2, v3, v4] = self.lanes;
+            let mut hash = v1.rotate_left(1)
+                .wrapping_add(v2.rotate_left(7))
+                .wrapping_add(v3.rotate_left(12))
+                .wrapping_add(v4.rotate_left(18));
+            for lane in self.lanes {
+                hash = (hash ^ round(0, lane)).wrapping_mul(PRIME_1).wrapping_add(PRIME_4);
+            }
+            hash
+        } else {
+            self.seed.wrapping_add(PRIME_5)
+        };
+        hash = hash.wrapping_add(self.total_len);
+
+        let mut rest = &self.pending[..self.pending_len];
+        while rest.len() >= 8 {
+            hash ^= round(0, u64::from_le_bytes(rest[..8].try_into().unwrap()));
+            hash = hash.rotate_left(27).wrapping_mul(PRIME_1).wrapping_add(PRIME_4);
+            rest = &rest[8..];
+        }
+        if rest.len() >= 4 {
+            hash ^= (u32::from_le_bytes(rest[..4].try_into().unwrap()) as u64).wrapping_mul(PRIME_1);
+            hash = hash.rotate_left(23).wrapping_mul(PRIME_2).wrapping_add(PRIME_3);
+            rest = &rest[4..];
+        }
+        for &byte in rest {
+            hash ^= (byte as u64).wrapping_mul(PRIME_5);
+            hash = hash.rotate_left(11).wrapping_mul(PRIME_1);
+        }
+
+        // Avalanche
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(PRIME_2);
+        hash ^= hash >> 29;
+        hash = hash.wrapping_mul(PRIME_3);
+        hash ^ (hash >> 32)
+    }
+
+    fn consume_stripe(&mut self, stripe: &[u8]) {
+        for (lane, word) in self.lanes.iter_mut().zip(stripe.chunks_exact(8)) {
+            *lane = round(*lane, u64::from_le_bytes(word.try_into().unwrap()));
+        }
+    }
+}
+
+impl Write for Xxh64 {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn round(lane: u64, input: u64) -> u64 {
+    lane.wrapping_add(input.wrapping_mul(PRIME_2)).rotate_left(31).wrapping_mul(PRIME_1)
+}
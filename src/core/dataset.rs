@@ -0,0 +1,74 @@
+use crate::core::accumulator::{CellCounts, CountAccumulator};
+use crate::core::filter::{visit_matches, Filter, FilterResult, QueryOptions};
+use crate::core::reader::create_reader;
+use rayon::prelude::*;
+use std::io;
+use std::path::Path;
+
+/// A fully decoded dataset held in memory, for running many queries without re-reading the file.
+///
+/// Each board takes 16 bytes, so the full 9x9 dataset needs roughly 3.4 GB.
+#[derive(Debug, Clone, Default)]
+pub struct Dataset {
+    boards: Vec<u128>,
+}
+
+impl Dataset {
+    pub fn new(boards: Vec<u128>) -> Self {
+        Self { boards }
+    }
+
+    /// Decodes every record from a reader.
+    pub fn from_records<I>(reader: I) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = io::Result<u128>>,
+    {
+        let boards = reader.into_iter().collect::<io::Result<Vec<_>>>()?;
+        Ok(Self::new(boards))
+    }
+
+    /// Loads a dataset file (or "-" for stdin), handling zstd compression.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_records(create_reader(path)?)
+    }
+
+    pub fn boards(&self) -> &[u128] {
+        &self.boards
+    }
+
+    pub fn len(&self) -> usize {
+        self.boards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.boards.is_empty()
+    }
+
+    /// Counts hits per cell over the boards matching the hit/miss masks.
+    pub fn filter_and_count(&self, hit_mask: u128, miss_mask: u128) -> FilterResult {
+        self.filter_and_accumulate(hit_mask, miss_mask, &QueryOptions::default(), CellCounts::new).into()
+    }
+
+    /// Feeds every matching board to a [`CountAccumulator`], scanning in parallel.
+    ///
+    /// The scan mode and chunk size in `options` are ignored since the boards are already in memory.
+    pub fn filter_and_accumulate<A, F>(&self, hit_mask: u128, miss_mask: u128, options: &QueryOptions, make_accumulator: F) -> A::Output
+    where
+        A: CountAccumulator,
+        F: Fn() -> A + Sync + Send,
+    {
+        let filter = Filter::new(hit_mask, miss_mask, options);
+
+        self.boards
+            .par_iter()
+            .fold(&make_accumulator, |mut accumulator, &board| {
+                visit_matches(&mut accumulator, board, &filter, options.expand_symmetries);
+                accumulator
+            })
+            .reduce(&make_accumulator, |mut accumulator, other| {
+                accumulator.merge(other);
+                accumulator
+            })
+            .finish()
+    }
+}
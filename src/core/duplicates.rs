@@ -0,0 +1,97 @@
+use crate::core::geometry::{BoardGeometry, RECORD_SIZE};
+use crate::generator::symmetries::canonicalize_with_geometry;
+use std::collections::HashMap;
+use std::io;
+
+/// A board that appears more than once in a dataset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Duplicate {
+    /// The repeated board (its canonical form for canonical duplicates).
+    pub board: u128,
+    /// Record number of the board's first appearance.
+    pub first: u64,
+    /// Record number of this repeat.
+    pub repeat: u64,
+}
+
+impl Duplicate {
+    /// Byte offset of the repeat within the decoded record stream.
+    pub fn repeat_offset(&self) -> u64 {
+        self.repeat * RECORD_SIZE as u64
+    }
+}
+
+/// Duplicates found under one notion of equality.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DuplicateStats {
+    /// Number of distinct boards.
+    pub distinct: u64,
+    /// Number of records that repeat an earlier one.
+    pub duplicates: u64,
+    /// The first few repeats, in record order.
+    pub examples: Vec<Duplicate>,
+}
+
+/// Result of [`find_duplicates`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DuplicateReport {
+    pub records: u64,
+    /// Records that are identical to an earlier record.
+    pub exact: DuplicateStats,
+    /// Records that are identical to an earlier record or one of its symmetries.
+    pub canonical: DuplicateStats,
+}
+
+impl DuplicateReport {
+    pub fn has_duplicates(&self) -> bool {
+        self.canonical.duplicates > 0
+    }
+}
+
+/// Scans a stream of decoded records for exact and canonical-equivalent duplicates, keeping up
+/// to `max_examples` examples of each.
+///
+/// Every distinct board is kept in memory along with where it first appeared.
+pub fn find_duplicates<I>(records: I, geometry: BoardGeometry, max_examples: usize) -> io::Result<DuplicateReport>
+where
+    I: IntoIterator<Item = io::Result<u128>>,
+{
+    let mut exact = DuplicateTracker::default();
+    let mut canonical = DuplicateTracker::default();
+    let mut count = 0u64;
+
+    for record in records {
+        let board = record?;
+        exact.visit(board, count, max_examples);
+        canonical.visit(canonicalize_with_geometry(board, geometry), count, max_examples);
+        count += 1;
+    }
+
+    Ok(DuplicateReport { records: count, exact: exact.finish(), canonical: canonical.finish() })
+}
+
+#[derive(Default)]
+struct DuplicateTracker {
+    first_seen: HashMap<u128, u64>,
+    stats: DuplicateStats,
+}
+
+impl DuplicateTracker {
+    fn visit(&mut self, board: u128, record: u64, max_examples: usize) {
+        let first = *self.first_seen.entry(board).or_insert(record);
+        if first != record {
+            self.stats.duplicates += 1;
+            if self.stats.examples.len() < max_examples {
+                self.stats.examples.push(Duplicate { board, first, repeat: record });
+            }
+        }
+    }
+
+    fn finish(mut self) -> DuplicateStats {
+        self.stats.distinct = self.first_seen.len() as u64;
+        self.stats
+    }
+}
@@ -0,0 +1,260 @@
+use crate::core::checksum::Xxh64;
+use crate::core::geometry::RECORD_SIZE;
+use crate::core::header::DatasetHeader;
+use crate::core::index::DatasetIndex;
+use crate::core::reader::read_record;
+use crate::core::writer::DeltaEncodingWriter;
+use std::io::{self, Read, Write};
+
+/// Summary of one encoded chunk of records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkStats {
+    /// Byte offset of the chunk's first record in the encoded output (or of its zstd frame,
+    /// when each chunk is compressed separately).
+    pub offset: u64,
+    /// Number of records in the chunk.
+    pub count: u64,
+    /// Bitwise OR of every record in the chunk.
+    pub union: u128,
+    /// Bitwise AND of every record in the chunk.
+    pub intersection: u128,
+    /// XXH64 of the chunk's encoded bytes, as stored, or `None` for indexes written before
+    /// chunks were checksummed.
+    pub checksum: Option<u64>,
+}
+
+impl ChunkStats {
+    /// Whether any record in the chunk could have every `hit_mask` cell set and no
+    /// `miss_mask` cell set.
+    pub fn may_contain(&self, hit_mask: u128, miss_mask: u128) -> bool {
+        self.count > 0 && (self.union & hit_mask) == hit_mask && (self.intersection & miss_mask) == 0
+    }
+
+    fn empty(offset: u64) -> Self {
+        Self { offset, count: 0, union: 0, intersection: !0, checksum: None }
+    }
+
+    fn add(&mut self, record: u128) {
+        self.count += 1;
+        self.union |= record;
+        self.intersection &= record;
+    }
+}
+
+/// Options controlling how records are split into chunks and encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodeOptions {
+    /// Maximum number of records per chunk.
+    pub chunk_size: usize,
+    /// Encode each chunk's first record against zero instead of the previous chunk's last
+    /// record, so every chunk can be decoded on its own starting at its offset.
+    ///
+    /// The output can then no longer be decoded as a single delta stream.
+    pub restart_delta: bool,
+    /// Compress each chunk as its own zstd frame at this level. Frames are concatenated, so the
+    /// output is still a valid zstd stream.
+    pub zstd_frames: Option<i32>,
+    /// Header written uncompressed before the records. It is always marked as delta-encoded,
+    /// with the restart interval filled in from these options.
+    pub header: Option<DatasetHeader>,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: 500_000_000,
+            restart_delta: false,
+            zstd_frames: None,
+            header: None,
+        }
+    }
+}
+
+impl EncodeOptions {
+    /// The header [`encode_indexed`] writes for these options, if any.
+    pub fn output_header(&self) -> Option<DatasetHeader> {
+        let restart_interval = self.restart_delta.then_some(self.chunk_size as u64);
+        self.header.as_ref().map(|header| DatasetHeader { delta_encoded: true, restart_interval, ..header.clone() })
+    }
+}
+
+/// Settings for compressing a whole encoded stream with zstd.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZstdOptions {
+    /// Compression level.
+    pub level: i32,
+    /// Number of zstd worker threads, or 0 to compress on the calling thread. Requires the
+    /// `zstdmt` feature.
+    pub threads: u32,
+}
+
+impl Default for ZstdOptions {
+    fn default() -> Self {
+        Self { level: 3, threads: 0 }
+    }
+}
+
+/// Wraps `writer` in a zstd encoder configured with `options`. Call
+/// [`zstd::stream::Encoder::finish`] when done writing.
+pub fn zstd_writer<W: Write>(writer: W, options: &ZstdOptions) -> io::Result<zstd::stream::Encoder<'static, W>> {
+    #[cfg_attr(not(feature = "zstdmt"), allow(unused_mut))]
+    let mut encoder = zstd::stream::Encoder::new(writer, options.level)?;
+
+    if options.threads > 0 {
+        #[cfg(feature = "zstdmt")]
+        encoder.multithread(options.threads)?;
+
+        #[cfg(not(feature = "zstdmt"))]
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "multithreaded compression requires rebuilding with `--features zstdmt`",
+        ));
+    }
+
+    Ok(encoder)
+}
+
+/// Delta-encodes a stream of raw 16-byte records, splitting it into chunks of at most
+/// `chunk_size` records and collecting union/intersection stats for each chunk.
+///
+/// The delta chain runs continuously across chunk boundaries, so the output can be decoded
+/// with a single [`crate::core::reader::DeltaDecodingReader`].
+pub fn encode_chunks<R: Read, W: Write>(reader: &mut R, writer: &mut W, chunk_size: usize) -> io::Result<Vec<ChunkStats>> {
+    encode_chunks_with_options(reader, writer, &EncodeOptions { chunk_size, ..EncodeOptions::default() })
+}
+
+/// Same as [`encode_chunks`], with control over chunk boundaries and compression.
+pub fn encode_chunks_with_options<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    options: &EncodeOptions,
+) -> io::Result<Vec<ChunkStats>> {
+    Ok(encode_indexed(reader, writer, options)?.chunks)
+}
+
+/// Same as [`encode_chunks_with_options`], returning an index describing the encoded output.
+pub fn encode_indexed<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    options: &EncodeOptions,
+) -> io::Result<DatasetIndex> {
+    assert!(options.chunk_size > 0, "Chunk size must be positive");
+
+    let mut output = CountingWriter { inner: writer, bytes_written: 0, checksum: Xxh64::new(0) };
+    let mut chunks = Vec::new();
+    let mut previous = 0u128;
+
+    if let Some(header) = options.output_header() {
+        header.write_to(&mut output)?;
+    }
+
+    while let Some(first) = read_record(reader)? {
+        let mut stats = ChunkStats::empty(output.bytes_written);
+        output.checksum = Xxh64::new(0);
+        let base = if options.restart_delta { 0 } else { previous };
+
+        previous = match options.zstd_frames {
+            Some(level) => {
+                let encoder = zstd::stream::Encoder::new(&mut output, level)?;
+                let (encoder, last) = encode_chunk(reader, encoder, first, base, options.chunk_size, &mut stats)?;
+                encoder.finish()?;
+                last
+            }
+            None => encode_chunk(reader, &mut output, first, base, options.chunk_size, &mut stats)?.1,
+        };
+
+        stats.checksum = Some(output.checksum.finish());
+        chunks.push(stats);
+    }
+
+    // Flush the writer to ensure all data is written
+    output.flush()?;
+
+    Ok(DatasetIndex::new(options, output.bytes_written, chunks))
+}
+
+/// Same as [`encode_indexed`], reading already-decoded records from an iterator instead of raw
+/// bytes.
+pub fn encode_records<I, W>(records: I, writer: &mut W, options: &EncodeOptions) -> io::Result<DatasetIndex>
+where
+    I: IntoIterator<Item = io::Result<u128>>,
+    W: Write,
+{
+    let mut reader = RecordBytes { records: records.into_iter(), pending: [0; RECORD_SIZE], pending_len: 0 };
+    encode_indexed(&mut reader, writer, options)
+}
+
+/// Adapts an iterator of records into a stream of raw little-endian record bytes.
+struct RecordBytes<I> {
+    records: I,
+    pending: [u8; RECORD_SIZE],
+    pending_len: usize,
+}
+
+impl<I: Iterator<Item = io::Result<u128>>> Read for RecordBytes<I> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending_len == 0 {
+            match self.records.next() {
+                Some(record) => {
+                    self.pending = record?.to_le_bytes();
+                    self.pending_len = RECORD_SIZE;
+                }
+                None => return Ok(0),
+            }
+        }
+
+        let start = RECORD_SIZE - self.pending_len;
+        let len = self.pending_len.min(buf.len());
+        buf[..len].copy_from_slice(&self.pending[start..start + len]);
+        self.pending_len -= len;
+        Ok(len)
+    }
+}
+
+/// Encodes `first` and up to `chunk_size - 1` following records into `sink`, returning the
+/// sink and the chunk's last record.
+fn encode_chunk<R: Read, W: Write>(
+    reader: &mut R,
+    sink: W,
+    first: u128,
+    base: u128,
+    chunk_size: usize,
+    stats: &mut ChunkStats,
+) -> io::Result<(W, u128)> {
+    let mut delta_writer = DeltaEncodingWriter::with_base(sink, base);
+    let mut record = Some(first);
+
+    while let Some(board) = record {
+        stats.add(board);
+        delta_writer.write_record(board)?;
+
+        if stats.count as usize == chunk_size {
+            break;
+        }
+        record = read_record(reader)?;
+    }
+
+    let last = delta_writer.last_record();
+    Ok((delta_writer.into_inner()?, last))
+}
+
+/// Tracks how many bytes have been written so chunk offsets can be reported, and hashes them
+/// so each chunk can be checksummed.
+struct CountingWriter<W: Write> {
+    inner: W,
+    bytes_written: u64,
+    checksum: Xxh64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes_written += written as u64;
+        self.checksum.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
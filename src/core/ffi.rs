@@ -1,9 +1,100 @@
 use crate::core::filter::filter_and_count;
+use crate::core::geometry::BoardGeometry;
+use crate::core::query::Query;
 use crate::core::reader::create_reader;
+use crate::core::session::Session;
+use crate::core::version::{version_json, ABI_VERSION, CRATE_VERSION};
+use crate::generator::point::Point;
+use crate::solver::game_state::{GameState, ShotResult};
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::sync::{Arc, OnceLock};
+
+/// Returns a JSON description of this build (crate version, ABI version, supported format
+/// versions, enabled features and kernel backends).
+///
+/// The returned string is owned by the library and valid for the lifetime of the process.
+#[no_mangle]
+pub extern "C" fn battleship_version() -> *const c_char {
+    static VERSION: OnceLock<CString> = OnceLock::new();
+    VERSION
+        .get_or_init(|| CString::new(version_json()).expect("Version JSON contains no NUL bytes"))
+        .as_ptr()
+}
+
+/// Returns the version of the exported C ABI.
+#[no_mangle]
+pub extern "C" fn battleship_abi_version() -> u32 {
+    ABI_VERSION
+}
+
+/// Returns the crate version, e.g. "0.1.0".
+///
+/// The returned string is owned by the library and valid for the lifetime of the process.
+#[no_mangle]
+pub extern "C" fn bs_version() -> *const c_char {
+    static VERSION: OnceLock<CString> = OnceLock::new();
+    VERSION
+        .get_or_init(|| CString::new(CRATE_VERSION).expect("Version contains no NUL bytes"))
+        .as_ptr()
+}
+
+/// Status codes returned by `battleship_filter_and_count`.
+pub const BATTLESHIP_OK: i32 = 0;
+pub const BATTLESHIP_ERR_NULL_POINTER: i32 = -1;
+pub const BATTLESHIP_ERR_INVALID_UTF8: i32 = -2;
+pub const BATTLESHIP_ERR_BAD_PATH: i32 = -3;
+pub const BATTLESHIP_ERR_DECODE: i32 = -4;
+pub const BATTLESHIP_ERR_INVALID_QUERY: i32 = -5;
+
+/// Hit and miss masks of a query, as 64-bit halves.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BsQuery {
+    pub hit_mask_low: u64,
+    pub hit_mask_high: u64,
+    pub miss_mask_low: u64,
+    pub miss_mask_high: u64,
+}
+
+impl BsQuery {
+    pub fn new(hit_mask: u128, miss_mask: u128) -> Self {
+        Self {
+            hit_mask_low: hit_mask as u64,
+            hit_mask_high: (hit_mask >> 64) as u64,
+            miss_mask_low: miss_mask as u64,
+            miss_mask_high: (miss_mask >> 64) as u64,
+        }
+    }
+
+    pub fn hit_mask(&self) -> u128 {
+        ((self.hit_mask_high as u128) << 64) | (self.hit_mask_low as u128)
+    }
+
+    pub fn miss_mask(&self) -> u128 {
+        ((self.miss_mask_high as u128) << 64) | (self.miss_mask_low as u128)
+    }
+}
+
+/// Outcome of a query: a status code, and on success the matched board count and hits per cell.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BsResult {
+    pub status: i32,
+    pub matched: u64,
+    pub counts: [u32; 81],
+}
+
+impl Default for BsResult {
+    fn default() -> Self {
+        Self { status: BATTLESHIP_OK, matched: 0, counts: [0; 81] }
+    }
+}
 
 /// C-compatible FFI export for filter_and_count.
 ///
-/// The 128-bit masks are passed as two 64-bit values each (high and low parts).
+/// The 128-bit masks are passed as two 64-bit values each (high and low parts). Returns 0 both
+/// when nothing matches and on any error; use `battleship_filter_and_count` to tell them apart.
 ///
 /// # Safety
 /// `out_counts` must point to a buffer of at least 81 u32 entries.
@@ -16,29 +107,465 @@ pub unsafe extern "C" fn filter_and_count_ffi(
     miss_mask_high: u64,
     out_counts: *mut u32,
 ) -> u64 {
+    let mut matched = 0;
+    let status = battleship_filter_and_count(
+        path_ptr,
+        hit_mask_low,
+        hit_mask_high,
+        miss_mask_low,
+        miss_mask_high,
+        out_counts,
+        &mut matched,
+    );
+
+    if status == BATTLESHIP_OK { matched } else { 0 }
+}
+
+/// Filters and counts boards like `filter_and_count_ffi`, returning a status code and writing
+/// the number of matched boards to `out_matched`.
+///
+/// Returns `BATTLESHIP_OK` on success, or one of the `BATTLESHIP_ERR_*` codes: a null pointer
+/// argument, a path that isn't valid UTF-8, a file that can't be opened, data that can't be
+/// read or decoded, or overlapping or out-of-range masks. On error, `out_counts` and
+/// `out_matched` are left untouched.
+///
+/// # Safety
+/// `path_ptr` must be null or a null-terminated string, `out_counts` must be null or point to a
+/// buffer of at least 81 u32 entries, and `out_matched` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn battleship_filter_and_count(
+    path_ptr: *const c_char,
+    hit_mask_low: u64,
+    hit_mask_high: u64,
+    miss_mask_low: u64,
+    miss_mask_high: u64,
+    out_counts: *mut u32,
+    out_matched: *mut u64,
+) -> i32 {
+    let mut scanned = 0;
+    battleship_filter_and_count_scanned(
+        path_ptr,
+        hit_mask_low,
+        hit_mask_high,
+        miss_mask_low,
+        miss_mask_high,
+        out_counts,
+        out_matched,
+        &mut scanned,
+    )
+}
+
+/// Filters and counts boards like `battleship_filter_and_count`, also writing the number of
+/// records read from the file, matching or not, to `out_scanned`. Comparing it with the
+/// number of boards a dataset should hold shows how selective a query was and catches
+/// truncated files. `out_scanned` is required like the other outputs: when it's null, nothing is
+/// counted and `BATTLESHIP_ERR_NULL_POINTER` is returned.
+///
+/// # Safety
+/// As for `battleship_filter_and_count`, and `out_scanned` must be null or valid for writes.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn battleship_filter_and_count_scanned(
+    path_ptr: *const c_char,
+    hit_mask_low: u64,
+    hit_mask_high: u64,
+    miss_mask_low: u64,
+    miss_mask_high: u64,
+    out_counts: *mut u32,
+    out_matched: *mut u64,
+    out_scanned: *mut u64,
+) -> i32 {
     use std::ffi::CStr;
-    let cstr = CStr::from_ptr(path_ptr);
+    use std::io::ErrorKind;
 
-    let path = match cstr.to_str() {
-        Ok(s) => s,
-        Err(_) => return 0,
-    };
+    if path_ptr.is_null() || out_counts.is_null() || out_matched.is_null() || out_scanned.is_null() {
+        return BATTLESHIP_ERR_NULL_POINTER;
+    }
 
-    let reader = match create_reader(path) {
-        Ok(r) => r,
-        Err(_) => return 0,
+    let path = match CStr::from_ptr(path_ptr).to_str() {
+        Ok(s) => s,
+        Err(_) => return BATTLESHIP_ERR_INVALID_UTF8,
     };
 
     // Reconstruct u128 values from high and low parts
     let hit_mask = ((hit_mask_high as u128) << 64) | (hit_mask_low as u128);
     let miss_mask = ((miss_mask_high as u128) << 64) | (miss_mask_low as u128);
 
-    match filter_and_count(reader, hit_mask, miss_mask) {
+    let query = match Query::compile(hit_mask, miss_mask, BoardGeometry::STANDARD) {
+        Ok(q) => q,
+        Err(_) => return BATTLESHIP_ERR_INVALID_QUERY,
+    };
+
+    let reader = match create_reader(path) {
+        Ok(r) => r,
+        // The file opened, but its header or compression couldn't be read
+        Err(e) if matches!(e.kind(), ErrorKind::InvalidData | ErrorKind::Unsupported | ErrorKind::UnexpectedEof) => {
+            return BATTLESHIP_ERR_DECODE;
+        }
+        Err(_) => return BATTLESHIP_ERR_BAD_PATH,
+    };
+    let mut scanned = 0u64;
+    let reader = query.guard(reader, path).inspect(|record| scanned += record.is_ok() as u64);
+
+    match filter_and_count(reader, query.hit_mask(), query.miss_mask()) {
         Ok((counts, matched)) => {
             let slice = std::slice::from_raw_parts_mut(out_counts, 81);
             slice.copy_from_slice(&counts[..]);
-            matched
+            *out_matched = matched;
+            *out_scanned = scanned;
+            BATTLESHIP_OK
+        }
+        Err(_) => BATTLESHIP_ERR_DECODE,
+    }
+}
+
+/// Runs a query against a dataset file, filling in `result`. Returns the status also stored in
+/// `result.status`, with the same codes as `battleship_filter_and_count`.
+///
+/// # Safety
+/// `path_ptr` must be null or a null-terminated string, `query` must be null or point to a
+/// `BsQuery`, and `result` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bs_filter(path_ptr: *const c_char, query: *const BsQuery, result: *mut BsResult) -> i32 {
+    if query.is_null() || result.is_null() {
+        return BATTLESHIP_ERR_NULL_POINTER;
+    }
+
+    let query = *query;
+    let result = &mut *result;
+    result.status = battleship_filter_and_count(
+        path_ptr,
+        query.hit_mask_low,
+        query.hit_mask_high,
+        query.miss_mask_low,
+        query.miss_mask_high,
+        result.counts.as_mut_ptr(),
+        &mut result.matched,
+    );
+    result.status
+}
+
+/// A dataset kept open across queries. Created by `bs_session_open` or
+/// `bs_session_open_indexed` and freed by `bs_session_close`.
+pub struct BsSession(Session);
+
+/// Decodes a whole dataset into memory so it can be queried repeatedly with
+/// `bs_session_query`. Returns null if the path isn't valid UTF-8 or the file can't be read.
+///
+/// # Safety
+/// `path_ptr` must be null or a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn bs_session_open(path_ptr: *const c_char) -> *mut BsSession {
+    match c_str(path_ptr).and_then(|path| Session::open(path).ok()) {
+        Some(session) => Box::into_raw(Box::new(BsSession(session))),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Loads the index written by the encoder for a dataset encoded with restarted deltas. Each
+/// query then decodes only the chunks that may contain matches. Returns null on failure.
+///
+/// # Safety
+/// `data_path_ptr` and `index_path_ptr` must be null or null-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn bs_session_open_indexed(data_path_ptr: *const c_char, index_path_ptr: *const c_char) -> *mut BsSession {
+    let session = c_str(data_path_ptr)
+        .zip(c_str(index_path_ptr))
+        .and_then(|(data_path, index_path)| Session::open_indexed(data_path, index_path).ok());
+
+    match session {
+        Some(session) => Box::into_raw(Box::new(BsSession(session))),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Counts hits per cell over the session's boards matching the hit/miss masks, writing the
+/// counts to `out_counts` and the number of matched boards to `out_matched`.
+///
+/// Returns the same status codes as `battleship_filter_and_count`.
+///
+/// # Safety
+/// `session` must be null or a pointer returned by `bs_session_open` that hasn't been closed,
+/// `out_counts` must be null or point to a buffer of at least 81 u32 entries, and
+/// `out_matched` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bs_session_query(
+    session: *const BsSession,
+    hit_mask_low: u64,
+    hit_mask_high: u64,
+    miss_mask_low: u64,
+    miss_mask_high: u64,
+    out_counts: *mut u32,
+    out_matched: *mut u64,
+) -> i32 {
+    if session.is_null() {
+        return BATTLESHIP_ERR_NULL_POINTER;
+    }
+
+    query_session(&(*session).0, hit_mask_low, hit_mask_high, miss_mask_low, miss_mask_high, out_counts, out_matched)
+}
+
+/// Runs a query for `bs_session_query` and `bs_context_query`.
+unsafe fn query_session(
+    session: &Session,
+    hit_mask_low: u64,
+    hit_mask_high: u64,
+    miss_mask_low: u64,
+    miss_mask_high: u64,
+    out_counts: *mut u32,
+    out_matched: *mut u64,
+) -> i32 {
+    if out_counts.is_null() || out_matched.is_null() {
+        return BATTLESHIP_ERR_NULL_POINTER;
+    }
+
+    let hit_mask = ((hit_mask_high as u128) << 64) | (hit_mask_low as u128);
+    let miss_mask = ((miss_mask_high as u128) << 64) | (miss_mask_low as u128);
+
+    let query = match Query::compile(hit_mask, miss_mask, BoardGeometry::STANDARD) {
+        Ok(q) => q,
+        Err(_) => return BATTLESHIP_ERR_INVALID_QUERY,
+    };
+
+    match session.query(&query) {
+        Ok(result) => {
+            std::slice::from_raw_parts_mut(out_counts, 81).copy_from_slice(&result.counts);
+            *out_matched = result.matched;
+            BATTLESHIP_OK
         }
-        Err(_) => 0,
+        Err(_) => BATTLESHIP_ERR_DECODE,
+    }
+}
+
+/// Frees a session. Passing null is a no-op.
+///
+/// # Safety
+/// `session` must be null or a pointer returned by `bs_session_open` that hasn't been closed.
+#[no_mangle]
+pub unsafe extern "C" fn bs_session_close(session: *mut BsSession) {
+    if !session.is_null() {
+        drop(Box::from_raw(session));
+    }
+}
+
+/// A reference-counted dataset that can be shared across threads.
+///
+/// Every function taking a `BsContext` may be called concurrently from any number of threads
+/// with the same context. Each thread that keeps the context should hold its own reference,
+/// taken with `bs_context_retain` and dropped with `bs_context_release`; the dataset is freed
+/// when the last reference is released.
+pub struct BsContext(Session);
+
+// Hosts rely on contexts being shareable, so make sure it stays that way
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<BsContext>();
+};
+
+fn into_context(session: Option<Session>) -> *const BsContext {
+    match session {
+        Some(session) => Arc::into_raw(Arc::new(BsContext(session))),
+        None => std::ptr::null(),
+    }
+}
+
+/// Like `bs_session_open`, returning a context holding one reference. Returns null on failure.
+///
+/// # Safety
+/// `path_ptr` must be null or a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn bs_context_open(path_ptr: *const c_char) -> *const BsContext {
+    into_context(c_str(path_ptr).and_then(|path| Session::open(path).ok()))
+}
+
+/// Like `bs_session_open_indexed`, returning a context holding one reference. Returns null on
+/// failure.
+///
+/// # Safety
+/// `data_path_ptr` and `index_path_ptr` must be null or null-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn bs_context_open_indexed(data_path_ptr: *const c_char, index_path_ptr: *const c_char) -> *const BsContext {
+    into_context(
+        c_str(data_path_ptr)
+            .zip(c_str(index_path_ptr))
+            .and_then(|(data_path, index_path)| Session::open_indexed(data_path, index_path).ok()),
+    )
+}
+
+/// Takes another reference to a context and returns it.
+///
+/// # Safety
+/// `context` must be a pointer returned by `bs_context_open` with at least one reference left.
+#[no_mangle]
+pub unsafe extern "C" fn bs_context_retain(context: *const BsContext) -> *const BsContext {
+    Arc::increment_strong_count(context);
+    context
+}
+
+/// Drops a reference to a context, freeing it when it was the last one. Passing null is a no-op.
+///
+/// # Safety
+/// `context` must be null or a pointer returned by `bs_context_open` with at least one
+/// reference left, and the caller must not use its reference afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn bs_context_release(context: *const BsContext) {
+    if !context.is_null() {
+        Arc::decrement_strong_count(context);
+    }
+}
+
+/// Same as `bs_session_query`, for a context. Safe to call concurrently on one context.
+///
+/// # Safety
+/// `context` must be null or a pointer returned by `bs_context_open` with at least one
+/// reference left, `out_counts` must be null or point to a buffer of at least 81 u32 entries,
+/// and `out_matched` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bs_context_query(
+    context: *const BsContext,
+    hit_mask_low: u64,
+    hit_mask_high: u64,
+    miss_mask_low: u64,
+    miss_mask_high: u64,
+    out_counts: *mut u32,
+    out_matched: *mut u64,
+) -> i32 {
+    if context.is_null() {
+        return BATTLESHIP_ERR_NULL_POINTER;
+    }
+
+    query_session(&(*context).0, hit_mask_low, hit_mask_high, miss_mask_low, miss_mask_high, out_counts, out_matched)
+}
+
+/// Same as `bs_filter`, for a context. Safe to call concurrently on one context.
+///
+/// # Safety
+/// `context` must be null or a pointer returned by `bs_context_open` with at least one
+/// reference left, `query` must be null or point to a `BsQuery`, and `result` must be null or
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bs_context_filter(context: *const BsContext, query: *const BsQuery, result: *mut BsResult) -> i32 {
+    if query.is_null() || result.is_null() {
+        return BATTLESHIP_ERR_NULL_POINTER;
+    }
+
+    let query = *query;
+    let result = &mut *result;
+    result.status = bs_context_query(
+        context,
+        query.hit_mask_low,
+        query.hit_mask_high,
+        query.miss_mask_low,
+        query.miss_mask_high,
+        result.counts.as_mut_ptr(),
+        &mut result.matched,
+    );
+    result.status
+}
+
+/// Borrows a C string as UTF-8, or `None` if it's null or not valid UTF-8.
+unsafe fn c_str<'a>(ptr: *const c_char) -> Option<&'a str> {
+    if ptr.is_null() {
+        return None;
     }
+    std::ffi::CStr::from_ptr(ptr).to_str().ok()
+}
+
+/// Result codes accepted by `battleship_game_record_shot`.
+pub const BATTLESHIP_SHOT_MISS: u32 = 0;
+pub const BATTLESHIP_SHOT_HIT: u32 = 1;
+pub const BATTLESHIP_SHOT_SUNK: u32 = 2;
+
+/// Creates an empty game. Free it with `battleship_game_free`.
+#[no_mangle]
+pub extern "C" fn battleship_game_new() -> *mut GameState {
+    Box::into_raw(Box::new(GameState::new()))
+}
+
+/// Frees a game created by `battleship_game_new`.
+///
+/// # Safety
+/// `game` must be null or a pointer returned by `battleship_game_new` that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn battleship_game_free(game: *mut GameState) {
+    if !game.is_null() {
+        drop(Box::from_raw(game));
+    }
+}
+
+/// Records a shot at column `x`, row `y` (both 0-based).
+///
+/// Returns 0 on success, -1 for an unknown result code, and -2 if the shot is out of bounds,
+/// was already taken, or contradicts earlier results.
+///
+/// # Safety
+/// `game` must be a valid pointer returned by `battleship_game_new`.
+#[no_mangle]
+pub unsafe extern "C" fn battleship_game_record_shot(game: *mut GameState, x: u32, y: u32, result: u32) -> i32 {
+    let game = &mut *game;
+
+    let result = match result {
+        BATTLESHIP_SHOT_MISS => ShotResult::Miss,
+        BATTLESHIP_SHOT_HIT => ShotResult::Hit,
+        BATTLESHIP_SHOT_SUNK => ShotResult::Sunk,
+        _ => return -1,
+    };
+
+    match game.record_shot(Point::new(x as i32, y as i32), result) {
+        Ok(()) => 0,
+        Err(_) => -2,
+    }
+}
+
+/// Removes the most recent shot. Returns 1 if a shot was removed and 0 if there were none.
+///
+/// # Safety
+/// `game` must be a valid pointer returned by `battleship_game_new`.
+#[no_mangle]
+pub unsafe extern "C" fn battleship_game_undo(game: *mut GameState) -> i32 {
+    let game = &mut *game;
+    game.undo().is_some() as i32
+}
+
+/// Writes the game's hit and miss masks (including misses inferred from sunk ships) as
+/// 64-bit halves.
+///
+/// # Safety
+/// `game` must be a valid pointer returned by `battleship_game_new`, and every output pointer
+/// must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn battleship_game_masks(
+    game: *const GameState,
+    hit_mask_low: *mut u64,
+    hit_mask_high: *mut u64,
+    miss_mask_low: *mut u64,
+    miss_mask_high: *mut u64,
+) {
+    let (hit_mask, miss_mask) = (*game).to_masks();
+    *hit_mask_low = hit_mask as u64;
+    *hit_mask_high = (hit_mask >> 64) as u64;
+    *miss_mask_low = miss_mask as u64;
+    *miss_mask_high = (miss_mask >> 64) as u64;
+}
+
+/// Runs `filter_and_count_ffi` with the game's current masks.
+///
+/// # Safety
+/// `game` must be a valid pointer returned by `battleship_game_new`, `path_ptr` must be a
+/// null-terminated string, and `out_counts` must point to a buffer of at least 81 u32 entries.
+#[no_mangle]
+pub unsafe extern "C" fn battleship_game_filter_and_count(
+    game: *const GameState,
+    path_ptr: *const c_char,
+    out_counts: *mut u32,
+) -> u64 {
+    let (hit_mask, miss_mask) = (*game).to_masks();
+    filter_and_count_ffi(
+        path_ptr,
+        hit_mask as u64,
+        (hit_mask >> 64) as u64,
+        miss_mask as u64,
+        (miss_mask >> 64) as u64,
+        out_counts,
+    )
 }
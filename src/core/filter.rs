@@ -1,4 +1,170 @@
+use crate::core::accumulator::{CellCounts, CountAccumulator, GeometryCellCounts};
+#[cfg(feature = "async")]
+use crate::core::async_reader::AsyncBoardReader;
+use crate::core::bitslice::BitsliceBatch;
+use crate::core::encoder::{encode_records, EncodeOptions};
+use crate::core::geometry::BoardGeometry;
+#[cfg(feature = "gpu")]
+use crate::core::gpu::{GpuCounter, GPU_BATCH_RECORDS};
+use crate::core::index::DatasetIndex;
+use crate::core::random::SplitMix64;
+use crate::core::reader::{dataset_len, open_chunk, open_chunk_verified, read_verified_chunk};
+use crate::generator::symmetries::distinct_symmetries_with_geometry;
 use rayon::prelude::*;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// How matched boards are counted during a scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScanMode {
+    /// Pick `Streaming` for selective queries and `Chunked` otherwise.
+    #[default]
+    Auto,
+    /// Buffer matches into chunks and count each chunk in parallel with rayon.
+    Chunked,
+    /// Count each match inline as it is read, without buffering.
+    Streaming,
+    /// Buffer records into chunks, transpose them 64 at a time into a [`BitsliceBatch`], and
+    /// test and count each batch word-parallel on every rayon thread. `Auto` never picks it.
+    Bitsliced,
+}
+
+/// Inclusive range of allowed ship-cell counts per record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PopcountRange {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl PopcountRange {
+    pub fn exactly(count: u32) -> Self {
+        Self { min: count, max: count }
+    }
+
+    pub fn contains(&self, board: u128) -> bool {
+        (self.min..=self.max).contains(&board.count_ones())
+    }
+}
+
+impl std::str::FromStr for PopcountRange {
+    type Err = String;
+
+    /// Parses `N`, `MIN-MAX`, `MIN-` or `-MAX`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |value: &str, default: u32| -> Result<u32, String> {
+            if value.is_empty() {
+                Ok(default)
+            } else {
+                value.trim().parse().map_err(|_| format!("Invalid popcount '{}'", value))
+            }
+        };
+
+        let range = match s.split_once('-') {
+            Some((min, max)) => Self { min: parse(min, 0)?, max: parse(max, 128)? },
+            None => Self::exactly(parse(s, 0)?),
+        };
+
+        if range.min > range.max {
+            return Err(format!("Popcount range '{}' is empty", s));
+        }
+
+        Ok(range)
+    }
+}
+
+/// Options controlling how a query is executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QueryOptions {
+    pub mode: ScanMode,
+    /// Number of matched boards buffered before a parallel counting pass (chunked mode).
+    pub chunk_size: usize,
+    /// Minimum number of constrained cells (hits + misses) for `Auto` to choose streaming.
+    pub selective_cells: u32,
+    /// Treat each record as a canonical board and count every distinct board in its symmetry
+    /// orbit, so a canonical-only dataset can answer queries about all boards.
+    pub expand_symmetries: bool,
+    /// Only match records whose number of ship cells falls in this range.
+    pub popcount: Option<PopcountRange>,
+    /// Dimensions of the boards in the dataset, used when expanding symmetries.
+    pub geometry: BoardGeometry,
+    /// Check every chunk against its checksum in the index before using it, failing with the
+    /// chunk number on a mismatch. Only indexed scans can check chunks.
+    pub strict: bool,
+    /// Number of threads that filter and count in parallel, in a rayon pool of the scan's own.
+    /// `None` uses rayon's global pool, which has a thread per CPU.
+    pub threads: Option<usize>,
+}
+
+impl Default for QueryOptions {
+    fn default() -> Self {
+        Self {
+            mode: ScanMode::Auto,
+            chunk_size: 1_000_000,
+            selective_cells: 6,
+            expand_symmetries: false,
+            popcount: None,
+            geometry: BoardGeometry::STANDARD,
+            strict: false,
+            threads: None,
+        }
+    }
+}
+
+impl QueryOptions {
+    /// Resolves `Auto` into a concrete mode for the given query masks.
+    pub fn resolve_mode(&self, hit_mask: u128, miss_mask: u128) -> ScanMode {
+        match self.mode {
+            ScanMode::Auto => {
+                if (hit_mask | miss_mask).count_ones() >= self.selective_cells {
+                    ScanMode::Streaming
+                } else {
+                    ScanMode::Chunked
+                }
+            }
+            mode => mode,
+        }
+    }
+}
+
+/// Per-cell counts and total matched boards produced by a query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FilterResult {
+    #[cfg_attr(feature = "serde", serde(with = "crate::core::serde_arrays"))]
+    pub counts: [u32; 81],
+    pub matched: u64,
+}
+
+impl FilterResult {
+    pub fn new(counts: [u32; 81], matched: u64) -> Self {
+        Self { counts, matched }
+    }
+
+    /// The fraction of matched boards with a ship in each cell.
+    ///
+    /// Every probability is 0 when no boards matched.
+    pub fn probabilities(&self) -> [f64; 81] {
+        let mut probabilities = [0.0; 81];
+
+        if self.matched > 0 {
+            for (probability, &count) in probabilities.iter_mut().zip(&self.counts) {
+                *probability = count as f64 / self.matched as f64;
+            }
+        }
+
+        probabilities
+    }
+}
+
+impl From<([u32; 81], u64)> for FilterResult {
+    fn from((counts, matched): ([u32; 81], u64)) -> Self {
+        Self::new(counts, matched)
+    }
+}
 
 /// Reads an iterator of u128 hit masks, filters records by hit/miss masks,
 /// and accumulates counts of hits per cell (81 cells).
@@ -6,70 +172,609 @@ pub fn filter_and_count<I>(reader: I, hit_mask: u128, miss_mask: u128) -> std::i
 where
     I: IntoIterator<Item = std::io::Result<u128>>,
 {
-    const CHUNK_SIZE: usize = 1_000_000;
-    let mut counts = [0u32; 81];
-    let mut total_matched = 0u64;
-    let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+    filter_and_count_with_options(reader, hit_mask, miss_mask, &QueryOptions::default())
+}
+
+/// Same as [`filter_and_count`], with explicit control over how the scan is executed.
+pub fn filter_and_count_with_options<I>(
+    reader: I,
+    hit_mask: u128,
+    miss_mask: u128,
+    options: &QueryOptions,
+) -> std::io::Result<([u32; 81], u64)>
+where
+    I: IntoIterator<Item = std::io::Result<u128>>,
+{
+    if options.geometry != BoardGeometry::STANDARD {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("81-cell counts require a 9x9 board, not {}; use filter_and_count_cells", options.geometry),
+        ));
+    }
+
+    filter_and_accumulate(reader, hit_mask, miss_mask, options, CellCounts::new)
+}
 
+/// Like [`filter_and_count_with_options`], for boards of any geometry. Returns one count per
+/// cell of `options.geometry`, in row-major order.
+pub fn filter_and_count_cells<I>(
+    reader: I,
+    hit_mask: u128,
+    miss_mask: u128,
+    options: &QueryOptions,
+) -> std::io::Result<(Vec<u32>, u64)>
+where
+    I: IntoIterator<Item = std::io::Result<u128>>,
+{
+    let geometry = options.geometry;
+    filter_and_accumulate(reader, hit_mask, miss_mask, options, || GeometryCellCounts::new(geometry))
+}
+
+/// Filters records by hit/miss masks and feeds every match to a [`CountAccumulator`].
+///
+/// `make_accumulator` is called once per parallel worker to create an empty accumulator.
+pub fn filter_and_accumulate<I, A, F>(
+    reader: I,
+    hit_mask: u128,
+    miss_mask: u128,
+    options: &QueryOptions,
+    make_accumulator: F,
+) -> std::io::Result<A::Output>
+where
+    I: IntoIterator<Item = std::io::Result<u128>>,
+    A: CountAccumulator,
+    F: Fn() -> A + Sync,
+{
+    // A chunk is only processed once it's full, so an empty one would buffer every record
+    if options.chunk_size == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Chunk size must be positive"));
+    }
+
+    let mut accumulator = make_accumulator();
+    let expand = options.expand_symmetries;
+    let filter = Filter::new(hit_mask, miss_mask, options);
+    let pool = ScanPool::new(options)?;
+
+    match options.resolve_mode(hit_mask, miss_mask) {
+        ScanMode::Streaming => {
+            for board in reader {
+                visit_matches(&mut accumulator, board?, &filter, expand);
+            }
+        }
+
+        ScanMode::Bitsliced => {
+            let mut chunk = Vec::with_capacity(options.chunk_size);
+
+            for board in reader {
+                let board = board?;
+
+                // Symmetric variants are batched as boards of their own
+                if expand {
+                    chunk.extend(distinct_symmetries_with_geometry(board, options.geometry));
+                } else {
+                    chunk.push(board);
+                }
+
+                if chunk.len() >= options.chunk_size {
+                    accumulator.merge(pool.install(|| process_bitsliced_chunk(&chunk, &filter, &make_accumulator)));
+                    chunk.clear();
+                }
+            }
+
+            if !chunk.is_empty() {
+                accumulator.merge(pool.install(|| process_bitsliced_chunk(&chunk, &filter, &make_accumulator)));
+            }
+        }
+
+        _ => {
+            let mut chunk = Vec::with_capacity(options.chunk_size);
+
+            for board in reader {
+                let board = board?;
+
+                // Filter (symmetric variants are filtered individually while processing the chunk)
+                if !expand && !filter.matches(board) { continue; }
+
+                chunk.push(board);
+
+                if chunk.len() == options.chunk_size {
+                    accumulator.merge(pool.install(|| process_chunk(&chunk, &filter, expand, &make_accumulator)));
+                    chunk.clear();
+                }
+            }
+
+            if !chunk.is_empty() {
+                accumulator.merge(pool.install(|| process_chunk(&chunk, &filter, expand, &make_accumulator)));
+            }
+        }
+    }
+
+    Ok(accumulator.finish())
+}
+
+/// Same as [`filter_and_count`], reading from an async dataset reader so that waiting for data
+/// doesn't block a thread.
+#[cfg(feature = "async")]
+pub async fn filter_and_count_async(
+    reader: &mut AsyncBoardReader,
+    hit_mask: u128,
+    miss_mask: u128,
+) -> io::Result<([u32; 81], u64)> {
+    filter_and_count_async_with_options(reader, hit_mask, miss_mask, &QueryOptions::default()).await
+}
+
+/// Same as [`filter_and_count_async`], honoring the popcount, symmetry expansion and geometry of
+/// `options`. Matches are always counted inline as they're read, whatever `options.mode` says.
+#[cfg(feature = "async")]
+pub async fn filter_and_count_async_with_options(
+    reader: &mut AsyncBoardReader,
+    hit_mask: u128,
+    miss_mask: u128,
+    options: &QueryOptions,
+) -> io::Result<([u32; 81], u64)> {
+    if options.geometry != BoardGeometry::STANDARD {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("81-cell counts require a 9x9 board, not {}", options.geometry),
+        ));
+    }
+
+    let filter = Filter::new(hit_mask, miss_mask, options);
+    let mut counts = CellCounts::new();
+
+    while let Some(board) = reader.next_board().await {
+        visit_matches(&mut counts, board?, &filter, options.expand_symmetries);
+    }
+
+    Ok(counts.finish())
+}
+
+/// Same as [`filter_and_count_cells`], filtering and counting on a GPU with a [`GpuCounter`].
+///
+/// Falls back to the CPU when no GPU is available or `options.expand_symmetries` is set, so the
+/// result never depends on the machine. Records are still read and decoded on the CPU, so this
+/// pays off for full-dataset scans where counting dominates.
+#[cfg(feature = "gpu")]
+pub fn filter_and_count_gpu<I>(reader: I, hit_mask: u128, miss_mask: u128, options: &QueryOptions) -> io::Result<(Vec<u32>, u64)>
+where
+    I: IntoIterator<Item = io::Result<u128>>,
+{
+    let counter = if options.expand_symmetries { None } else { GpuCounter::new().ok() };
+    let Some(counter) = counter else {
+        return filter_and_count_cells(reader, hit_mask, miss_mask, options);
+    };
+
+    let mut counts = vec![0u32; options.geometry.cells()];
+    let mut matched = 0;
+    let mut count_batch = |batch: &[u128]| -> io::Result<()> {
+        let (batch_counts, batch_matched) = counter.count(batch, hit_mask, miss_mask, options)?;
+        for (count, batch_count) in counts.iter_mut().zip(batch_counts) {
+            *count += batch_count;
+        }
+        matched += batch_matched;
+        Ok(())
+    };
+
+    let mut batch = Vec::with_capacity(GPU_BATCH_RECORDS);
     for board in reader {
-        let board = match board {
-            Ok(val) => val,
-            Err(e) => return Err(e),
-        };
+        batch.push(board?);
+        if batch.len() == GPU_BATCH_RECORDS {
+            count_batch(&batch)?;
+            batch.clear();
+        }
+    }
+    if !batch.is_empty() {
+        count_batch(&batch)?;
+    }
 
-        // Filter
-        if (board & hit_mask) != hit_mask { continue; }
-        if (board & miss_mask) != 0 { continue; }
+    Ok((counts, matched))
+}
 
-        // Count matched board
-        total_matched += 1;
+/// Evaluates several (hit mask, miss mask) queries in a single scan, returning the per-cell
+/// counts and number of matched boards of each query, in the same order as `queries`.
+///
+/// Every record is decoded once and tested against all queries, so this is much cheaper than
+/// one [`filter_and_count`] call per query when decoding dominates.
+pub fn filter_and_count_batch<I>(reader: I, queries: &[(u128, u128)]) -> io::Result<Vec<([u32; 81], u64)>>
+where
+    I: IntoIterator<Item = io::Result<u128>>,
+{
+    if queries.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let options = QueryOptions::default();
+    let filters: Vec<Filter> = queries.iter().map(|&(hit_mask, miss_mask)| Filter::new(hit_mask, miss_mask, &options)).collect();
+    let make_counts = || vec![CellCounts::new(); filters.len()];
 
-        chunk.push(board);
+    let mut totals = make_counts();
+    let mut chunk = Vec::with_capacity(options.chunk_size);
 
-        if chunk.len() == CHUNK_SIZE {
-            let local_counts = process_chunk(&chunk);
+    for board in reader {
+        chunk.push(board?);
+
+        if chunk.len() == options.chunk_size {
+            merge_batch(&mut totals, process_batch_chunk(&chunk, &filters, &make_counts));
             chunk.clear();
+        }
+    }
+
+    if !chunk.is_empty() {
+        merge_batch(&mut totals, process_batch_chunk(&chunk, &filters, &make_counts));
+    }
+
+    Ok(totals.into_iter().map(CellCounts::finish).collect())
+}
+
+/// Calls `f` with every board that has all `hit_mask` cells set and no `miss_mask` cell set, in
+/// dataset order, returning the number of matches.
+///
+/// Use this for reductions the crate doesn't provide, such as co-occurrence matrices; the scan
+/// is sequential, so `f` can freely mutate its captures.
+pub fn for_each_matching<I, F>(reader: I, hit_mask: u128, miss_mask: u128, f: F) -> io::Result<u64>
+where
+    I: IntoIterator<Item = io::Result<u128>>,
+    F: FnMut(u128),
+{
+    for_each_matching_with_options(reader, hit_mask, miss_mask, &QueryOptions::default(), f)
+}
+
+/// Same as [`for_each_matching`], honoring the popcount, symmetry expansion and geometry of
+/// `options`. With `options.expand_symmetries`, `f` is called with each matching board of every
+/// record's orbit.
+pub fn for_each_matching_with_options<I, F>(
+    reader: I,
+    hit_mask: u128,
+    miss_mask: u128,
+    options: &QueryOptions,
+    mut f: F,
+) -> io::Result<u64>
+where
+    I: IntoIterator<Item = io::Result<u128>>,
+    F: FnMut(u128),
+{
+    let filter = Filter::new(hit_mask, miss_mask, options);
+    let mut matched = 0u64;
+
+    for board in reader {
+        for_each_match(board?, &filter, options.expand_symmetries, |board| {
+            matched += 1;
+            f(board);
+        });
+    }
+
+    Ok(matched)
+}
+
+/// How many matches of the query `record` adds: 0 or 1, or with `options.expand_symmetries`, the
+/// number of matching distinct boards in its symmetry orbit. Lets callers that see records on
+/// their way into a scan report its matches as it goes.
+pub fn matching_boards(record: u128, hit_mask: u128, miss_mask: u128, options: &QueryOptions) -> u64 {
+    let mut matched = 0;
+    for_each_match(record, &Filter::new(hit_mask, miss_mask, options), options.expand_symmetries, |_| matched += 1);
+    matched
+}
+
+/// Returns `n` boards chosen uniformly at random from those with every `hit_mask` cell set and
+/// no `miss_mask` cell set, in the order they appear in the dataset. Returns every match if
+/// there are fewer than `n`.
+pub fn sample_matching<I>(reader: I, hit_mask: u128, miss_mask: u128, n: usize, seed: u64) -> io::Result<Vec<u128>>
+where
+    I: IntoIterator<Item = io::Result<u128>>,
+{
+    sample_matching_with_options(reader, hit_mask, miss_mask, n, seed, &QueryOptions::default())
+}
+
+/// Same as [`sample_matching`], honoring the popcount, symmetry expansion and geometry of
+/// `options`.
+///
+/// Uses reservoir sampling in one sequential pass, so only `n` boards are held in memory and a
+/// seed always picks the same boards from the same dataset.
+pub fn sample_matching_with_options<I>(
+    reader: I,
+    hit_mask: u128,
+    miss_mask: u128,
+    n: usize,
+    seed: u64,
+    options: &QueryOptions,
+) -> io::Result<Vec<u128>>
+where
+    I: IntoIterator<Item = io::Result<u128>>,
+{
+    let filter = Filter::new(hit_mask, miss_mask, options);
+    let mut rng = SplitMix64::new(seed);
+    // (position among matches, board), so the sample can be returned in dataset order
+    let mut reservoir: Vec<(u64, u128)> = Vec::with_capacity(n);
+    let mut seen = 0u64;
 
-            for i in 0..81 {
-                counts[i] += local_counts[i];
+    for board in reader {
+        for_each_match(board?, &filter, options.expand_symmetries, |board| {
+            if reservoir.len() < n {
+                reservoir.push((seen, board));
+            } else {
+                let slot = rng.next_below(seen + 1) as usize;
+                if slot < n {
+                    reservoir[slot] = (seen, board);
+                }
             }
+            seen += 1;
+        });
+    }
+
+    reservoir.sort_unstable_by_key(|&(position, _)| position);
+    Ok(reservoir.into_iter().map(|(_, board)| board).collect())
+}
+
+/// Writes every board with all `hit_mask` cells set and no `miss_mask` cell set to `writer` as a
+/// new encoded dataset (see [`encode_records`]), returning its index.
+///
+/// Follow-up queries against the narrowed dataset give the same results as against the original
+/// while reading far fewer records. With `options.expand_symmetries`, every matching board of
+/// each record's orbit is written, so the output is no longer canonical.
+pub fn filter_and_write<I, W>(
+    reader: I,
+    hit_mask: u128,
+    miss_mask: u128,
+    options: &QueryOptions,
+    writer: &mut W,
+    encode_options: &EncodeOptions,
+) -> io::Result<DatasetIndex>
+where
+    I: IntoIterator<Item = io::Result<u128>>,
+    W: Write,
+{
+    let filter = Filter::new(hit_mask, miss_mask, options);
+    let expand = options.expand_symmetries;
+    let mut records = reader.into_iter();
+    let mut pending = VecDeque::new();
+
+    let matches = std::iter::from_fn(move || loop {
+        if let Some(board) = pending.pop_front() {
+            return Some(Ok(board));
+        }
+
+        match records.next()? {
+            Ok(board) => for_each_match(board, &filter, expand, |board| pending.push_back(board)),
+            Err(e) => return Some(Err(e)),
         }
+    });
+
+    encode_records(matches, writer, encode_options)
+}
+
+/// Counts matches in an encoded dataset using its chunk index, decoding only the chunks whose
+/// union/intersection masks show they may contain a match.
+///
+/// Chunks are decoded in parallel. The dataset must have been encoded with restarted delta
+/// chains so each chunk can be decoded on its own; otherwise this falls back to a full scan.
+pub fn filter_and_count_indexed<P, Q>(data_path: P, index_path: Q, hit_mask: u128, miss_mask: u128) -> io::Result<([u32; 81], u64)>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let index = DatasetIndex::load(index_path)?;
+    filter_and_accumulate_indexed(data_path, &index, hit_mask, miss_mask, &QueryOptions::default(), CellCounts::new)
+}
+
+/// Same as [`filter_and_count_indexed`] with an already loaded index, query options and a
+/// custom [`CountAccumulator`].
+pub fn filter_and_accumulate_indexed<P, A, F>(
+    data_path: P,
+    index: &DatasetIndex,
+    hit_mask: u128,
+    miss_mask: u128,
+    options: &QueryOptions,
+    make_accumulator: F,
+) -> io::Result<A::Output>
+where
+    P: AsRef<Path>,
+    A: CountAccumulator,
+    F: Fn() -> A + Sync,
+{
+    let data_path = data_path.as_ref();
+
+    let data_len = dataset_len(data_path)?;
+    if data_len != index.data_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("index describes {} bytes of data, but {} has {} bytes", index.data_len, data_path.display(), data_len),
+        ));
     }
 
-    if !chunk.is_empty() {
-        let local_counts = process_chunk(&chunk);
-        for i in 0..81 {
-            counts[i] += local_counts[i];
+    if !index.restart_delta {
+        // Chunks can't be decoded on their own, so they're all checked before the full scan
+        if options.strict {
+            for chunk in 0..index.chunks.len() {
+                read_verified_chunk(data_path, index, chunk)?;
+            }
         }
+
+        let reader = crate::core::reader::create_reader(data_path)?;
+        return filter_and_accumulate(reader, hit_mask, miss_mask, options, make_accumulator);
     }
 
-    Ok((counts, total_matched))
+    let filter = Filter::new(hit_mask, miss_mask, options);
+    let expand = options.expand_symmetries;
+
+    // Chunk masks describe the stored boards, not their symmetric variants, so they can only
+    // rule chunks out when symmetries aren't expanded
+    let candidates: Vec<usize> = (0..index.chunks.len())
+        .filter(|&i| expand || index.chunks[i].may_contain(hit_mask, miss_mask))
+        .collect();
+
+    let accumulator = ScanPool::new(options)?.install(|| {
+        candidates.par_iter()
+            .try_fold(&make_accumulator, |mut accumulator, &i| {
+                scan_indexed_chunk(data_path, index, i, &mut accumulator, &filter, options)?;
+                Ok::<_, io::Error>(accumulator)
+            })
+            .try_reduce(&make_accumulator, |mut accumulator, other| {
+                accumulator.merge(other);
+                Ok(accumulator)
+            })
+    })?;
+
+    Ok(accumulator.finish())
 }
 
-fn process_chunk(chunk: &[u128]) -> [u32; 81] {
-    chunk.par_iter()
-        .map(|&board| {
-            let mut cell_counts = [0u32; 81];
-
-            // Count hits per cell (only consider bits 0-80 for 81-cell board)
-            let mut mask = board & ((1u128 << 81) - 1); // Mask to only consider first 81 bits
-            while mask != 0 {
-                let bit = mask.trailing_zeros() as usize;
-                if bit < 81 {
-                    cell_counts[bit] += 1;
+fn scan_indexed_chunk<A: CountAccumulator>(
+    data_path: &Path,
+    index: &DatasetIndex,
+    chunk: usize,
+    accumulator: &mut A,
+    filter: &Filter,
+    options: &QueryOptions,
+) -> io::Result<()> {
+    let records = if options.strict {
+        open_chunk_verified(data_path, index, chunk)?
+    } else {
+        open_chunk(data_path, index, chunk)?
+    };
+
+    for board in records {
+        visit_matches(accumulator, board?, filter, options.expand_symmetries);
+    }
+
+    Ok(())
+}
+
+/// The per-record predicate applied during a scan.
+pub(crate) struct Filter {
+    hit_mask: u128,
+    miss_mask: u128,
+    popcount: Option<PopcountRange>,
+    geometry: BoardGeometry,
+}
+
+impl Filter {
+    pub(crate) fn new(hit_mask: u128, miss_mask: u128, options: &QueryOptions) -> Self {
+        Self { hit_mask, miss_mask, popcount: options.popcount, geometry: options.geometry }
+    }
+
+    pub(crate) fn matches(&self, board: u128) -> bool {
+        if (board & self.hit_mask) != self.hit_mask { return false; }
+        if (board & self.miss_mask) != 0 { return false; }
+
+        match self.popcount {
+            Some(range) => range.contains(board),
+            None => true,
+        }
+    }
+
+    /// The records of `batch` that match, as a bit per record.
+    pub(crate) fn matches_batch(&self, batch: &BitsliceBatch) -> u64 {
+        let mut matches = batch.matching(self.hit_mask, self.miss_mask);
+
+        if let (Some(range), true) = (self.popcount, matches != 0) {
+            for (k, &board) in batch.records().iter().enumerate() {
+                if !range.contains(board) {
+                    matches &= !(1 << k);
                 }
-                mask &= mask - 1; // Faster way to clear lowest set bit
             }
+        }
 
-            cell_counts
+        matches
+    }
+}
+
+/// Visits `board` if it matches, or every matching distinct board in its symmetry orbit.
+pub(crate) fn visit_matches<A: CountAccumulator>(accumulator: &mut A, board: u128, filter: &Filter, expand: bool) {
+    for_each_match(board, filter, expand, |board| accumulator.visit(board));
+}
+
+/// Calls `f` with `board` if it matches, or with every matching distinct board in its symmetry orbit.
+pub(crate) fn for_each_match(board: u128, filter: &Filter, expand: bool, mut f: impl FnMut(u128)) {
+    if !expand {
+        if filter.matches(board) {
+            f(board);
+        }
+        return;
+    }
+
+    for variant in distinct_symmetries_with_geometry(board, filter.geometry) {
+        if filter.matches(variant) {
+            f(variant);
+        }
+    }
+}
+
+/// Where a scan's parallel work runs: a pool of `options.threads` threads, or rayon's global pool.
+struct ScanPool(Option<rayon::ThreadPool>);
+
+impl ScanPool {
+    fn new(options: &QueryOptions) -> io::Result<Self> {
+        let pool = match options.threads {
+            Some(threads) => Some(rayon::ThreadPoolBuilder::new().num_threads(threads).build().map_err(io::Error::other)?),
+            None => None,
+        };
+        Ok(Self(pool))
+    }
+
+    fn install<T: Send>(&self, work: impl FnOnce() -> T + Send) -> T {
+        match &self.0 {
+            Some(pool) => pool.install(work),
+            None => work(),
+        }
+    }
+}
+
+fn process_chunk<A, F>(chunk: &[u128], filter: &Filter, expand: bool, make_accumulator: &F) -> A
+where
+    A: CountAccumulator,
+    F: Fn() -> A + Sync,
+{
+    chunk.par_iter()
+        .fold(make_accumulator, |mut accumulator, &board| {
+            visit_matches(&mut accumulator, board, filter, expand);
+            accumulator
+        })
+        .reduce(make_accumulator, |mut accumulator, other| {
+            accumulator.merge(other);
+            accumulator
+        })
+}
+
+fn process_bitsliced_chunk<A, F>(chunk: &[u128], filter: &Filter, make_accumulator: &F) -> A
+where
+    A: CountAccumulator,
+    F: Fn() -> A + Sync,
+{
+    chunk.par_chunks(BitsliceBatch::CAPACITY)
+        .fold(make_accumulator, |mut accumulator, records| {
+            let batch = BitsliceBatch::new(records);
+            let matches = filter.matches_batch(&batch);
+            if matches != 0 {
+                accumulator.visit_batch(&batch, matches);
+            }
+            accumulator
         })
-        .reduce(
-            || [0u32; 81],
-            |mut acc_counts, counts| {
-                for i in 0..81 {
-                    acc_counts[i] += counts[i];
+        .reduce(make_accumulator, |mut accumulator, other| {
+            accumulator.merge(other);
+            accumulator
+        })
+}
+
+fn process_batch_chunk<F>(chunk: &[u128], filters: &[Filter], make_counts: &F) -> Vec<CellCounts>
+where
+    F: Fn() -> Vec<CellCounts> + Sync,
+{
+    chunk.par_iter()
+        .fold(make_counts, |mut counts, &board| {
+            for (count, filter) in counts.iter_mut().zip(filters) {
+                if filter.matches(board) {
+                    count.visit(board);
                 }
-                acc_counts
-            },
-        )
+            }
+            counts
+        })
+        .reduce(make_counts, |mut counts, other| {
+            merge_batch(&mut counts, other);
+            counts
+        })
+}
+
+fn merge_batch(counts: &mut [CellCounts], other: Vec<CellCounts>) {
+    for (count, other) in counts.iter_mut().zip(other) {
+        count.merge(other);
+    }
 }
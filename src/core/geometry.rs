@@ -0,0 +1,125 @@
+use crate::generator::point::Point;
+use std::fmt;
+use std::str::FromStr;
+
+/// Size in bytes of one record in a dataset file. Every supported geometry fits in a single
+/// little-endian u128, one bit per cell.
+pub const RECORD_SIZE: usize = 16;
+
+/// The dimensions of a board. Records store one bit per cell in row-major order, so a
+/// geometry can have at most 128 cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "String", try_from = "String"))]
+pub struct BoardGeometry {
+    width: usize,
+    height: usize,
+}
+
+impl BoardGeometry {
+    /// The 9x9 board used by the bundled dataset.
+    pub const STANDARD: BoardGeometry = BoardGeometry::new(9, 9);
+
+    /// The 10x10 board from classic Battleship.
+    pub const CLASSIC: BoardGeometry = BoardGeometry::new(10, 10);
+
+    pub const fn new(width: usize, height: usize) -> Self {
+        assert!(width > 0 && height > 0, "Board dimensions must be positive");
+        assert!(width * height <= RECORD_SIZE * 8, "Board must fit in a 128-bit record");
+        Self { width, height }
+    }
+
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    pub const fn height(&self) -> usize {
+        self.height
+    }
+
+    pub const fn cells(&self) -> usize {
+        self.width * self.height
+    }
+
+    pub const fn is_square(&self) -> bool {
+        self.width == self.height
+    }
+
+    /// A mask with one bit set for every cell on the board.
+    pub const fn cell_mask(&self) -> u128 {
+        if self.cells() == 128 {
+            u128::MAX
+        } else {
+            (1u128 << self.cells()) - 1
+        }
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        (0..self.width as i32).contains(&point.x) && (0..self.height as i32).contains(&point.y)
+    }
+
+    pub fn index_of(&self, point: Point) -> usize {
+        assert!(self.contains(point), "Point {:?} is out of bounds for a {} board", point, self);
+        point.y as usize * self.width + point.x as usize
+    }
+
+    pub fn point_of(&self, index: usize) -> Point {
+        assert!(index < self.cells(), "Index {} is out of bounds for a {} board", index, self);
+        Point::new((index % self.width) as i32, (index / self.width) as i32)
+    }
+}
+
+impl Default for BoardGeometry {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+impl fmt::Display for BoardGeometry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+impl FromStr for BoardGeometry {
+    type Err = String;
+
+    /// Parses `WIDTHxHEIGHT` (e.g., `10x10`) or a single side length for square boards.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |value: &str| -> Result<usize, String> {
+            value.trim().parse().map_err(|_| format!("Invalid board size '{}'", s))
+        };
+
+        let (width, height) = match s.to_ascii_lowercase().split_once('x') {
+            Some((width, height)) => (parse(width)?, parse(height)?),
+            None => {
+                let side = parse(s)?;
+                (side, side)
+            }
+        };
+
+        if width == 0 || height == 0 {
+            return Err(format!("Board size '{}' must have positive dimensions", s));
+        }
+
+        if width.checked_mul(height).filter(|&cells| cells <= RECORD_SIZE * 8).is_none() {
+            return Err(format!("Board size '{}' has more than {} cells", s, RECORD_SIZE * 8));
+        }
+
+        Ok(Self::new(width, height))
+    }
+}
+
+impl From<BoardGeometry> for String {
+    fn from(geometry: BoardGeometry) -> Self {
+        geometry.to_string()
+    }
+}
+
+impl TryFrom<String> for BoardGeometry {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
@@ -0,0 +1,172 @@
+use crate::core::filter::QueryOptions;
+use std::io;
+use std::sync::mpsc;
+
+/// Records uploaded and counted per dispatch.
+pub const GPU_BATCH_RECORDS: usize = 1 << 20;
+
+/// Threads per workgroup, as declared in gpu.wgsl.
+const WORKGROUP_SIZE: usize = 256;
+
+/// Per-cell counters plus the matched-record counter.
+const COUNTERS: usize = 129;
+
+/// Bytes of the shader's `Params` uniform: two vec4<u32> masks and four u32s.
+const PARAMS_LEN: usize = 48;
+
+/// Counts matching boards on a GPU with a wgpu compute shader.
+///
+/// Records are uploaded in batches of up to [`GPU_BATCH_RECORDS`]; every record is tested by its
+/// own invocation, and each workgroup sums its matches before adding them to the totals. Only
+/// plain hit/miss queries with an optional popcount range run on the GPU; see
+/// [`crate::core::filter::filter_and_count_gpu`] for the version that falls back to the CPU.
+pub struct GpuCounter {
+    adapter_name: String,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    records: wgpu::Buffer,
+    params: wgpu::Buffer,
+    counts: wgpu::Buffer,
+    readback: wgpu::Buffer,
+}
+
+impl GpuCounter {
+    /// Sets up the first GPU wgpu finds, failing with `Unsupported` if there's none.
+    pub fn new() -> io::Result<Self> {
+        let unavailable = |message: String| io::Error::new(io::ErrorKind::Unsupported, message);
+
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        }))
+        .ok_or_else(|| unavailable("No GPU adapter is available".to_string()))?;
+
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("battleship"),
+                required_features: wgpu::Features::empty(),
+                required_limits: wgpu::Limits::downlevel_defaults(),
+                memory_hints: wgpu::MemoryHints::Performance,
+            },
+            None,
+        ))
+        .map_err(|e| unavailable(format!("Couldn't open the GPU: {}", e)))?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("battleship count"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("gpu.wgsl").into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("battleship count"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let buffer = |label, size: usize, usage| {
+            device.create_buffer(&wgpu::BufferDescriptor { label: Some(label), size: size as u64, usage, mapped_at_creation: false })
+        };
+        let records = buffer("records", GPU_BATCH_RECORDS * 16, wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST);
+        let params = buffer("params", PARAMS_LEN, wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST);
+        let counts = buffer("counts", COUNTERS * 4, wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST);
+        let readback = buffer("readback", COUNTERS * 4, wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST);
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("battleship count"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: records.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: params.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: counts.as_entire_binding() },
+            ],
+        });
+
+        Ok(Self {
+            adapter_name: adapter.get_info().name,
+            device,
+            queue,
+            pipeline,
+            bind_group,
+            records,
+            params,
+            counts,
+            readback,
+        })
+    }
+
+    /// Name of the GPU in use.
+    pub fn adapter_name(&self) -> &str {
+        &self.adapter_name
+    }
+
+    /// Counts the ship cells of the boards in `records` that match, returning one count per
+    /// cell of `options.geometry` and the number of matches. Symmetry expansion isn't supported.
+    pub fn count(&self, records: &[u128], hit_mask: u128, miss_mask: u128, options: &QueryOptions) -> io::Result<(Vec<u32>, u64)> {
+        if options.expand_symmetries {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "The GPU counter can't expand symmetries"));
+        }
+
+        let mut counts = vec![0u32; options.geometry.cells()];
+        let mut matched = 0;
+
+        for batch in records.chunks(GPU_BATCH_RECORDS) {
+            let totals = self.count_batch(batch, hit_mask, miss_mask, options)?;
+            for (count, total) in counts.iter_mut().zip(&totals) {
+                *count += total;
+            }
+            matched += totals[COUNTERS - 1] as u64;
+        }
+
+        Ok((counts, matched))
+    }
+
+    fn count_batch(&self, batch: &[u128], hit_mask: u128, miss_mask: u128, options: &QueryOptions) -> io::Result<[u32; COUNTERS]> {
+        let (min_popcount, max_popcount) = options.popcount.map_or((0, 128), |range| (range.min, range.max));
+
+        let mut params = Vec::with_capacity(PARAMS_LEN);
+        params.extend_from_slice(&hit_mask.to_le_bytes());
+        params.extend_from_slice(&miss_mask.to_le_bytes());
+        for value in [batch.len() as u32, options.geometry.cells() as u32, min_popcount, max_popcount] {
+            params.extend_from_slice(&value.to_le_bytes());
+        }
+        let records: Vec<u8> = batch.iter().flat_map(|board| board.to_le_bytes()).collect();
+
+        self.queue.write_buffer(&self.params, 0, &params);
+        self.queue.write_buffer(&self.records, 0, &records);
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("battleship count") });
+        encoder.clear_buffer(&self.counts, 0, None);
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("battleship count"), timestamp_writes: None });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.dispatch_workgroups(batch.len().div_ceil(WORKGROUP_SIZE) as u32, 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&self.counts, 0, &self.readback, 0, (COUNTERS * 4) as u64);
+        self.queue.submit(Some(encoder.finish()));
+
+        let (sender, receiver) = mpsc::channel();
+        let slice = self.readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver.recv()
+            .map_err(|_| io::Error::other("The GPU stopped before returning its counts"))?
+            .map_err(|e| io::Error::other(format!("Couldn't read the GPU's counts: {}", e)))?;
+
+        let mut totals = [0u32; COUNTERS];
+        for (total, bytes) in totals.iter_mut().zip(slice.get_mapped_range().chunks_exact(4)) {
+            *total = u32::from_le_bytes(bytes.try_into().unwrap());
+        }
+        self.readback.unmap();
+
+        Ok(totals)
+    }
+}
@@ -0,0 +1,60 @@
+// Filters a batch of records and counts the ship cells of the matches, for `GpuCounter`.
+//
+// Each invocation tests one record. Matches are counted in workgroup memory first, then each
+// workgroup adds its totals to `counts` once, so the global atomics see little contention.
+// `counts[128]` is the number of matched records.
+
+struct Params {
+    hit: vec4<u32>,
+    miss: vec4<u32>,
+    records: u32,
+    cells: u32,
+    min_popcount: u32,
+    max_popcount: u32,
+}
+
+@group(0) @binding(0) var<storage, read> records: array<vec4<u32>>;
+@group(0) @binding(1) var<uniform> params: Params;
+@group(0) @binding(2) var<storage, read_write> counts: array<atomic<u32>, 129>;
+
+var<workgroup> local_counts: array<atomic<u32>, 129>;
+
+@compute @workgroup_size(256)
+fn main(@builtin(global_invocation_id) global: vec3<u32>, @builtin(local_invocation_index) lane: u32) {
+    if lane < 129u {
+        atomicStore(&local_counts[lane], 0u);
+    }
+    workgroupBarrier();
+
+    let index = global.x;
+    if index < params.records {
+        let board = records[index];
+        let popcount = countOneBits(board.x) + countOneBits(board.y) + countOneBits(board.z) + countOneBits(board.w);
+        let matches = all((board & params.hit) == params.hit)
+            && all((board & params.miss) == vec4<u32>(0u))
+            && popcount >= params.min_popcount
+            && popcount <= params.max_popcount;
+
+        if matches {
+            atomicAdd(&local_counts[128], 1u);
+            for (var word = 0u; word < 4u; word++) {
+                var bits = board[word];
+                while bits != 0u {
+                    let cell = word * 32u + firstTrailingBit(bits);
+                    if cell < params.cells {
+                        atomicAdd(&local_counts[cell], 1u);
+                    }
+                    bits &= bits - 1u;
+                }
+            }
+        }
+    }
+    workgroupBarrier();
+
+    if lane < 129u {
+        let count = atomicLoad(&local_counts[lane]);
+        if count != 0u {
+            atomicAdd(&counts[lane], count);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! gRPC service, built with the `grpc` feature (see `proto/battleship.proto`).
+//!
+//! Queries run against a [`Session`], so results match the CLI's and the other bindings'.
+
+use crate::core::geometry::BoardGeometry;
+use crate::core::query::Query;
+use crate::core::session::Session;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+/// Types and stubs generated from `proto/battleship.proto`.
+pub mod proto {
+    tonic::include_proto!("battleship");
+}
+
+use proto::battleship_server::{Battleship, BattleshipServer};
+use proto::{Board, Heatmap, QueryRequest};
+
+/// Boards buffered for a `MatchBoards` client before the scan waits for it to catch up.
+const STREAM_BUFFER: usize = 1024;
+
+/// Answers `Query` and `MatchBoards` requests from a shared session.
+#[derive(Debug, Clone)]
+pub struct BattleshipService {
+    session: Arc<Session>,
+}
+
+impl BattleshipService {
+    pub fn new(session: Session) -> Self {
+        Self { session: Arc::new(session) }
+    }
+
+    /// Wraps the service for adding to a [`tonic::transport::Server`].
+    pub fn into_server(self) -> BattleshipServer<Self> {
+        BattleshipServer::new(self)
+    }
+}
+
+fn compile(request: &QueryRequest) -> io::Result<Query> {
+    let hit_mask = (request.hit_high as u128) << 64 | request.hit_low as u128;
+    let miss_mask = (request.miss_high as u128) << 64 | request.miss_low as u128;
+    Ok(Query::compile(hit_mask, miss_mask, BoardGeometry::STANDARD)?)
+}
+
+fn status(error: io::Error) -> Status {
+    match error.kind() {
+        io::ErrorKind::InvalidInput => Status::invalid_argument(error.to_string()),
+        _ => Status::internal(error.to_string()),
+    }
+}
+
+#[tonic::async_trait]
+impl Battleship for BattleshipService {
+    async fn query(&self, request: Request<QueryRequest>) -> Result<Response<Heatmap>, Status> {
+        let query = compile(request.get_ref()).map_err(status)?;
+        let session = Arc::clone(&self.session);
+
+        // Scans take long enough to stall other requests if run on the async workers
+        let result = tokio::task::spawn_blocking(move || session.query(&query))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map_err(status)?;
+
+        Ok(Response::new(Heatmap { counts: result.counts.to_vec(), matched: result.matched }))
+    }
+
+    type MatchBoardsStream = ReceiverStream<Result<Board, Status>>;
+
+    async fn match_boards(&self, request: Request<QueryRequest>) -> Result<Response<Self::MatchBoardsStream>, Status> {
+        let query = compile(request.get_ref()).map_err(status)?;
+        let session = Arc::clone(&self.session);
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+
+        tokio::task::spawn_blocking(move || {
+            // Stops scanning as soon as the client goes away
+            let scanned = session.for_each_match(&query, |board| {
+                sender.blocking_send(Ok(Board { low: board as u64, high: (board >> 64) as u64 })).is_ok()
+            });
+
+            if let Err(e) = scanned {
+                let _ = sender.blocking_send(Err(status(e)));
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
+
+/// Serves `session` on `addr` until the process exits.
+pub async fn serve(session: Session, addr: SocketAddr) -> Result<(), tonic::transport::Error> {
+    tonic::transport::Server::builder()
+        .add_service(BattleshipService::new(session).into_server())
+        .serve(addr)
+        .await
+}
@@ -0,0 +1,150 @@
+use crate::core::geometry::BoardGeometry;
+use crate::generator::fleet::{Fleet, MAX_SHIP_LENGTH};
+use std::fmt;
+use std::io::{self, Cursor, Read, Write};
+
+/// Identifies a dataset file that starts with a [`DatasetHeader`].
+pub const HEADER_MAGIC: [u8; 4] = *b"BSDS";
+
+/// Format version written in new headers. Version 0 is the original headerless format.
+pub const HEADER_VERSION: u16 = 1;
+
+/// Size in bytes of an encoded header.
+pub const HEADER_LEN: usize = 32;
+
+const FLAG_DELTA_ENCODED: u8 = 1 << 0;
+
+/// Where the number of ships of each length is stored, as `(length, offset)` pairs.
+const FLEET_OFFSETS: [(usize, usize); MAX_SHIP_LENGTH - 1] = [(2, 9), (3, 10), (4, 11), (5, 28), (6, 29), (7, 30), (8, 31)];
+
+/// Stored in place of the record count when it isn't known, e.g. when streaming to stdout.
+const UNKNOWN_RECORD_COUNT: u64 = u64::MAX;
+
+/// Describes the records that follow it in a dataset file.
+///
+/// The header is always stored uncompressed, ahead of any zstd frames. It is 32 bytes,
+/// little-endian: the magic `BSDS`, a u16 format version, u8 board width and height, u8 flags,
+/// u8 counts of two-, three- and four-long ships, a u64 record count (`u64::MAX` if unknown), a
+/// u64 delta restart interval (0 if the chain never restarts), and u8 counts of five- to
+/// eight-long ships. The five- to eight-long counts and the two-long count were reserved bytes
+/// in files written before fleets could have other lengths, so those files read the same.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DatasetHeader {
+    pub geometry: BoardGeometry,
+    pub fleet: Fleet,
+    /// Whether records are XOR delta-encoded (the encoder's output) or raw (the generator's).
+    pub delta_encoded: bool,
+    pub record_count: Option<u64>,
+    /// The delta chain restarts from zero every this many records, so chunks can be decoded
+    /// independently.
+    pub restart_interval: Option<u64>,
+}
+
+impl DatasetHeader {
+    pub fn new(geometry: BoardGeometry, fleet: Fleet, delta_encoded: bool) -> Self {
+        Self { geometry, fleet, delta_encoded, record_count: None, restart_interval: None }
+    }
+
+    pub fn to_bytes(&self) -> [u8; HEADER_LEN] {
+        let mut bytes = [0u8; HEADER_LEN];
+
+        bytes[0..4].copy_from_slice(&HEADER_MAGIC);
+        bytes[4..6].copy_from_slice(&HEADER_VERSION.to_le_bytes());
+        bytes[6] = self.geometry.width() as u8;
+        bytes[7] = self.geometry.height() as u8;
+        bytes[8] = if self.delta_encoded { FLAG_DELTA_ENCODED } else { 0 };
+        for (length, offset) in FLEET_OFFSETS {
+            bytes[offset] = self.fleet.count(length) as u8;
+        }
+        bytes[12..20].copy_from_slice(&self.record_count.unwrap_or(UNKNOWN_RECORD_COUNT).to_le_bytes());
+        bytes[20..28].copy_from_slice(&self.restart_interval.unwrap_or(0).to_le_bytes());
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; HEADER_LEN]) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        if bytes[0..4] != HEADER_MAGIC {
+            return Err(invalid("Not a dataset header".to_string()));
+        }
+
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version != HEADER_VERSION {
+            return Err(invalid(format!("Unsupported dataset format version {}", version)));
+        }
+
+        let (width, height) = (bytes[6] as usize, bytes[7] as usize);
+        if width == 0 || height == 0 || width * height > 128 {
+            return Err(invalid(format!("Invalid board size {}x{} in dataset header", width, height)));
+        }
+
+        let mut counts = [0usize; MAX_SHIP_LENGTH + 1];
+        for (length, offset) in FLEET_OFFSETS {
+            counts[length] = bytes[offset] as usize;
+        }
+
+        let record_count = u64::from_le_bytes(bytes[12..20].try_into().unwrap());
+        let restart_interval = u64::from_le_bytes(bytes[20..28].try_into().unwrap());
+
+        Ok(Self {
+            geometry: BoardGeometry::new(width, height),
+            fleet: Fleet::from_counts(&counts),
+            delta_encoded: bytes[8] & FLAG_DELTA_ENCODED != 0,
+            record_count: (record_count != UNKNOWN_RECORD_COUNT).then_some(record_count),
+            restart_interval: (restart_interval != 0).then_some(restart_interval),
+        })
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_bytes())
+    }
+}
+
+impl fmt::Display for DatasetHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "format {}, {} board, fleet {}, {}",
+            HEADER_VERSION,
+            self.geometry,
+            self.fleet,
+            if self.delta_encoded { "delta-encoded" } else { "raw" },
+        )?;
+
+        match self.record_count {
+            Some(count) => write!(f, ", {} records", count),
+            None => write!(f, ", unknown record count"),
+        }
+    }
+}
+
+/// A reader positioned after an optional header, with any bytes peeked at chained back in front.
+pub type AfterHeader<R> = io::Chain<Cursor<Vec<u8>>, R>;
+
+/// Reads a header from the start of `reader` if there is one.
+///
+/// Returns the header and a reader positioned at the first record. Headerless streams are
+/// returned unchanged, with the bytes peeked at to look for the magic chained back in front.
+pub fn read_optional_header<R: Read>(mut reader: R) -> io::Result<(Option<DatasetHeader>, AfterHeader<R>)> {
+    let prefix = read_prefix(&mut reader, HEADER_MAGIC.len())?;
+
+    if prefix != HEADER_MAGIC {
+        return Ok((None, Cursor::new(prefix).chain(reader)));
+    }
+
+    let mut bytes = [0u8; HEADER_LEN];
+    bytes[..HEADER_MAGIC.len()].copy_from_slice(&prefix);
+    reader.read_exact(&mut bytes[HEADER_MAGIC.len()..])?;
+
+    let header = DatasetHeader::from_bytes(&bytes)?;
+    Ok((Some(header), Cursor::new(Vec::new()).chain(reader)))
+}
+
+/// Reads up to `len` bytes, stopping early only at the end of the stream.
+pub(crate) fn read_prefix<R: Read>(reader: &mut R, len: usize) -> io::Result<Vec<u8>> {
+    let mut prefix = Vec::with_capacity(len);
+    reader.by_ref().take(len as u64).read_to_end(&mut prefix)?;
+    Ok(prefix)
+}
@@ -0,0 +1,94 @@
+use crate::core::geometry::BoardGeometry;
+
+/// Terminal color depth used when rendering a heatmap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// xterm 256-color palette (6×6×6 color cube).
+    Ansi256,
+    /// 24-bit RGB escape sequences.
+    TrueColor,
+}
+
+/// Gradient stops from cold (no ships) to hot (most frequent cell).
+const GRADIENT: [(f64, (u8, u8, u8)); 4] = [
+    (0.0, (0, 0, 96)),
+    (0.35, (0, 128, 255)),
+    (0.7, (255, 220, 0)),
+    (1.0, (220, 0, 0)),
+];
+
+/// Levels used by each channel of the xterm 6×6×6 color cube.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Renders a 9x9 grid of counts as an ANSI-colored heatmap, normalized to the maximum cell.
+///
+/// Each cell shows its value as a percentage of the maximum count.
+pub fn render_heatmap(counts: &[u32; 81], mode: ColorMode) -> String {
+    render_heatmap_with_geometry(counts, BoardGeometry::STANDARD, mode)
+}
+
+/// Like [`render_heatmap`], for a row-major grid of counts on a board of any geometry.
+pub fn render_heatmap_with_geometry(counts: &[u32], geometry: BoardGeometry, mode: ColorMode) -> String {
+    assert_eq!(counts.len(), geometry.cells(), "Expected one count per cell of a {} board", geometry);
+
+    let max = counts.iter().copied().max().unwrap_or(0);
+    let mut result = String::new();
+
+    for y in 0..geometry.height() {
+        for x in 0..geometry.width() {
+            let count = counts[y * geometry.width() + x];
+            let t = if max == 0 { 0.0 } else { count as f64 / max as f64 };
+            let (r, g, b) = gradient_color(t);
+
+            // Dark text on bright backgrounds, light text on dark ones
+            let luminance = 0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64;
+            let foreground = if luminance > 140.0 { (0, 0, 0) } else { (255, 255, 255) };
+
+            result.push_str(&color_escape(mode, foreground, false));
+            result.push_str(&color_escape(mode, (r, g, b), true));
+            result.push_str(&format!(" {:>3} ", (t * 100.0).round() as u32));
+        }
+
+        result.push_str("\x1b[0m\n");
+    }
+
+    result
+}
+
+fn gradient_color(t: f64) -> (u8, u8, u8) {
+    let t = t.clamp(0.0, 1.0);
+
+    for window in GRADIENT.windows(2) {
+        let (start, from) = window[0];
+        let (end, to) = window[1];
+
+        if t <= end {
+            let local = (t - start) / (end - start);
+            let lerp = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * local).round() as u8;
+            return (lerp(from.0, to.0), lerp(from.1, to.1), lerp(from.2, to.2));
+        }
+    }
+
+    GRADIENT[GRADIENT.len() - 1].1
+}
+
+fn color_escape(mode: ColorMode, (r, g, b): (u8, u8, u8), background: bool) -> String {
+    let layer = if background { 48 } else { 38 };
+
+    match mode {
+        ColorMode::TrueColor => format!("\x1b[{};2;{};{};{}m", layer, r, g, b),
+        ColorMode::Ansi256 => {
+            let index = 16 + 36 * cube_index(r) + 6 * cube_index(g) + cube_index(b);
+            format!("\x1b[{};5;{}m", layer, index)
+        }
+    }
+}
+
+fn cube_index(value: u8) -> usize {
+    CUBE_LEVELS
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, &level)| (level as i32 - value as i32).abs())
+        .map(|(i, _)| i)
+        .unwrap()
+}
@@ -0,0 +1,163 @@
+use crate::core::encoder::{ChunkStats, EncodeOptions};
+use crate::core::reader::{is_remote, open_remote};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Identifies an index file.
+const INDEX_MAGIC: [u8; 4] = *b"BSIX";
+
+/// Version of the index file layout written by [`DatasetIndex::write_to`] when chunks have
+/// checksums. Indexes without them are written as version 1, which lacks the checksum field.
+pub const INDEX_VERSION: u16 = 2;
+
+const FLAG_RESTART_DELTA: u16 = 1 << 0;
+const FLAG_ZSTD_FRAMES: u16 = 1 << 1;
+const FLAG_CHECKSUMS: u16 = 1 << 2;
+
+/// Chunk layout of an encoded dataset, written alongside it by the encoder.
+///
+/// The file is little-endian: the magic `BSIX`, a u16 version, u16 flags, the u64 length of the
+/// encoded data, a u64 chunk count, then per chunk its u64 offset, u64 record count, u128
+/// union and intersection masks, and in version 2 with the checksum flag, the u64 XXH64 of its
+/// encoded bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatasetIndex {
+    /// Each chunk's delta chain starts from zero, so chunks can be decoded independently.
+    pub restart_delta: bool,
+    /// Each chunk is a separate zstd frame.
+    pub zstd_frames: bool,
+    /// Length in bytes of the encoded data.
+    pub data_len: u64,
+    pub chunks: Vec<ChunkStats>,
+}
+
+impl DatasetIndex {
+    pub fn new(options: &EncodeOptions, data_len: u64, chunks: Vec<ChunkStats>) -> Self {
+        Self {
+            restart_delta: options.restart_delta,
+            zstd_frames: options.zstd_frames.is_some(),
+            data_len,
+            chunks,
+        }
+    }
+
+    /// Total number of records across every chunk.
+    pub fn record_count(&self) -> u64 {
+        self.chunks.iter().map(|chunk| chunk.count).sum()
+    }
+
+    /// The chunk containing record number `record`, and the record's position within it.
+    pub fn locate(&self, record: u64) -> Option<(usize, u64)> {
+        let mut first = 0;
+
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            if record < first + chunk.count {
+                return Some((index, record - first));
+            }
+            first += chunk.count;
+        }
+
+        None
+    }
+
+    /// Byte range of chunk `index` in the encoded data.
+    pub fn chunk_range(&self, index: usize) -> std::ops::Range<u64> {
+        let start = self.chunks[index].offset;
+        let end = self.chunks.get(index + 1).map_or(self.data_len, |next| next.offset);
+        start..end
+    }
+
+    /// Loads an index from a file, or from a remote location (see
+    /// [`crate::core::reader::is_remote`]).
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let location = path.as_ref().to_string_lossy();
+        if is_remote(&location) {
+            return Self::read_from(&mut BufReader::new(open_remote(&location)?));
+        }
+
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let checksums = !self.chunks.is_empty() && self.chunks.iter().all(|chunk| chunk.checksum.is_some());
+        let version: u16 = if checksums { INDEX_VERSION } else { 1 };
+
+        let mut flags = 0;
+        if self.restart_delta { flags |= FLAG_RESTART_DELTA; }
+        if self.zstd_frames { flags |= FLAG_ZSTD_FRAMES; }
+        if checksums { flags |= FLAG_CHECKSUMS; }
+
+        writer.write_all(&INDEX_MAGIC)?;
+        writer.write_all(&version.to_le_bytes())?;
+        writer.write_all(&flags.to_le_bytes())?;
+        writer.write_all(&self.data_len.to_le_bytes())?;
+        writer.write_all(&(self.chunks.len() as u64).to_le_bytes())?;
+
+        for chunk in &self.chunks {
+            writer.write_all(&chunk.offset.to_le_bytes())?;
+            writer.write_all(&chunk.count.to_le_bytes())?;
+            writer.write_all(&chunk.union.to_le_bytes())?;
+            writer.write_all(&chunk.intersection.to_le_bytes())?;
+            if let (true, Some(checksum)) = (checksums, chunk.checksum) {
+                writer.write_all(&checksum.to_le_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != INDEX_MAGIC {
+            return Err(invalid("Not a dataset index file"));
+        }
+
+        let version = u16::from_le_bytes(read_array(reader)?);
+        if !(1..=INDEX_VERSION).contains(&version) {
+            return Err(invalid(&format!("Unsupported index version {}", version)));
+        }
+
+        let flags = u16::from_le_bytes(read_array(reader)?);
+        let checksums = version >= 2 && flags & FLAG_CHECKSUMS != 0;
+        let data_len = u64::from_le_bytes(read_array(reader)?);
+        let chunk_count = u64::from_le_bytes(read_array(reader)?);
+
+        let mut chunks = Vec::new();
+        for _ in 0..chunk_count {
+            let offset = u64::from_le_bytes(read_array(reader)?);
+            let count = u64::from_le_bytes(read_array(reader)?);
+            let union = u128::from_le_bytes(read_array(reader)?);
+            let intersection = u128::from_le_bytes(read_array(reader)?);
+            let checksum = if checksums { Some(u64::from_le_bytes(read_array(reader)?)) } else { None };
+
+            if offset > data_len || chunks.last().is_some_and(|last: &ChunkStats| last.offset > offset) {
+                return Err(invalid("Index chunk offsets are out of order"));
+            }
+
+            chunks.push(ChunkStats { offset, count, union, intersection, checksum });
+        }
+
+        Ok(Self {
+            restart_delta: flags & FLAG_RESTART_DELTA != 0,
+            zstd_frames: flags & FLAG_ZSTD_FRAMES != 0,
+            data_len,
+            chunks,
+        })
+    }
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut buffer = [0u8; N];
+    reader.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
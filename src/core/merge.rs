@@ -0,0 +1,78 @@
+use crate::core::geometry::BoardGeometry;
+use crate::core::header::DatasetHeader;
+use crate::generator::symmetries::canonicalize_with_geometry;
+use rayon::prelude::*;
+use std::io;
+
+/// Options for [`merge_records`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MergeOptions {
+    /// Sort the merged records numerically.
+    pub sort: bool,
+    /// Remove duplicate records. Implies sorting.
+    pub dedup: bool,
+    /// Replace each record with its canonical form (see
+    /// [`crate::generator::symmetries::canonicalize_with_geometry`]) before sorting, so
+    /// deduplication removes boards that are symmetric to each other.
+    pub canonicalize: bool,
+    /// Board geometry used for canonicalization.
+    pub geometry: BoardGeometry,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        Self { sort: false, dedup: false, canonicalize: false, geometry: BoardGeometry::STANDARD }
+    }
+}
+
+/// Checks that the headers of the datasets being merged describe the same boards, returning the
+/// first one. Headerless inputs are assumed to match.
+pub fn merge_headers(headers: &[Option<DatasetHeader>]) -> io::Result<Option<DatasetHeader>> {
+    let mut present = headers.iter().flatten();
+    let Some(first) = present.next() else {
+        return Ok(None);
+    };
+
+    for header in present {
+        if header.geometry != first.geometry || header.fleet != first.fleet {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "can't merge a {} board with fleet {} into a {} board with fleet {}",
+                    header.geometry, header.fleet, first.geometry, first.fleet,
+                ),
+            ));
+        }
+    }
+
+    Ok(Some(first.clone()))
+}
+
+/// Combines the decoded records of several datasets into one stream.
+///
+/// Without sorting or deduplication, the inputs are streamed one after another. Otherwise every
+/// record is loaded into memory first.
+pub fn merge_records<'a, I>(inputs: Vec<I>, options: &MergeOptions) -> io::Result<Box<dyn Iterator<Item = io::Result<u128>> + 'a>>
+where
+    I: IntoIterator<Item = io::Result<u128>>,
+    I: 'a,
+    I::IntoIter: 'a,
+{
+    let geometry = options.geometry;
+    let canonicalize = options.canonicalize;
+    let records = inputs.into_iter().flatten().map(move |record| {
+        record.map(|board| if canonicalize { canonicalize_with_geometry(board, geometry) } else { board })
+    });
+
+    if !options.sort && !options.dedup {
+        return Ok(Box::new(records));
+    }
+
+    let mut boards = records.collect::<io::Result<Vec<u128>>>()?;
+    boards.par_sort_unstable();
+    if options.dedup {
+        boards.dedup();
+    }
+
+    Ok(Box::new(boards.into_iter().map(Ok)))
+}
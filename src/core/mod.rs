@@ -1,3 +1,45 @@
+pub mod accumulator;
+#[cfg(feature = "async")]
+pub mod async_reader;
+pub mod bitslice;
+pub mod cache;
+pub mod canonicalize;
+pub mod checksum;
+pub mod dataset;
+pub mod duplicates;
+pub mod encoder;
 pub mod ffi;
 pub mod filter;
+pub mod geometry;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod header;
+pub mod heatmap;
+pub mod index;
+pub mod merge;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod partial;
+pub mod protocol;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod query;
+pub mod random;
 pub mod reader;
+#[cfg(feature = "remote")]
+pub mod remote;
+pub mod rollup;
+#[cfg(feature = "serde")]
+pub(crate) mod serde_arrays;
+pub mod session;
+pub mod shuffle;
+pub mod sort;
+pub mod split;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod version;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod writer;
@@ -0,0 +1,87 @@
+//! Parquet export, built with the `parquet` feature, for analyzing boards in tools like Spark,
+//! DuckDB or pandas.
+//!
+//! Each row is one matching board. The `board` column holds its mask as 16 little-endian bytes,
+//! the same bytes as a raw record, and there's a boolean column per cell named by its coordinate
+//! (`A1`, `A2`, ...) so queries can test cells by name:
+//!
+//! ```sql
+//! SELECT count(*) FROM 'boards.parquet' WHERE E5 AND NOT A1;
+//! ```
+
+use crate::core::filter::{for_each_match, Filter, QueryOptions};
+use crate::core::geometry::{BoardGeometry, RECORD_SIZE};
+use arrow_array::builder::FixedSizeBinaryBuilder;
+use arrow_array::{ArrayRef, BooleanArray, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+/// Boards per row group, and so per batch held in memory.
+const ROWS_PER_GROUP: usize = 1 << 16;
+
+/// The schema of files written by [`write_matches_parquet`] for boards of `geometry`.
+pub fn parquet_schema(geometry: BoardGeometry) -> SchemaRef {
+    let board = Field::new("board", DataType::FixedSizeBinary(RECORD_SIZE as i32), false);
+    let cells = (0..geometry.cells()).map(|index| Field::new(geometry.point_of(index).to_string(), DataType::Boolean, false));
+    Arc::new(Schema::new(std::iter::once(board).chain(cells).collect::<Vec<_>>()))
+}
+
+/// Writes every board with all `hit_mask` cells set and no `miss_mask` cell set to `writer` as a
+/// zstd-compressed Parquet file, returning the number of boards written.
+///
+/// Honors the popcount, symmetry expansion and geometry of `options` like the other scans.
+pub fn write_matches_parquet<I, W>(reader: I, hit_mask: u128, miss_mask: u128, options: &QueryOptions, writer: W) -> io::Result<u64>
+where
+    I: IntoIterator<Item = io::Result<u128>>,
+    W: Write + Send,
+{
+    let geometry = options.geometry;
+    let schema = parquet_schema(geometry);
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .set_max_row_group_size(ROWS_PER_GROUP)
+        .build();
+    let mut writer = ArrowWriter::try_new(writer, Arc::clone(&schema), Some(properties)).map_err(io::Error::other)?;
+
+    let filter = Filter::new(hit_mask, miss_mask, options);
+    let mut batch = Vec::with_capacity(ROWS_PER_GROUP);
+    let mut written = 0u64;
+
+    for board in reader {
+        for_each_match(board?, &filter, options.expand_symmetries, |board| batch.push(board));
+
+        if batch.len() >= ROWS_PER_GROUP {
+            writer.write(&record_batch(&schema, geometry, &batch)?).map_err(io::Error::other)?;
+            written += batch.len() as u64;
+            batch.clear();
+        }
+    }
+
+    if !batch.is_empty() {
+        writer.write(&record_batch(&schema, geometry, &batch)?).map_err(io::Error::other)?;
+        written += batch.len() as u64;
+    }
+
+    writer.close().map_err(io::Error::other)?;
+    Ok(written)
+}
+
+fn record_batch(schema: &SchemaRef, geometry: BoardGeometry, boards: &[u128]) -> io::Result<RecordBatch> {
+    let mut masks = FixedSizeBinaryBuilder::with_capacity(boards.len(), RECORD_SIZE as i32);
+    for board in boards {
+        masks.append_value(board.to_le_bytes()).map_err(io::Error::other)?;
+    }
+
+    let mut columns: Vec<ArrayRef> = Vec::with_capacity(geometry.cells() + 1);
+    columns.push(Arc::new(masks.finish()));
+    for cell in 0..geometry.cells() {
+        let column: BooleanArray = boards.iter().map(|&board| Some(board & (1u128 << cell) != 0)).collect();
+        columns.push(Arc::new(column));
+    }
+
+    RecordBatch::try_new(Arc::clone(schema), columns).map_err(io::Error::other)
+}
@@ -0,0 +1,154 @@
+use crate::core::cache::hash_options;
+use crate::core::filter::QueryOptions;
+use crate::core::geometry::BoardGeometry;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Identifies a partial result file.
+const PARTIAL_MAGIC: [u8; 4] = *b"BSPR";
+
+/// Version of the layout written by [`PartialResult::write_to`].
+pub const PARTIAL_VERSION: u16 = 1;
+
+/// The counts of one query over part of a dataset (a shard, or a range of records), which can be
+/// merged with the counts of the other parts computed elsewhere.
+///
+/// Counts are exact integers, so merging the parts in any order or grouping gives the same
+/// result as one scan of the whole dataset. Each part remembers its query and the options that
+/// change counts, and parts of different queries refuse to merge.
+///
+/// The file is little-endian: the magic `BSPR`, a u16 version, u8 board width and height, the
+/// u128 hit and miss masks, a u64 hash of the query options, then the number of matched boards
+/// and a count per cell as LEB128 varints, so typical files take a few hundred bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialResult {
+    pub geometry: BoardGeometry,
+    pub hit_mask: u128,
+    pub miss_mask: u128,
+    /// Hash of the query options that affect the counts, as the query cache computes it.
+    pub options: u64,
+    pub matched: u64,
+    /// Ship count per cell, in row-major order.
+    pub counts: Vec<u64>,
+}
+
+impl PartialResult {
+    /// An empty result of the query, to merge parts into.
+    pub fn empty(hit_mask: u128, miss_mask: u128, options: &QueryOptions) -> Self {
+        Self::new(hit_mask, miss_mask, options, &vec![0; options.geometry.cells()], 0)
+    }
+
+    /// The result of the query over one part, from its per-cell counts and number of matches.
+    pub fn new(hit_mask: u128, miss_mask: u128, options: &QueryOptions, counts: &[u32], matched: u64) -> Self {
+        Self {
+            geometry: options.geometry,
+            hit_mask,
+            miss_mask,
+            options: hash_options(options),
+            matched,
+            counts: counts.iter().map(|&count| count as u64).collect(),
+        }
+    }
+
+    /// Adds the counts of `other` to these, failing with `InvalidInput` if it's the result of a
+    /// different query.
+    pub fn merge(&mut self, other: &PartialResult) -> io::Result<()> {
+        if (self.geometry, self.hit_mask, self.miss_mask, self.options) != (other.geometry, other.hit_mask, other.miss_mask, other.options) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Partial results of different queries or query options can't be merged",
+            ));
+        }
+
+        for (count, other_count) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other_count;
+        }
+        self.matched += other.matched;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&PARTIAL_MAGIC)?;
+        writer.write_all(&PARTIAL_VERSION.to_le_bytes())?;
+        writer.write_all(&[self.geometry.width() as u8, self.geometry.height() as u8])?;
+        writer.write_all(&self.hit_mask.to_le_bytes())?;
+        writer.write_all(&self.miss_mask.to_le_bytes())?;
+        writer.write_all(&self.options.to_le_bytes())?;
+
+        write_varint(writer, self.matched)?;
+        for &count in &self.counts {
+            write_varint(writer, count)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != PARTIAL_MAGIC {
+            return Err(invalid("Not a partial result file"));
+        }
+
+        let version = u16::from_le_bytes(read_array(reader)?);
+        if version != PARTIAL_VERSION {
+            return Err(invalid(&format!("Unsupported partial result version {}", version)));
+        }
+
+        let [width, height] = read_array(reader)?;
+        let (width, height) = (width as usize, height as usize);
+        if width == 0 || height == 0 || width * height > 128 {
+            return Err(invalid(&format!("Invalid board size {}x{} in partial result", width, height)));
+        }
+        let geometry = BoardGeometry::new(width, height);
+
+        let hit_mask = u128::from_le_bytes(read_array(reader)?);
+        let miss_mask = u128::from_le_bytes(read_array(reader)?);
+        let options = u64::from_le_bytes(read_array(reader)?);
+        let matched = read_varint(reader)?;
+        let counts = (0..geometry.cells()).map(|_| read_varint(reader)).collect::<io::Result<_>>()?;
+
+        Ok(Self { geometry, hit_mask, miss_mask, options, matched, counts })
+    }
+}
+
+fn write_varint<W: Write>(writer: &mut W, mut value: u64) -> io::Result<()> {
+    while value >= 0x80 {
+        writer.write_all(&[value as u8 | 0x80])?;
+        value >>= 7;
+    }
+    writer.write_all(&[value as u8])
+}
+
+fn read_varint<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut value = 0u64;
+
+    for shift in (0..64).step_by(7) {
+        let [byte] = read_array(reader)?;
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(io::Error::new(io::ErrorKind::InvalidData, "Varint in partial result is too long"))
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut buffer = [0u8; N];
+    reader.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
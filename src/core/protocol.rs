@@ -0,0 +1,128 @@
+//! The length-prefixed binary protocol of `battleship daemon`, which answers queries against a
+//! [`Session`] kept in memory over a unix socket or TCP connection.
+//!
+//! Every message is a frame: a little-endian u32 payload length, then the payload. A client sends
+//! any number of requests on one connection, each answered in order:
+//!
+//! - A request is 32 bytes: the hit mask, then the miss mask, each a little-endian u128 with one
+//!   bit per cell of a 9x9 board.
+//! - A response starts with a status byte. After [`STATUS_OK`] come the number of matched boards
+//!   as a little-endian u64 and the count of each cell as a little-endian u32, in row-major order
+//!   (333 bytes in all). After [`STATUS_ERROR`] comes a UTF-8 message saying what was wrong with
+//!   the request.
+
+use crate::core::filter::FilterResult;
+use crate::core::geometry::BoardGeometry;
+use crate::core::query::Query;
+use crate::core::session::Session;
+use std::io::{self, Read, Write};
+
+/// Status byte of a response carrying counts.
+pub const STATUS_OK: u8 = 0;
+
+/// Status byte of a response carrying an error message.
+pub const STATUS_ERROR: u8 = 1;
+
+/// Size in bytes of a request payload.
+pub const REQUEST_LEN: usize = 32;
+
+/// Longest payload either side accepts, so a garbled length can't make the other allocate
+/// gigabytes.
+pub const MAX_FRAME_LEN: u32 = 1 << 16;
+
+/// Reads a frame's payload, or `None` when the stream ends cleanly before a new frame.
+pub fn read_frame<R: Read>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let len = u32::from_le_bytes(len);
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {} bytes is longer than the limit of {}", len, MAX_FRAME_LEN),
+        ));
+    }
+
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload)?;
+    Ok(Some(payload))
+}
+
+pub fn write_frame<W: Write>(writer: &mut W, payload: &[u8]) -> io::Result<()> {
+    writer.write_all(&(payload.len() as u32).to_le_bytes())?;
+    writer.write_all(payload)?;
+    writer.flush()
+}
+
+/// Sends a query, for clients.
+pub fn write_request<W: Write>(writer: &mut W, query: &Query) -> io::Result<()> {
+    let mut payload = [0u8; REQUEST_LEN];
+    payload[..16].copy_from_slice(&query.hit_mask().to_le_bytes());
+    payload[16..].copy_from_slice(&query.miss_mask().to_le_bytes());
+    write_frame(writer, &payload)
+}
+
+/// Reads the response to a query, for clients. An error response becomes an `InvalidInput`
+/// error with the daemon's message.
+pub fn read_response<R: Read>(reader: &mut R) -> io::Result<FilterResult> {
+    let payload = read_frame(reader)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "the daemon closed the connection"))?;
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("malformed response of {} bytes", payload.len()));
+
+    match payload.split_first() {
+        Some((&STATUS_OK, body)) if body.len() == 8 + 4 * BoardGeometry::STANDARD.cells() => {
+            let matched = u64::from_le_bytes(body[..8].try_into().expect("8 bytes"));
+            let mut counts = [0u32; 81];
+            for (count, bytes) in counts.iter_mut().zip(body[8..].chunks_exact(4)) {
+                *count = u32::from_le_bytes(bytes.try_into().expect("4 bytes"));
+            }
+            Ok(FilterResult::new(counts, matched))
+        }
+        Some((&STATUS_ERROR, message)) => {
+            Err(io::Error::new(io::ErrorKind::InvalidInput, String::from_utf8_lossy(message).into_owned()))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Answers requests on `stream` until the client closes it. Invalid requests get an error
+/// response and leave the connection open; only failing to read or write the stream ends it early.
+pub fn serve_connection<S: Read + Write>(session: &Session, mut stream: S) -> io::Result<()> {
+    while let Some(request) = read_frame(&mut stream)? {
+        let response = match answer(session, &request) {
+            Ok(result) => {
+                let mut payload = Vec::with_capacity(1 + 8 + 4 * result.counts.len());
+                payload.push(STATUS_OK);
+                payload.extend_from_slice(&result.matched.to_le_bytes());
+                for count in result.counts {
+                    payload.extend_from_slice(&count.to_le_bytes());
+                }
+                payload
+            }
+            Err(e) => {
+                let mut payload = vec![STATUS_ERROR];
+                payload.extend_from_slice(e.to_string().as_bytes());
+                payload
+            }
+        };
+        write_frame(&mut stream, &response)?;
+    }
+    Ok(())
+}
+
+fn answer(session: &Session, request: &[u8]) -> io::Result<FilterResult> {
+    if request.len() != REQUEST_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("a request is {} bytes, not {}", REQUEST_LEN, request.len()),
+        ));
+    }
+
+    let hit_mask = u128::from_le_bytes(request[..16].try_into().expect("16 bytes"));
+    let miss_mask = u128::from_le_bytes(request[16..].try_into().expect("16 bytes"));
+    session.query(&Query::compile(hit_mask, miss_mask, BoardGeometry::STANDARD)?)
+}
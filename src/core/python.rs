@@ -0,0 +1,102 @@
+//! Python bindings, built with the `python` feature.
+//!
+//! Build the extension with `maturin develop --release` (see `pyproject.toml`), then:
+//!
+//! ```python
+//! import battleship
+//! hits = battleship.mask_from_cells([(4, 4)])
+//! counts, matched = battleship.filter_and_count("data.bin", hits, 0)
+//! x, y = battleship.recommend_shot(counts, hits)
+//! ```
+//!
+//! Masks are plain Python ints with bit `y * 9 + x` set for cell `(x, y)`.
+
+use crate::core::filter;
+use crate::core::geometry::BoardGeometry;
+use crate::core::query::Query;
+use crate::core::reader::create_reader;
+use crate::core::version::CRATE_VERSION;
+use crate::generator::board_mask::BoardMask;
+use crate::generator::point::Point;
+use crate::solver::recommend;
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+
+/// Width of the board the bindings work with.
+pub const BOARD_WIDTH: usize = BoardGeometry::STANDARD.width();
+/// Height of the board the bindings work with.
+pub const BOARD_HEIGHT: usize = BoardGeometry::STANDARD.height();
+/// Number of cells, and so the length of every counts list.
+pub const CELL_COUNT: usize = BoardGeometry::STANDARD.cells();
+
+fn point(x: i32, y: i32) -> PyResult<Point> {
+    let point = Point::new(x, y);
+    if !BoardGeometry::STANDARD.contains(point) {
+        return Err(PyValueError::new_err(format!("cell ({}, {}) is outside the board", x, y)));
+    }
+    Ok(point)
+}
+
+/// Filters the dataset at `path` to boards with ships on every `hit` cell and no ship on any
+/// `miss` cell, returning the ship count of each cell and the number of matching boards.
+#[pyfunction]
+fn filter_and_count(py: Python<'_>, path: &str, hit: u128, miss: u128) -> PyResult<(Vec<u32>, u64)> {
+    let query = Query::compile(hit, miss, BoardGeometry::STANDARD).map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+    // Release the GIL so other Python threads keep running during the scan
+    py.allow_threads(|| {
+        let reader = query.guard(create_reader(path)?, path);
+        filter::filter_and_count(reader, query.hit_mask(), query.miss_mask())
+    })
+    .map(|(counts, matched)| (counts.to_vec(), matched))
+    .map_err(|e| PyIOError::new_err(format!("{}: {}", path, e)))
+}
+
+/// Returns a mask with bit `y * 9 + x` set for each `(x, y)` cell.
+#[pyfunction]
+fn mask_from_cells(cells: Vec<(i32, i32)>) -> PyResult<u128> {
+    cells.into_iter().try_fold(0u128, |mask, (x, y)| {
+        Ok(mask | (1u128 << BoardGeometry::STANDARD.index_of(point(x, y)?)))
+    })
+}
+
+/// Returns the `(x, y)` cells set in `mask`, in row-major order.
+#[pyfunction]
+fn cells_from_mask(mask: u128) -> PyResult<Vec<(i32, i32)>> {
+    if mask & !BoardGeometry::STANDARD.cell_mask() != 0 {
+        return Err(PyValueError::new_err("mask has bits set outside the board"));
+    }
+
+    Ok(BoardMask::new(mask).iter_points().map(|point| (point.x, point.y)).collect())
+}
+
+/// Returns the index of cell `(x, y)` in a counts list, which is also its bit in a mask.
+#[pyfunction]
+fn cell_index(x: i32, y: i32) -> PyResult<usize> {
+    Ok(BoardGeometry::STANDARD.index_of(point(x, y)?))
+}
+
+/// Returns the unshot `(x, y)` cell with the highest count, or `None` if every cell is in
+/// `already_shot`. Ties go to the first cell in row-major order.
+#[pyfunction]
+fn recommend_shot(counts: Vec<u32>, already_shot: u128) -> PyResult<Option<(i32, i32)>> {
+    let counts: [u32; CELL_COUNT] = counts
+        .try_into()
+        .map_err(|counts: Vec<u32>| PyValueError::new_err(format!("expected {} counts, got {}", CELL_COUNT, counts.len())))?;
+
+    Ok(recommend::recommend_shot(&counts, already_shot).map(|point| (point.x, point.y)))
+}
+
+#[pymodule]
+fn battleship(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", CRATE_VERSION)?;
+    m.add("BOARD_WIDTH", BOARD_WIDTH)?;
+    m.add("BOARD_HEIGHT", BOARD_HEIGHT)?;
+    m.add("CELL_COUNT", CELL_COUNT)?;
+    m.add_function(wrap_pyfunction!(filter_and_count, m)?)?;
+    m.add_function(wrap_pyfunction!(mask_from_cells, m)?)?;
+    m.add_function(wrap_pyfunction!(cells_from_mask, m)?)?;
+    m.add_function(wrap_pyfunction!(cell_index, m)?)?;
+    m.add_function(wrap_pyfunction!(recommend_shot, m)?)?;
+    Ok(())
+}
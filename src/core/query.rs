@@ -0,0 +1,166 @@
+use crate::core::geometry::BoardGeometry;
+use std::fmt;
+use std::io;
+
+/// A validated hit/miss query for a specific board geometry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedQuery"))]
+pub struct Query {
+    hit_mask: u128,
+    miss_mask: u128,
+    geometry: BoardGeometry,
+}
+
+/// A deserialized query that hasn't been through [`Query::compile`] yet.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedQuery {
+    hit_mask: u128,
+    miss_mask: u128,
+    geometry: BoardGeometry,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedQuery> for Query {
+    type Error = QueryError;
+
+    fn try_from(query: UncheckedQuery) -> Result<Self, Self::Error> {
+        Query::compile(query.hit_mask, query.miss_mask, query.geometry)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    /// A query mask references a cell that doesn't exist on the query's board.
+    MaskOutOfBounds { mask: &'static str, cell: usize, geometry: BoardGeometry },
+    /// The same cell is marked as both a hit and a miss.
+    ConflictingCells { cells: u128 },
+    /// The dataset was built for a different board than the query.
+    GeometryMismatch { query: BoardGeometry, dataset: BoardGeometry, source: String },
+    /// A dataset record has ships outside the query's board.
+    RecordOutOfBounds { record: u64, geometry: BoardGeometry, source: String },
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::MaskOutOfBounds { mask, cell, geometry } => {
+                write!(f, "{} mask uses cell {}, which is outside the {} board", mask, cell, geometry)
+            }
+            QueryError::ConflictingCells { cells } => {
+                write!(f, "cells 0x{:x} are marked as both hit and miss", cells)
+            }
+            QueryError::GeometryMismatch { query, dataset, source } => {
+                write!(f, "query is for a {} board, but dataset {} contains {} boards", query, source, dataset)
+            }
+            QueryError::RecordOutOfBounds { record, geometry, source } => {
+                write!(f, "record {} in {} has ships outside the {} board; the dataset was built for a different board geometry", record, source, geometry)
+            }
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+impl From<QueryError> for io::Error {
+    fn from(error: QueryError) -> Self {
+        let kind = match error {
+            QueryError::MaskOutOfBounds { .. } | QueryError::ConflictingCells { .. } => io::ErrorKind::InvalidInput,
+            QueryError::GeometryMismatch { .. } | QueryError::RecordOutOfBounds { .. } => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, error)
+    }
+}
+
+impl Query {
+    /// Validates hit/miss masks against a board geometry.
+    pub fn compile(hit_mask: u128, miss_mask: u128, geometry: BoardGeometry) -> Result<Self, QueryError> {
+        for (name, mask) in [("hit", hit_mask), ("miss", miss_mask)] {
+            let outside = mask & !geometry.cell_mask();
+            if outside != 0 {
+                return Err(QueryError::MaskOutOfBounds {
+                    mask: name,
+                    cell: outside.trailing_zeros() as usize,
+                    geometry,
+                });
+            }
+        }
+
+        if hit_mask & miss_mask != 0 {
+            return Err(QueryError::ConflictingCells { cells: hit_mask & miss_mask });
+        }
+
+        Ok(Self { hit_mask, miss_mask, geometry })
+    }
+
+    pub fn hit_mask(&self) -> u128 {
+        self.hit_mask
+    }
+
+    pub fn miss_mask(&self) -> u128 {
+        self.miss_mask
+    }
+
+    pub fn geometry(&self) -> BoardGeometry {
+        self.geometry
+    }
+
+    /// Fails if a dataset with a known geometry can't answer this query.
+    pub fn check_dataset(&self, dataset: BoardGeometry, source: &str) -> Result<(), QueryError> {
+        if dataset != self.geometry {
+            return Err(QueryError::GeometryMismatch {
+                query: self.geometry,
+                dataset,
+                source: source.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Wraps a record stream so that any record with ships outside this query's board
+    /// becomes an error instead of silently producing garbage counts.
+    pub fn guard<I>(&self, reader: I, source: &str) -> GeometryGuard<I::IntoIter>
+    where
+        I: IntoIterator<Item = io::Result<u128>>,
+    {
+        GeometryGuard {
+            inner: reader.into_iter(),
+            geometry: self.geometry,
+            source: source.to_string(),
+            record: 0,
+        }
+    }
+}
+
+/// Iterator adapter created by [`Query::guard`].
+pub struct GeometryGuard<I> {
+    inner: I,
+    geometry: BoardGeometry,
+    source: String,
+    record: u64,
+}
+
+impl<I: Iterator<Item = io::Result<u128>>> Iterator for GeometryGuard<I> {
+    type Item = io::Result<u128>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let board = match self.inner.next()? {
+            Ok(board) => board,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let record = self.record;
+        self.record += 1;
+
+        if board & !self.geometry.cell_mask() != 0 {
+            return Some(Err(QueryError::RecordOutOfBounds {
+                record,
+                geometry: self.geometry,
+                source: self.source.clone(),
+            }.into()));
+        }
+
+        Some(Ok(board))
+    }
+}
@@ -1,6 +1,14 @@
+use crate::core::checksum::xxh64;
+use crate::core::geometry::RECORD_SIZE;
+use crate::core::header::{read_optional_header, read_prefix, DatasetHeader, HEADER_LEN};
+use crate::core::index::DatasetIndex;
+use rayon::prelude::*;
 use std::fs::File;
-use std::io::{self, Read, BufReader};
+use std::io::{self, Read, BufReader, Seek, SeekFrom};
+use std::ops::Range;
 use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
 
 /// The zstd magic number (little endian: [0x28, 0xB5, 0x2F, 0xFD])
 const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
@@ -8,12 +16,18 @@ const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
 /// A reader that yields delta-XOR decoded u128s from an underlying reader.
 pub struct DeltaDecodingReader<R: Read> {
     inner: R,
-    prev: u128,
+    chain: DeltaChain,
 }
 
 impl<R: Read> DeltaDecodingReader<R> {
     pub fn new(inner: R) -> Self {
-        Self { inner, prev: 0 }
+        Self { inner, chain: DeltaChain::new(0) }
+    }
+
+    /// Creates a reader for a stream whose delta chain restarts from zero every `interval`
+    /// records, as written by the encoder with restarted deltas.
+    pub fn with_restart_interval(inner: R, interval: u64) -> Self {
+        Self { inner, chain: DeltaChain::new(interval) }
     }
 }
 
@@ -21,68 +35,718 @@ impl<R: Read> Iterator for DeltaDecodingReader<R> {
     type Item = io::Result<u128>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut buf = [0u8; 16];
-        match self.inner.read_exact(&mut buf) {
-            Ok(()) => {
-                let encoded = u128::from_le_bytes(buf);
-                let decoded = self.prev ^ encoded;
-                self.prev = decoded;
-
-                Some(Ok(decoded))
-            }
-            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => None,
+        match read_record_bytes(&mut self.inner) {
+            Ok(Some(buf)) => Some(Ok(self.chain.decode(u128::from_le_bytes(buf)))),
+            Ok(None) => None,
             Err(e) => Some(Err(e)),
         }
     }
 }
 
-/// Creates a reader that automatically handles zstd compression by chaining magic bytes back.
+/// The state of a delta chain being decoded, one record at a time.
+pub(crate) struct DeltaChain {
+    prev: u128,
+    /// Records left until the delta chain restarts from zero (only counted when restarting).
+    until_restart: u64,
+    restart_interval: u64,
+}
+
+impl DeltaChain {
+    /// A chain restarting every `restart_interval` records, or never if it is 0.
+    pub(crate) fn new(restart_interval: u64) -> Self {
+        Self { prev: 0, until_restart: restart_interval, restart_interval }
+    }
+
+    pub(crate) fn decode(&mut self, encoded: u128) -> u128 {
+        if self.restart_interval != 0 {
+            if self.until_restart == 0 {
+                self.prev = 0;
+                self.until_restart = self.restart_interval;
+            }
+            self.until_restart -= 1;
+        }
+
+        self.prev ^= encoded;
+        self.prev
+    }
+}
+
+/// A reader that yields raw (not delta-encoded) u128 records from an underlying reader.
+pub struct RawRecordReader<R: Read> {
+    inner: R,
+}
+
+impl<R: Read> RawRecordReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner }
+    }
+}
+
+impl<R: Read> Iterator for RawRecordReader<R> {
+    type Item = io::Result<u128>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        read_record_bytes(&mut self.inner).map(|buf| buf.map(u128::from_le_bytes)).transpose()
+    }
+}
+
+/// Reads one raw record from a stream of 16-byte records, returning `None` at the end of the
+/// stream. A stream that ends partway through a record is `InvalidData`.
+pub(crate) fn read_record<R: Read>(reader: &mut R) -> io::Result<Option<u128>> {
+    match read_record_bytes(reader) {
+        Ok(buf) => Ok(buf.map(u128::from_le_bytes)),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Err(io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+        Err(e) => Err(e),
+    }
+}
+
+/// Reads one record's bytes, returning `None` at the end of the stream.
+///
+/// A stream that ends partway through a record is reported as an error rather than silently
+/// dropping the partial record, since that usually means the file was truncated.
+fn read_record_bytes<R: Read>(reader: &mut R) -> io::Result<Option<[u8; RECORD_SIZE]>> {
+    let mut buf = [0u8; RECORD_SIZE];
+    let mut filled = 0;
+
+    while filled < RECORD_SIZE {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("stream ends {} bytes into a {}-byte record; the file may be truncated", filled, RECORD_SIZE),
+                ));
+            }
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(Some(buf))
+}
+
+/// Compression formats recognized by their magic bytes.
+const COMPRESSION_MAGICS: [(&str, &[u8]); 4] = [
+    ("zstd", &ZSTD_MAGIC),
+    ("gzip", &[0x1F, 0x8B]),
+    ("xz", &[0xFD, b'7', b'z', b'X', b'Z', 0x00]),
+    ("lz4", &[0x04, 0x22, 0x4D, 0x18]),
+];
+
+/// Names the compression format ("zstd", "gzip", "xz" or "lz4") of a stream starting with
+/// `prefix`, or `None` if it doesn't start with a known magic number.
+pub fn detect_compression(prefix: &[u8]) -> Option<&'static str> {
+    COMPRESSION_MAGICS.iter()
+        .find(|(_, magic)| prefix.starts_with(magic))
+        .map(|(format, _)| *format)
+}
+
+/// Creates a reader that automatically handles compression by chaining magic bytes back.
+///
+/// zstd is always supported; gzip, xz and lz4 require the corresponding cargo features.
 fn create_reader_with_magic_detection<R: Read + 'static>(mut reader: R) -> io::Result<Box<dyn Read>> {
-    let mut magic = [0u8; 4];
-
-    match reader.read_exact(&mut magic) {
-        Ok(()) => {
-            if magic == ZSTD_MAGIC {
-                // It's zstd compressed, prepend magic bytes and wrap with decoder
-                let chained = std::io::Cursor::new(magic).chain(reader);
-                let decoder = zstd::stream::Decoder::new(chained)?;
-                Ok(Box::new(decoder))
-            } else {
-                // Not zstd, prepend the magic bytes we consumed
-                let chained = std::io::Cursor::new(magic).chain(reader);
-                Ok(Box::new(chained))
+    let prefix = read_prefix(&mut reader, 6)?;
+    let format = detect_compression(&prefix);
+
+    // Prepend the magic bytes we consumed
+    let chained = io::Cursor::new(prefix).chain(reader);
+
+    match format {
+        Some("zstd") => Ok(Box::new(Decompressed(zstd::stream::Decoder::new(chained)?))),
+        #[cfg(feature = "gzip")]
+        Some("gzip") => Ok(Box::new(Decompressed(flate2::read::MultiGzDecoder::new(chained)))),
+        #[cfg(feature = "xz")]
+        Some("xz") => Ok(Box::new(Decompressed(xz2::read::XzDecoder::new_multi_decoder(chained)))),
+        #[cfg(feature = "lz4")]
+        Some("lz4") => Ok(Box::new(Decompressed(lz4_flex::frame::FrameDecoder::new(chained)))),
+        Some(format) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("input is {} compressed; rebuild with `--features {}` to read it", format, format),
+        )),
+        None => Ok(Box::new(chained)),
+    }
+}
+
+/// Reports the errors of a decompressor, which flags corrupt streams as `Other`, as
+/// [`io::ErrorKind::InvalidData`] like the rest of the reader does, so callers can tell corrupt
+/// data from other failures.
+struct Decompressed<R>(R);
+
+impl<R: Read> Read for Decompressed<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf).map_err(|e| match e.kind() {
+            io::ErrorKind::Other => io::Error::new(io::ErrorKind::InvalidData, e),
+            _ => e,
+        })
+    }
+}
+
+/// Records read from a dataset, decoded according to its header.
+pub enum RecordReader<R: Read> {
+    Delta(DeltaDecodingReader<R>),
+    Raw(RawRecordReader<R>),
+}
+
+impl<R: Read> Iterator for RecordReader<R> {
+    type Item = io::Result<u128>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            RecordReader::Delta(reader) => reader.next(),
+            RecordReader::Raw(reader) => reader.next(),
+        }
+    }
+}
+
+impl<R: Read> RecordReader<R> {
+    /// How the records are being decoded, either as the header says or as detected.
+    pub fn encoding(&self) -> RecordEncoding {
+        match self {
+            RecordReader::Delta(_) => RecordEncoding::Delta,
+            RecordReader::Raw(_) => RecordEncoding::Raw,
+        }
+    }
+}
+
+/// How records are stored in a dataset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordEncoding {
+    /// Each record is XORed with the previous one (the encoder's output).
+    Delta,
+    /// Each record is a board (the generator's output).
+    Raw,
+}
+
+/// Number of leading records sampled to guess the encoding of a headerless dataset.
+pub(crate) const DETECTION_SAMPLE_RECORDS: usize = 256;
+
+/// Guesses how `records` (read without decoding) are encoded.
+///
+/// Every board in a dataset has the same number of ship cells, so the interpretation that gives
+/// every record the same popcount wins. Returns `None` when both or neither do, e.g. when there
+/// are fewer than two records (for which both encodings are identical anyway).
+pub fn detect_encoding(records: &[u128]) -> Option<RecordEncoding> {
+    let raw = has_uniform_popcount(records.iter().copied());
+    let delta = has_uniform_popcount(records.iter().scan(0u128, |prev, &record| {
+        *prev ^= record;
+        Some(*prev)
+    }));
+
+    match (raw, delta) {
+        (true, false) => Some(RecordEncoding::Raw),
+        (false, true) => Some(RecordEncoding::Delta),
+        _ => None,
+    }
+}
+
+fn has_uniform_popcount(mut boards: impl Iterator<Item = u128>) -> bool {
+    let first = boards.next().map(u128::count_ones);
+    boards.all(|board| Some(board.count_ones()) == first)
+}
+
+/// Records of a dataset opened with [`open_records`].
+pub type DatasetRecords = RecordReader<BufReader<Box<dyn Read>>>;
+
+/// Opens a dataset for reading, parsing its header if it has one.
+///
+/// Handles files, stdin ("-") and remote datasets (see [`is_remote`]), as well as zstd
+/// compression. Records are decoded as the header describes; for headerless files the encoding
+/// is detected from the first records with [`detect_encoding`], falling back to delta encoding
+/// when it's ambiguous.
+pub fn open_records<P: AsRef<Path>>(path: P) -> io::Result<(Option<DatasetHeader>, DatasetRecords)> {
+    open_records_with_threads(path, 1)
+}
+
+/// Same as [`open_records`], decompressing up to `threads` zstd frames at once when the file
+/// consists of several frames (e.g. one per chunk, as written by the encoder with
+/// `--zstd-frames`).
+///
+/// zstd can't split a single frame across threads, so single-frame files, uncompressed files,
+/// stdin and remote datasets are read as usual. Frames are decompressed in the background while
+/// records are read, holding up to two batches of `threads` decompressed frames in memory.
+pub fn open_records_with_threads<P: AsRef<Path>>(path: P, threads: usize) -> io::Result<(Option<DatasetHeader>, DatasetRecords)> {
+    let path = path.as_ref();
+    if is_remote(&path.to_string_lossy()) {
+        return read_records(open_remote(&path.to_string_lossy())?);
+    }
+
+    let input: Box<dyn Read> = if path.to_string_lossy() == "-" {
+        Box::new(io::stdin())
+    } else {
+        Box::new(File::open(path)?)
+    };
+
+    let (header, rest) = read_optional_header(input)?;
+
+    let frames = if threads > 1 && path.to_string_lossy() != "-" {
+        let start = if header.is_some() { HEADER_LEN as u64 } else { 0 };
+        zstd_frame_ranges(&mut File::open(path)?, start)?
+    } else {
+        None
+    };
+
+    let decompressed: Box<dyn Read> = match frames {
+        Some(frames) if frames.len() > 1 => Box::new(Decompressed(ParallelFrameReader::new(File::open(path)?, frames, threads)?)),
+        _ => create_reader_with_magic_detection(rest)?,
+    };
+
+    decode_records(header, decompressed)
+}
+
+/// Whether `location` names a remote dataset (an http://, https:// or s3:// URL) rather than a
+/// file. Remote datasets are streamed over HTTP with the `remote` feature.
+pub fn is_remote(location: &str) -> bool {
+    ["http://", "https://", "s3://"].iter().any(|scheme| location.starts_with(scheme))
+}
+
+/// Streams a remote dataset, or fails when this build can't.
+pub(crate) fn open_remote(location: &str) -> io::Result<Box<dyn Read>> {
+    #[cfg(feature = "remote")]
+    {
+        Ok(crate::core::remote::open_remote(location)?)
+    }
+
+    #[cfg(not(feature = "remote"))]
+    {
+        Err(remote_unsupported(location))
+    }
+}
+
+/// Length in bytes of a dataset file, or of a remote dataset.
+pub fn dataset_len<P: AsRef<Path>>(path: P) -> io::Result<u64> {
+    let location = path.as_ref().to_string_lossy();
+    if !is_remote(&location) {
+        return Ok(std::fs::metadata(path.as_ref())?.len());
+    }
+
+    #[cfg(feature = "remote")]
+    {
+        crate::core::remote::remote_len(&location)
+    }
+
+    #[cfg(not(feature = "remote"))]
+    {
+        Err(remote_unsupported(&location))
+    }
+}
+
+/// Opens the bytes in `range` of a dataset file, or of a remote dataset with a range request.
+fn open_range(path: &Path, range: Range<u64>) -> io::Result<Box<dyn Read>> {
+    let location = path.to_string_lossy();
+    if !is_remote(&location) {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(range.start))?;
+        return Ok(Box::new(file.take(range.end - range.start)));
+    }
+
+    #[cfg(feature = "remote")]
+    {
+        Ok(crate::core::remote::open_remote_range(&location, range)?)
+    }
+
+    #[cfg(not(feature = "remote"))]
+    {
+        Err(remote_unsupported(&location))
+    }
+}
+
+#[cfg(not(feature = "remote"))]
+fn remote_unsupported(location: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{} is a remote dataset; rebuild with `--features remote` to read it", location),
+    )
+}
+
+/// Reads a dataset from any reader, e.g. a buffer already in memory, parsing its header and
+/// decoding records the same way as [`open_records`].
+pub fn read_records<R: Read + 'static>(input: R) -> io::Result<(Option<DatasetHeader>, DatasetRecords)> {
+    let (header, rest) = read_optional_header(input)?;
+    decode_records(header, create_reader_with_magic_detection(rest)?)
+}
+
+/// Decodes the records of a decompressed stream, looking for a header inside it when there
+/// wasn't one in front of the compressed data.
+fn decode_records(header: Option<DatasetHeader>, decompressed: Box<dyn Read>) -> io::Result<(Option<DatasetHeader>, DatasetRecords)> {
+    // The header ends up inside the compressed stream when a whole file is compressed with the zstd tool
+    let (header, decompressed): (_, Box<dyn Read>) = match header {
+        Some(header) => (Some(header), decompressed),
+        None => {
+            let (header, rest) = read_optional_header(decompressed)?;
+            (header, Box::new(rest))
+        }
+    };
+
+    let mut buffered_reader = BufReader::with_capacity(128 * 1024, decompressed); // 128KB BufReader
+
+    let encoding = match &header {
+        Some(header) if !header.delta_encoded => RecordEncoding::Raw,
+        Some(_) => RecordEncoding::Delta,
+        None => {
+            let sample = read_prefix(&mut buffered_reader, DETECTION_SAMPLE_RECORDS * RECORD_SIZE)?;
+            let records: Vec<u128> = sample.chunks_exact(RECORD_SIZE)
+                .map(|bytes| u128::from_le_bytes(bytes.try_into().unwrap()))
+                .collect();
+            let encoding = detect_encoding(&records).unwrap_or(RecordEncoding::Delta);
+
+            // Put the sampled bytes back in front of the rest of the stream
+            let rest: Box<dyn Read> = Box::new(io::Cursor::new(sample).chain(buffered_reader));
+            buffered_reader = BufReader::with_capacity(128 * 1024, rest);
+            encoding
+        }
+    };
+
+    let records = match (encoding, &header) {
+        (RecordEncoding::Raw, _) => RecordReader::Raw(RawRecordReader::new(buffered_reader)),
+        (_, Some(DatasetHeader { restart_interval: Some(interval), .. })) => {
+            RecordReader::Delta(DeltaDecodingReader::with_restart_interval(buffered_reader, *interval))
+        }
+        _ => RecordReader::Delta(DeltaDecodingReader::new(buffered_reader)),
+    };
+
+    Ok((header, records))
+}
+
+/// Magic numbers of skippable frames, 0x184D2A50 through 0x184D2A5F (little endian).
+fn is_skippable_frame(magic: [u8; 4]) -> bool {
+    magic[0] & 0xF0 == 0x50 && magic[1..] == [0x2A, 0x4D, 0x18]
+}
+
+/// Finds the byte range of each zstd frame in `file` from `start` to the end, by walking frame
+/// and block headers without decompressing anything. Skippable frames are left out.
+///
+/// Returns `None` if the data there isn't a sequence of zstd frames.
+pub fn zstd_frame_ranges(file: &mut File, start: u64) -> io::Result<Option<Vec<Range<u64>>>> {
+    let len = file.metadata()?.len();
+    let mut frames = Vec::new();
+    let mut offset = start;
+
+    let mut read_at = |offset: u64, buf: &mut [u8]| -> io::Result<bool> {
+        if offset + buf.len() as u64 > len {
+            return Ok(false);
+        }
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(buf)?;
+        Ok(true)
+    };
+
+    while offset < len {
+        let mut magic = [0u8; 4];
+        if !read_at(offset, &mut magic)? {
+            return Ok(None);
+        }
+
+        if is_skippable_frame(magic) {
+            let mut size = [0u8; 4];
+            if !read_at(offset + 4, &mut size)? {
+                return Ok(None);
             }
+            offset += 8 + u32::from_le_bytes(size) as u64;
+            continue;
         }
-        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
-            // Empty or too small, return as-is
-            Ok(Box::new(reader))
+
+        if magic != ZSTD_MAGIC {
+            return Ok(None);
         }
-        Err(e) => Err(e),
+
+        // Frame header descriptor: content size flag, single segment, checksum, dictionary id size
+        let mut descriptor = [0u8; 1];
+        if !read_at(offset + 4, &mut descriptor)? {
+            return Ok(None);
+        }
+        let descriptor = descriptor[0];
+        let single_segment = descriptor & 0x20 != 0;
+        let has_checksum = descriptor & 0x04 != 0;
+        let window_len = if single_segment { 0 } else { 1 };
+        let dictionary_len = [0, 1, 2, 4][(descriptor & 0x03) as usize];
+        let content_size_len = match descriptor >> 6 {
+            0 => if single_segment { 1 } else { 0 },
+            1 => 2,
+            2 => 4,
+            _ => 8,
+        };
+
+        let frame_start = offset;
+        offset += 5 + window_len + dictionary_len + content_size_len;
+
+        loop {
+            let mut block_header = [0u8; 3];
+            if !read_at(offset, &mut block_header)? {
+                return Ok(None);
+            }
+            let block_header = u32::from_le_bytes([block_header[0], block_header[1], block_header[2], 0]);
+            let last = block_header & 1 != 0;
+            let size = match (block_header >> 1) & 0x03 {
+                1 => 1, // RLE blocks store a single byte
+                3 => return Ok(None), // Reserved block type
+                _ => (block_header >> 3) as u64,
+            };
+
+            offset += 3 + size;
+            if last {
+                break;
+            }
+        }
+
+        if has_checksum {
+            offset += 4;
+        }
+        if offset > len {
+            return Ok(None);
+        }
+
+        frames.push(frame_start..offset);
     }
+
+    Ok(Some(frames))
 }
 
-/// Creates a delta-decoding iterator for a file path that automatically handles zstd compression.
-fn create_file_reader<P: AsRef<Path>>(path: P) -> io::Result<DeltaDecodingReader<BufReader<Box<dyn Read>>>> {
-    let file = File::open(path)?;
-    let reader = create_reader_with_magic_detection(file)?;
-    let buffered_reader = BufReader::with_capacity(128 * 1024, reader); // 128KB BufReader
-    Ok(DeltaDecodingReader::new(buffered_reader))
+/// Decompresses a sequence of zstd frames a batch at a time, one frame per thread, yielding the
+/// decompressed bytes in order.
+///
+/// Frames are decompressed on a background thread that feeds a bounded channel, so the next
+/// batch is decompressed while the previous one is being read.
+struct ParallelFrameReader {
+    decoded: mpsc::Receiver<io::Result<Vec<u8>>>,
+    current: io::Cursor<Vec<u8>>,
 }
 
-/// Creates a delta-decoding iterator for stdin that automatically handles zstd compression.
-fn create_stdin_reader() -> io::Result<DeltaDecodingReader<BufReader<Box<dyn Read>>>> {
-    let stdin = io::stdin();
-    let reader = create_reader_with_magic_detection(stdin)?;
-    let buffered_reader = BufReader::with_capacity(128 * 1024, reader); // 128KB BufReader
-    Ok(DeltaDecodingReader::new(buffered_reader))
+impl ParallelFrameReader {
+    fn new(file: File, frames: Vec<Range<u64>>, threads: usize) -> io::Result<Self> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .map_err(io::Error::other)?;
+
+        // Room for one batch waiting to be read while the next is decompressed
+        let (sender, decoded) = mpsc::sync_channel(threads);
+        thread::spawn(move || decode_frames(file, frames, &pool, &sender));
+
+        Ok(Self { decoded, current: io::Cursor::new(Vec::new()) })
+    }
+}
+
+/// Decompresses `frames` of `file` in parallel batches and sends them in order, stopping at the
+/// first error or once the reader is dropped.
+fn decode_frames(mut file: File, frames: Vec<Range<u64>>, pool: &rayon::ThreadPool, sender: &mpsc::SyncSender<io::Result<Vec<u8>>>) {
+    for batch in frames.chunks(pool.current_num_threads()) {
+        for frame in decode_batch(&mut file, batch, pool) {
+            let failed = frame.is_err();
+            if sender.send(frame).is_err() || failed {
+                return;
+            }
+        }
+    }
+}
+
+/// Reads a batch of compressed frames and decompresses them in parallel. Frames that can't be
+/// read or decompressed are errors in their place, so the frames before them are still sent;
+/// reading stops at the first frame that can't be read.
+fn decode_batch(file: &mut File, batch: &[Range<u64>], pool: &rayon::ThreadPool) -> Vec<io::Result<Vec<u8>>> {
+    let mut compressed = Vec::with_capacity(batch.len());
+    let mut read_error = None;
+
+    for range in batch {
+        let mut bytes = vec![0u8; (range.end - range.start) as usize];
+        match file.seek(SeekFrom::Start(range.start)).and_then(|_| file.read_exact(&mut bytes)) {
+            Ok(()) => compressed.push(bytes),
+            Err(e) => {
+                read_error = Some(e);
+                break;
+            }
+        }
+    }
+
+    let mut decoded: Vec<_> = pool.install(|| {
+        compressed.par_iter()
+            .map(|bytes| zstd::stream::decode_all(bytes.as_slice()))
+            .collect()
+    });
+    decoded.extend(read_error.map(Err));
+    decoded
+}
+
+impl Read for ParallelFrameReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = self.current.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+
+            // The sender hangs up once every frame has been sent
+            match self.decoded.recv() {
+                Ok(frame) => self.current = io::Cursor::new(frame?),
+                Err(_) => return Ok(0),
+            }
+        }
+    }
 }
 
-/// Creates a delta-decoding iterator for a given path, handling both file and stdin input, as well as zstd compression.
+/// Creates a record iterator for a given path, handling both file and stdin input, as well as zstd compression.
 pub fn create_reader<P: AsRef<Path>>(path: P) -> io::Result<impl IntoIterator<Item = io::Result<u128>>> {
-    let path_str = path.as_ref().to_string_lossy();
-    if path_str == "-" {
-        create_stdin_reader()
+    Ok(open_records(path)?.1)
+}
+
+/// Opens a single chunk of an indexed dataset, decoding only that chunk's bytes (one zstd frame
+/// when the dataset was encoded with a frame per chunk).
+///
+/// The chunk must start its own delta chain, i.e. the dataset was encoded with restarted deltas.
+pub fn open_chunk<P: AsRef<Path>>(data_path: P, index: &DatasetIndex, chunk: usize) -> io::Result<std::iter::Take<DeltaDecodingReader<Box<dyn Read>>>> {
+    open_chunk_with(data_path.as_ref(), index, chunk, false)
+}
+
+/// Same as [`open_chunk`], first checking the chunk's bytes against its checksum in the index
+/// with [`read_verified_chunk`].
+pub fn open_chunk_verified<P: AsRef<Path>>(data_path: P, index: &DatasetIndex, chunk: usize) -> io::Result<std::iter::Take<DeltaDecodingReader<Box<dyn Read>>>> {
+    open_chunk_with(data_path.as_ref(), index, chunk, true)
+}
+
+fn open_chunk_with(data_path: &Path, index: &DatasetIndex, chunk: usize, verify: bool) -> io::Result<std::iter::Take<DeltaDecodingReader<Box<dyn Read>>>> {
+    if !index.restart_delta {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "dataset chunks share one delta chain, so they can't be decoded independently",
+        ));
+    }
+
+    let bytes: Box<dyn Read> = if verify {
+        Box::new(io::Cursor::new(read_verified_chunk(data_path, index, chunk)?))
     } else {
-        create_file_reader(path)
+        open_range(data_path, index.chunk_range(chunk))?
+    };
+
+    let reader: Box<dyn Read> = if index.zstd_frames {
+        Box::new(Decompressed(zstd::stream::Decoder::new(bytes)?))
+    } else {
+        Box::new(BufReader::new(bytes))
+    };
+
+    Ok(DeltaDecodingReader::new(reader).take(index.chunks[chunk].count as usize))
+}
+
+/// Reads the encoded bytes of chunk `chunk`, checking them against the XXH64 checksum recorded
+/// in the index.
+///
+/// Fails with [`io::ErrorKind::InvalidData`], naming the chunk, when they don't match or the
+/// index was written without checksums.
+pub fn read_verified_chunk<P: AsRef<Path>>(data_path: P, index: &DatasetIndex, chunk: usize) -> io::Result<Vec<u8>> {
+    let data_path = data_path.as_ref();
+    let Some(expected) = index.chunks[chunk].checksum else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the index has no chunk checksums; re-encode the dataset to add them",
+        ));
+    };
+
+    let mut bytes = Vec::new();
+    open_range(data_path, index.chunk_range(chunk))?.read_to_end(&mut bytes)?;
+
+    let checksum = xxh64(&bytes, 0);
+    if checksum != expected {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("chunk {} of {} is corrupt: checksum {:016x}, expected {:016x}", chunk, data_path.display(), checksum, expected),
+        ));
+    }
+
+    Ok(bytes)
+}
+
+/// Reads records from an indexed dataset starting at record number `start`, without decoding
+/// any chunk before the one containing it. Use [`Iterator::take`] to read a range.
+pub fn read_records_from<P: AsRef<Path>>(data_path: P, index: &DatasetIndex, start: u64) -> io::Result<IndexedRecords<'_>> {
+    let data_path = data_path.as_ref().to_path_buf();
+
+    let Some((chunk, skip)) = index.locate(start) else {
+        return Ok(IndexedRecords { data_path, index, next_chunk: index.chunks.len(), current: None });
+    };
+
+    let mut current = open_chunk(&data_path, index, chunk)?;
+
+    // Records within a frame still have to be decoded in order to follow the delta chain
+    for record in current.by_ref().take(skip as usize) {
+        record?;
+    }
+
+    Ok(IndexedRecords { data_path, index, next_chunk: chunk + 1, current: Some(current) })
+}
+
+/// Iterator returned by [`read_records_from`], opening each following chunk as it's reached.
+pub struct IndexedRecords<'a> {
+    data_path: std::path::PathBuf,
+    index: &'a DatasetIndex,
+    next_chunk: usize,
+    current: Option<std::iter::Take<DeltaDecodingReader<Box<dyn Read>>>>,
+}
+
+impl Iterator for IndexedRecords<'_> {
+    type Item = io::Result<u128>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(record) = self.current.as_mut()?.next() {
+                return Some(record);
+            }
+
+            if self.next_chunk == self.index.chunks.len() {
+                self.current = None;
+                return None;
+            }
+
+            match open_chunk(&self.data_path, self.index, self.next_chunk) {
+                Ok(chunk) => self.current = Some(chunk),
+                Err(e) => {
+                    self.current = None;
+                    return Some(Err(e));
+                }
+            }
+            self.next_chunk += 1;
+        }
+    }
+}
+
+/// Skips the first `skip` records and yields at most `limit` after them (all of them when
+/// `limit` is `None`), for spot checks or splitting a scan into ranges of records.
+///
+/// Unlike [`Iterator::skip`], an error in a skipped record is returned rather than dropped, so
+/// a corrupt record before the range still fails the scan. Delta-encoded records have to be
+/// decoded to be skipped; with an index, [`read_records_from`] jumps to the right chunk instead.
+pub fn record_range<I>(records: I, skip: u64, limit: Option<u64>) -> RecordRange<I::IntoIter>
+where
+    I: IntoIterator<Item = io::Result<u128>>,
+{
+    RecordRange { records: records.into_iter(), skip, remaining: limit }
+}
+
+/// Iterator returned by [`record_range`].
+pub struct RecordRange<I> {
+    records: I,
+    skip: u64,
+    remaining: Option<u64>,
+}
+
+impl<I: Iterator<Item = io::Result<u128>>> Iterator for RecordRange<I> {
+    type Item = io::Result<u128>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.skip > 0 {
+            self.skip -= 1;
+            if let Err(e) = self.records.next()? {
+                self.skip = 0;
+                self.remaining = Some(0);
+                return Some(Err(e));
+            }
+        }
+
+        match &mut self.remaining {
+            Some(0) => None,
+            Some(remaining) => {
+                *remaining -= 1;
+                self.records.next()
+            }
+            None => self.records.next(),
+        }
     }
 }
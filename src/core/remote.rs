@@ -0,0 +1,89 @@
+use std::env;
+use std::io::{self, Read};
+use std::ops::Range;
+
+/// A response body being read.
+pub type RemoteBody = Box<dyn Read + Send + Sync>;
+
+/// The http(s) URL to fetch `location` from.
+///
+/// `s3://bucket/key` locations are fetched from the bucket's virtual-hosted endpoint in
+/// `AWS_REGION` (or `AWS_DEFAULT_REGION`, else us-east-1), or path-style from `AWS_ENDPOINT_URL`
+/// when it's set, e.g. for MinIO. Requests aren't signed, so private objects need a presigned
+/// https URL instead.
+pub fn resolve_url(location: &str) -> io::Result<String> {
+    let Some(object) = location.strip_prefix("s3://") else {
+        return Ok(location.to_string());
+    };
+
+    let Some((bucket, key)) = object.split_once('/').filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty()) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("'{}' isn't an s3://bucket/key location", location),
+        ));
+    };
+
+    if let Ok(endpoint) = env::var("AWS_ENDPOINT_URL") {
+        return Ok(format!("{}/{}/{}", endpoint.trim_end_matches('/'), bucket, key));
+    }
+
+    let region = env::var("AWS_REGION")
+        .or_else(|_| env::var("AWS_DEFAULT_REGION"))
+        .unwrap_or_else(|_| "us-east-1".to_string());
+    Ok(format!("https://{}.s3.{}.amazonaws.com/{}", bucket, region, key))
+}
+
+/// Streams the whole object at `location`.
+pub fn open_remote(location: &str) -> io::Result<RemoteBody> {
+    let url = resolve_url(location)?;
+    let response = ureq::get(&url).call().map_err(|e| request_error(&url, e))?;
+    Ok(response.into_reader())
+}
+
+/// Streams the bytes in `range` of the object at `location` with an HTTP range request.
+///
+/// Fails if the server ignores the range and would send the whole object instead.
+pub fn open_remote_range(location: &str, range: Range<u64>) -> io::Result<RemoteBody> {
+    let url = resolve_url(location)?;
+    if range.is_empty() {
+        return Ok(Box::new(io::empty()));
+    }
+
+    let response = ureq::get(&url)
+        .set("Range", &format!("bytes={}-{}", range.start, range.end - 1))
+        .call()
+        .map_err(|e| request_error(&url, e))?;
+
+    if response.status() != 206 {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} doesn't support range requests (status {})", url, response.status()),
+        ));
+    }
+
+    Ok(Box::new(response.into_reader().take(range.end - range.start)))
+}
+
+/// Length in bytes of the object at `location`, from a HEAD request.
+pub fn remote_len(location: &str) -> io::Result<u64> {
+    let url = resolve_url(location)?;
+    let response = ureq::head(&url).call().map_err(|e| request_error(&url, e))?;
+
+    response.header("Content-Length")
+        .and_then(|len| len.parse().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("{} has no Content-Length", url)))
+}
+
+fn request_error(url: &str, error: ureq::Error) -> io::Error {
+    match error {
+        ureq::Error::Status(status, _) => {
+            let kind = match status {
+                404 => io::ErrorKind::NotFound,
+                401 | 403 => io::ErrorKind::PermissionDenied,
+                _ => io::ErrorKind::Other,
+            };
+            io::Error::new(kind, format!("{} returned status {}", url, status))
+        }
+        ureq::Error::Transport(transport) => io::Error::other(format!("couldn't fetch {}: {}", url, transport)),
+    }
+}
@@ -0,0 +1,245 @@
+use crate::core::accumulator::{CountAccumulator, MatchCount, PairCorrelations};
+use crate::core::filter::{filter_and_accumulate, QueryOptions};
+use crate::core::geometry::BoardGeometry;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Identifies a rollup table file.
+const ROLLUP_MAGIC: [u8; 4] = *b"BSRU";
+
+/// Version of the table file layout written by [`RollupTable::write_to`].
+pub const ROLLUP_VERSION: u16 = 1;
+
+const FLAG_EXPAND_SYMMETRIES: u16 = 1 << 0;
+
+/// Cells of the standard board, the only one tables are built for.
+const CELLS: usize = 81;
+
+/// Precomputed heatmaps of every query with a single hit or miss, built by one scan of a
+/// 9x9 dataset.
+///
+/// The table holds how often each pair of cells has a ship on the same board. Conditioning on a
+/// hit in cell `c` is row `c` of it; conditioning on a miss is the unconditional counts minus
+/// that row. Queries with more shots can't be answered exactly, but pairs of their shots bound
+/// how many boards can match (see [`RollupTable::upper_bounds`]), which rules out queries that
+/// match nothing without a scan.
+///
+/// Tables say nothing about which dataset they were built from, so it's up to the caller to
+/// keep them with their dataset. The file is little-endian: the magic `BSRU`, a u16 version,
+/// u16 flags, the u64 number of boards, then the 81×81 pair counts as u64s, row by row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RollupTable {
+    /// Records were counted as every board in their symmetry orbit.
+    pub expand_symmetries: bool,
+    /// Number of boards counted.
+    pub total: u64,
+    pairs: Box<[[u64; CELLS]; CELLS]>,
+}
+
+impl RollupTable {
+    /// Scans `reader` once to build the table. Only `options.expand_symmetries` and the scan
+    /// settings are used; the table always covers every board, so popcount ranges are rejected.
+    pub fn build<I>(reader: I, options: &QueryOptions) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = io::Result<u128>>,
+    {
+        if options.geometry != BoardGeometry::STANDARD {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Rollup tables require a 9x9 board, not {}", options.geometry),
+            ));
+        }
+        if options.popcount.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Rollup tables cover every board and can't be limited to a popcount range",
+            ));
+        }
+
+        let (pairs, total) = filter_and_accumulate(reader, 0, 0, options, RollupCounts::new)?;
+        Ok(Self { expand_symmetries: options.expand_symmetries, total, pairs })
+    }
+
+    /// True if queries run with `options` can be answered from the table.
+    pub fn answers(&self, options: &QueryOptions) -> bool {
+        options.geometry == BoardGeometry::STANDARD
+            && options.popcount.is_none()
+            && options.expand_symmetries == self.expand_symmetries
+    }
+
+    /// Number of boards with a ship in both cell `a` and cell `b` (in cell `a` when they're the
+    /// same).
+    pub fn pair_count(&self, a: usize, b: usize) -> u64 {
+        self.pairs[a][b]
+    }
+
+    /// The per-cell counts and number of matched boards of a query with at most one shot, or
+    /// `None` if it has more and needs a scan.
+    pub fn lookup(&self, hit_mask: u128, miss_mask: u128) -> Option<([u32; 81], u64)> {
+        let shots = hit_mask | miss_mask;
+        if shots & !BoardGeometry::STANDARD.cell_mask() != 0 || shots.count_ones() > 1 {
+            return None;
+        }
+
+        // A cell can't be both hit and missed
+        if hit_mask & miss_mask != 0 {
+            return Some(([0; 81], 0));
+        }
+
+        let mut counts = [0; 81];
+        let matched = if hit_mask != 0 {
+            let cell = hit_mask.trailing_zeros() as usize;
+            for (count, &pair) in counts.iter_mut().zip(&self.pairs[cell]) {
+                *count = pair as u32;
+            }
+            self.pairs[cell][cell]
+        } else if miss_mask != 0 {
+            let cell = miss_mask.trailing_zeros() as usize;
+            for (other, count) in counts.iter_mut().enumerate() {
+                *count = (self.pairs[other][other] - self.pairs[cell][other]) as u32;
+            }
+            self.total - self.pairs[cell][cell]
+        } else {
+            for (cell, count) in counts.iter_mut().enumerate() {
+                *count = self.pairs[cell][cell] as u32;
+            }
+            self.total
+        };
+
+        Some((counts, matched))
+    }
+
+    /// Upper bounds on the per-cell counts and number of matched boards of any query.
+    ///
+    /// Every shot and every pair of shots is a weaker query the table answers exactly, and no
+    /// query can match more boards than a weaker one. When the bound on matched boards is 0, the
+    /// query matches nothing. Costs O(shots²), so it's cheap next to a scan.
+    pub fn upper_bounds(&self, hit_mask: u128, miss_mask: u128) -> ([u32; 81], u64) {
+        let shots = hit_mask | miss_mask;
+        if hit_mask & miss_mask != 0 || shots & !BoardGeometry::STANDARD.cell_mask() != 0 {
+            return ([0; 81], 0);
+        }
+
+        let cells: Vec<(usize, bool)> = (0..CELLS)
+            .filter(|&cell| shots >> cell & 1 != 0)
+            .map(|cell| (cell, hit_mask >> cell & 1 != 0))
+            .collect();
+
+        // Boards matching both conditions on cells `a` and `b` (the same cell gives one condition)
+        let both = |(a, a_hit): (usize, bool), (b, b_hit): (usize, bool)| match (a_hit, b_hit) {
+            (true, true) => self.pairs[a][b],
+            (true, false) => self.pairs[a][a] - self.pairs[a][b],
+            (false, true) => self.pairs[b][b] - self.pairs[a][b],
+            (false, false) if a == b => self.total - self.pairs[a][a],
+            (false, false) => self.total + self.pairs[a][b] - self.pairs[a][a] - self.pairs[b][b],
+        };
+
+        let mut matched = self.total;
+        for (i, &first) in cells.iter().enumerate() {
+            for &second in &cells[i..] {
+                matched = matched.min(both(first, second));
+            }
+        }
+
+        let mut counts = [0; 81];
+        for (cell, count) in counts.iter_mut().enumerate() {
+            let bound = if miss_mask >> cell & 1 != 0 {
+                0
+            } else {
+                cells.iter().fold(matched.min(self.pairs[cell][cell]), |bound, &shot| bound.min(both(shot, (cell, true))))
+            };
+            *count = bound as u32;
+        }
+
+        (counts, matched)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let flags = if self.expand_symmetries { FLAG_EXPAND_SYMMETRIES } else { 0 };
+
+        writer.write_all(&ROLLUP_MAGIC)?;
+        writer.write_all(&ROLLUP_VERSION.to_le_bytes())?;
+        writer.write_all(&flags.to_le_bytes())?;
+        writer.write_all(&self.total.to_le_bytes())?;
+        for row in self.pairs.iter() {
+            for count in row {
+                writer.write_all(&count.to_le_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != ROLLUP_MAGIC {
+            return Err(invalid("Not a rollup table file"));
+        }
+
+        let version = u16::from_le_bytes(read_array(reader)?);
+        if version != ROLLUP_VERSION {
+            return Err(invalid(&format!("Unsupported rollup table version {}", version)));
+        }
+
+        let flags = u16::from_le_bytes(read_array(reader)?);
+        let total = u64::from_le_bytes(read_array(reader)?);
+        let mut pairs = Box::new([[0; CELLS]; CELLS]);
+        for row in pairs.iter_mut() {
+            for count in row.iter_mut() {
+                *count = u64::from_le_bytes(read_array(reader)?);
+            }
+        }
+
+        Ok(Self { expand_symmetries: flags & FLAG_EXPAND_SYMMETRIES != 0, total, pairs })
+    }
+}
+
+/// Pair counts plus the number of boards, which the pairs alone miss for empty boards.
+struct RollupCounts {
+    pairs: PairCorrelations,
+    boards: MatchCount,
+}
+
+impl RollupCounts {
+    fn new() -> Self {
+        Self { pairs: PairCorrelations::new(), boards: MatchCount::new() }
+    }
+}
+
+impl CountAccumulator for RollupCounts {
+    type Output = (Box<[[u64; 81]; 81]>, u64);
+
+    fn visit(&mut self, board: u128) {
+        self.pairs.visit(board);
+        self.boards.visit(board);
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.pairs.merge(other.pairs);
+        self.boards.merge(other.boards);
+    }
+
+    fn finish(self) -> Self::Output {
+        (self.pairs.finish(), self.boards.finish())
+    }
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut buffer = [0u8; N];
+    reader.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
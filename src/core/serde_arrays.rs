@@ -0,0 +1,22 @@
+//! Serializes fixed-size arrays longer than serde supports (32 elements) as sequences, for use
+//! with `#[serde(with = "crate::core::serde_arrays")]`.
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub fn serialize<S, T, const N: usize>(array: &[T; N], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize,
+{
+    array.as_slice().serialize(serializer)
+}
+
+pub fn deserialize<'de, D, T, const N: usize>(deserializer: D) -> Result<[T; N], D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    let items = Vec::<T>::deserialize(deserializer)?;
+    items.try_into().map_err(|items: Vec<T>| D::Error::invalid_length(items.len(), &format!("{} elements", N).as_str()))
+}
@@ -0,0 +1,93 @@
+use crate::core::accumulator::CellCounts;
+use crate::core::dataset::Dataset;
+use crate::core::filter::{filter_and_accumulate_indexed, FilterResult, QueryOptions};
+use crate::core::geometry::BoardGeometry;
+use crate::core::index::DatasetIndex;
+use crate::core::query::Query;
+use crate::core::reader::{create_reader, open_chunk};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A dataset kept open for running many queries against, either fully decoded in memory or as a
+/// loaded index whose matching chunks are decoded on each query.
+#[derive(Debug)]
+pub enum Session {
+    Decoded(Dataset),
+    Indexed { data_path: PathBuf, index: DatasetIndex },
+}
+
+impl Session {
+    /// Decodes every record of a dataset file into memory.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Session::Decoded(Dataset::load(path)?))
+    }
+
+    /// Loads the index of a dataset encoded with restarted deltas, leaving the records on disk.
+    pub fn open_indexed<P: AsRef<Path>, Q: AsRef<Path>>(data_path: P, index_path: Q) -> io::Result<Self> {
+        Ok(Session::Indexed { data_path: data_path.as_ref().to_path_buf(), index: DatasetIndex::load(index_path)? })
+    }
+
+    /// Counts hits per cell over the records matching a standard-board query.
+    pub fn query(&self, query: &Query) -> io::Result<FilterResult> {
+        require_standard(query)?;
+
+        match self {
+            Session::Decoded(dataset) => Ok(dataset.filter_and_count(query.hit_mask(), query.miss_mask())),
+            Session::Indexed { data_path, index } => {
+                let options = QueryOptions::default();
+                let result = filter_and_accumulate_indexed(data_path, index, query.hit_mask(), query.miss_mask(), &options, CellCounts::new)?;
+                Ok(result.into())
+            }
+        }
+    }
+
+    /// Passes every record matching a standard-board query to `visit`, in dataset order,
+    /// stopping early once `visit` returns `false`.
+    ///
+    /// Indexed sessions only decode the chunks that may contain a match.
+    pub fn for_each_match(&self, query: &Query, mut visit: impl FnMut(u128) -> bool) -> io::Result<()> {
+        require_standard(query)?;
+        let (hit_mask, miss_mask) = (query.hit_mask(), query.miss_mask());
+        let matches = |board: u128| board & hit_mask == hit_mask && board & miss_mask == 0;
+
+        match self {
+            Session::Decoded(dataset) => {
+                for &board in dataset.boards() {
+                    if matches(board) && !visit(board) {
+                        break;
+                    }
+                }
+            }
+            Session::Indexed { data_path, index } if index.restart_delta => {
+                for chunk in (0..index.chunks.len()).filter(|&i| index.chunks[i].may_contain(hit_mask, miss_mask)) {
+                    for board in open_chunk(data_path, index, chunk)? {
+                        let board = board?;
+                        if matches(board) && !visit(board) {
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+            Session::Indexed { data_path, .. } => {
+                for board in create_reader(data_path)? {
+                    let board = board?;
+                    if matches(board) && !visit(board) {
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn require_standard(query: &Query) -> io::Result<()> {
+    if query.geometry() != BoardGeometry::STANDARD {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("sessions count 9x9 boards, not {}", query.geometry()),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use crate::core::random::SplitMix64;
+use crate::core::reader::read_record;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of temporary buckets used when a stream doesn't fit in memory.
+const BUCKET_COUNT: usize = 64;
+
+static NEXT_BUCKET_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Randomly permutes a stream of raw (not delta-encoded) 16-byte records.
+///
+/// Streams of up to `max_records_in_memory` records are shuffled in memory. Larger streams are
+/// scattered into random temporary buckets under `temp_dir`, and each bucket is then shuffled
+/// recursively, which still yields a uniform permutation. The same seed always produces the
+/// same output order.
+///
+/// Returns the number of records written.
+pub fn shuffle_records<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    seed: u64,
+    max_records_in_memory: usize,
+    temp_dir: &Path,
+) -> io::Result<u64> {
+    assert!(max_records_in_memory > 0, "Must allow at least one record in memory");
+
+    let mut rng = SplitMix64::new(seed);
+    let mut records = Vec::new();
+
+    while records.len() < max_records_in_memory {
+        match read_record(reader)? {
+            Some(record) => records.push(record),
+            None => {
+                rng.shuffle(&mut records);
+                for record in &records {
+                    writer.write_all(&record.to_le_bytes())?;
+                }
+                writer.flush()?;
+                return Ok(records.len() as u64);
+            }
+        }
+    }
+
+    // Too large for memory: scatter everything into random buckets
+    let mut buckets = (0..BUCKET_COUNT)
+        .map(|_| TempBucket::create(temp_dir))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    for record in records.drain(..) {
+        buckets[rng.next_below(BUCKET_COUNT as u64) as usize].write(record)?;
+    }
+
+    while let Some(record) = read_record(reader)? {
+        buckets[rng.next_below(BUCKET_COUNT as u64) as usize].write(record)?;
+    }
+
+    let mut total = 0u64;
+    for bucket in buckets {
+        let mut bucket_reader = bucket.into_reader()?;
+        total += shuffle_records(&mut bucket_reader, writer, rng.next_u64(), max_records_in_memory, temp_dir)?;
+    }
+
+    Ok(total)
+}
+
+/// A temporary file of raw records that is removed when dropped.
+pub(crate) struct TempBucket {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl TempBucket {
+    pub(crate) fn create(dir: &Path) -> io::Result<Self> {
+        let id = NEXT_BUCKET_ID.fetch_add(1, Ordering::Relaxed);
+        let path = dir.join(format!("battleship-bucket-{}-{}.tmp", std::process::id(), id));
+        let file = File::options().read(true).write(true).create_new(true).open(&path)?;
+        Ok(Self { path, writer: BufWriter::new(file) })
+    }
+
+    pub(crate) fn write(&mut self, record: u128) -> io::Result<()> {
+        self.writer.write_all(&record.to_le_bytes())
+    }
+
+    pub(crate) fn into_reader(mut self) -> io::Result<TempBucketReader> {
+        self.writer.flush()?;
+        let mut file = self.writer.get_ref().try_clone()?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok(TempBucketReader { reader: BufReader::new(file), _bucket: self })
+    }
+}
+
+impl Drop for TempBucket {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+pub(crate) struct TempBucketReader {
+    reader: BufReader<File>,
+    _bucket: TempBucket,
+}
+
+impl Read for TempBucketReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
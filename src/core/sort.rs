@@ -0,0 +1,78 @@
+use crate::core::reader::read_record;
+use crate::core::shuffle::{TempBucket, TempBucketReader};
+use rayon::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Sorts a stream of records numerically, writing them raw (not delta-encoded) to `writer`.
+///
+/// Sorted records have small XOR deltas, so they compress much better once encoded. Streams of
+/// up to `max_records_in_memory` records are sorted in memory. Larger streams are split into
+/// sorted runs of that size in temporary files under `temp_dir`, which are then merged.
+///
+/// Returns the number of records written.
+pub fn sort_records<I, W>(records: I, writer: &mut W, max_records_in_memory: usize, temp_dir: &Path) -> io::Result<u64>
+where
+    I: IntoIterator<Item = io::Result<u128>>,
+    W: Write,
+{
+    assert!(max_records_in_memory > 0, "Must allow at least one record in memory");
+
+    let mut records = records.into_iter();
+    let mut runs = Vec::new();
+    let mut run = Vec::new();
+
+    loop {
+        run.clear();
+        for record in records.by_ref().take(max_records_in_memory) {
+            run.push(record?);
+        }
+        run.par_sort_unstable();
+
+        // Everything fit in memory: no need for temporary files
+        if runs.is_empty() && run.len() < max_records_in_memory {
+            for record in &run {
+                writer.write_all(&record.to_le_bytes())?;
+            }
+            writer.flush()?;
+            return Ok(run.len() as u64);
+        }
+
+        if run.is_empty() {
+            break;
+        }
+
+        let mut bucket = TempBucket::create(temp_dir)?;
+        for &record in &run {
+            bucket.write(record)?;
+        }
+        runs.push(bucket.into_reader()?);
+    }
+
+    merge_sorted_runs(runs, writer)
+}
+
+/// Merges sorted runs of raw records into `writer`, returning the number of records written.
+fn merge_sorted_runs<W: Write>(mut runs: Vec<TempBucketReader>, writer: &mut W) -> io::Result<u64> {
+    let mut heap = BinaryHeap::with_capacity(runs.len());
+    for (i, run) in runs.iter_mut().enumerate() {
+        if let Some(record) = read_record(run)? {
+            heap.push(Reverse((record, i)));
+        }
+    }
+
+    let mut total = 0u64;
+    while let Some(Reverse((record, i))) = heap.pop() {
+        writer.write_all(&record.to_le_bytes())?;
+        total += 1;
+
+        if let Some(next) = read_record(&mut runs[i])? {
+            heap.push(Reverse((next, i)));
+        }
+    }
+
+    writer.flush()?;
+    Ok(total)
+}
@@ -0,0 +1,48 @@
+use crate::core::writer::DeltaEncodingWriter;
+use std::io::{self, Write};
+
+/// How [`split_records`] assigns records to shards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitMode {
+    /// Record `i` goes to shard `i % shards`.
+    RoundRobin,
+    /// Each shard gets one contiguous range of records, with sizes differing by at most one.
+    /// `total` is the number of records in the input.
+    Ranges { total: u64 },
+}
+
+/// Partitions a stream of decoded records into `shards`, delta-encoding each shard as its own
+/// stream starting from zero so shards can be decoded independently.
+///
+/// The writers should already be positioned after any header. Returns the number of records
+/// written to each shard.
+pub fn split_records<I, W>(records: I, shards: &mut [W], mode: SplitMode) -> io::Result<Vec<u64>>
+where
+    I: IntoIterator<Item = io::Result<u128>>,
+    W: Write,
+{
+    assert!(!shards.is_empty(), "Must split into at least one shard");
+
+    let shard_count = shards.len() as u64;
+    let mut writers: Vec<_> = shards.iter_mut().map(DeltaEncodingWriter::new).collect();
+
+    for (i, record) in records.into_iter().enumerate() {
+        let i = i as u64;
+        let shard = match mode {
+            SplitMode::RoundRobin => i % shard_count,
+            SplitMode::Ranges { total } if i < total => (i as u128 * shard_count as u128 / total as u128) as u64,
+            // More records than expected: keep them in order at the end
+            SplitMode::Ranges { .. } => shard_count - 1,
+        };
+        writers[shard as usize].write_record(record?)?;
+    }
+
+    writers
+        .into_iter()
+        .map(|writer| {
+            let count = writer.records_written();
+            writer.into_inner()?;
+            Ok(count)
+        })
+        .collect()
+}
@@ -0,0 +1,166 @@
+//! SQLite storage, built with the `sqlite` feature, for keeping boards in a queryable database
+//! instead of a flat file.
+//!
+//! The database has a `boards` table with one row per board: `board` holds the mask as a 16-byte
+//! big-endian blob, so blobs compare in the same order as the masks, and `popcount` holds its
+//! number of ship cells. Both are indexed. A `meta` table records the board geometry.
+//!
+//! ```sql
+//! SELECT count(*) FROM boards WHERE popcount = 27;
+//! ```
+
+use crate::core::filter::{filter_and_count_cells, QueryOptions};
+use crate::core::geometry::BoardGeometry;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use std::io::{self, Write};
+use std::path::Path;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS boards (id INTEGER PRIMARY KEY, board BLOB NOT NULL, popcount INTEGER NOT NULL);
+    CREATE INDEX IF NOT EXISTS boards_by_board ON boards (board);
+    CREATE INDEX IF NOT EXISTS boards_by_popcount ON boards (popcount);
+";
+
+fn sql_error(error: rusqlite::Error) -> io::Error {
+    io::Error::other(error)
+}
+
+/// A database of boards, see the [module docs](self).
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: Connection,
+    geometry: BoardGeometry,
+}
+
+impl SqliteStore {
+    /// Opens the database at `path`, creating it for boards of `geometry` if it doesn't exist.
+    /// An existing database must hold boards of the same geometry.
+    pub fn create<P: AsRef<Path>>(path: P, geometry: BoardGeometry) -> io::Result<Self> {
+        let connection = Connection::open(path).map_err(sql_error)?;
+        connection.execute_batch(SCHEMA).map_err(sql_error)?;
+        connection
+            .execute("INSERT OR IGNORE INTO meta (key, value) VALUES ('geometry', ?1)", params![geometry.to_string()])
+            .map_err(sql_error)?;
+
+        let store = Self::from_connection(connection)?;
+        if store.geometry != geometry {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("database holds {} boards, not {}", store.geometry, geometry),
+            ));
+        }
+        Ok(store)
+    }
+
+    /// Opens an existing database.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let flags = OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+        Self::from_connection(Connection::open_with_flags(path, flags).map_err(sql_error)?)
+    }
+
+    fn from_connection(connection: Connection) -> io::Result<Self> {
+        let not_a_store = || io::Error::new(io::ErrorKind::InvalidData, "not a board database (no geometry in its meta table)");
+
+        let geometry: Option<String> = connection
+            .query_row("SELECT value FROM meta WHERE key = 'geometry'", [], |row| row.get(0))
+            .optional()
+            .map_err(|_| not_a_store())?;
+        let geometry = geometry
+            .ok_or_else(not_a_store)?
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("invalid geometry in database: {}", e)))?;
+
+        Ok(Self { connection, geometry })
+    }
+
+    pub fn geometry(&self) -> BoardGeometry {
+        self.geometry
+    }
+
+    /// Number of boards in the database.
+    pub fn len(&self) -> io::Result<u64> {
+        self.connection.query_row("SELECT count(*) FROM boards", [], |row| row.get(0)).map_err(sql_error)
+    }
+
+    pub fn is_empty(&self) -> io::Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Appends every record of `reader` in a single transaction, returning the number added.
+    pub fn import<I>(&mut self, reader: I) -> io::Result<u64>
+    where
+        I: IntoIterator<Item = io::Result<u128>>,
+    {
+        let transaction = self.connection.transaction().map_err(sql_error)?;
+        let mut imported = 0u64;
+
+        {
+            let mut insert = transaction.prepare("INSERT INTO boards (board, popcount) VALUES (?1, ?2)").map_err(sql_error)?;
+            for board in reader {
+                let board = board?;
+                insert.execute(params![board.to_be_bytes(), board.count_ones()]).map_err(sql_error)?;
+                imported += 1;
+            }
+        }
+
+        // Dropping the transaction on an error above rolls back the partial import
+        transaction.commit().map_err(sql_error)?;
+        Ok(imported)
+    }
+
+    /// Writes every board as a raw 16-byte little-endian record, in the order they were
+    /// imported, returning the number written.
+    pub fn export<W: Write>(&self, writer: &mut W) -> io::Result<u64> {
+        let mut select = self.connection.prepare("SELECT board FROM boards ORDER BY id").map_err(sql_error)?;
+        let mut exported = 0u64;
+
+        for board in select.query_map([], |row| row.get::<_, Vec<u8>>(0)).map_err(sql_error)? {
+            writer.write_all(&blob_to_board(&board.map_err(sql_error)?)?.to_le_bytes())?;
+            exported += 1;
+        }
+
+        Ok(exported)
+    }
+
+    /// Counts ships per cell over the boards with every `hit_mask` cell set and no `miss_mask`
+    /// cell set, like [`filter_and_count_cells`].
+    ///
+    /// Only boards that can match are read from the database. A board with every hit is at least
+    /// `hit_mask` and one with no misses is at most the mask of cells that aren't misses, so the
+    /// `board` index narrows the scan to that range, and the `popcount` index narrows it to
+    /// `options.popcount`. The exact test runs on the boards that remain.
+    pub fn filter_and_count(&self, hit_mask: u128, miss_mask: u128, options: &QueryOptions) -> io::Result<(Vec<u32>, u64)> {
+        if options.geometry != self.geometry {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("query is for {} boards, but the database holds {} boards", options.geometry, self.geometry),
+            ));
+        }
+
+        // The stored boards' symmetric variants fall anywhere, so only popcount narrows those
+        let (low, high) = if options.expand_symmetries {
+            (0, u128::MAX)
+        } else {
+            (hit_mask, self.geometry.cell_mask() & !miss_mask)
+        };
+        let (min_popcount, max_popcount) = options.popcount.map_or((0, 128), |range| (range.min, range.max));
+
+        let mut select = self.connection
+            .prepare("SELECT board FROM boards WHERE board BETWEEN ?1 AND ?2 AND popcount BETWEEN ?3 AND ?4")
+            .map_err(sql_error)?;
+        let rows = select
+            .query_map(params![low.to_be_bytes(), high.to_be_bytes(), min_popcount, max_popcount], |row| row.get::<_, Vec<u8>>(0))
+            .map_err(sql_error)?;
+
+        let records = rows.map(|row| blob_to_board(&row.map_err(sql_error)?));
+        filter_and_count_cells(records, hit_mask, miss_mask, options)
+    }
+}
+
+fn blob_to_board(blob: &[u8]) -> io::Result<u128> {
+    let bytes: [u8; 16] = blob
+        .try_into()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("board blob has {} bytes, not 16", blob.len())))?;
+    Ok(u128::from_be_bytes(bytes))
+}
@@ -0,0 +1,64 @@
+/// The crate version.
+pub const CRATE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Version of the C ABI exported from [`crate::core::ffi`]. Incremented on any breaking change
+/// to exported function signatures or struct layouts.
+pub const ABI_VERSION: u32 = 1;
+
+/// Dataset format versions this build can read.
+///
+/// Version 0 is the original headerless stream of 16-byte delta-encoded records. Version 1 adds
+/// a [`crate::core::header::DatasetHeader`] in front of the records.
+pub const FORMAT_VERSIONS: &[u32] = &[0, 1];
+
+/// Counting backends compiled into this build.
+#[cfg(not(feature = "gpu"))]
+pub const KERNEL_BACKENDS: &[&str] = &["chunked-rayon", "streaming", "bitsliced"];
+
+/// Counting backends compiled into this build.
+#[cfg(feature = "gpu")]
+pub const KERNEL_BACKENDS: &[&str] = &["chunked-rayon", "streaming", "bitsliced", "gpu"];
+
+/// Cargo features enabled in this build.
+pub fn enabled_features() -> Vec<&'static str> {
+    let features: &[(&str, bool)] = &[
+        ("gzip", cfg!(feature = "gzip")),
+        ("xz", cfg!(feature = "xz")),
+        ("lz4", cfg!(feature = "lz4")),
+        ("zstdmt", cfg!(feature = "zstdmt")),
+        ("python", cfg!(feature = "python")),
+        ("wasm", cfg!(feature = "wasm")),
+        ("serde", cfg!(feature = "serde")),
+        ("msgpack", cfg!(feature = "msgpack")),
+        ("server", cfg!(feature = "server")),
+        ("grpc", cfg!(feature = "grpc")),
+        ("async", cfg!(feature = "async")),
+        ("remote", cfg!(feature = "remote")),
+        ("gpu", cfg!(feature = "gpu")),
+        ("parquet", cfg!(feature = "parquet")),
+        ("sqlite", cfg!(feature = "sqlite")),
+    ];
+
+    features
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect()
+}
+
+/// Describes this build as a single-line JSON object.
+pub fn version_json() -> String {
+    let list = |items: &[&str]| {
+        items.iter().map(|item| format!("\"{}\"", item)).collect::<Vec<_>>().join(",")
+    };
+    let formats = FORMAT_VERSIONS.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(",");
+
+    format!(
+        "{{\"version\":\"{}\",\"abi_version\":{},\"format_versions\":[{}],\"features\":[{}],\"kernel_backends\":[{}]}}",
+        CRATE_VERSION,
+        ABI_VERSION,
+        formats,
+        list(&enabled_features()),
+        list(KERNEL_BACKENDS),
+    )
+}
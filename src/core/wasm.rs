@@ -0,0 +1,96 @@
+//! WebAssembly bindings, built with the `wasm` feature for `wasm32-unknown-unknown`.
+//!
+//! A page downloads a slice of a dataset (any format the reader understands), loads it once,
+//! and runs queries against it client-side:
+//!
+//! ```js
+//! import init, { Dataset, recommendShot } from "./pkg/battleship.js";
+//! await init();
+//! const bytes = new Uint8Array(await (await fetch("boards.bin")).arrayBuffer());
+//! const dataset = new Dataset(bytes);
+//! const result = dataset.filter(1n << 40n, 0n);
+//! const cell = recommendShot(result.counts, 1n << 40n);
+//! ```
+//!
+//! Masks are BigInts with bit `y * 9 + x` set for cell `(x, y)`.
+
+use crate::core::dataset::Dataset as BoardSet;
+use crate::core::filter::FilterResult;
+use crate::core::geometry::BoardGeometry;
+use crate::core::query::Query;
+use crate::core::reader::read_records;
+use crate::solver::recommend;
+use std::io::Cursor;
+use wasm_bindgen::prelude::*;
+
+/// Boards decoded from an in-memory dataset, ready for repeated queries.
+#[wasm_bindgen]
+pub struct Dataset {
+    boards: BoardSet,
+}
+
+#[wasm_bindgen]
+impl Dataset {
+    /// Decodes every record in `bytes`. Only datasets of the standard 9x9 board are supported.
+    #[wasm_bindgen(constructor)]
+    pub fn new(bytes: Vec<u8>) -> Result<Dataset, JsError> {
+        let (header, records) = read_records(Cursor::new(bytes))?;
+
+        let query = Query::compile(0, 0, BoardGeometry::STANDARD)?;
+        if let Some(header) = header {
+            query.check_dataset(header.geometry, "buffer")?;
+        }
+
+        let boards = BoardSet::from_records(query.guard(records, "buffer"))?;
+        Ok(Dataset { boards })
+    }
+
+    /// Number of boards in the dataset.
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.boards.len()
+    }
+
+    /// Counts ships per cell over the boards with a ship on every `hit` cell and none on any
+    /// `miss` cell.
+    pub fn filter(&self, hit: u128, miss: u128) -> Result<QueryResult, JsError> {
+        let query = Query::compile(hit, miss, BoardGeometry::STANDARD)?;
+        let result = self.boards.filter_and_count(query.hit_mask(), query.miss_mask());
+        Ok(QueryResult(result))
+    }
+}
+
+/// Ship counts per cell and the number of matching boards.
+#[wasm_bindgen]
+pub struct QueryResult(FilterResult);
+
+#[wasm_bindgen]
+impl QueryResult {
+    /// Ship count of each cell in row-major order.
+    #[wasm_bindgen(getter)]
+    pub fn counts(&self) -> Vec<u32> {
+        self.0.counts.to_vec()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn matched(&self) -> u64 {
+        self.0.matched
+    }
+
+    /// Fraction of matching boards with a ship on each cell.
+    #[wasm_bindgen(getter)]
+    pub fn probabilities(&self) -> Vec<f64> {
+        self.0.probabilities().to_vec()
+    }
+}
+
+/// Returns the index (`y * 9 + x`) of the unshot cell with the highest count, or `undefined`
+/// if every cell is in `already_shot`.
+#[wasm_bindgen(js_name = recommendShot)]
+pub fn recommend_shot(counts: &[u32], already_shot: u128) -> Result<Option<usize>, JsError> {
+    let counts: &[u32; 81] = counts
+        .try_into()
+        .map_err(|_| JsError::new(&format!("expected 81 counts, got {}", counts.len())))?;
+
+    Ok(recommend::recommend_shot(counts, already_shot).map(|point| BoardGeometry::STANDARD.index_of(point)))
+}
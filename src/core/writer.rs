@@ -0,0 +1,58 @@
+use std::io::{self, Write};
+
+/// A writer that delta-XOR encodes u128 records into an underlying writer.
+///
+/// This produces the same stream format that [`crate::core::reader::DeltaDecodingReader`] decodes.
+pub struct DeltaEncodingWriter<W: Write> {
+    inner: W,
+    prev: u128,
+    records_written: u64,
+}
+
+impl<W: Write> DeltaEncodingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_base(inner, 0)
+    }
+
+    /// Creates a writer whose first record is encoded as a delta from `base`, to continue a
+    /// delta chain started by another writer.
+    pub fn with_base(inner: W, base: u128) -> Self {
+        Self { inner, prev: base, records_written: 0 }
+    }
+
+    /// Encodes a single record as the XOR delta from the previous one.
+    pub fn write_record(&mut self, board: u128) -> io::Result<()> {
+        let delta = board ^ self.prev;
+        self.inner.write_all(&delta.to_le_bytes())?;
+        self.prev = board;
+        self.records_written += 1;
+        Ok(())
+    }
+
+    /// Encodes every record from an iterator.
+    pub fn write_all_records<I: IntoIterator<Item = u128>>(&mut self, boards: I) -> io::Result<()> {
+        for board in boards {
+            self.write_record(board)?;
+        }
+        Ok(())
+    }
+
+    /// The most recently written record, or the base if nothing has been written yet.
+    pub fn last_record(&self) -> u128 {
+        self.prev
+    }
+
+    pub fn records_written(&self) -> u64 {
+        self.records_written
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// Flushes and returns the underlying writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
@@ -0,0 +1,106 @@
+use crate::generator::board_state::{BoardState, CellState};
+use crate::generator::point::{Direction, Point};
+
+/// A change made to a board through [`BoardHistory`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BoardEdit {
+    PlaceShip { length: i32, start: Point, direction: Direction },
+    Mark { point: Point, state: CellState },
+}
+
+/// A [`BoardState`] with a journal of the edits made to it, so they can be undone and redone.
+///
+/// `BoardState` itself stays `Copy` for the enumerator; interactive tools wrap it in this instead.
+/// Making a new edit after undoing discards the undone edits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoardHistory {
+    state: BoardState,
+    /// Applied edits, each with the state from before it.
+    done: Vec<(BoardEdit, BoardState)>,
+    /// Undone edits, most recently undone last.
+    undone: Vec<BoardEdit>,
+}
+
+impl BoardHistory {
+    pub fn new(state: BoardState) -> Self {
+        Self { state, done: Vec::new(), undone: Vec::new() }
+    }
+
+    pub fn state(&self) -> &BoardState {
+        &self.state
+    }
+
+    /// Edits that are currently applied, oldest first.
+    pub fn edits(&self) -> impl Iterator<Item = &BoardEdit> {
+        self.done.iter().map(|(edit, _)| edit)
+    }
+
+    /// Places a ship like [`BoardState::place_ship`], recording it if it fits.
+    pub fn place_ship(&mut self, length: i32, start: Point, direction: Direction) -> bool {
+        self.edit(BoardEdit::PlaceShip { length, start, direction })
+    }
+
+    /// Marks a cell like [`BoardState::set`].
+    pub fn set(&mut self, point: Point, state: CellState) {
+        self.edit(BoardEdit::Mark { point, state });
+    }
+
+    /// Applies and records an edit. Returns false, leaving the board and journal unchanged, if
+    /// it's a ship placement that doesn't fit.
+    pub fn edit(&mut self, edit: BoardEdit) -> bool {
+        if self.apply(edit) {
+            self.undone.clear();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// Reverts the most recent edit and returns it.
+    pub fn undo(&mut self) -> Option<BoardEdit> {
+        let (edit, previous) = self.done.pop()?;
+        self.state = previous;
+        self.undone.push(edit);
+        Some(edit)
+    }
+
+    /// Reapplies the most recently undone edit and returns it.
+    pub fn redo(&mut self) -> Option<BoardEdit> {
+        let edit = self.undone.pop()?;
+        let applied = self.apply(edit);
+        debug_assert!(applied, "An undone edit applies to the state it was undone from");
+        Some(edit)
+    }
+
+    fn apply(&mut self, edit: BoardEdit) -> bool {
+        let previous = self.state;
+
+        match edit {
+            BoardEdit::PlaceShip { length, start, direction } => {
+                if !self.state.place_ship(length, start, direction) {
+                    return false;
+                }
+            }
+            BoardEdit::Mark { point, state } => self.state.set(point, state),
+        }
+
+        self.done.push((edit, previous));
+        true
+    }
+}
+
+impl From<BoardState> for BoardHistory {
+    fn from(state: BoardState) -> Self {
+        Self::new(state)
+    }
+}
@@ -1,35 +1,119 @@
+use crate::core::geometry::BoardGeometry;
 use crate::generator::point::Point;
-use std::ops::{BitAnd, BitOr, Not, Shl, Shr};
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, Not, Shl, Shr};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BoardMask {
     raw_value: u128,
+    geometry: BoardGeometry,
 }
 
 impl BoardMask {
     pub const FULL: BoardMask = BoardMask {
         raw_value: (1u128 << 81) - 1,
+        geometry: BoardGeometry::STANDARD,
     };
 
     pub const EMPTY: BoardMask = BoardMask {
         raw_value: 0,
+        geometry: BoardGeometry::STANDARD,
     };
 
+    /// Creates a mask for the standard 9x9 board.
     pub fn new(raw_value: u128) -> Self {
-        Self { raw_value }
+        Self::with_geometry(raw_value, BoardGeometry::STANDARD)
+    }
+
+    pub fn with_geometry(raw_value: u128, geometry: BoardGeometry) -> Self {
+        Self { raw_value, geometry }
+    }
+
+    pub fn empty(geometry: BoardGeometry) -> Self {
+        Self::with_geometry(0, geometry)
+    }
+
+    pub fn full(geometry: BoardGeometry) -> Self {
+        Self::with_geometry(geometry.cell_mask(), geometry)
+    }
+
+    /// Parses a list of cells like "A3 B3 C3" (see [`Point::from_coordinate`]) into a mask for
+    /// the standard 9x9 board.
+    pub fn from_coordinates(coordinates: &str) -> Result<Self, String> {
+        Self::from_coordinates_with_geometry(coordinates, BoardGeometry::STANDARD)
+    }
+
+    /// Parses a list of cells separated by spaces and/or commas into a mask. Fails if a cell
+    /// can't be parsed or isn't on the board.
+    pub fn from_coordinates_with_geometry(coordinates: &str, geometry: BoardGeometry) -> Result<Self, String> {
+        let mut mask = Self::empty(geometry);
+
+        for coordinate in coordinates.split(|c: char| c == ',' || c.is_whitespace()).filter(|c| !c.is_empty()) {
+            let point = Point::from_coordinate(coordinate)?;
+            if !geometry.contains(point) {
+                return Err(format!("Cell '{}' is outside the {} board", coordinate, geometry));
+            }
+            mask.set(point, true);
+        }
+
+        Ok(mask)
+    }
+
+    /// Mask of row `y` on the standard 9x9 board.
+    pub fn row(y: i32) -> Self {
+        Self::row_with_geometry(y, BoardGeometry::STANDARD)
+    }
+
+    /// Mask of row `y`. Panics if the row isn't on the board.
+    pub fn row_with_geometry(y: i32, geometry: BoardGeometry) -> Self {
+        assert!((0..geometry.height() as i32).contains(&y), "Row {} is out of bounds for a {} board", y, geometry);
+        let width = geometry.width();
+        Self::with_geometry((u128::MAX >> (128 - width)) << (y as usize * width), geometry)
+    }
+
+    /// Mask of column `x` on the standard 9x9 board.
+    pub fn column(x: i32) -> Self {
+        Self::column_with_geometry(x, BoardGeometry::STANDARD)
+    }
+
+    /// Mask of column `x`. Panics if the column isn't on the board.
+    pub fn column_with_geometry(x: i32, geometry: BoardGeometry) -> Self {
+        assert!((0..geometry.width() as i32).contains(&x), "Column {} is out of bounds for a {} board", x, geometry);
+        Self::from_points_with_geometry((0..geometry.height() as i32).map(|y| Point::new(x, y)), geometry)
+    }
+
+    /// Mask of the rectangle with corners `a` and `b` (inclusive, in any order) on the standard
+    /// 9x9 board.
+    pub fn rect(a: Point, b: Point) -> Self {
+        Self::rect_with_geometry(a, b, BoardGeometry::STANDARD)
+    }
+
+    /// Mask of the rectangle with corners `a` and `b` (inclusive, in any order). Parts of the
+    /// rectangle outside the board are left out, so e.g. the 3x3 area around an edge cell can
+    /// be built without bounds checks.
+    pub fn rect_with_geometry(a: Point, b: Point, geometry: BoardGeometry) -> Self {
+        let (min_x, max_x) = (a.x.min(b.x).max(0), a.x.max(b.x).min(geometry.width() as i32 - 1));
+        let (min_y, max_y) = (a.y.min(b.y).max(0), a.y.max(b.y).min(geometry.height() as i32 - 1));
+
+        let points = (min_y..=max_y).flat_map(|y| (min_x..=max_x).map(move |x| Point::new(x, y)));
+        Self::from_points_with_geometry(points, geometry)
     }
 
     pub fn raw_value(&self) -> u128 {
         self.raw_value
     }
 
+    pub fn geometry(&self) -> BoardGeometry {
+        self.geometry
+    }
+
     pub fn get(&self, point: Point) -> bool {
-        let index = Self::index_of(point);
+        let index = self.geometry.index_of(point);
         (self.raw_value & (1u128 << index)) != 0
     }
 
     pub fn set(&mut self, point: Point, value: bool) {
-        let index = Self::index_of(point);
+        let index = self.geometry.index_of(point);
 
         if value {
             self.raw_value |= 1u128 << index;
@@ -39,29 +123,79 @@ impl BoardMask {
         }
     }
 
+    /// Number of set cells.
+    pub fn count(&self) -> u32 {
+        self.raw_value.count_ones()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.raw_value == 0
+    }
+
+    /// Whether any cell is set in both masks.
+    pub fn intersects(&self, other: BoardMask) -> bool {
+        debug_assert_eq!(self.geometry, other.geometry, "Masks must share a board geometry");
+        self.raw_value & other.raw_value != 0
+    }
+
+    /// Whether every cell set in `other` is also set in this mask.
+    pub fn contains_all(&self, other: BoardMask) -> bool {
+        debug_assert_eq!(self.geometry, other.geometry, "Masks must share a board geometry");
+        self.raw_value & other.raw_value == other.raw_value
+    }
+
     pub fn first_set_position(&self) -> Option<Point> {
         if self.raw_value == 0 {
             None
         }
         else {
-            Some(Self::point_of(self.raw_value.trailing_zeros() as usize))
+            Some(self.geometry.point_of(self.raw_value.trailing_zeros() as usize))
+        }
+    }
+
+    /// The set cells, in row-major order.
+    pub fn iter_points(&self) -> impl Iterator<Item = Point> {
+        let geometry = self.geometry;
+        let mut remaining = self.raw_value & geometry.cell_mask();
+
+        std::iter::from_fn(move || {
+            if remaining == 0 {
+                return None;
+            }
+
+            let index = remaining.trailing_zeros() as usize;
+            remaining &= remaining - 1;
+            Some(geometry.point_of(index))
+        })
+    }
+
+    /// Creates a mask for the standard 9x9 board with `points` set.
+    pub fn from_points<I: IntoIterator<Item = Point>>(points: I) -> Self {
+        Self::from_points_with_geometry(points, BoardGeometry::STANDARD)
+    }
+
+    /// Creates a mask with `points` set. Panics if a point is outside the board.
+    pub fn from_points_with_geometry<I: IntoIterator<Item = Point>>(points: I, geometry: BoardGeometry) -> Self {
+        let mut mask = Self::empty(geometry);
+        for point in points {
+            mask.set(point, true);
         }
+        mask
     }
 
+    /// Whether `point` is on the standard 9x9 board.
     pub fn contains(point: Point) -> bool {
-        (0..9).contains(&point.x) && (0..9).contains(&point.y)
+        BoardGeometry::STANDARD.contains(point)
     }
 
+    /// Bit index of `point` on the standard 9x9 board.
     pub fn index_of(point: Point) -> usize {
-        assert!(Self::contains(point), "Point {:?} is out of bounds", point);
-        (point.y * 9 + point.x) as usize
+        BoardGeometry::STANDARD.index_of(point)
     }
 
+    /// Point at bit `index` on the standard 9x9 board.
     pub fn point_of(index: usize) -> Point {
-        assert!(index < 81, "Index {} is out of bounds", index);
-        let x = (index % 9) as i32;
-        let y = (index / 9) as i32;
-        Point::new(x, y)
+        BoardGeometry::STANDARD.point_of(index)
     }
 }
 
@@ -69,7 +203,8 @@ impl BitAnd for BoardMask {
     type Output = BoardMask;
 
     fn bitand(self, rhs: BoardMask) -> Self::Output {
-        BoardMask::new(self.raw_value & rhs.raw_value)
+        debug_assert_eq!(self.geometry, rhs.geometry, "Masks must share a board geometry");
+        BoardMask::with_geometry(self.raw_value & rhs.raw_value, self.geometry)
     }
 }
 
@@ -77,7 +212,29 @@ impl BitOr for BoardMask {
     type Output = BoardMask;
 
     fn bitor(self, rhs: BoardMask) -> Self::Output {
-        BoardMask::new(self.raw_value | rhs.raw_value)
+        debug_assert_eq!(self.geometry, rhs.geometry, "Masks must share a board geometry");
+        BoardMask::with_geometry(self.raw_value | rhs.raw_value, self.geometry)
+    }
+}
+
+impl BitXor for BoardMask {
+    type Output = BoardMask;
+
+    fn bitxor(self, rhs: BoardMask) -> Self::Output {
+        debug_assert_eq!(self.geometry, rhs.geometry, "Masks must share a board geometry");
+        BoardMask::with_geometry(self.raw_value ^ rhs.raw_value, self.geometry)
+    }
+}
+
+impl BitAndAssign for BoardMask {
+    fn bitand_assign(&mut self, rhs: BoardMask) {
+        *self = *self & rhs;
+    }
+}
+
+impl BitOrAssign for BoardMask {
+    fn bitor_assign(&mut self, rhs: BoardMask) {
+        *self = *self | rhs;
     }
 }
 
@@ -85,7 +242,7 @@ impl Shl<usize> for BoardMask {
     type Output = BoardMask;
 
     fn shl(self, rhs: usize) -> Self::Output {
-        BoardMask::new(self.raw_value << rhs)
+        BoardMask::with_geometry(self.raw_value << rhs, self.geometry)
     }
 }
 
@@ -93,7 +250,7 @@ impl Shr<usize> for BoardMask {
     type Output = BoardMask;
 
     fn shr(self, rhs: usize) -> Self::Output {
-        BoardMask::new(self.raw_value >> rhs)
+        BoardMask::with_geometry(self.raw_value >> rhs, self.geometry)
     }
 }
 
@@ -101,6 +258,6 @@ impl Not for BoardMask {
     type Output = BoardMask;
 
     fn not(self) -> Self::Output {
-        BoardMask::new(!self.raw_value & BoardMask::FULL.raw_value)
+        BoardMask::with_geometry(!self.raw_value & self.geometry.cell_mask(), self.geometry)
     }
 }
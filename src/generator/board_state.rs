@@ -1,6 +1,8 @@
-use crate::generator::{board_mask::BoardMask, common_masks::CommonMasks, point::{Direction, Point}};
+use crate::core::geometry::BoardGeometry;
+use crate::generator::{board_mask::BoardMask, common_masks::CommonMasks, fleet::{Fleet, MAX_SHIP_LENGTH}, point::{Direction, Point}};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CellState {
     Open,
     Hit,
@@ -8,21 +10,44 @@ pub enum CellState {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BoardState {
     hit_mask: BoardMask,
     miss_mask: BoardMask,
-    three_count_remaining: usize,
-    four_count_remaining: usize,
+    /// Ships left to place, indexed by length.
+    remaining: [u8; MAX_SHIP_LENGTH + 1],
 }
 
 impl BoardState {
     pub const EMPTY: Self = Self {
         hit_mask: BoardMask::EMPTY,
         miss_mask: BoardMask::EMPTY,
-        three_count_remaining: 5,
-        four_count_remaining: 3,
+        remaining: [0, 0, 0, 5, 3, 0, 0, 0, 0],
     };
 
+    /// An empty board of the given size with the standard fleet (5 three-long and 3 four-long ships).
+    pub fn empty(geometry: BoardGeometry) -> Self {
+        Self::with_fleet(geometry, &Fleet::standard())
+    }
+
+    /// An empty board of the given size with every ship in `fleet` left to place.
+    pub fn with_fleet(geometry: BoardGeometry, fleet: &Fleet) -> Self {
+        let mut remaining = [0; MAX_SHIP_LENGTH + 1];
+        for &(length, count) in fleet.ships() {
+            remaining[length] = count as u8;
+        }
+
+        Self {
+            hit_mask: BoardMask::empty(geometry),
+            miss_mask: BoardMask::empty(geometry),
+            remaining,
+        }
+    }
+
+    pub fn geometry(&self) -> BoardGeometry {
+        self.hit_mask.geometry()
+    }
+
     pub fn hit_mask(&self) -> BoardMask {
         self.hit_mask
     }
@@ -32,15 +57,36 @@ impl BoardState {
     }
 
     pub fn open_mask(&self) -> BoardMask {
-        BoardMask::FULL & !self.hit_mask & !self.miss_mask
+        BoardMask::full(self.geometry()) & !self.hit_mask & !self.miss_mask
+    }
+
+    /// Number of ships of `length` left to place.
+    pub fn remaining(&self, length: usize) -> usize {
+        self.remaining.get(length).map_or(0, |&count| count as usize)
+    }
+
+    /// The ships left to place.
+    pub fn remaining_fleet(&self) -> Fleet {
+        Fleet::from_counts(&self.remaining.map(usize::from))
+    }
+
+    pub(crate) fn remaining_counts(&self) -> [u8; MAX_SHIP_LENGTH + 1] {
+        self.remaining
     }
 
-    pub fn three_count_remaining(&self) -> usize {
-        self.three_count_remaining
+    /// Lengths with ships left to place, shortest first.
+    pub fn remaining_lengths(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..=MAX_SHIP_LENGTH).filter(|&length| self.remaining[length] > 0)
     }
 
-    pub fn four_count_remaining(&self) -> usize {
-        self.four_count_remaining
+    /// Number of cells the ships left to place will cover.
+    pub fn cells_remaining(&self) -> usize {
+        self.remaining.iter().enumerate().map(|(length, &count)| length * count as usize).sum()
+    }
+
+    /// Whether every ship has been placed.
+    pub fn all_ships_placed(&self) -> bool {
+        self.remaining.iter().all(|&count| count == 0)
     }
 
     pub fn get(&self, point: Point) -> CellState {
@@ -75,34 +121,25 @@ impl BoardState {
     pub fn placing_ship(&self, length: i32, starting_point: Point, direction: Direction) -> Option<BoardState> {
         let mut copy = *self;
 
-        match length {
-            3 => {
-                if copy.three_count_remaining == 0 {
-                    return None;
-                }
-                else {
-                    copy.three_count_remaining -= 1;
-                }
-            }
-            4 => {
-                if copy.four_count_remaining == 0 {
-                    return None;
-                }
-                else {
-                    copy.four_count_remaining -= 1;
-                }
-            }
+        match usize::try_from(length).ok().and_then(|length| copy.remaining.get_mut(length)) {
+            Some(count) if *count > 0 => *count -= 1,
             _ => return None,
         }
 
-        let move_hit_mask = CommonMasks::mask_for_ship_hit(length, starting_point, direction);
-        let move_miss_mask = CommonMasks::mask_for_ship_outline(length, starting_point, direction);
+        let masks = CommonMasks::for_geometry(self.geometry());
+        let move_hit_mask = masks.ship_hit(length, starting_point, direction);
+        let move_miss_mask = masks.ship_outline(length, starting_point, direction);
+
+        // The masks are full when the ship doesn't fit on the board
+        if move_hit_mask == BoardMask::full(self.geometry()) {
+            return None;
+        }
 
-        if (self.hit_mask & move_hit_mask).raw_value() != 0 {
+        if self.hit_mask.intersects(move_hit_mask) {
             return None;
         }
 
-        if (self.hit_mask & move_miss_mask).raw_value() != 0 {
+        if self.hit_mask.intersects(move_miss_mask) {
             return None;
         }
 
@@ -122,25 +159,150 @@ impl BoardState {
         }
     }
 
+    /// Parses a board drawn the way [`render`](Self::render) draws one without colors, e.g. as
+    /// printed by [`debug_description`](Self::debug_description). The grid's size sets the
+    /// geometry, and every ship of the standard fleet is left to place.
+    ///
+    /// Framed rows hold a cell every other character between the `│` borders: `X` for a hit, `•`
+    /// for a miss and a space for an open cell. Unframed rows are compact, a character per cell,
+    /// with `·` or `.` for open cells. Frame lines, column labels and row labels are skipped.
+    pub fn from_ascii(s: &str) -> Result<Self, String> {
+        let mut rows: Vec<Vec<CellState>> = Vec::new();
+
+        for line in s.lines() {
+            let line = line.trim_end();
+            let trimmed = line.trim_start();
+            let is_frame = trimmed.starts_with('┌') || trimmed.starts_with('└');
+            let is_column_labels = trimmed.chars().all(|c| c.is_ascii_digit() || c == ' ');
+            if trimmed.is_empty() || is_frame || is_column_labels {
+                continue;
+            }
+
+            let cells: Vec<char> = match (line.find('│'), line.rfind('│')) {
+                (Some(start), Some(end)) if start < end => {
+                    line[start + '│'.len_utf8()..end].chars().skip(1).step_by(2).collect()
+                }
+                (Some(_), _) => return Err(format!("Row '{}' isn't closed by a '│'", line)),
+                // A compact row, after its label if it has one
+                _ => trimmed.rsplit(' ').next().unwrap_or_default().chars().collect(),
+            };
+
+            let row = cells.into_iter()
+                .map(|c| match c {
+                    'X' | 'x' => Ok(CellState::Hit),
+                    '•' => Ok(CellState::Miss),
+                    ' ' | '·' | '.' => Ok(CellState::Open),
+                    other => Err(format!("Unexpected '{}' in row '{}'", other, line)),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            rows.push(row);
+        }
+
+        let width = rows.first().map_or(0, Vec::len);
+        if width == 0 {
+            return Err("Board has no cells".to_string());
+        }
+        if let Some(row) = rows.iter().position(|row| row.len() != width) {
+            return Err(format!("Row {} has {} cells, but the first row has {}", row + 1, rows[row].len(), width));
+        }
+
+        let geometry: BoardGeometry = format!("{}x{}", width, rows.len()).parse()?;
+        let mut board = Self::empty(geometry);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &state) in row.iter().enumerate() {
+                board.set(Point::new(x as i32, y as i32), state);
+            }
+        }
+
+        Ok(board)
+    }
+
+    /// The board in the default wide style, framed, without labels or colors.
     pub fn debug_description(&self) -> String {
-        let mut result = String::from("┌───────────────────┐\n");
+        self.render(RenderOptions::default())
+    }
+
+    /// Draws the board as text: hits as `X`, misses as `•`, and open cells blank (or `·` when
+    /// compact).
+    pub fn render(&self, options: RenderOptions) -> String {
+        let geometry = self.geometry();
+        let cell_width = if options.compact { 1 } else { 2 };
+        let mut result = String::new();
 
-        for y in 0..9 {
-            result.push('│');
+        // Rows are labelled by letter and columns by number, as cells are written (e.g. "B5")
+        let row_label = |y: usize| if options.labels { format!("{} ", (b'A' + y as u8) as char) } else { String::new() };
+        let margin = if options.labels { "  " } else { "" };
 
-            for x in 0..9 {
-                let point = Point::new(x, y);
-                match self.get(point) {
-                    CellState::Hit => result.push_str(" X"),
-                    CellState::Miss => result.push_str(" •"),
-                    CellState::Open => result.push_str("  "),
+        if options.labels {
+            result.push_str(margin);
+            if !options.compact {
+                result.push(' ');
+            }
+            for x in 0..geometry.width() {
+                // Compact columns only have room for the last digit
+                let label = (x + 1).to_string();
+                result.push_str(&format!("{:>width$}", &label[label.len().saturating_sub(cell_width)..], width = cell_width));
+            }
+            result.push('\n');
+        }
+
+        let border = "─".repeat(geometry.width() * 2 + 1);
+        if !options.compact {
+            result.push_str(&format!("{}┌{}┐\n", margin, border));
+        }
+
+        for y in 0..geometry.height() {
+            result.push_str(&row_label(y));
+            if !options.compact {
+                result.push('│');
+            }
+
+            for x in 0..geometry.width() {
+                let (symbol, color) = match self.get(Point::new(x as i32, y as i32)) {
+                    CellState::Hit => ("X", HIT_COLOR),
+                    CellState::Miss => ("•", MISS_COLOR),
+                    CellState::Open if options.compact => ("·", OPEN_COLOR),
+                    CellState::Open => (" ", OPEN_COLOR),
+                };
+
+                if !options.compact {
+                    result.push(' ');
+                }
+                if options.color {
+                    result.push_str(&format!("{}{}{}", color, symbol, RESET_COLOR));
+                } else {
+                    result.push_str(symbol);
                 }
             }
 
-            result.push_str(" │\n");
+            if !options.compact {
+                result.push_str(" │");
+            }
+            result.push('\n');
         }
 
-        result.push_str("└───────────────────┘");
+        if !options.compact {
+            result.push_str(&format!("{}└{}┘", margin, border));
+        } else {
+            result.pop();
+        }
         result
     }
 }
+
+/// How [`BoardState::render`] draws a board. The default is the wide, framed style without
+/// labels or colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RenderOptions {
+    /// Color hits and misses with ANSI escapes.
+    pub color: bool,
+    /// Label rows with letters (A–I on a 9x9 board) and columns with numbers (1–9).
+    pub labels: bool,
+    /// One character per cell and no frame, instead of two characters per cell in a box.
+    pub compact: bool,
+}
+
+const HIT_COLOR: &str = "\x1b[1;31m";
+const MISS_COLOR: &str = "\x1b[34m";
+const OPEN_COLOR: &str = "\x1b[2m";
+const RESET_COLOR: &str = "\x1b[0m";
@@ -1,87 +1,97 @@
-use crate::generator::{board_mask::BoardMask, point::{Direction, Point}};
-use std::sync::Once;
+use crate::core::geometry::BoardGeometry;
+use crate::generator::{board_mask::BoardMask, fleet::{MAX_SHIP_LENGTH, MIN_SHIP_LENGTH}, point::{Direction, Point}};
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
 
+/// Hit and outline masks for every ship position on a board, looked up by the search for each
+/// placement.
 pub struct CommonMasks {
-    horizontal_three_long_hit_masks: Vec<BoardMask>,
-    horizontal_three_long_miss_masks: Vec<BoardMask>,
-    horizontal_four_long_hit_masks: Vec<BoardMask>,
-    horizontal_four_long_miss_masks: Vec<BoardMask>,
-    vertical_three_long_hit_masks: Vec<BoardMask>,
-    vertical_three_long_miss_masks: Vec<BoardMask>,
-    vertical_four_long_hit_masks: Vec<BoardMask>,
-    vertical_four_long_miss_masks: Vec<BoardMask>,
+    geometry: BoardGeometry,
+    /// Masks for each length, direction and starting cell, see [`CommonMasks::slot`].
+    hit_masks: Vec<BoardMask>,
+    miss_masks: Vec<BoardMask>,
 }
 
-static mut MASKS: Option<CommonMasks> = None;
-static INIT: Once = Once::new();
+static STANDARD_MASKS: OnceLock<CommonMasks> = OnceLock::new();
+static OTHER_MASKS: OnceLock<RwLock<HashMap<BoardGeometry, &'static CommonMasks>>> = OnceLock::new();
 
 impl CommonMasks {
-    fn instance() -> &'static CommonMasks {
-        unsafe {
-            INIT.call_once(|| {
-                MASKS = Some(CommonMasks::new());
-            });
-            MASKS.as_ref().unwrap()
+    /// Returns the shared masks for a board geometry, building them on first use.
+    pub fn for_geometry(geometry: BoardGeometry) -> &'static CommonMasks {
+        if geometry == BoardGeometry::STANDARD {
+            return STANDARD_MASKS.get_or_init(|| CommonMasks::new(geometry));
         }
+
+        let cache = OTHER_MASKS.get_or_init(|| RwLock::new(HashMap::new()));
+
+        if let Some(masks) = cache.read().unwrap().get(&geometry) {
+            return masks;
+        }
+
+        // Masks live for the rest of the process, so leaking them gives a 'static reference
+        cache.write().unwrap()
+            .entry(geometry)
+            .or_insert_with(|| Box::leak(Box::new(CommonMasks::new(geometry))))
     }
 
-    fn new() -> Self {
+    fn new(geometry: BoardGeometry) -> Self {
+        let cells = geometry.cells();
+        let slots = (MAX_SHIP_LENGTH + 1) * 2 * cells;
         let mut masks = CommonMasks {
-            horizontal_three_long_hit_masks: Vec::with_capacity(81),
-            horizontal_three_long_miss_masks: Vec::with_capacity(81),
-            horizontal_four_long_hit_masks: Vec::with_capacity(81),
-            horizontal_four_long_miss_masks: Vec::with_capacity(81),
-            vertical_three_long_hit_masks: Vec::with_capacity(81),
-            vertical_three_long_miss_masks: Vec::with_capacity(81),
-            vertical_four_long_hit_masks: Vec::with_capacity(81),
-            vertical_four_long_miss_masks: Vec::with_capacity(81),
+            geometry,
+            hit_masks: vec![BoardMask::full(geometry); slots],
+            miss_masks: vec![BoardMask::full(geometry); slots],
         };
 
-        for i in 0..81 {
-            let point = BoardMask::point_of(i);
+        for length in MIN_SHIP_LENGTH..=MAX_SHIP_LENGTH {
+            for direction in [Direction::Horizontal, Direction::Vertical] {
+                for i in 0..cells {
+                    let point = geometry.point_of(i);
+                    let slot = masks.slot(length as i32, point, direction);
 
-            masks.horizontal_three_long_hit_masks.push(Self::generate_mask_for_ship_hit(3, point, Direction::Horizontal));
-            masks.horizontal_three_long_miss_masks.push(Self::generate_mask_for_ship_outline(3, point, Direction::Horizontal));
-            masks.horizontal_four_long_hit_masks.push(Self::generate_mask_for_ship_hit(4, point, Direction::Horizontal));
-            masks.horizontal_four_long_miss_masks.push(Self::generate_mask_for_ship_outline(4, point, Direction::Horizontal));
-
-            masks.vertical_three_long_hit_masks.push(Self::generate_mask_for_ship_hit(3, point, Direction::Vertical));
-            masks.vertical_three_long_miss_masks.push(Self::generate_mask_for_ship_outline(3, point, Direction::Vertical));
-            masks.vertical_four_long_hit_masks.push(Self::generate_mask_for_ship_hit(4, point, Direction::Vertical));
-            masks.vertical_four_long_miss_masks.push(Self::generate_mask_for_ship_outline(4, point, Direction::Vertical));
+                    masks.hit_masks[slot] = Self::generate_mask_for_ship_hit(geometry, length as i32, point, direction);
+                    masks.miss_masks[slot] = Self::generate_mask_for_ship_outline(geometry, length as i32, point, direction);
+                }
+            }
         }
 
         masks
     }
 
+    /// Index of the masks for a ship. Panics if the length isn't a supported ship length.
+    fn slot(&self, length: i32, starting_point: Point, direction: Direction) -> usize {
+        let length = usize::try_from(length).ok()
+            .filter(|length| (MIN_SHIP_LENGTH..=MAX_SHIP_LENGTH).contains(length))
+            .expect("Invalid ship length");
+        let direction = match direction {
+            Direction::Horizontal => 0,
+            Direction::Vertical => 1,
+        };
+        (length * 2 + direction) * self.geometry.cells() + self.geometry.index_of(starting_point)
+    }
+
+    /// Hit mask for a ship on the standard 9x9 board.
     pub fn mask_for_ship_hit(length: i32, starting_point: Point, direction: Direction) -> BoardMask {
-        let masks = Self::instance();
-        let index = BoardMask::index_of(starting_point);
-
-        match (direction, length) {
-            (Direction::Horizontal, 3) => masks.horizontal_three_long_hit_masks[index],
-            (Direction::Horizontal, 4) => masks.horizontal_four_long_hit_masks[index],
-            (Direction::Vertical, 3) => masks.vertical_three_long_hit_masks[index],
-            (Direction::Vertical, 4) => masks.vertical_four_long_hit_masks[index],
-            _ => panic!("Invalid ship length or direction"),
-        }
+        Self::for_geometry(BoardGeometry::STANDARD).ship_hit(length, starting_point, direction)
     }
 
+    /// Outline mask for a ship on the standard 9x9 board.
     pub fn mask_for_ship_outline(length: i32, starting_point: Point, direction: Direction) -> BoardMask {
-        let masks = Self::instance();
-        let index = BoardMask::index_of(starting_point);
-
-        match (direction, length) {
-            (Direction::Horizontal, 3) => masks.horizontal_three_long_miss_masks[index],
-            (Direction::Horizontal, 4) => masks.horizontal_four_long_miss_masks[index],
-            (Direction::Vertical, 3) => masks.vertical_three_long_miss_masks[index],
-            (Direction::Vertical, 4) => masks.vertical_four_long_miss_masks[index],
-            _ => panic!("Invalid ship length or direction"),
-        }
+        Self::for_geometry(BoardGeometry::STANDARD).ship_outline(length, starting_point, direction)
     }
 
-    fn generate_mask_for_ship_hit(length: i32, starting_point: Point, direction: Direction) -> BoardMask {
-        let mut mask = BoardMask::EMPTY;
+    /// Cells occupied by a ship, or a full mask if the ship doesn't fit on the board.
+    pub fn ship_hit(&self, length: i32, starting_point: Point, direction: Direction) -> BoardMask {
+        self.hit_masks[self.slot(length, starting_point, direction)]
+    }
+
+    /// Cells surrounding a ship, or a full mask if the ship doesn't fit on the board.
+    pub fn ship_outline(&self, length: i32, starting_point: Point, direction: Direction) -> BoardMask {
+        self.miss_masks[self.slot(length, starting_point, direction)]
+    }
+
+    fn generate_mask_for_ship_hit(geometry: BoardGeometry, length: i32, starting_point: Point, direction: Direction) -> BoardMask {
+        let mut mask = BoardMask::empty(geometry);
 
         let start = starting_point;
         let end = starting_point + direction * (length - 1);
@@ -90,11 +100,11 @@ impl CommonMasks {
             for y in start.y..=end.y {
                 let point = Point::new(x, y);
 
-                if BoardMask::contains(point) {
+                if geometry.contains(point) {
                     mask.set(point, true);
                 }
                 else {
-                    return BoardMask::FULL; // If any point is out of bounds, return FULL mask
+                    return BoardMask::full(geometry); // If any point is out of bounds, return FULL mask
                 }
             }
         }
@@ -102,29 +112,16 @@ impl CommonMasks {
         mask
     }
 
-    fn generate_mask_for_ship_outline(length: i32, starting_point: Point, direction: Direction) -> BoardMask {
+    fn generate_mask_for_ship_outline(geometry: BoardGeometry, length: i32, starting_point: Point, direction: Direction) -> BoardMask {
         let start = starting_point - Point::new(1, 1);
         let end = starting_point + direction * (length - 1) + Point::new(1, 1);
 
-        let hit_mask = Self::generate_mask_for_ship_hit(length, starting_point, direction);
+        let hit_mask = Self::generate_mask_for_ship_hit(geometry, length, starting_point, direction);
 
-        if hit_mask.raw_value() == BoardMask::FULL.raw_value() {
-            return BoardMask::FULL; // If the hit mask is FULL, return FULL mask
+        if hit_mask == BoardMask::full(geometry) {
+            return BoardMask::full(geometry); // If the hit mask is FULL, return FULL mask
         }
 
-        let mut mask = BoardMask::EMPTY;
-
-        for x in start.x..=end.x {
-            for y in start.y..=end.y {
-                let point = Point::new(x, y);
-
-                if BoardMask::contains(point) {
-                    mask.set(point, true);
-                }
-            }
-        }
-
-        mask = mask & !hit_mask;
-        mask
+        BoardMask::rect_with_geometry(start, end, geometry) & !hit_mask
     }
 }
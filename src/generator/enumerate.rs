@@ -0,0 +1,243 @@
+use crate::core::geometry::BoardGeometry;
+use crate::generator::board_state::{BoardState, CellState};
+use crate::generator::fleet::{Fleet, MAX_SHIP_LENGTH};
+use crate::generator::point::Direction;
+use crate::generator::symmetries::{canonicalize_with_geometry, misses_for_earliest_variant, Bitboard};
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+/// Calls `visit` for every valid board on the standard 9x9 board with the given fleet, in a
+/// deterministic order, and returns the number of boards visited.
+///
+/// Boards are produced in memory; nothing is written to disk.
+pub fn enumerate_boards<F>(fleet: &Fleet, visit: F) -> usize
+where
+    F: FnMut(&BoardState),
+{
+    enumerate_boards_with_geometry(BoardGeometry::STANDARD, fleet, visit)
+}
+
+/// Like [`enumerate_boards`], for a board of any geometry.
+pub fn enumerate_boards_with_geometry<F>(geometry: BoardGeometry, fleet: &Fleet, mut visit: F) -> usize
+where
+    F: FnMut(&BoardState),
+{
+    count_of_valid_endings(&BoardState::with_fleet(geometry, fleet), &mut visit)
+}
+
+/// Number of subtrees to split the search into per worker thread. Subtrees vary wildly in
+/// size, so having many more subtrees than threads keeps every thread busy.
+pub const SUBTREES_PER_THREAD: usize = 16;
+
+/// Runs `search` on each subtree in parallel and passes the results to `consume` in the same
+/// order a sequential search would produce them.
+///
+/// Subtrees are processed in batches of a few per thread so only a bounded number of results
+/// is held in memory while waiting for earlier subtrees to finish. Stops at the first error
+/// returned by `consume`.
+pub fn for_each_subtree_in_order<T, E, S, C>(subtrees: &[BoardState], search: S, mut consume: C) -> Result<(), E>
+where
+    T: Send,
+    S: Fn(&BoardState) -> T + Sync,
+    C: FnMut(T) -> Result<(), E>,
+{
+    for batch in subtrees.chunks(rayon::current_num_threads() * 2) {
+        let results: Vec<T> = batch.par_iter().map(&search).collect();
+        for result in results {
+            consume(result)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Expands the first levels of the search tree until there are at least `target` subtrees
+/// (or nothing left to expand), preserving the order in which they'd be searched.
+pub fn split_search(start: &BoardState, target: usize) -> Vec<BoardState> {
+    let mut frontier = vec![*start];
+
+    while frontier.len() < target {
+        let mut next = Vec::with_capacity(frontier.len() * 5);
+        let mut expanded = false;
+
+        for state in frontier {
+            match next_states(&state) {
+                Some(children) => {
+                    next.extend(children);
+                    expanded = true;
+                }
+                None => next.push(state),
+            }
+        }
+
+        frontier = next;
+
+        if !expanded {
+            break;
+        }
+    }
+
+    frontier
+}
+
+/// Counts the complete boards reachable from search states without visiting each one, by
+/// remembering the count below every state it has seen.
+///
+/// Once the search reaches a point, everything before it is decided and only matters through
+/// the ships right above and to the left of it, so states that agree from there on (and have
+/// the same ships left) have the same count. There are few enough such states that counting
+/// every standard board takes well under a second, and a counter reused across subtrees of the
+/// same search shares its work between them.
+#[derive(Debug, Default)]
+pub struct EndingCounter {
+    counts: HashMap<EndingKey, u64>,
+}
+
+/// Everything about a state that its count depends on: the hits from the cell diagonally
+/// above and left of the first open point onward, the misses from the point onward, the point
+/// itself and the ships left.
+type EndingKey = (u128, u128, usize, [u8; MAX_SHIP_LENGTH + 1]);
+
+impl EndingCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of complete boards reachable from `state`, the same as
+    /// [`count_of_valid_endings`] returns.
+    pub fn count(&mut self, state: &BoardState) -> u64 {
+        let Some(point) = state.open_mask().first_set_position() else {
+            return state.all_ships_placed() as u64;
+        };
+
+        let geometry = state.geometry();
+        let index = geometry.index_of(point);
+        let key = (
+            state.hit_mask().raw_value() >> index.saturating_sub(geometry.width() + 1),
+            state.miss_mask().raw_value() >> index,
+            index,
+            state.remaining_counts(),
+        );
+        if let Some(&count) = self.counts.get(&key) {
+            return count;
+        }
+
+        // Every state has children here since it has an open point
+        let count = next_states(state).unwrap_or_default().iter().map(|child| self.count(child)).sum();
+        self.counts.insert(key, count);
+        count
+    }
+}
+
+/// The ships that can be tried at the first open point of `state`, in search order: each length
+/// left to place, shortest first, horizontal before vertical.
+pub(crate) fn placements(state: &BoardState) -> impl Iterator<Item = (i32, Direction)> + '_ {
+    state.remaining_lengths()
+        .flat_map(|length| [(length as i32, Direction::Horizontal), (length as i32, Direction::Vertical)])
+}
+
+/// The states reachable by deciding the first open point, in search order, or `None` if the
+/// board has no open points left.
+fn next_states(state: &BoardState) -> Option<Vec<BoardState>> {
+    let point = state.open_mask().first_set_position()?;
+
+    let mut states: Vec<BoardState> = placements(state)
+        .filter_map(|(length, direction)| state.placing_ship(length, point, direction))
+        .collect();
+
+    let mut unplaced_state = *state;
+    unplaced_state.set(point, CellState::Miss);
+    states.push(unplaced_state);
+
+    Some(states)
+}
+
+/// Visits every complete board reachable from `state` and returns how many there are.
+pub fn count_of_valid_endings<F>(state: &BoardState, save_board: &mut F) -> usize
+where
+    F: FnMut(&BoardState),
+{
+    if let Some(point) = state.open_mask().first_set_position() {
+        let mut valid = 0;
+
+        for (length, direction) in placements(state) {
+            if let Some(placed_state) = state.placing_ship(length, point, direction) {
+                valid += count_of_valid_endings(&placed_state, save_board);
+            }
+        }
+
+        // Try marking the point as a miss
+        let mut unplaced_state = *state;
+        unplaced_state.set(point, CellState::Miss);
+        valid += count_of_valid_endings(&unplaced_state, save_board);
+
+        valid
+    }
+    else {
+        // No more open positions
+        if state.all_ships_placed() {
+            save_board(state);
+            1
+        }
+        else {
+            0
+        }
+    }
+}
+
+/// Visits every canonical board (see [`is_canonical_with_geometry`]) reachable from `state` and
+/// returns how many there are.
+///
+/// Visits the same boards as filtering [`count_of_valid_endings`] with
+/// [`is_canonical_with_geometry`], in a different order, without searching most of the rest.
+/// Instead of canonical boards, the search looks for the variant of each board with its ships
+/// earliest in search order (see [`misses_for_earliest_variant`]), which it can recognize
+/// early on, and skips every subtree that can't contain one. `save_board` receives the
+/// canonical form of each one found.
+///
+/// [`is_canonical_with_geometry`]: crate::generator::symmetries::is_canonical_with_geometry
+pub fn count_of_canonical_endings<F>(state: &BoardState, save_board: &mut F) -> usize
+where
+    F: FnMut(Bitboard),
+{
+    let geometry = state.geometry();
+    let Some(forced) = misses_for_earliest_variant(state.hit_mask().raw_value(), state.miss_mask().raw_value(), geometry) else {
+        return 0;
+    };
+
+    let mut state = *state;
+    let mut remaining = forced;
+    while remaining != 0 {
+        state.set(geometry.point_of(remaining.trailing_zeros() as usize), CellState::Miss);
+        remaining &= remaining - 1;
+    }
+
+    if let Some(point) = state.open_mask().first_set_position() {
+        let mut valid = 0;
+
+        for (length, direction) in placements(&state) {
+            // Ships can't cover the cells that were just ruled out
+            let placed_state = state.placing_ship(length, point, direction)
+                .filter(|placed| !placed.hit_mask().intersects(state.miss_mask()));
+            if let Some(placed_state) = placed_state {
+                valid += count_of_canonical_endings(&placed_state, save_board);
+            }
+        }
+
+        let mut unplaced_state = state;
+        unplaced_state.set(point, CellState::Miss);
+        valid += count_of_canonical_endings(&unplaced_state, save_board);
+
+        valid
+    }
+    else {
+        // No more open positions, so the check above was exact
+        if state.all_ships_placed() {
+            save_board(canonicalize_with_geometry(state.hit_mask().raw_value(), geometry));
+            1
+        }
+        else {
+            0
+        }
+    }
+}
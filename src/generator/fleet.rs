@@ -0,0 +1,142 @@
+use crate::core::geometry::BoardGeometry;
+use std::fmt;
+use std::str::FromStr;
+
+/// Shortest ship a fleet can have. A one-cell ship would be placed once per direction.
+pub const MIN_SHIP_LENGTH: usize = 2;
+
+/// Longest ship a fleet can have.
+pub const MAX_SHIP_LENGTH: usize = 8;
+
+/// Most ships of one length a fleet can have, so counts fit in a byte of the dataset header.
+pub const MAX_SHIPS_PER_LENGTH: usize = u8::MAX as usize;
+
+/// The ships placed on every board, as `(length, count)` pairs.
+///
+/// Pairs are kept sorted by length with at most one pair per length and no zero counts, so two
+/// fleets with the same ships compare equal however they were written.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fleet {
+    ships: Vec<(usize, usize)>,
+}
+
+impl Fleet {
+    /// Builds a fleet from `(length, count)` pairs in any order. Pairs for the same length add
+    /// up.
+    ///
+    /// Panics if a length is outside [`MIN_SHIP_LENGTH`]..=[`MAX_SHIP_LENGTH`] or there are
+    /// more than [`MAX_SHIPS_PER_LENGTH`] ships of one length; use [`str::parse`] to check
+    /// untrusted input instead.
+    pub fn new(ships: &[(usize, usize)]) -> Self {
+        let mut counts = [0usize; MAX_SHIP_LENGTH + 1];
+        for &(length, count) in ships {
+            assert!((MIN_SHIP_LENGTH..=MAX_SHIP_LENGTH).contains(&length), "Unsupported ship length {}", length);
+            counts[length] += count;
+        }
+        Self::from_counts(&counts)
+    }
+
+    /// The fleet used by the bundled dataset: five three-long ships and three four-long ships.
+    pub fn standard() -> Self {
+        Self { ships: vec![(3, 5), (4, 3)] }
+    }
+
+    /// Builds a fleet from the number of ships of each length, indexed by length.
+    pub(crate) fn from_counts(counts: &[usize]) -> Self {
+        let ships = counts.iter().enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(length, &count)| {
+                assert!(count <= MAX_SHIPS_PER_LENGTH, "Too many ships of length {}", length);
+                (length, count)
+            })
+            .collect();
+        Self { ships }
+    }
+
+    /// The `(length, count)` pairs, shortest ships first.
+    pub fn ships(&self) -> &[(usize, usize)] {
+        &self.ships
+    }
+
+    /// Number of ships of `length`.
+    pub fn count(&self, length: usize) -> usize {
+        self.ships.iter().find(|&&(ship, _)| ship == length).map_or(0, |&(_, count)| count)
+    }
+
+    /// Number of ships in the fleet.
+    pub fn ship_count(&self) -> usize {
+        self.ships.iter().map(|&(_, count)| count).sum()
+    }
+
+    /// Number of cells covered by the whole fleet.
+    pub fn cells(&self) -> usize {
+        self.ships.iter().map(|&(length, count)| length * count).sum()
+    }
+
+    /// Checks that every ship fits on a board of `geometry` and that the fleet doesn't cover
+    /// more cells than the board has. A fleet that passes may still have no valid boards.
+    pub fn check_fits(&self, geometry: BoardGeometry) -> Result<(), String> {
+        let longest = geometry.width().max(geometry.height());
+        if let Some(&(length, _)) = self.ships.iter().find(|&&(length, _)| length > longest) {
+            return Err(format!("A {}-long ship doesn't fit on a {} board", length, geometry));
+        }
+
+        if self.cells() > geometry.cells() {
+            return Err(format!("Fleet {} covers {} cells, more than a {} board has", self, self.cells(), geometry));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Fleet {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl fmt::Display for Fleet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (length, count)) in self.ships.iter().enumerate() {
+            write!(f, "{}{}:{}", if i > 0 { "," } else { "" }, length, count)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Fleet {
+    type Err = String;
+
+    /// Parses comma-separated `LENGTH:COUNT` pairs, e.g. `3:5,4:3`. Lengths that aren't
+    /// listed have no ships.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut counts = [0usize; MAX_SHIP_LENGTH + 1];
+
+        for entry in s.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let (length, count) = entry.split_once(':')
+                .ok_or_else(|| format!("Invalid fleet entry '{}', expected LENGTH:COUNT", entry))?;
+            let length: usize = length.trim().parse()
+                .map_err(|_| format!("Invalid ship length in '{}'", entry))?;
+            let count: usize = count.trim().parse()
+                .map_err(|_| format!("Invalid ship count in '{}'", entry))?;
+
+            if !(MIN_SHIP_LENGTH..=MAX_SHIP_LENGTH).contains(&length) {
+                return Err(format!(
+                    "Unsupported ship length {}; lengths must be {} to {}",
+                    length, MIN_SHIP_LENGTH, MAX_SHIP_LENGTH,
+                ));
+            }
+
+            counts[length] = counts[length].checked_add(count).filter(|&total| total <= MAX_SHIPS_PER_LENGTH)
+                .ok_or_else(|| format!("Too many {}-long ships; at most {} are supported", length, MAX_SHIPS_PER_LENGTH))?;
+        }
+
+        let fleet = Fleet::from_counts(&counts);
+        if fleet.ships.is_empty() {
+            return Err(format!("Fleet '{}' has no ships", s));
+        }
+
+        Ok(fleet)
+    }
+}
@@ -1,5 +1,12 @@
+pub mod board_history;
 pub mod board_mask;
 pub mod board_state;
 pub mod common_masks;
+pub mod enumerate;
+pub mod fleet;
 pub mod point;
 pub mod symmetries;
+pub mod verify;
+
+pub use enumerate::enumerate_boards;
+pub use fleet::Fleet;
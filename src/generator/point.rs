@@ -1,12 +1,16 @@
+use std::fmt;
 use std::ops::{Add, Mul, Sub};
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point {
     pub x: i32,
     pub y: i32,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Direction {
     Horizontal,
     Vertical,
@@ -16,6 +20,42 @@ impl Point {
     pub fn new(x: i32, y: i32) -> Self {
         Self { x, y }
     }
+
+    /// Parses a cell like "B5": a row letter (A is the top row) followed by a 1-based column
+    /// number. Letters are case-insensitive. Doesn't check that the cell is on any board.
+    pub fn from_coordinate(coordinate: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid cell '{}', expected a row letter and column number like B5", coordinate);
+
+        let mut chars = coordinate.trim().chars();
+        let row = chars.next().filter(char::is_ascii_alphabetic).ok_or_else(invalid)?.to_ascii_uppercase();
+        let column: i32 = chars.as_str().parse().map_err(|_| invalid())?;
+
+        if column < 1 {
+            return Err(invalid());
+        }
+
+        Ok(Point::new(column - 1, row as i32 - 'A' as i32))
+    }
+}
+
+impl FromStr for Point {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Point::from_coordinate(s)
+    }
+}
+
+impl fmt::Display for Point {
+    /// Formats as a coordinate like "B5" (see [`Point::from_coordinate`]), or as "(x, y)" for
+    /// points that don't have one.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if (0..26).contains(&self.y) && self.x >= 0 {
+            write!(f, "{}{}", (b'A' + self.y as u8) as char, self.x + 1)
+        } else {
+            write!(f, "({}, {})", self.x, self.y)
+        }
+    }
 }
 
 impl Add for Point {
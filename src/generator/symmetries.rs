@@ -1,53 +1,239 @@
-pub type Bitboard = u128;
+use crate::core::geometry::BoardGeometry;
+use std::sync::{OnceLock, RwLock};
 
-const BOARD_SIZE: usize = 9;
+pub type Bitboard = u128;
 
-fn index(x: usize, y: usize) -> usize {
-    y * BOARD_SIZE + x
+/// A rotation or reflection of the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sym {
+    Identity,
+    FlipHorizontal,
+    FlipVertical,
+    Rotate180,
+    /// Mirror along the main diagonal.
+    Transpose,
+    Rotate90,
+    Rotate270,
+    /// Mirror along the anti-diagonal.
+    AntiTranspose,
 }
 
-fn get_bit(board: Bitboard, x: usize, y: usize) -> bool {
-    (board >> index(x, y)) & 1 == 1
+impl Sym {
+    /// Every symmetry of a square board. Only the first four map rectangular boards onto
+    /// themselves.
+    pub const ALL: [Sym; 8] = [
+        Sym::Identity,
+        Sym::FlipHorizontal,
+        Sym::FlipVertical,
+        Sym::Rotate180,
+        Sym::Transpose,
+        Sym::Rotate90,
+        Sym::Rotate270,
+        Sym::AntiTranspose,
+    ];
+
+    /// The symmetries of boards of `geometry`: all 8 for square boards, or 4 for rectangular
+    /// boards, which can't be rotated by 90° or transposed onto themselves.
+    pub fn of(geometry: BoardGeometry) -> &'static [Sym] {
+        if geometry.is_square() { &Self::ALL } else { &Self::ALL[..4] }
+    }
+
+    /// The symmetry that undoes this one.
+    pub fn inverse(self) -> Sym {
+        match self {
+            Sym::Rotate90 => Sym::Rotate270,
+            Sym::Rotate270 => Sym::Rotate90,
+            other => other,
+        }
+    }
+
+    /// Where this symmetry sends cell `(x, y)` of a `w` by `h` board.
+    fn map(self, x: usize, y: usize, w: usize, h: usize) -> (usize, usize) {
+        match self {
+            Sym::Identity => (x, y),
+            Sym::FlipHorizontal => (w - 1 - x, y),
+            Sym::FlipVertical => (x, h - 1 - y),
+            Sym::Rotate180 => (w - 1 - x, h - 1 - y),
+            Sym::Transpose => (y, x),
+            Sym::Rotate90 => (w - 1 - y, x),
+            Sym::Rotate270 => (y, w - 1 - x),
+            Sym::AntiTranspose => (w - 1 - y, w - 1 - x),
+        }
+    }
 }
 
-fn set_bit(board: &mut Bitboard, x: usize, y: usize) {
-    *board |= 1u128 << index(x, y);
+/// Cell permutations of the symmetries of one geometry: `cells[sym as usize][i]` is the cell
+/// that `sym` moves cell `i` to.
+struct Tables {
+    geometry: BoardGeometry,
+    cells: [[u8; 128]; 8],
 }
 
-fn transform<F>(original: Bitboard, transform_fn: F) -> Bitboard
-where
-    F: Fn(usize, usize) -> (usize, usize),
-{
-    let mut result: Bitboard = 0;
-    for y in 0..BOARD_SIZE {
-        for x in 0..BOARD_SIZE {
-            if get_bit(original, x, y) {
-                let (nx, ny) = transform_fn(x, y);
-                set_bit(&mut result, nx, ny);
+impl Tables {
+    fn new(geometry: BoardGeometry) -> Self {
+        let (w, h) = (geometry.width(), geometry.height());
+        let mut cells = [[0u8; 128]; 8];
+
+        for &sym in Sym::of(geometry) {
+            for (index, target) in cells[sym as usize].iter_mut().take(geometry.cells()).enumerate() {
+                let (x, y) = sym.map(index % w, index / w, w, h);
+                *target = (y * w + x) as u8;
             }
         }
+
+        Self { geometry, cells }
+    }
+}
+
+/// The tables of `geometry`, built the first time they're needed.
+fn tables(geometry: BoardGeometry) -> &'static Tables {
+    static STANDARD: OnceLock<Tables> = OnceLock::new();
+    static OTHERS: RwLock<Vec<&'static Tables>> = RwLock::new(Vec::new());
+
+    if geometry == BoardGeometry::STANDARD {
+        return STANDARD.get_or_init(|| Tables::new(geometry));
+    }
+
+    let find = |others: &[&'static Tables]| others.iter().copied().find(|tables| tables.geometry == geometry);
+    if let Some(tables) = find(&OTHERS.read().unwrap()) {
+        return tables;
+    }
+
+    // Only a handful of geometries are ever used, so their tables live for the whole run
+    let mut others = OTHERS.write().unwrap();
+    if let Some(tables) = find(&others) {
+        return tables;
+    }
+    let tables: &'static Tables = Box::leak(Box::new(Tables::new(geometry)));
+    others.push(tables);
+    tables
+}
+
+/// Applies a symmetry to a 9x9 board.
+pub fn apply_symmetry(board: Bitboard, sym: Sym) -> Bitboard {
+    apply_symmetry_with_geometry(board, sym, BoardGeometry::STANDARD)
+}
+
+/// Applies a symmetry to a board of any geometry, moving each ship cell through a precomputed
+/// permutation table.
+///
+/// Panics if `sym` isn't one of [`Sym::of`] `geometry`.
+pub fn apply_symmetry_with_geometry(board: Bitboard, sym: Sym, geometry: BoardGeometry) -> Bitboard {
+    assert!(Sym::of(geometry).contains(&sym), "{:?} doesn't map a {} board onto itself", sym, geometry);
+
+    let permutation = &tables(geometry).cells[sym as usize];
+    let mut result: Bitboard = 0;
+    let mut remaining = board & geometry.cell_mask();
+    while remaining != 0 {
+        result |= 1u128 << permutation[remaining.trailing_zeros() as usize];
+        remaining &= remaining - 1;
     }
     result
 }
 
+/// Returns every symmetric variant of a 9x9 board (including the board itself).
 pub fn generate_symmetries(board: Bitboard) -> Vec<Bitboard> {
-    vec![
-        board,
-        transform(board, |x, y| (BOARD_SIZE - 1 - x, y)),              // horizontal flip
-        transform(board, |x, y| (x, BOARD_SIZE - 1 - y)),              // vertical flip
-        transform(board, |x, y| (BOARD_SIZE - 1 - x, BOARD_SIZE - 1 - y)), // rotate 180°
-        transform(board, |x, y| (y, x)),                              // transpose (main diag)
-        transform(board, |x, y| (BOARD_SIZE - 1 - y, x)),              // rotate 90°
-        transform(board, |x, y| (y, BOARD_SIZE - 1 - x)),              // rotate 270°
-        transform(board, |x, y| (BOARD_SIZE - 1 - y, BOARD_SIZE - 1 - x)), // anti-diagonal mirror
-    ]
+    generate_symmetries_with_geometry(board, BoardGeometry::STANDARD)
+}
+
+/// Returns every symmetric variant of a board, one per [`Sym::of`] `geometry` in that order:
+/// 8 for square boards, or 4 for rectangular boards.
+pub fn generate_symmetries_with_geometry(board: Bitboard, geometry: BoardGeometry) -> Vec<Bitboard> {
+    Sym::of(geometry).iter().map(|&sym| apply_symmetry_with_geometry(board, sym, geometry)).collect()
+}
+
+/// Returns the distinct symmetric variants of a board in ascending order, without allocating.
+pub fn distinct_symmetries_with_geometry(board: Bitboard, geometry: BoardGeometry) -> impl Iterator<Item = Bitboard> {
+    // Slots past the geometry's symmetries keep the board itself, which dedup drops
+    let mut variants = [board; 8];
+    for (variant, &sym) in variants.iter_mut().zip(Sym::of(geometry)) {
+        *variant = apply_symmetry_with_geometry(board, sym, geometry);
+    }
+    variants.sort_unstable();
+
+    let mut previous = None;
+    variants.into_iter().filter(move |&variant| previous.replace(variant) != Some(variant))
 }
 
 pub fn canonicalize(board: Bitboard) -> Bitboard {
-    generate_symmetries(board).into_iter().min().unwrap()
+    canonicalize_with_geometry(board, BoardGeometry::STANDARD)
+}
+
+pub fn canonicalize_with_geometry(board: Bitboard, geometry: BoardGeometry) -> Bitboard {
+    Sym::of(geometry).iter().map(|&sym| apply_symmetry_with_geometry(board, sym, geometry)).min().unwrap()
 }
 
 pub fn is_canonical(board: Bitboard) -> bool {
-    let symmetries = generate_symmetries(board);
-    board == *symmetries.iter().min().unwrap()
+    is_canonical_with_geometry(board, BoardGeometry::STANDARD)
+}
+
+/// Whether `board` is the smallest of its symmetric variants, as [`canonicalize_with_geometry`]
+/// would return.
+///
+/// Runs once per board during generation, so rather than building every variant it compares
+/// each one against `board` a cell at a time, from the most significant, and stops at the first
+/// cell where they differ. Most variants differ within a few cells, and nothing is allocated.
+pub fn is_canonical_with_geometry(board: Bitboard, geometry: BoardGeometry) -> bool {
+    let tables = tables(geometry);
+    Sym::of(geometry)[1..].iter().all(|&sym| !is_smaller_variant(board, geometry, &tables.cells[sym.inverse() as usize]))
+}
+
+/// Whether the variant of `board` whose inverse permutation is `inverse` is smaller than `board`.
+fn is_smaller_variant(board: Bitboard, geometry: BoardGeometry, inverse: &[u8; 128]) -> bool {
+    for index in (0..geometry.cells()).rev() {
+        // The variant's cell `index` holds whatever `board` has where the symmetry takes it from
+        let variant_bit = (board >> inverse[index]) & 1 == 1;
+        let board_bit = (board >> index) & 1 == 1;
+
+        if variant_bit != board_bit {
+            return board_bit;
+        }
+    }
+
+    false
+}
+
+/// Checks whether a partly decided board, with ships on `hits`, none on `misses` and any other
+/// cells still open, can still turn out to be the variant the generator searches for in place
+/// of a canonical board: the one with its ships earliest, i.e. the largest when compared from
+/// the *least* significant cell. That's the order the generator decides cells in, so most
+/// variants are settled, one way or the other, within the first few decided cells.
+///
+/// Returns `None` if the board can't be that variant, or the open cells that must stay empty for
+/// it to be one. Exact once every cell is decided.
+pub(crate) fn misses_for_earliest_variant(hits: Bitboard, misses: Bitboard, geometry: BoardGeometry) -> Option<Bitboard> {
+    let tables = tables(geometry);
+    let decided = hits | misses;
+    let mut forced = 0;
+
+    for &sym in &Sym::of(geometry)[1..] {
+        let inverse = &tables.cells[sym.inverse() as usize];
+
+        for (index, &source) in inverse.iter().enumerate().take(geometry.cells()) {
+            // Cells that must stay empty are as good as decided
+            let known = decided | forced;
+            if (known >> index) & 1 == 0 {
+                break;
+            }
+
+            let board_bit = (hits >> index) & 1 == 1;
+            let source_decided = (known >> source) & 1 == 1;
+            let variant_bit = (hits >> source) & 1 == 1;
+
+            if board_bit {
+                // The board is ahead unless the variant has a ship here too
+                if !source_decided || !variant_bit {
+                    break;
+                }
+            } else if variant_bit {
+                // The variant's ships come first
+                return None;
+            } else if !source_decided {
+                // It'd come first if it had a ship here, so it can't
+                forced |= 1 << source;
+            }
+        }
+    }
+
+    Some(forced)
 }
@@ -0,0 +1,54 @@
+use crate::analysis::decompose_ships_with_geometry;
+use crate::core::geometry::BoardGeometry;
+use crate::generator::fleet::{Fleet, MAX_SHIP_LENGTH, MIN_SHIP_LENGTH};
+use std::fmt;
+
+/// Why a record isn't a valid board.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidBoard {
+    /// Bits are set beyond the last cell of the board.
+    OutsideBoard,
+    /// The cell at this index isn't part of a straight ship of a supported length that is clear of
+    /// every other ship, e.g. because ships touch or bend. It's the first cell of the group of
+    /// touching cells it belongs to.
+    StrayCell(usize),
+    /// The ships are well formed, but they aren't the expected fleet.
+    WrongFleet(Fleet),
+}
+
+impl fmt::Display for InvalidBoard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidBoard::OutsideBoard => write!(f, "cells set outside the board"),
+            InvalidBoard::StrayCell(index) => write!(f, "cell {} isn't part of a separate straight ship", index),
+            InvalidBoard::WrongFleet(found) => write!(f, "found fleet {}", found),
+        }
+    }
+}
+
+/// Checks that `board` consists of exactly the ships in `fleet`, each straight and not touching
+/// any other ship, even diagonally. This is the rule the generator places ships by.
+///
+/// The board is split into groups of touching cells with [`decompose_ships_with_geometry`], so
+/// every group must be a single straight ship.
+pub fn verify_board(board: u128, geometry: BoardGeometry, fleet: &Fleet) -> Result<(), InvalidBoard> {
+    if board & !geometry.cell_mask() != 0 {
+        return Err(InvalidBoard::OutsideBoard);
+    }
+
+    // Ships of any length are recognized, so boards with the wrong ships report what they have
+    let mut found = [0usize; MAX_SHIP_LENGTH + 1];
+    for ship in decompose_ships_with_geometry(board, geometry) {
+        if ship.direction.is_none() || !(MIN_SHIP_LENGTH..=MAX_SHIP_LENGTH).contains(&ship.length) {
+            return Err(InvalidBoard::StrayCell(geometry.index_of(ship.start)));
+        }
+        found[ship.length] += 1;
+    }
+
+    let found = Fleet::from_counts(&found);
+    if found != *fleet {
+        return Err(InvalidBoard::WrongFleet(found));
+    }
+
+    Ok(())
+}
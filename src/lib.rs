@@ -1,2 +1,4 @@
+pub mod analysis;
 pub mod core;
-pub mod generator;
\ No newline at end of file
+pub mod generator;
+pub mod solver;
@@ -1,43 +1,142 @@
-use clap::Parser;
-use battleship::core::filter::filter_and_count;
+mod commands;
+
+use battleship::core::version::{version_json, CRATE_VERSION};
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser, Subcommand};
+use commands::config::Config;
+use std::process::ExitCode;
+use commands::{canonicalize, completions, daemon, decode, duplicates, encode, estimate, exit, filter, generate, inspect, merge, merge_partials, play, rollup, shuffle, sort, split, symmetries, verify};
 
 #[derive(Parser)]
-#[command(name = "battleship-filter")]
-#[command(about = "Filter and count ship hit frequencies from a board data file (supports zstd compression)", long_about = None)]
+#[command(name = "battleship")]
+#[command(about = "Generate, encode and query datasets of every valid Battleship board", long_about = None)]
+#[command(version, disable_version_flag = true, arg_required_else_help = true)]
 struct Cli {
-    /// Path to the board data file (raw 16-byte masks, optionally zstd compressed). Use "-" to read from stdin.
-    #[arg(short, long)]
-    file: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Print version information
+    #[arg(short = 'V', long, action = ArgAction::SetTrue)]
+    version: bool,
 
-    /// Hit mask as hex (e.g., 0xabcdef...)
-    #[arg(long)]
-    hit: String,
+    /// With --version, print build information as JSON
+    #[arg(long, requires = "version")]
+    json: bool,
+}
 
-    /// Miss mask as hex
-    #[arg(short, long)]
-    miss: String,
+#[derive(Subcommand)]
+enum Command {
+    /// Filter and count ship hit frequencies from a board data file (supports zstd compression)
+    Filter(Box<filter::FilterArgs>),
+    /// Enumerate every valid board and write them as raw 16-byte records
+    Generate(generate::GenerateArgs),
+    /// Delta-encode raw 16-byte board records from stdin to stdout
+    Encode(encode::EncodeArgs),
+    /// Convert a delta-encoded dataset back to raw 16-byte records
+    Decode(decode::DecodeArgs),
+    /// Check that every record in a dataset is a valid placement of the fleet
+    Verify(verify::VerifyArgs),
+    /// Interactively record shot results and get updated heatmaps and recommendations
+    Play(play::PlayArgs),
+    /// Randomly permute raw 16-byte board records (run before delta encoding)
+    Shuffle(shuffle::ShuffleArgs),
+    /// Sort board records numerically so their deltas compress better (run before delta encoding)
+    Sort(sort::SortArgs),
+    /// Partition a dataset into independently delta-encoded shards
+    Split(split::SplitArgs),
+    /// Combine datasets into one delta-encoded dataset
+    Merge(merge::MergeArgs),
+    /// Add up the partial counts that `filter --emit-partial` wrote for shards of a dataset
+    MergePartials(merge_partials::MergePartialsArgs),
+    /// Report duplicate boards in a dataset, exact and up to symmetry
+    Duplicates(duplicates::DuplicatesArgs),
+    /// Reduce a dataset to one canonical board per symmetry class, removing duplicates
+    Canonicalize(canonicalize::CanonicalizeArgs),
+    /// Report how many boards of a dataset fall into each symmetry orbit size
+    Symmetries(symmetries::SymmetriesArgs),
+    /// Estimate ship probabilities without a dataset, from random boards or by enumerating matches
+    Estimate(estimate::EstimateArgs),
+    /// Describe a dataset: its compression, encoding, record count and popcounts
+    Inspect(inspect::InspectArgs),
+    /// Precompute the heatmap of every one-shot query so `filter --rollup` can skip the scan
+    Rollup(rollup::RollupArgs),
+    /// Keep a dataset in memory and answer queries over a unix socket or TCP with a compact binary protocol
+    Daemon(daemon::DaemonArgs),
+    /// Print shell completions for bash, zsh, fish, elvish or PowerShell
+    Completions(completions::CompletionsArgs),
+    /// Write the boards matching a query to a Parquet file for analysis in other tools
+    #[cfg(feature = "parquet")]
+    Export(commands::export::ExportArgs),
+    /// Import datasets into a SQLite database, export them back, and query them
+    #[cfg(feature = "sqlite")]
+    Sqlite(commands::sqlite::SqliteArgs),
+    /// Serve heatmaps and recommendations for a dataset as JSON over HTTP
+    #[cfg(feature = "server")]
+    Serve(commands::serve::ServeArgs),
+    /// Serve heatmaps and matching boards for a dataset over gRPC
+    #[cfg(feature = "grpc")]
+    ServeGrpc(commands::serve_grpc::ServeGrpcArgs),
 }
 
-fn main() -> std::io::Result<()> {
-    let cli = Cli::parse();
-    let hit_mask = u128::from_str_radix(cli.hit.trim_start_matches("0x"), 16)
-        .expect("Invalid hit mask hex");
-    let miss_mask = u128::from_str_radix(cli.miss.trim_start_matches("0x"), 16)
-        .expect("Invalid miss mask hex");
-    
-    let reader = battleship::core::reader::create_reader(&cli.file)
-        .expect("Failed to create file reader");
-
-    let (counts, matched) = filter_and_count(reader, hit_mask, miss_mask)?;
-
-    eprintln!("Matched boards: {}", matched);
-    // Print 9x9 grid of counts
-    for y in 0..9 {
-        for x in 0..9 {
-            let idx = y * 9 + x;
-            print!("{}{}", counts[idx], if x < 8 { "," } else { "" });
+fn main() -> ExitCode {
+    let args: Vec<_> = std::env::args_os().collect();
+    let config = match Config::load_for_args(&args) {
+        Ok(config) => config,
+        Err(error) => return fail(&error),
+    };
+
+    let matches = config.apply(Cli::command()).get_matches_from(args);
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    let Some(command) = cli.command else {
+        // arg_required_else_help means the only way to get here is --version
+        if cli.json {
+            println!("{}", version_json());
+        } else {
+            println!("battleship {}", CRATE_VERSION);
         }
-        println!();
+        return ExitCode::SUCCESS;
+    };
+
+    match run(command, &config) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => fail(&error),
+    }
+}
+
+fn fail(error: &std::io::Error) -> ExitCode {
+    eprintln!("Error: {}", error);
+    exit::for_error(error)
+}
+
+fn run(command: Command, config: &Config) -> std::io::Result<()> {
+    match command {
+        Command::Filter(args) => filter::run(*args),
+        Command::Generate(args) => generate::run(args),
+        Command::Encode(args) => encode::run(args),
+        Command::Decode(args) => decode::run(args),
+        Command::Verify(args) => verify::run(args),
+        Command::Play(args) => play::run(args),
+        Command::Shuffle(args) => shuffle::run(args),
+        Command::Sort(args) => sort::run(args),
+        Command::Split(args) => split::run(args),
+        Command::Merge(args) => merge::run(args),
+        Command::MergePartials(args) => merge_partials::run(args),
+        Command::Duplicates(args) => duplicates::run(args),
+        Command::Symmetries(args) => symmetries::run(args),
+        Command::Canonicalize(args) => canonicalize::run(args),
+        Command::Inspect(args) => inspect::run(args),
+        Command::Estimate(args) => estimate::run(args),
+        Command::Rollup(args) => rollup::run(args),
+        Command::Daemon(args) => daemon::run(args),
+        // Completions describe the whole interface, with the defaults the config file gives it
+        Command::Completions(args) => completions::run(args, config.apply(Cli::command())),
+        #[cfg(feature = "parquet")]
+        Command::Export(args) => commands::export::run(args),
+        #[cfg(feature = "sqlite")]
+        Command::Sqlite(args) => commands::sqlite::run(args),
+        #[cfg(feature = "server")]
+        Command::Serve(args) => commands::serve::run(args),
+        #[cfg(feature = "grpc")]
+        Command::ServeGrpc(args) => commands::serve_grpc::run(args),
     }
-    Ok(())
 }
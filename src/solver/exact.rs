@@ -0,0 +1,125 @@
+use crate::core::geometry::BoardGeometry;
+use crate::generator::board_mask::BoardMask;
+use crate::generator::board_state::{BoardState, CellState};
+use crate::generator::enumerate::{placements, SUBTREES_PER_THREAD};
+use crate::generator::fleet::Fleet;
+use crate::generator::point::Point;
+use rayon::prelude::*;
+
+/// Exact ship counts over the boards of `fleet` on the standard board with a ship on every
+/// `hit_mask` cell and none on any `miss_mask` cell, computed without a dataset.
+///
+/// Returns one count per cell in row-major order and the number of matching boards. Boards are
+/// enumerated with the generator's search, skipping every branch that contradicts a shot, so the
+/// work shrinks quickly as shots are recorded. With few shots this is as slow as generating the
+/// whole dataset; [`estimate_probabilities`](crate::solver::monte_carlo::estimate_probabilities)
+/// is the cheap alternative.
+pub fn exact_counts(hit_mask: u128, miss_mask: u128, fleet: &Fleet) -> (Vec<u64>, u64) {
+    exact_counts_with_geometry(BoardGeometry::STANDARD, hit_mask, miss_mask, fleet)
+}
+
+/// Like [`exact_counts`], for a board of any geometry.
+pub fn exact_counts_with_geometry(geometry: BoardGeometry, hit_mask: u128, miss_mask: u128, fleet: &Fleet) -> (Vec<u64>, u64) {
+    let search = ConstrainedSearch { hit_mask, miss_mask: miss_mask & geometry.cell_mask() };
+
+    let mut start = BoardState::with_fleet(geometry, fleet);
+    for point in BoardMask::with_geometry(search.miss_mask, geometry).iter_points() {
+        start.set(point, CellState::Miss);
+    }
+
+    let subtrees = search.split(start, rayon::current_num_threads() * SUBTREES_PER_THREAD);
+
+    subtrees.par_iter()
+        .map(|subtree| {
+            let mut counts = vec![0u64; geometry.cells()];
+            let mut matched = 0u64;
+            search.visit(subtree, &mut |board| {
+                matched += 1;
+                for point in BoardMask::with_geometry(board, geometry).iter_points() {
+                    counts[geometry.index_of(point)] += 1;
+                }
+            });
+            (counts, matched)
+        })
+        .reduce(|| (vec![0u64; geometry.cells()], 0), |(mut counts, matched), (other, other_matched)| {
+            for (count, other) in counts.iter_mut().zip(other) {
+                *count += other;
+            }
+            (counts, matched + other_matched)
+        })
+}
+
+/// The generator's search, restricted to boards consistent with a set of shots.
+struct ConstrainedSearch {
+    hit_mask: u128,
+    miss_mask: u128,
+}
+
+impl ConstrainedSearch {
+    /// Whether the ships left to place could still cover every hit that isn't covered yet.
+    fn may_complete(&self, state: &BoardState) -> bool {
+        let uncovered = self.hit_mask & !state.hit_mask().raw_value();
+        let remaining = state.cells_remaining();
+        uncovered.count_ones() as usize <= remaining && remaining <= state.open_mask().count() as usize
+    }
+
+    /// The consistent states reachable by deciding `point`, in the generator's search order.
+    fn children(&self, state: &BoardState, point: Point) -> Vec<BoardState> {
+        let geometry = state.geometry();
+        let mut children: Vec<BoardState> = placements(state)
+            .filter_map(|(length, direction)| state.placing_ship(length, point, direction))
+            .filter(|placed| placed.hit_mask().raw_value() & self.miss_mask == 0)
+            .collect();
+
+        // A hit cell must hold a ship, so it can't be left empty
+        if self.hit_mask & (1u128 << geometry.index_of(point)) == 0 {
+            let mut unplaced = *state;
+            unplaced.set(point, CellState::Miss);
+            children.push(unplaced);
+        }
+
+        children.retain(|child| self.may_complete(child));
+        children
+    }
+
+    /// Passes every complete consistent board reachable from `state` to `visit`.
+    fn visit(&self, state: &BoardState, visit: &mut impl FnMut(u128)) {
+        let Some(point) = state.open_mask().first_set_position() else {
+            if state.all_ships_placed() {
+                visit(state.hit_mask().raw_value());
+            }
+            return;
+        };
+
+        for child in self.children(state, point) {
+            self.visit(&child, visit);
+        }
+    }
+
+    /// Expands the top of the search until there are at least `target` subtrees.
+    fn split(&self, start: BoardState, target: usize) -> Vec<BoardState> {
+        let mut frontier = if self.may_complete(&start) { vec![start] } else { Vec::new() };
+
+        while !frontier.is_empty() && frontier.len() < target {
+            let mut expanded = false;
+            let mut next = Vec::with_capacity(frontier.len() * 5);
+
+            for state in frontier {
+                match state.open_mask().first_set_position() {
+                    Some(point) => {
+                        next.extend(self.children(&state, point));
+                        expanded = true;
+                    }
+                    None => next.push(state),
+                }
+            }
+
+            frontier = next;
+            if !expanded {
+                break;
+            }
+        }
+
+        frontier
+    }
+}
@@ -0,0 +1,225 @@
+use crate::generator::board_mask::BoardMask;
+use crate::generator::point::{Direction, Point};
+use std::fmt;
+
+/// The outcome of firing at a cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ShotResult {
+    Miss,
+    Hit,
+    /// A hit that sank a ship. The ship is the straight run of hits through this cell.
+    Sunk,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Shot {
+    pub point: Point,
+    pub result: ShotResult,
+}
+
+/// A ship known to be sunk, inferred from the hits around a `Sunk` shot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SunkShip {
+    pub start: Point,
+    pub length: i32,
+    pub direction: Direction,
+    /// Cells occupied by the ship.
+    pub cells: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameError {
+    OutOfBounds(Point),
+    AlreadyShot(Point),
+    /// A sunk ship's hits extend both horizontally and vertically.
+    AmbiguousSunkShip(Point),
+    /// The shot contradicts earlier results (e.g., a hit next to a sunk ship).
+    Contradiction(Point),
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::OutOfBounds(p) => write!(f, "cell ({}, {}) is outside the board", p.x, p.y),
+            GameError::AlreadyShot(p) => write!(f, "cell ({}, {}) has already been shot", p.x, p.y),
+            GameError::AmbiguousSunkShip(p) => write!(f, "hits around ({}, {}) don't form a straight ship", p.x, p.y),
+            GameError::Contradiction(p) => write!(f, "shot at ({}, {}) contradicts earlier results", p.x, p.y),
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
+/// Tracks the shots fired in a game and derives the hit/miss masks used to query datasets.
+///
+/// Cells around a sunk ship can't contain another ship, so they are included in the miss mask
+/// even though they were never fired at.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "RecordedShots"))]
+pub struct GameState {
+    shots: Vec<Shot>,
+}
+
+/// Deserialized shots, replayed through [`GameState::record_shot`] so an inconsistent game is
+/// rejected.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RecordedShots {
+    shots: Vec<Shot>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RecordedShots> for GameState {
+    type Error = GameError;
+
+    fn try_from(recorded: RecordedShots) -> Result<Self, Self::Error> {
+        let mut state = GameState::new();
+        for shot in recorded.shots {
+            state.record_shot(shot.point, shot.result)?;
+        }
+        Ok(state)
+    }
+}
+
+impl GameState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shots in the order they were recorded.
+    pub fn shots(&self) -> &[Shot] {
+        &self.shots
+    }
+
+    pub fn is_shot(&self, point: Point) -> bool {
+        self.shots.iter().any(|shot| shot.point == point)
+    }
+
+    /// Mask of every cell that was fired at.
+    pub fn shot_mask(&self) -> u128 {
+        self.shots.iter().fold(0, |mask, shot| mask | bit(shot.point))
+    }
+
+    /// Records a shot, rejecting ones that are invalid or inconsistent with earlier results.
+    pub fn record_shot(&mut self, point: Point, result: ShotResult) -> Result<(), GameError> {
+        if !BoardMask::contains(point) {
+            return Err(GameError::OutOfBounds(point));
+        }
+
+        if self.is_shot(point) {
+            return Err(GameError::AlreadyShot(point));
+        }
+
+        self.shots.push(Shot { point, result });
+
+        if let Err(error) = self.validate() {
+            self.shots.pop();
+            return Err(error);
+        }
+
+        Ok(())
+    }
+
+    /// Removes and returns the most recent shot.
+    pub fn undo(&mut self) -> Option<Shot> {
+        self.shots.pop()
+    }
+
+    /// Ships that have been sunk, in the order they were sunk.
+    pub fn sunk_ships(&self) -> Vec<SunkShip> {
+        self.derive().map(|(_, _, ships)| ships).unwrap_or_default()
+    }
+
+    /// Returns `(hit_mask, miss_mask)` including misses inferred from sunk ships.
+    pub fn to_masks(&self) -> (u128, u128) {
+        self.derive().map(|(hits, misses, _)| (hits, misses)).unwrap_or_default()
+    }
+
+    fn validate(&self) -> Result<(), GameError> {
+        self.derive().map(|_| ())
+    }
+
+    fn derive(&self) -> Result<(u128, u128, Vec<SunkShip>), GameError> {
+        let mut hits = 0u128;
+        let mut misses = 0u128;
+
+        for shot in &self.shots {
+            match shot.result {
+                ShotResult::Miss => misses |= bit(shot.point),
+                ShotResult::Hit | ShotResult::Sunk => hits |= bit(shot.point),
+            }
+        }
+
+        let mut ships = Vec::new();
+        for shot in self.shots.iter().filter(|shot| shot.result == ShotResult::Sunk) {
+            let ship = sunk_ship_at(hits, shot.point)?;
+            misses |= outline(ship.cells);
+            ships.push(ship);
+        }
+
+        if hits & misses != 0 {
+            let last = self.shots.last().map(|shot| shot.point).unwrap_or(Point::new(0, 0));
+            return Err(GameError::Contradiction(last));
+        }
+
+        Ok((hits, misses, ships))
+    }
+}
+
+fn bit(point: Point) -> u128 {
+    1u128 << BoardMask::index_of(point)
+}
+
+fn is_hit(hits: u128, point: Point) -> bool {
+    BoardMask::contains(point) && hits & bit(point) != 0
+}
+
+/// Finds the straight run of hits passing through `point`.
+fn sunk_ship_at(hits: u128, point: Point) -> Result<SunkShip, GameError> {
+    let horizontal = is_hit(hits, point - Direction::Horizontal) || is_hit(hits, point + Direction::Horizontal);
+    let vertical = is_hit(hits, point - Direction::Vertical) || is_hit(hits, point + Direction::Vertical);
+
+    if horizontal && vertical {
+        return Err(GameError::AmbiguousSunkShip(point));
+    }
+
+    let direction = if vertical { Direction::Vertical } else { Direction::Horizontal };
+
+    let mut start = point;
+    while is_hit(hits, start - direction) {
+        start = start - direction;
+    }
+
+    let mut cells = 0u128;
+    let mut length = 0;
+    let mut current = start;
+    while is_hit(hits, current) {
+        cells |= bit(current);
+        length += 1;
+        current = current + direction;
+    }
+
+    Ok(SunkShip { start, length, direction, cells })
+}
+
+/// Cells adjacent (including diagonally) to `cells`, excluding `cells` themselves.
+fn outline(cells: u128) -> u128 {
+    let mut result = 0u128;
+
+    for point in BoardMask::new(cells).iter_points() {
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let neighbor = point + Point::new(dx, dy);
+                if BoardMask::contains(neighbor) {
+                    result |= bit(neighbor);
+                }
+            }
+        }
+    }
+
+    result & !cells
+}
@@ -0,0 +1,9 @@
+pub mod exact;
+pub mod game_state;
+pub mod monte_carlo;
+pub mod probabilities;
+pub mod recommend;
+pub mod strategy;
+
+pub use probabilities::cell_probabilities;
+pub use strategy::Strategy;
@@ -0,0 +1,170 @@
+use crate::core::geometry::BoardGeometry;
+use crate::core::random::SplitMix64;
+use crate::generator::board_state::BoardState;
+use crate::generator::fleet::Fleet;
+use crate::generator::point::Direction;
+use rayon::prelude::*;
+
+/// Ship probabilities estimated from random boards by [`estimate_probabilities`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonteCarloEstimate {
+    /// Estimated probability of a ship on each cell, in row-major order.
+    pub probabilities: Vec<f64>,
+    /// Number of random boards drawn.
+    pub samples: u64,
+    /// Number of drawn boards consistent with the shots. The estimate is all zeros when there
+    /// are none.
+    pub boards: u64,
+}
+
+/// Samples are drawn in blocks of this many, each with its own generator, so the estimate
+/// doesn't depend on how the blocks are spread over threads.
+const SAMPLES_PER_BLOCK: u64 = 4096;
+
+/// Estimates the probability of a ship on each cell of the standard board, over the boards of
+/// `fleet` with a ship on every `hit_mask` cell and none on any `miss_mask` cell, without a
+/// dataset.
+///
+/// Each sample places the ships one at a time, longest first, picking uniformly among the
+/// placements that fit next to the ships already placed and avoid the misses. Boards that leave
+/// a hit uncovered are rejected. Crowded boards have fewer placements to pick from at each step
+/// and so come up more often than they should; weighting each board by the product of its
+/// placement counts corrects for that, so the estimate converges on the exact probabilities.
+/// The same seed always gives the same estimate.
+pub fn estimate_probabilities(hit_mask: u128, miss_mask: u128, fleet: &Fleet, samples: u64, seed: u64) -> MonteCarloEstimate {
+    estimate_probabilities_with_geometry(BoardGeometry::STANDARD, hit_mask, miss_mask, fleet, samples, seed)
+}
+
+/// Like [`estimate_probabilities`], for a board of any geometry.
+pub fn estimate_probabilities_with_geometry(
+    geometry: BoardGeometry,
+    hit_mask: u128,
+    miss_mask: u128,
+    fleet: &Fleet,
+    samples: u64,
+    seed: u64,
+) -> MonteCarloEstimate {
+    let sampler = Sampler::new(geometry, fleet, miss_mask);
+    let blocks = samples.div_ceil(SAMPLES_PER_BLOCK);
+
+    let tallies: Vec<Tally> = (0..blocks)
+        .into_par_iter()
+        .map(|block| {
+            let count = SAMPLES_PER_BLOCK.min(samples - block * SAMPLES_PER_BLOCK);
+            // Mixed, since seeds a multiple of the generator's increment apart give overlapping streams
+            let mut rng = SplitMix64::new(SplitMix64::new(seed.wrapping_add(block)).next_u64());
+            let mut tally = Tally::new(geometry);
+
+            for _ in 0..count {
+                if let Some((board, weight)) = sampler.sample(&mut rng) {
+                    if board & hit_mask == hit_mask {
+                        tally.add(board, weight);
+                    }
+                }
+            }
+
+            tally
+        })
+        .collect();
+
+    // Summed in block order so floating point rounding doesn't depend on the thread count
+    let mut total = Tally::new(geometry);
+    for tally in tallies {
+        total.merge(tally);
+    }
+
+    let probabilities = total.weighted_counts.iter()
+        .map(|&count| if total.weight > 0.0 { count / total.weight } else { 0.0 })
+        .collect();
+
+    MonteCarloEstimate { probabilities, samples, boards: total.boards }
+}
+
+/// Weighted ship counts of the consistent boards drawn so far.
+struct Tally {
+    weighted_counts: Vec<f64>,
+    weight: f64,
+    boards: u64,
+}
+
+impl Tally {
+    fn new(geometry: BoardGeometry) -> Self {
+        Self { weighted_counts: vec![0.0; geometry.cells()], weight: 0.0, boards: 0 }
+    }
+
+    fn add(&mut self, board: u128, weight: f64) {
+        self.boards += 1;
+        self.weight += weight;
+        for (cell, count) in self.weighted_counts.iter_mut().enumerate() {
+            if board & (1u128 << cell) != 0 {
+                *count += weight;
+            }
+        }
+    }
+
+    fn merge(&mut self, other: Tally) {
+        self.boards += other.boards;
+        self.weight += other.weight;
+        for (count, other) in self.weighted_counts.iter_mut().zip(other.weighted_counts) {
+            *count += other;
+        }
+    }
+}
+
+/// Draws boards by placing ships one at a time.
+struct Sampler {
+    start: BoardState,
+    /// Ship lengths in the order they're placed.
+    ships: Vec<i32>,
+    /// Every ship position on the board as `(length, start cell, direction)`, misses excluded.
+    placements: Vec<(i32, usize, Direction)>,
+}
+
+impl Sampler {
+    fn new(geometry: BoardGeometry, fleet: &Fleet, miss_mask: u128) -> Self {
+        let start = BoardState::with_fleet(geometry, fleet);
+        let ships = fleet.ships().iter().rev()
+            .flat_map(|&(length, count)| std::iter::repeat_n(length as i32, count))
+            .collect();
+
+        let mut placements = Vec::new();
+        for length in fleet.ships().iter().map(|&(length, _)| length as i32) {
+            for cell in 0..geometry.cells() {
+                for direction in [Direction::Horizontal, Direction::Vertical] {
+                    let fits = start.placing_ship(length, geometry.point_of(cell), direction)
+                        .is_some_and(|placed| placed.hit_mask().raw_value() & miss_mask == 0);
+                    if fits {
+                        placements.push((length, cell, direction));
+                    }
+                }
+            }
+        }
+
+        Self { start, ships, placements }
+    }
+
+    /// Places every ship, returning the board and its weight, or `None` if a ship has nowhere
+    /// left to go.
+    fn sample(&self, rng: &mut SplitMix64) -> Option<(u128, f64)> {
+        let geometry = self.start.geometry();
+        let mut state = self.start;
+        let mut weight = 1.0;
+        let mut options = Vec::with_capacity(self.placements.len());
+
+        for &length in &self.ships {
+            options.clear();
+            options.extend(self.placements.iter()
+                .filter(|&&(ship, _, _)| ship == length)
+                .filter_map(|&(_, cell, direction)| state.placing_ship(length, geometry.point_of(cell), direction)));
+
+            if options.is_empty() {
+                return None;
+            }
+
+            weight *= options.len() as f64;
+            state = options[rng.next_below(options.len() as u64) as usize];
+        }
+
+        Some((state.hit_mask().raw_value(), weight))
+    }
+}
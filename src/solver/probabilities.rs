@@ -0,0 +1,22 @@
+use crate::core::dataset::Dataset;
+use crate::solver::game_state::GameState;
+
+/// Probability of a ship on each cell given the shots fired so far, over the boards in `dataset`
+/// consistent with `state`.
+///
+/// Cells that have been fired at are 0, whatever their result, so the highest value is always
+/// a useful next shot. Cells no consistent board has a ship on are 0 too, as is every cell when
+/// no board is consistent with `state`.
+pub fn cell_probabilities(dataset: &Dataset, state: &GameState) -> [f64; 81] {
+    let (hit_mask, miss_mask) = state.to_masks();
+    let mut probabilities = dataset.filter_and_count(hit_mask, miss_mask).probabilities();
+
+    let shot = state.shot_mask();
+    for (index, probability) in probabilities.iter_mut().enumerate() {
+        if shot & (1u128 << index) != 0 {
+            *probability = 0.0;
+        }
+    }
+
+    probabilities
+}
@@ -0,0 +1,49 @@
+use crate::core::geometry::BoardGeometry;
+use crate::generator::point::Point;
+use std::cmp::Reverse;
+
+/// Returns the unshot cell with the highest count, or `None` if every cell has been shot.
+///
+/// `already_shot` is a mask of every cell that has been fired at (hits and misses). Ties are
+/// broken by picking the first cell in row-major order, so the result is deterministic.
+pub fn recommend_shot(counts: &[u32; 81], already_shot: u128) -> Option<Point> {
+    recommend_shot_with_geometry(counts, BoardGeometry::STANDARD, already_shot)
+}
+
+/// Like [`recommend_shot`], for a row-major grid of counts on a board of any geometry.
+pub fn recommend_shot_with_geometry(counts: &[u32], geometry: BoardGeometry, already_shot: u128) -> Option<Point> {
+    let mut best: Option<(usize, u32)> = None;
+
+    for (index, &count) in counts.iter().enumerate() {
+        if already_shot & (1u128 << index) != 0 {
+            continue;
+        }
+
+        match best {
+            Some((_, best_count)) if best_count >= count => {}
+            _ => best = Some((index, count)),
+        }
+    }
+
+    best.map(|(index, _)| geometry.point_of(index))
+}
+
+/// Returns up to `k` unshot cells with their counts, highest count first, ties in row-major
+/// order. The first entry is the cell [`recommend_shot`] picks.
+pub fn top_shots(counts: &[u32; 81], already_shot: u128, k: usize) -> Vec<(Point, u32)> {
+    top_shots_with_geometry(counts, BoardGeometry::STANDARD, already_shot, k)
+}
+
+/// Like [`top_shots`], for a row-major grid of counts on a board of any geometry.
+pub fn top_shots_with_geometry(counts: &[u32], geometry: BoardGeometry, already_shot: u128, k: usize) -> Vec<(Point, u32)> {
+    let mut cells: Vec<(usize, u32)> = counts.iter()
+        .copied()
+        .enumerate()
+        .filter(|&(index, _)| already_shot & (1u128 << index) == 0)
+        .collect();
+
+    // Stable, so equal counts stay in row-major order
+    cells.sort_by_key(|&(_, count)| Reverse(count));
+    cells.truncate(k);
+    cells.into_iter().map(|(index, count)| (geometry.point_of(index), count)).collect()
+}
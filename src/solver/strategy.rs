@@ -0,0 +1,184 @@
+use crate::core::dataset::Dataset;
+use crate::core::geometry::BoardGeometry;
+use crate::core::random::SplitMix64;
+use crate::generator::board_mask::BoardMask;
+use crate::generator::point::{Direction, Point};
+use crate::solver::game_state::GameState;
+use crate::solver::probabilities::cell_probabilities;
+
+/// A way of choosing shots, so simulations and the interactive tool can swap between them.
+///
+/// Strategies only pick cells whose contents are still unknown: not fired at, and not next to a
+/// sunk ship. They return `None` once there are none left.
+pub trait Strategy {
+    fn next_shot(&mut self, state: &GameState) -> Option<Point>;
+}
+
+/// Mask of cells that haven't been fired at and could still hold a ship.
+fn unknown_cells(state: &GameState) -> u128 {
+    let (hit_mask, miss_mask) = state.to_masks();
+    BoardGeometry::STANDARD.cell_mask() & !(hit_mask | miss_mask | state.shot_mask())
+}
+
+/// Picks uniformly among `cells`.
+fn random_cell(rng: &mut SplitMix64, cells: u128) -> Option<Point> {
+    if cells == 0 {
+        return None;
+    }
+
+    let choice = rng.next_below(cells.count_ones() as u64) as usize;
+    BoardMask::new(cells).iter_points().nth(choice)
+}
+
+/// Picks the unknown cell with the highest score, ties in row-major order.
+fn best_cell(state: &GameState, score: impl Fn(usize) -> f64) -> Option<Point> {
+    let mut best: Option<(usize, f64)> = None;
+
+    for point in BoardMask::new(unknown_cells(state)).iter_points() {
+        let index = BoardMask::index_of(point);
+        let value = score(index);
+        match best {
+            Some((_, best_value)) if best_value >= value => {}
+            _ => best = Some((index, value)),
+        }
+    }
+
+    best.map(|(index, _)| BoardMask::point_of(index))
+}
+
+/// Fires at a uniformly random unknown cell. The baseline the others should beat.
+#[derive(Debug, Clone)]
+pub struct Random {
+    rng: SplitMix64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { rng: SplitMix64::new(seed) }
+    }
+}
+
+impl Strategy for Random {
+    fn next_shot(&mut self, state: &GameState) -> Option<Point> {
+        random_cell(&mut self.rng, unknown_cells(state))
+    }
+}
+
+/// The classic human strategy, which needs no dataset.
+///
+/// While there are hits that aren't part of a sunk ship it targets them: it extends a line of
+/// hits along its direction, or tries the neighbors of a lone hit. Otherwise it hunts at random
+/// over a diagonal lattice of cells spaced `spacing` apart, which every ship at least that long
+/// must cross.
+#[derive(Debug, Clone)]
+pub struct HuntTarget {
+    rng: SplitMix64,
+    spacing: i32,
+}
+
+impl HuntTarget {
+    /// Hunts on a lattice spaced by the shortest ship in the standard fleet.
+    pub fn new(seed: u64) -> Self {
+        Self::with_spacing(seed, 3)
+    }
+
+    pub fn with_spacing(seed: u64, spacing: i32) -> Self {
+        assert!(spacing > 0, "Spacing must be positive");
+        Self { rng: SplitMix64::new(seed), spacing }
+    }
+
+    fn target(state: &GameState, unknown: u128) -> Option<Point> {
+        let (hit_mask, _) = state.to_masks();
+        let sunk = state.sunk_ships().iter().fold(0u128, |mask, ship| mask | ship.cells);
+        let open = hit_mask & !sunk;
+        let is_open = |point: Point| BoardMask::contains(point) && open & (1u128 << BoardMask::index_of(point)) != 0;
+        let is_unknown = |point: Point| BoardMask::contains(point) && unknown & (1u128 << BoardMask::index_of(point)) != 0;
+
+        let mut neighbors = None;
+        for hit in BoardMask::new(open).iter_points() {
+            for direction in [Direction::Horizontal, Direction::Vertical] {
+                let before = hit - direction;
+                let after = hit + direction;
+
+                // Part of a line of hits: the ship continues off one of its ends
+                if is_open(before) || is_open(after) {
+                    for end in [before, after] {
+                        if is_unknown(end) {
+                            return Some(end);
+                        }
+                    }
+                    continue;
+                }
+
+                if neighbors.is_none() {
+                    neighbors = [before, after].into_iter().find(|&point| is_unknown(point));
+                }
+            }
+        }
+
+        neighbors
+    }
+}
+
+impl Strategy for HuntTarget {
+    fn next_shot(&mut self, state: &GameState) -> Option<Point> {
+        let unknown = unknown_cells(state);
+        if let Some(point) = Self::target(state, unknown) {
+            return Some(point);
+        }
+
+        let lattice = BoardMask::new(unknown)
+            .iter_points()
+            .filter(|point| (point.x + point.y) % self.spacing == 0);
+        let lattice = BoardMask::from_points(lattice).raw_value();
+
+        // Ships can hide off the lattice once it's exhausted, e.g. when it's sparser than the fleet
+        random_cell(&mut self.rng, if lattice != 0 { lattice } else { unknown })
+    }
+}
+
+/// Fires at the cell most likely to hold a ship over the boards of a dataset.
+#[derive(Debug, Clone, Copy)]
+pub struct MaxProbability<'a> {
+    dataset: &'a Dataset,
+}
+
+impl<'a> MaxProbability<'a> {
+    pub fn new(dataset: &'a Dataset) -> Self {
+        Self { dataset }
+    }
+}
+
+impl Strategy for MaxProbability<'_> {
+    fn next_shot(&mut self, state: &GameState) -> Option<Point> {
+        let probabilities = cell_probabilities(self.dataset, state);
+        best_cell(state, |index| probabilities[index])
+    }
+}
+
+/// Fires at the cell whose result is least predictable over the boards of a dataset, i.e. the
+/// one with the most binary entropy, which on average rules out the most boards.
+#[derive(Debug, Clone, Copy)]
+pub struct Entropy<'a> {
+    dataset: &'a Dataset,
+}
+
+impl<'a> Entropy<'a> {
+    pub fn new(dataset: &'a Dataset) -> Self {
+        Self { dataset }
+    }
+}
+
+impl Strategy for Entropy<'_> {
+    fn next_shot(&mut self, state: &GameState) -> Option<Point> {
+        let probabilities = cell_probabilities(self.dataset, state);
+        best_cell(state, |index| binary_entropy(probabilities[index]))
+    }
+}
+
+fn binary_entropy(p: f64) -> f64 {
+    if p <= 0.0 || p >= 1.0 {
+        return 0.0;
+    }
+    -(p * p.log2() + (1.0 - p) * (1.0 - p).log2())
+}
@@ -249,3 +249,2691 @@ fn test_full_data_with_progress() {
 
     println!("✅ Full dataset validation passed!");
 }
+
+#[test]
+fn test_heatmap_normalizes_to_max_cell() {
+    use battleship::core::heatmap::{render_heatmap, ColorMode};
+
+    let mut counts = [0u32; 81];
+    counts[0] = 50;
+    counts[80] = 200;
+
+    let rendered = render_heatmap(&counts, ColorMode::TrueColor);
+    let rows: Vec<&str> = rendered.lines().collect();
+
+    assert_eq!(rows.len(), 9);
+    assert!(rows[0].contains("  25 "), "First cell should be 25% of max");
+    assert!(rows[8].ends_with(" 100 \x1b[0m"), "Last cell should be the maximum");
+}
+
+#[test]
+fn test_shuffle_is_seeded_permutation() {
+    use battleship::core::shuffle::shuffle_records;
+
+    let records: Vec<u128> = (0..1000u128).map(|i| i * 0x1_0000_0001).collect();
+    let input: Vec<u8> = records.iter().flat_map(|r| r.to_le_bytes()).collect();
+
+    let shuffle = |seed: u64, max_in_memory: usize| {
+        let mut output = Vec::new();
+        let count = shuffle_records(&mut input.as_slice(), &mut output, seed, max_in_memory, &std::env::temp_dir()).unwrap();
+        assert_eq!(count, 1000);
+        output.chunks(16).map(|c| u128::from_le_bytes(c.try_into().unwrap())).collect::<Vec<_>>()
+    };
+
+    let in_memory = shuffle(42, 10_000);
+    let spilled = shuffle(42, 50);
+
+    assert_ne!(in_memory, records, "Records should be reordered");
+    assert_eq!(in_memory, shuffle(42, 10_000), "Same seed should give the same order");
+
+    for result in [in_memory, spilled] {
+        let mut sorted = result.clone();
+        sorted.sort();
+        assert_eq!(sorted, records, "Shuffle must be a permutation");
+    }
+
+    // A truncated last record is an error rather than being dropped
+    let truncated = &input[..input.len() - 5];
+    let error = shuffle_records(&mut &truncated[..], &mut Vec::new(), 42, 10_000, &std::env::temp_dir()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_delta_encoding_writer_round_trip() {
+    use battleship::core::reader::DeltaDecodingReader;
+    use battleship::core::writer::DeltaEncodingWriter;
+
+    let boards: Vec<u128> = vec![0x1F, 0x1F << 9, 0x123456789ABCDEF, 0, (1u128 << 80) | 1];
+
+    let mut writer = DeltaEncodingWriter::new(Vec::new());
+    writer.write_all_records(boards.iter().copied()).unwrap();
+    assert_eq!(writer.records_written(), boards.len() as u64);
+
+    let encoded = writer.into_inner().unwrap();
+    assert_eq!(encoded.len(), boards.len() * 16);
+
+    let decoded: Vec<u128> = DeltaDecodingReader::new(encoded.as_slice())
+        .collect::<std::io::Result<_>>()
+        .unwrap();
+    assert_eq!(decoded, boards);
+}
+
+#[test]
+fn test_streaming_and_chunked_modes_agree() {
+    use battleship::core::filter::{filter_and_count_with_options, QueryOptions, ScanMode};
+
+    let boards: Vec<u128> = (0..5000u128).map(|i| (i * 0x9E3779B97F4A7C15) & ((1u128 << 81) - 1)).collect();
+    let query = |mode: ScanMode, hit: u128, miss: u128| {
+        let options = QueryOptions { mode, chunk_size: 128, ..QueryOptions::default() };
+        filter_and_count_with_options(boards.iter().map(|&b| Ok(b)), hit, miss, &options).unwrap()
+    };
+
+    for (hit, miss) in [(0, 0), (0x1, 0x2), (0b1011, 0b110100 << 40)] {
+        let chunked = query(ScanMode::Chunked, hit, miss);
+        assert_eq!(query(ScanMode::Streaming, hit, miss), chunked);
+        assert_eq!(query(ScanMode::Auto, hit, miss), chunked);
+    }
+
+    let options = QueryOptions::default();
+    assert_eq!(options.resolve_mode(0, 0), ScanMode::Chunked);
+    assert_eq!(options.resolve_mode(0b111, 0b111000), ScanMode::Streaming);
+
+    let options = QueryOptions { mode: ScanMode::Chunked, chunk_size: 0, ..QueryOptions::default() };
+    let err = filter_and_count_with_options(boards.iter().map(|&b| Ok(b)), 0, 0, &options).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_scan_runs_on_a_pool_of_the_requested_size() {
+    use battleship::core::accumulator::CountAccumulator;
+    use battleship::core::filter::{filter_and_accumulate, QueryOptions, ScanMode};
+
+    /// Records the largest pool a board was counted on, and how many boards were counted.
+    struct PoolSize(usize, u64);
+
+    impl CountAccumulator for PoolSize {
+        type Output = (usize, u64);
+
+        fn visit(&mut self, _board: u128) {
+            self.0 = self.0.max(rayon::current_num_threads());
+            self.1 += 1;
+        }
+
+        fn merge(&mut self, other: Self) {
+            self.0 = self.0.max(other.0);
+            self.1 += other.1;
+        }
+
+        fn finish(self) -> Self::Output {
+            (self.0, self.1)
+        }
+    }
+
+    let boards: Vec<u128> = (0..1000).collect();
+    for mode in [ScanMode::Chunked, ScanMode::Bitsliced] {
+        let options = QueryOptions { mode, chunk_size: 100, threads: Some(3), ..QueryOptions::default() };
+        let result = filter_and_accumulate(boards.iter().map(|&b| Ok(b)), 0, 0, &options, || PoolSize(0, 0)).unwrap();
+        assert_eq!(result, (3, 1000));
+    }
+}
+
+#[test]
+fn test_bitsliced_mode_matches_scalar() {
+    use battleship::core::accumulator::PairCorrelations;
+    use battleship::core::bitslice::BitsliceBatch;
+    use battleship::core::filter::{filter_and_accumulate, filter_and_count_cells, filter_and_count_with_options, QueryOptions, ScanMode};
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::core::random::SplitMix64;
+
+    let mut random = SplitMix64::new(11);
+    let mut random_mask = || (random.next_u64() as u128) << 64 | random.next_u64() as u128;
+    let boards: Vec<u128> = (0..5000).map(|_| random_mask() & random_mask()).collect();
+
+    // Lane c holds bit c of every record
+    let batch = BitsliceBatch::new(&boards[..50]);
+    assert_eq!(batch.len(), 50);
+    assert_eq!(batch.filled(), (1 << 50) - 1);
+    for cell in 0..128 {
+        let expected = boards[..50].iter().enumerate().fold(0u64, |lane, (k, &board)| lane | ((board >> cell & 1) as u64) << k);
+        assert_eq!(batch.lane(cell), expected, "lane {}", cell);
+    }
+    let matches = batch.matching(1 << 3, 1 << 100);
+    assert_eq!(batch.matched_records(matches).collect::<Vec<_>>(), boards[..50].iter().copied().filter(|b| b >> 3 & 1 == 1 && b >> 100 & 1 == 0).collect::<Vec<_>>());
+
+    let nine = boards.iter().map(|b| b & ((1u128 << 81) - 1)).collect::<Vec<_>>();
+    let scan = |boards: &[u128], options: &QueryOptions, hit, miss| {
+        filter_and_count_cells(boards.iter().map(|&b| Ok(b)), hit, miss, options).unwrap()
+    };
+    let variants = [
+        QueryOptions { chunk_size: 100, ..QueryOptions::default() },
+        QueryOptions { popcount: Some("18-22".parse().unwrap()), ..QueryOptions::default() },
+        QueryOptions { expand_symmetries: true, chunk_size: 333, ..QueryOptions::default() },
+    ];
+    for options in variants {
+        for (hit, miss) in [(0, 0), (0x1, 0x2), (0b1011, 0b110100 << 40)] {
+            let bitsliced = QueryOptions { mode: ScanMode::Bitsliced, ..options };
+            let chunked = QueryOptions { mode: ScanMode::Chunked, ..options };
+            assert_eq!(scan(&nine, &bitsliced, hit, miss), scan(&nine, &chunked, hit, miss));
+        }
+    }
+
+    let bitsliced = QueryOptions { mode: ScanMode::Bitsliced, chunk_size: 1000, ..QueryOptions::default() };
+    assert_eq!(
+        filter_and_count_with_options(nine.iter().map(|&b| Ok(b)), 1 << 7, 0, &bitsliced).unwrap(),
+        filter_and_count(nine.iter().map(|&b| Ok(b)), 1 << 7, 0).unwrap(),
+    );
+
+    // Other accumulators see each matched board through the default visit_batch
+    let pairs = |mode| {
+        let options = QueryOptions { mode, ..QueryOptions::default() };
+        filter_and_accumulate(nine.iter().map(|&b| Ok(b)), 1 << 40, 1 << 41, &options, PairCorrelations::new).unwrap()
+    };
+    assert_eq!(pairs(ScanMode::Bitsliced), pairs(ScanMode::Streaming));
+
+    // Cells past the 81st are counted on larger boards
+    let classic = QueryOptions { geometry: BoardGeometry::CLASSIC, ..QueryOptions::default() };
+    let hundred = boards.iter().map(|b| b & BoardGeometry::CLASSIC.cell_mask()).collect::<Vec<_>>();
+    assert_eq!(
+        scan(&hundred, &QueryOptions { mode: ScanMode::Bitsliced, ..classic }, 1 << 90, 1 << 95),
+        scan(&hundred, &classic, 1 << 90, 1 << 95),
+    );
+}
+
+#[cfg(feature = "gpu")]
+#[test]
+fn test_gpu_counts_match_cpu() {
+    use battleship::core::filter::{filter_and_count_cells, filter_and_count_gpu, QueryOptions};
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::core::gpu::GpuCounter;
+
+    let boards: Vec<u128> = (0..20_000u128).map(|i| (i * 0x9E3779B97F4A7C15) & ((1u128 << 81) - 1)).collect();
+    let variants = [
+        QueryOptions::default(),
+        QueryOptions { popcount: Some("38-42".parse().unwrap()), ..QueryOptions::default() },
+        QueryOptions { expand_symmetries: true, ..QueryOptions::default() },
+        QueryOptions { geometry: BoardGeometry::new(8, 8), ..QueryOptions::default() },
+    ];
+
+    // Without a GPU these check the CPU fallback
+    for options in variants {
+        for (hit, miss) in [(0, 0), (0x1, 0x2), (0b1011, 0b110100 << 40)] {
+            let cpu = filter_and_count_cells(boards.iter().map(|&b| Ok(b)), hit, miss, &options).unwrap();
+            assert_eq!(filter_and_count_gpu(boards.iter().map(|&b| Ok(b)), hit, miss, &options).unwrap(), cpu);
+        }
+    }
+
+    match GpuCounter::new() {
+        Ok(counter) => {
+            let options = QueryOptions::default();
+            let cpu = filter_and_count_cells(boards.iter().map(|&b| Ok(b)), 1 << 5, 1 << 6, &options).unwrap();
+            assert_eq!(counter.count(&boards, 1 << 5, 1 << 6, &options).unwrap(), cpu);
+            assert!(counter.count(&boards, 0, 0, &QueryOptions { expand_symmetries: true, ..options }).is_err());
+        }
+        Err(e) => assert_eq!(e.kind(), std::io::ErrorKind::Unsupported),
+    }
+}
+
+#[test]
+fn test_encode_chunks_stats() {
+    use battleship::core::encoder::encode_chunks;
+    use battleship::core::reader::DeltaDecodingReader;
+
+    let boards: Vec<u128> = vec![0b0111, 0b0110, 0b1110, 0b1100, 0b0101];
+    let raw: Vec<u8> = boards.iter().flat_map(|b| b.to_le_bytes()).collect();
+
+    let mut encoded = Vec::new();
+    let chunks = encode_chunks(&mut raw.as_slice(), &mut encoded, 2).unwrap();
+
+    assert_eq!(chunks.len(), 3);
+    assert_eq!(chunks.iter().map(|c| c.count).collect::<Vec<_>>(), vec![2, 2, 1]);
+    assert_eq!(chunks.iter().map(|c| c.offset).collect::<Vec<_>>(), vec![0, 32, 64]);
+    assert_eq!((chunks[0].union, chunks[0].intersection), (0b0111, 0b0110));
+    assert_eq!((chunks[1].union, chunks[1].intersection), (0b1110, 0b1100));
+    assert_eq!((chunks[2].union, chunks[2].intersection), (0b0101, 0b0101));
+
+    let decoded: Vec<u128> = DeltaDecodingReader::new(encoded.as_slice())
+        .collect::<std::io::Result<_>>()
+        .unwrap();
+    assert_eq!(decoded, boards);
+
+    let truncated = &raw[..raw.len() - 5];
+    let err = encode_chunks(&mut &truncated[..], &mut Vec::new(), 2).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_encode_chunks_with_independent_frames() {
+    use battleship::core::encoder::{encode_chunks_with_options, EncodeOptions};
+    use battleship::core::reader::DeltaDecodingReader;
+
+    let boards: Vec<u128> = vec![0b0111, 0b0110, 0b1110, 0b1100, 0b0101];
+    let raw: Vec<u8> = boards.iter().flat_map(|b| b.to_le_bytes()).collect();
+    let decode = |bytes: &[u8]| -> Vec<u128> {
+        DeltaDecodingReader::new(bytes).collect::<std::io::Result<_>>().unwrap()
+    };
+
+    // Restarted chunks decode on their own from their offsets
+    let options = EncodeOptions { chunk_size: 2, restart_delta: true, zstd_frames: Some(1), ..EncodeOptions::default() };
+    let mut encoded = Vec::new();
+    let chunks = encode_chunks_with_options(&mut raw.as_slice(), &mut encoded, &options).unwrap();
+    assert_eq!(chunks.len(), 3);
+
+    for (index, chunk) in chunks.iter().enumerate() {
+        let end = chunks.get(index + 1).map_or(encoded.len(), |next| next.offset as usize);
+        let frame = zstd::decode_all(&encoded[chunk.offset as usize..end]).unwrap();
+        assert_eq!(decode(&frame), boards[index * 2..(index * 2 + 2).min(boards.len())]);
+    }
+
+    // Without restarts, the concatenated frames are one continuous delta stream
+    let options = EncodeOptions { restart_delta: false, ..options };
+    let mut encoded = Vec::new();
+    encode_chunks_with_options(&mut raw.as_slice(), &mut encoded, &options).unwrap();
+    assert_eq!(decode(&zstd::decode_all(encoded.as_slice()).unwrap()), boards);
+}
+
+#[test]
+fn test_encode_indexed_writes_a_chunk_index() {
+    use battleship::core::encoder::{encode_chunks_with_options, encode_indexed, EncodeOptions};
+    use battleship::core::index::DatasetIndex;
+
+    let boards: Vec<u128> = vec![0b0111, 0b0110, 0b1110, 0b1100, 0b0101];
+    let raw: Vec<u8> = boards.iter().flat_map(|b| b.to_le_bytes()).collect();
+
+    let options = EncodeOptions { chunk_size: 2, restart_delta: true, zstd_frames: Some(1), ..EncodeOptions::default() };
+    let mut encoded = Vec::new();
+    let chunks = encode_chunks_with_options(&mut raw.as_slice(), &mut encoded, &options).unwrap();
+
+    // The index survives a round trip and points at the same frames
+    let index = {
+        let mut sink = Vec::new();
+        encode_indexed(&mut raw.as_slice(), &mut sink, &options).unwrap()
+    };
+    assert_eq!(index.chunks, chunks);
+    assert_eq!((index.data_len, index.record_count()), (encoded.len() as u64, 5));
+    assert!(index.restart_delta && index.zstd_frames);
+    assert_eq!(index.chunk_range(2), chunks[2].offset..encoded.len() as u64);
+
+    let mut bytes = Vec::new();
+    index.write_to(&mut bytes).unwrap();
+    assert_eq!(DatasetIndex::read_from(&mut bytes.as_slice()).unwrap(), index);
+    assert!(DatasetIndex::read_from(&mut &bytes[..bytes.len() - 1]).is_err());
+    assert!(DatasetIndex::read_from(&mut &b"nope"[..]).is_err());
+}
+
+#[test]
+fn test_custom_accumulators() {
+    use battleship::core::accumulator::{MatchCount, PairCorrelations, TopKBoards};
+    use battleship::core::filter::{filter_and_accumulate, QueryOptions, ScanMode};
+
+    let boards: Vec<u128> = vec![0b0011, 0b0110, 0b0111, 0b1000, 0b1111];
+
+    for mode in [ScanMode::Chunked, ScanMode::Streaming] {
+        let options = QueryOptions { mode, chunk_size: 2, ..QueryOptions::default() };
+        let scan = || boards.iter().map(|&b| Ok(b));
+
+        let matched = filter_and_accumulate(scan(), 0b0010, 0, &options, MatchCount::new).unwrap();
+        assert_eq!(matched, 4);
+
+        let pairs = filter_and_accumulate(scan(), 0, 0, &options, PairCorrelations::new).unwrap();
+        assert_eq!(pairs[0][1], 3);
+        assert_eq!(pairs[1][0], 3);
+        assert_eq!(pairs[1][1], 4);
+        assert_eq!(pairs[0][3], 1);
+
+        let top = filter_and_accumulate(scan(), 0, 0, &options, || TopKBoards::new(2, |b: u128| b.count_ones() as u64)).unwrap();
+        assert_eq!(top, vec![(4, 0b1111), (3, 0b0111)]);
+    }
+}
+
+#[test]
+fn test_rollup_table() {
+    use battleship::core::filter::{filter_and_count_with_options, QueryOptions};
+    use battleship::core::random::SplitMix64;
+    use battleship::core::rollup::RollupTable;
+
+    let mut rng = SplitMix64::new(7);
+    let mut random_mask = || ((rng.next_u64() as u128) << 64 | rng.next_u64() as u128) & ((1u128 << 81) - 1);
+    // Like touching ships, cells 0 and 1 never both have a ship
+    let boards: Vec<u128> = (0..3000)
+        .map(|_| random_mask() & random_mask() & random_mask())
+        .map(|board| if board & 1 != 0 { board & !0b10 } else { board })
+        .chain([0])
+        .collect();
+    let scan = || boards.iter().map(|&b| Ok(b));
+
+    let options = QueryOptions::default();
+    let table = RollupTable::build(scan(), &options).unwrap();
+    assert_eq!(table.total, boards.len() as u64);
+    assert!(table.answers(&options));
+    assert!(!table.answers(&QueryOptions { expand_symmetries: true, ..options }));
+
+    // Every query with at most one shot is answered exactly
+    assert_eq!(table.lookup(0, 0), Some(filter_and_count(scan(), 0, 0).unwrap()));
+    for cell in 0..81 {
+        let shot = 1u128 << cell;
+        assert_eq!(table.lookup(shot, 0), Some(filter_and_count(scan(), shot, 0).unwrap()));
+        assert_eq!(table.lookup(0, shot), Some(filter_and_count(scan(), 0, shot).unwrap()));
+        assert_eq!(table.lookup(shot, shot), Some(([0; 81], 0)));
+    }
+    assert_eq!(table.lookup(0b11, 0), None);
+
+    // Deeper queries are bounded from above
+    for _ in 0..200 {
+        let hit = (0..3).map(|_| 1u128 << rng.next_below(81)).fold(0, |mask, shot| mask | shot);
+        let miss = (0..3).map(|_| 1u128 << rng.next_below(81)).fold(0, |mask, shot| mask | shot) & !hit;
+        let (counts, matched) = filter_and_count(scan(), hit, miss).unwrap();
+        let (count_bounds, matched_bound) = table.upper_bounds(hit, miss);
+        assert!(matched <= matched_bound);
+        assert!(counts.iter().zip(count_bounds).all(|(&count, bound)| count <= bound));
+    }
+    assert_eq!(table.upper_bounds(0b11 | 1 << 50, 1 << 60), ([0; 81], 0));
+
+    let mut bytes = Vec::new();
+    table.write_to(&mut bytes).unwrap();
+    assert_eq!(RollupTable::read_from(&mut bytes.as_slice()).unwrap(), table);
+
+    // Expanded tables count whole symmetry orbits
+    let expand = QueryOptions { expand_symmetries: true, ..options };
+    let expanded = RollupTable::build(scan(), &expand).unwrap();
+    assert_eq!(expanded.lookup(1 << 40, 0), Some(filter_and_count_with_options(scan(), 1 << 40, 0, &expand).unwrap()));
+    assert!(RollupTable::build(scan(), &QueryOptions { popcount: Some("20".parse().unwrap()), ..options }).is_err());
+}
+
+#[test]
+fn test_query_rejects_mismatched_geometry() {
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::core::query::{Query, QueryError};
+
+    // A hit in the last cell of a 10x10 board doesn't exist on a 9x9 board
+    let ten_by_ten_hit = 1u128 << 99;
+    assert!(matches!(
+        Query::compile(ten_by_ten_hit, 0, BoardGeometry::STANDARD),
+        Err(QueryError::MaskOutOfBounds { cell: 99, .. })
+    ));
+    assert!(Query::compile(ten_by_ten_hit, 0, BoardGeometry::CLASSIC).is_ok());
+    assert!(matches!(Query::compile(0b11, 0b10, BoardGeometry::STANDARD), Err(QueryError::ConflictingCells { cells: 0b10 })));
+
+    let query = Query::compile(0, 0, BoardGeometry::CLASSIC).unwrap();
+    let error = query.check_dataset(BoardGeometry::STANDARD, "boards.bin").unwrap_err();
+    assert_eq!(error.to_string(), "query is for a 10x10 board, but dataset boards.bin contains 9x9 boards");
+
+    // Records from a 10x10 dataset are rejected by a 9x9 query
+    let query = Query::compile(0, 0, BoardGeometry::STANDARD).unwrap();
+    let records = vec![Ok(0b111u128), Ok(1u128 << 95)];
+    let error = filter_and_count(query.guard(records, "classic.bin"), 0, 0).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert!(error.to_string().contains("record 1 in classic.bin"));
+}
+
+#[test]
+fn test_symmetry_expansion_counts_full_orbits() {
+    use battleship::core::filter::{filter_and_count_with_options, QueryOptions, ScanMode};
+
+    let corner_ship: u128 = 0b111; // Horizontal ship in the top-left corner: 8 distinct variants
+    let center_cell: u128 = 1 << 40; // Invariant under every symmetry: 1 variant
+
+    for mode in [ScanMode::Chunked, ScanMode::Streaming] {
+        let options = QueryOptions { mode, expand_symmetries: true, ..QueryOptions::default() };
+        let records = || [corner_ship, center_cell].into_iter().map(Ok);
+
+        let (counts, matched) = filter_and_count_with_options(records(), 0, 0, &options).unwrap();
+        assert_eq!(matched, 9);
+        assert_eq!(counts[0], 2, "Corner cell is covered by two orientations");
+        assert_eq!(counts[40], 1);
+        assert_eq!(counts.iter().map(|&c| c as u64).sum::<u64>(), 8 * 3 + 1);
+
+        // Only the variants touching the bottom-right corner survive this hit
+        let (_, matched) = filter_and_count_with_options(records(), 1 << 80, 0, &options).unwrap();
+        assert_eq!(matched, 2);
+    }
+}
+
+#[test]
+fn test_popcount_filter() {
+    use battleship::core::filter::{filter_and_count_with_options, PopcountRange, QueryOptions};
+
+    assert_eq!("27".parse::<PopcountRange>(), Ok(PopcountRange::exactly(27)));
+    assert_eq!("20-30".parse::<PopcountRange>(), Ok(PopcountRange { min: 20, max: 30 }));
+    assert_eq!("-3".parse::<PopcountRange>(), Ok(PopcountRange { min: 0, max: 3 }));
+    assert!("30-20".parse::<PopcountRange>().is_err());
+    assert!("abc".parse::<PopcountRange>().is_err());
+
+    let records = || [0b1u128, 0b11, 0b111, 0b1111].into_iter().map(Ok);
+    let query = |popcount: &str| {
+        let options = QueryOptions { popcount: Some(popcount.parse().unwrap()), ..QueryOptions::default() };
+        filter_and_count_with_options(records(), 0b1, 0, &options).unwrap().1
+    };
+
+    assert_eq!(query("3"), 1);
+    assert_eq!(query("2-3"), 2);
+    assert_eq!(query("5-"), 0);
+}
+
+#[test]
+fn test_version_introspection() {
+    use battleship::core::ffi::{battleship_abi_version, battleship_version};
+    use battleship::core::version::{ABI_VERSION, CRATE_VERSION};
+
+    assert_eq!(battleship_abi_version(), ABI_VERSION);
+
+    let json = unsafe { std::ffi::CStr::from_ptr(battleship_version()) }.to_str().unwrap();
+    assert!(json.starts_with(&format!("{{\"version\":\"{}\"", CRATE_VERSION)));
+    assert!(json.contains(&format!("\"abi_version\":{}", ABI_VERSION)));
+    assert!(json.contains("\"kernel_backends\":[\"chunked-rayon\""));
+}
+
+#[test]
+fn test_filter_result_probabilities() {
+    use battleship::core::filter::FilterResult;
+
+    let result: FilterResult = filter_and_count(vec![Ok(0b011u128), Ok(0b110), Ok(0b100), Ok(0b101)], 0, 0).unwrap().into();
+    let probabilities = result.probabilities();
+
+    assert_eq!(result.matched, 4);
+    assert_eq!(&probabilities[..4], &[0.5, 0.5, 0.75, 0.0]);
+
+    let empty = FilterResult::new([0; 81], 0);
+    assert!(empty.probabilities().iter().all(|&p| p == 0.0));
+}
+
+#[test]
+fn test_recommend_shot() {
+    use battleship::generator::point::Point;
+    use battleship::solver::recommend::recommend_shot;
+
+    let mut counts = [1u32; 81];
+    counts[10] = 7;
+    counts[20] = 9;
+    counts[30] = 9;
+
+    assert_eq!(recommend_shot(&counts, 0), Some(Point::new(2, 2)), "Ties go to the first cell");
+    assert_eq!(recommend_shot(&counts, 1 << 20), Some(Point::new(3, 3)));
+    assert_eq!(recommend_shot(&counts, (1 << 20) | (1 << 30)), Some(Point::new(1, 1)));
+    assert_eq!(recommend_shot(&counts, (1u128 << 81) - 1), None);
+}
+
+#[test]
+fn test_top_shots() {
+    use battleship::generator::point::Point;
+    use battleship::solver::recommend::{recommend_shot, top_shots};
+
+    let mut counts = [1u32; 81];
+    counts[10] = 7;
+    counts[20] = 9;
+    counts[30] = 9;
+
+    let top = top_shots(&counts, 0, 3);
+    assert_eq!(top, vec![(Point::new(2, 2), 9), (Point::new(3, 3), 9), (Point::new(1, 1), 7)]);
+    assert_eq!(Some(top[0].0), recommend_shot(&counts, 0));
+
+    assert_eq!(top_shots(&counts, 1 << 20, 2), vec![(Point::new(3, 3), 9), (Point::new(1, 1), 7)]);
+    assert_eq!(top_shots(&counts, 0, 100).len(), 81);
+    assert!(top_shots(&counts, (1u128 << 81) - 1, 3).is_empty());
+}
+
+#[test]
+fn test_in_memory_dataset_matches_streaming_filter() {
+    use battleship::core::dataset::Dataset;
+
+    let boards: Vec<u128> = (0..2000u128).map(|i| (i * 0x9E3779B97F4A7C15) & ((1u128 << 81) - 1)).collect();
+    let dataset = Dataset::new(boards.clone());
+
+    for (hit, miss) in [(0, 0), (0b101, 0b10), (1 << 80, 1 << 40)] {
+        let streamed = filter_and_count(boards.iter().map(|&b| Ok(b)), hit, miss).unwrap();
+        assert_eq!(dataset.filter_and_count(hit, miss), streamed.into());
+    }
+}
+
+#[test]
+fn test_game_state_tracks_shots_and_sunk_ships() {
+    use battleship::generator::point::{Direction, Point};
+    use battleship::solver::game_state::{GameError, GameState, ShotResult};
+
+    let mut game = GameState::new();
+    game.record_shot(Point::new(1, 1), ShotResult::Hit).unwrap();
+    game.record_shot(Point::new(2, 1), ShotResult::Hit).unwrap();
+    game.record_shot(Point::new(5, 5), ShotResult::Miss).unwrap();
+
+    assert_eq!(game.to_masks(), ((1 << 10) | (1 << 11), 1 << 50));
+    assert_eq!(game.record_shot(Point::new(5, 5), ShotResult::Hit), Err(GameError::AlreadyShot(Point::new(5, 5))));
+    assert_eq!(game.record_shot(Point::new(9, 0), ShotResult::Hit), Err(GameError::OutOfBounds(Point::new(9, 0))));
+
+    // Sinking the ship marks every surrounding cell as water
+    game.record_shot(Point::new(3, 1), ShotResult::Sunk).unwrap();
+    let ships = game.sunk_ships();
+    assert_eq!(ships.len(), 1);
+    assert_eq!((ships[0].start, ships[0].length, ships[0].direction), (Point::new(1, 1), 3, Direction::Horizontal));
+
+    let (hits, misses) = game.to_masks();
+    assert_eq!(hits, 0b1110 << 9);
+    assert_eq!(misses, (0b11111 | (0b10001 << 9) | (0b11111 << 18)) | (1 << 50));
+
+    // A hit touching a sunk ship is impossible
+    assert_eq!(game.record_shot(Point::new(4, 2), ShotResult::Hit), Err(GameError::Contradiction(Point::new(4, 2))));
+    assert_eq!(game.shots().len(), 4);
+
+    assert_eq!(game.undo().map(|shot| shot.result), Some(ShotResult::Sunk));
+    assert!(game.sunk_ships().is_empty());
+    assert_eq!(game.to_masks().1, 1 << 50);
+}
+
+#[test]
+fn test_game_state_ffi() {
+    use battleship::core::ffi::*;
+
+    unsafe {
+        let game = battleship_game_new();
+        assert_eq!(battleship_game_record_shot(game, 0, 0, BATTLESHIP_SHOT_HIT), 0);
+        assert_eq!(battleship_game_record_shot(game, 8, 8, BATTLESHIP_SHOT_MISS), 0);
+        assert_eq!(battleship_game_record_shot(game, 8, 8, BATTLESHIP_SHOT_MISS), -2);
+        assert_eq!(battleship_game_record_shot(game, 1, 0, 7), -1);
+
+        let (mut hit_low, mut hit_high, mut miss_low, mut miss_high) = (0, 0, 0, 0);
+        battleship_game_masks(game, &mut hit_low, &mut hit_high, &mut miss_low, &mut miss_high);
+        assert_eq!((hit_low, hit_high, miss_low, miss_high), (1, 0, 0, 1 << 16));
+
+        assert_eq!(battleship_game_undo(game), 1);
+        assert_eq!(battleship_game_undo(game), 1);
+        assert_eq!(battleship_game_undo(game), 0);
+        battleship_game_free(game);
+    }
+}
+
+#[test]
+fn test_filter_ffi_status_codes() {
+    use battleship::core::ffi::*;
+    use battleship::core::writer::DeltaEncodingWriter;
+    use std::ffi::CString;
+
+    let dir = std::env::temp_dir().join(format!("battleship-ffi-status-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut writer = DeltaEncodingWriter::new(Vec::new());
+    writer.write_all_records([0b111u128, 0b111 << 9]).unwrap();
+    let data_path = dir.join("boards.bin");
+    std::fs::write(&data_path, writer.into_inner().unwrap()).unwrap();
+    let garbage_path = dir.join("garbage.bin");
+    std::fs::write(&garbage_path, b"BSDS\xff\xff").unwrap();
+
+    let path = |p: &std::path::Path| CString::new(p.to_str().unwrap()).unwrap();
+    let mut counts = [0u32; 81];
+    let mut matched = 0u64;
+
+    unsafe {
+        let run = |path: *const std::os::raw::c_char, hit: u64, miss: u64, counts: &mut [u32; 81], matched: &mut u64| {
+            battleship_filter_and_count(path, hit, 0, miss, 0, counts.as_mut_ptr(), matched)
+        };
+
+        assert_eq!(run(path(&data_path).as_ptr(), 1, 0, &mut counts, &mut matched), BATTLESHIP_OK);
+        assert_eq!((matched, counts[0], counts[9]), (1, 1, 0));
+        assert_eq!(filter_and_count_ffi(path(&data_path).as_ptr(), 0, 0, 0, 0, counts.as_mut_ptr()), 2);
+
+        assert_eq!(run(path(&dir.join("missing.bin")).as_ptr(), 0, 0, &mut counts, &mut matched), BATTLESHIP_ERR_BAD_PATH);
+        assert_eq!(run(path(&garbage_path).as_ptr(), 0, 0, &mut counts, &mut matched), BATTLESHIP_ERR_DECODE);
+        assert_eq!(run(path(&data_path).as_ptr(), 1, 1, &mut counts, &mut matched), BATTLESHIP_ERR_INVALID_QUERY);
+        assert_eq!(run(c"\xff".as_ptr(), 0, 0, &mut counts, &mut matched), BATTLESHIP_ERR_INVALID_UTF8);
+        assert_eq!(run(std::ptr::null(), 0, 0, &mut counts, &mut matched), BATTLESHIP_ERR_NULL_POINTER);
+        assert_eq!(matched, 1, "Outputs are only written on success");
+
+        let mut scanned = 0u64;
+        let status = battleship_filter_and_count_scanned(
+            path(&data_path).as_ptr(), 1 << 9, 0, 0, 0, counts.as_mut_ptr(), &mut matched, &mut scanned,
+        );
+        assert_eq!((status, matched, scanned), (BATTLESHIP_OK, 1, 2));
+        let status = battleship_filter_and_count_scanned(
+            path(&data_path).as_ptr(), 0, 0, 0, 0, counts.as_mut_ptr(), &mut matched, std::ptr::null_mut(),
+        );
+        assert_eq!(status, BATTLESHIP_ERR_NULL_POINTER);
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_ffi_session_queries() {
+    use battleship::core::encoder::{encode_indexed, EncodeOptions};
+    use battleship::core::ffi::*;
+    use std::ffi::CString;
+
+    let dir = std::env::temp_dir().join(format!("battleship-ffi-session-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let boards: Vec<u128> = (0..50u128).map(|i| 0b111 << (i % 70)).collect();
+    let raw: Vec<u8> = boards.iter().flat_map(|b| b.to_le_bytes()).collect();
+    let options = EncodeOptions { chunk_size: 8, restart_delta: true, ..EncodeOptions::default() };
+    let mut encoded = Vec::new();
+    let index = encode_indexed(&mut raw.as_slice(), &mut encoded, &options).unwrap();
+
+    let (data_path, index_path) = (dir.join("boards.bin"), dir.join("boards.idx"));
+    std::fs::write(&data_path, &encoded).unwrap();
+    index.save(&index_path).unwrap();
+    let data = CString::new(data_path.to_str().unwrap()).unwrap();
+    let index = CString::new(index_path.to_str().unwrap()).unwrap();
+
+    unsafe {
+        let indexed = bs_session_open_indexed(data.as_ptr(), index.as_ptr());
+        let missing = CString::new(dir.join("missing.idx").to_str().unwrap()).unwrap();
+        assert!(bs_session_open_indexed(data.as_ptr(), missing.as_ptr()).is_null());
+
+        assert!(!indexed.is_null());
+        for _ in 0..2 {
+            let (mut counts, mut matched) = ([0u32; 81], 0u64);
+            assert_eq!(bs_session_query(indexed, 1 << 4, 0, 0, 0, counts.as_mut_ptr(), &mut matched), BATTLESHIP_OK);
+            assert_eq!(matched, 3);
+            assert_eq!(counts[2..7], [1, 2, 3, 2, 1]);
+        }
+        let mut counts = [0u32; 81];
+        assert_eq!(bs_session_query(indexed, 1, 0, 1, 0, counts.as_mut_ptr(), &mut 0), BATTLESHIP_ERR_INVALID_QUERY);
+        bs_session_close(indexed);
+
+        std::fs::write(&data_path, &raw).unwrap();
+        let decoded = bs_session_open(data.as_ptr());
+        assert!(!decoded.is_null());
+        let (mut counts, mut matched) = ([0u32; 81], 0u64);
+        assert_eq!(bs_session_query(decoded, 0, 0, 0, 0, counts.as_mut_ptr(), &mut matched), BATTLESHIP_OK);
+        assert_eq!(matched, 50);
+        assert_eq!(bs_session_query(decoded, 0, 0, 0, 0, std::ptr::null_mut(), &mut matched), BATTLESHIP_ERR_NULL_POINTER);
+        bs_session_close(decoded);
+        bs_session_close(std::ptr::null_mut());
+
+        assert!(bs_session_open(missing.as_ptr()).is_null());
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_ffi_context_is_shared_across_threads() {
+    use battleship::core::ffi::*;
+    use std::ffi::CString;
+
+    let path = std::env::temp_dir().join(format!("battleship-ffi-context-{}.bin", std::process::id()));
+    let raw: Vec<u8> = (0..60u128).flat_map(|i| (0b111u128 << (i % 70)).to_le_bytes()).collect();
+    std::fs::write(&path, &raw).unwrap();
+    let c_path = CString::new(path.to_str().unwrap()).unwrap();
+
+    // Raw pointers aren't Send, so pass the address to each thread
+    let context = unsafe { bs_context_open(c_path.as_ptr()) } as usize;
+    assert_ne!(context, 0);
+
+    let threads: Vec<_> = (0..4u64)
+        .map(|i| {
+            let context = unsafe { bs_context_retain(context as *const BsContext) } as usize;
+            std::thread::spawn(move || unsafe {
+                let context = context as *const BsContext;
+                let (mut counts, mut matched) = ([0u32; 81], 0u64);
+                let status = bs_context_query(context, 1 << (i + 10), 0, 0, 0, counts.as_mut_ptr(), &mut matched);
+                bs_context_release(context);
+                (status, matched)
+            })
+        })
+        .collect();
+
+    // The dataset stays alive while the threads hold their references
+    unsafe { bs_context_release(context as *const BsContext) };
+
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), (BATTLESHIP_OK, 3));
+    }
+
+    assert!(unsafe { bs_context_open(std::ptr::null()) }.is_null());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_ffi_struct_api() {
+    use battleship::core::ffi::*;
+    use std::ffi::{CStr, CString};
+
+    let version = unsafe { CStr::from_ptr(bs_version()) }.to_str().unwrap();
+    assert_eq!(version, env!("CARGO_PKG_VERSION"));
+
+    // Layouts match the C declarations in battleship.h
+    assert_eq!(std::mem::size_of::<BsQuery>(), 32);
+    assert_eq!(std::mem::offset_of!(BsResult, matched), 8);
+    assert_eq!(std::mem::offset_of!(BsResult, counts), 16);
+
+    let mask = 1u128 << 70 | 1;
+    let query = BsQuery::new(mask, 2);
+    assert_eq!((query.hit_mask_low, query.hit_mask_high), (1, 1 << 6));
+    assert_eq!((query.hit_mask(), query.miss_mask()), (mask, 2));
+
+    let path = std::env::temp_dir().join(format!("battleship-ffi-struct-{}.bin", std::process::id()));
+    let raw: Vec<u8> = [0b111u128, 0b1110].iter().flat_map(|b| b.to_le_bytes()).collect();
+    std::fs::write(&path, &raw).unwrap();
+    let c_path = CString::new(path.to_str().unwrap()).unwrap();
+
+    unsafe {
+        let mut result = BsResult::default();
+        assert_eq!(bs_filter(c_path.as_ptr(), &BsQuery::new(1 << 1, 0), &mut result), BATTLESHIP_OK);
+        assert_eq!((result.status, result.matched), (BATTLESHIP_OK, 2));
+        assert_eq!(result.counts[..5], [1, 2, 2, 1, 0]);
+
+        let context = bs_context_open(c_path.as_ptr());
+        let mut result = BsResult::default();
+        assert_eq!(bs_context_filter(context, &BsQuery::new(1, 0), &mut result), BATTLESHIP_OK);
+        assert_eq!(result.matched, 1);
+        assert_eq!(bs_context_filter(context, &BsQuery::new(1, 1), &mut result), BATTLESHIP_ERR_INVALID_QUERY);
+        assert_eq!(result.status, BATTLESHIP_ERR_INVALID_QUERY);
+        assert_eq!(bs_context_filter(context, std::ptr::null(), &mut result), BATTLESHIP_ERR_NULL_POINTER);
+        bs_context_release(context);
+    }
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_classic_board_geometry() {
+    use battleship::core::filter::{filter_and_count_cells, filter_and_count_with_options, QueryOptions};
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::generator::board_state::BoardState;
+    use battleship::generator::symmetries::generate_symmetries_with_geometry;
+
+    let classic = BoardGeometry::CLASSIC;
+    assert_eq!("10x10".parse::<BoardGeometry>(), Ok(classic));
+    assert_eq!("11".parse::<BoardGeometry>(), Ok(BoardGeometry::new(11, 11)));
+    assert!("12x11".parse::<BoardGeometry>().is_err());
+    assert!("4294967296x4294967296".parse::<BoardGeometry>().is_err());
+
+    let state = BoardState::empty(classic);
+    assert_eq!(state.geometry(), classic);
+    assert_eq!(state.open_mask().raw_value().count_ones(), 100);
+
+    // A ship in the top-left corner of a rectangular board only has 4 symmetric variants
+    let wide = BoardGeometry::new(12, 8);
+    let mut variants = generate_symmetries_with_geometry(0b111, wide);
+    variants.sort_unstable();
+    variants.dedup();
+    assert_eq!(variants.len(), 4);
+    assert!(variants.contains(&(0b111 << (7 * 12 + 9))));
+
+    // Counts cover all 100 cells, including the last row
+    let records = || [0b111u128, 0b111 << 97].into_iter().map(Ok);
+    let options = QueryOptions { geometry: classic, ..QueryOptions::default() };
+    let (counts, matched) = filter_and_count_cells(records(), 1 << 99, 0, &options).unwrap();
+    assert_eq!((counts.len(), matched), (100, 1));
+    assert_eq!(counts[97..], [1, 1, 1]);
+
+    let error = filter_and_count_with_options(records(), 0, 0, &options).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_fleet_parses_length_count_pairs() {
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::generator::Fleet;
+
+    assert_eq!("3:5,4:3".parse::<Fleet>(), Ok(Fleet::standard()));
+    assert_eq!(Fleet::standard().to_string().parse::<Fleet>(), Ok(Fleet::standard()));
+    assert!("9:1".parse::<Fleet>().is_err());
+    assert!("1:1".parse::<Fleet>().is_err());
+    assert!("3:0".parse::<Fleet>().is_err());
+    assert!("3:1,3:18446744073709551615".parse::<Fleet>().is_err());
+
+    // Any supported lengths, in any order, merged by length
+    let mixed: Fleet = "5:1, 2:2, 3:1, 2:1".parse().unwrap();
+    assert_eq!(mixed, Fleet::new(&[(2, 3), (3, 1), (5, 1)]));
+    assert_eq!(mixed.to_string(), "2:3,3:1,5:1");
+    assert_eq!((mixed.ship_count(), mixed.cells(), mixed.count(2), mixed.count(4)), (5, 14, 3, 0));
+    assert!(mixed.check_fits(BoardGeometry::STANDARD).is_ok());
+    assert!(Fleet::new(&[(8, 1)]).check_fits(BoardGeometry::new(7, 3)).is_err());
+    assert!(Fleet::new(&[(2, 30)]).check_fits(BoardGeometry::new(7, 7)).is_err());
+}
+
+#[test]
+fn test_enumerate_boards_in_parallel_matches_sequential_order() {
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::generator::board_state::BoardState;
+    use battleship::generator::enumerate::{count_of_valid_endings, enumerate_boards_with_geometry, for_each_subtree_in_order, split_search};
+    use battleship::generator::point::{Direction, Point};
+    use battleship::generator::{enumerate_boards, Fleet};
+
+    // A small fleet keeps the full enumeration fast
+    let mut boards = Vec::new();
+    let count = enumerate_boards(&Fleet::new(&[(3, 1)]), |board| boards.push(board.hit_mask().raw_value()));
+    assert_eq!(count, 2 * 9 * 7, "A lone three-long ship fits 7 ways in each of 9 rows and 9 columns");
+    assert_eq!(boards.len(), count);
+    assert!(boards.iter().all(|board| board.count_ones() == 3));
+
+    // Every length is searched, and each ship counted once
+    assert_eq!(enumerate_boards(&Fleet::new(&[(2, 1)]), |_| {}), 2 * 9 * 8);
+    assert_eq!(enumerate_boards_with_geometry(BoardGeometry::new(6, 1), &Fleet::new(&[(5, 1)]), |_| {}), 2);
+
+    let classic = enumerate_boards_with_geometry(BoardGeometry::CLASSIC, &Fleet::new(&[(4, 1)]), |_| {});
+    assert_eq!(classic, 2 * 10 * 7);
+
+    // Start with a few ships placed so the remaining search is small
+    let mut start = BoardState::EMPTY;
+    assert!(start.place_ship(4, Point::new(0, 0), Direction::Horizontal));
+    assert!(start.place_ship(4, Point::new(5, 0), Direction::Horizontal));
+    assert!(start.place_ship(4, Point::new(0, 2), Direction::Vertical));
+    assert!(start.place_ship(3, Point::new(2, 2), Direction::Horizontal));
+    assert!(start.place_ship(3, Point::new(6, 2), Direction::Horizontal));
+
+    let mut sequential = Vec::new();
+    let sequential_count = count_of_valid_endings(&start, &mut |board| sequential.push(board.hit_mask().raw_value()));
+
+    let subtrees = split_search(&start, 32);
+    assert!(subtrees.len() >= 32);
+
+    let mut parallel = Vec::new();
+    let mut parallel_count = 0;
+    for_each_subtree_in_order(&subtrees, |root| {
+        let mut boards = Vec::new();
+        let count = count_of_valid_endings(root, &mut |board| boards.push(board.hit_mask().raw_value()));
+        (count, boards)
+    }, |(count, boards)| {
+        parallel_count += count;
+        parallel.extend(boards);
+        Ok::<_, ()>(())
+    }).unwrap();
+
+    assert!(sequential_count > 0);
+    assert_eq!(parallel_count, sequential_count);
+    assert_eq!(parallel, sequential);
+}
+
+#[test]
+fn test_ending_counter_matches_search() {
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::generator::board_state::BoardState;
+    use battleship::generator::enumerate::{count_of_valid_endings, split_search, EndingCounter};
+    use battleship::generator::Fleet;
+
+    let rectangular: BoardGeometry = "8x6".parse().unwrap();
+    for (geometry, fleet) in [(BoardGeometry::STANDARD, Fleet::new(&[(3, 2), (4, 1)])), (rectangular, Fleet::new(&[(3, 2), (4, 2)])), (BoardGeometry::CLASSIC, Fleet::new(&[(3, 1), (4, 1)]))] {
+        let start = BoardState::with_fleet(geometry, &fleet);
+        let mut counter = EndingCounter::new();
+        let total = count_of_valid_endings(&start, &mut |_| {}) as u64;
+        assert!(total > 0);
+        assert_eq!(counter.count(&start), total, "{} board with fleet {}", geometry, fleet);
+
+        // Subtrees counted by the same counter add up to the whole
+        let subtrees = split_search(&start, 64);
+        let sizes: Vec<u64> = subtrees.iter().map(|root| counter.count(root)).collect();
+        assert_eq!(sizes.iter().sum::<u64>(), total);
+        for (root, size) in subtrees.iter().zip(sizes).take(8) {
+            assert_eq!(count_of_valid_endings(root, &mut |_| {}) as u64, size);
+        }
+    }
+
+    assert_eq!(EndingCounter::new().count(&BoardState::with_fleet(BoardGeometry::STANDARD, &Fleet::standard())), 213_723_152);
+}
+
+#[test]
+fn test_canonical_search_matches_filtered_enumeration() {
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::generator::board_state::BoardState;
+    use battleship::generator::enumerate::{count_of_canonical_endings, enumerate_boards_with_geometry, split_search};
+    use battleship::generator::symmetries::is_canonical_with_geometry;
+    use battleship::generator::Fleet;
+
+    let rectangular: BoardGeometry = "8x6".parse().unwrap();
+    for (geometry, fleet) in [(BoardGeometry::STANDARD, Fleet::new(&[(3, 2), (4, 1)])), (rectangular, Fleet::new(&[(3, 2), (4, 1)])), (BoardGeometry::STANDARD, Fleet::new(&[(3, 3)]))] {
+        let mut expected = Vec::new();
+        enumerate_boards_with_geometry(geometry, &fleet, |state| {
+            let board = state.hit_mask().raw_value();
+            if is_canonical_with_geometry(board, geometry) {
+                expected.push(board);
+            }
+        });
+
+        // Split the search to check the pruning holds up when starting part way in
+        let mut found = Vec::new();
+        let mut count = 0;
+        for root in split_search(&BoardState::with_fleet(geometry, &fleet), 64) {
+            count += count_of_canonical_endings(&root, &mut |board| found.push(board));
+        }
+
+        assert!(!expected.is_empty());
+        assert_eq!(count, found.len());
+        expected.sort_unstable();
+        found.sort_unstable();
+        assert_eq!(found, expected, "{} board with fleet {}", geometry, fleet);
+    }
+}
+
+#[test]
+fn test_indexed_filter_matches_full_scan() {
+    use battleship::core::encoder::{encode_indexed, EncodeOptions};
+    use battleship::core::filter::filter_and_count_indexed;
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::core::header::DatasetHeader;
+    use battleship::core::random::SplitMix64;
+    use battleship::generator::Fleet;
+
+    let mut rng = SplitMix64::new(7);
+    let mut boards: Vec<u128> = (0..2_000).map(|_| (rng.next_u64() as u128 | ((rng.next_u64() as u128) << 64)) & ((1 << 81) - 1)).collect();
+    boards.sort_unstable(); // Sorted boards give chunks with tighter masks
+    let raw: Vec<u8> = boards.iter().flat_map(|b| b.to_le_bytes()).collect();
+
+    let dir = std::env::temp_dir().join(format!("battleship-indexed-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    for zstd_frames in [None, Some(1)] {
+        let (data_path, index_path) = (dir.join("boards.bin"), dir.join("boards.idx"));
+        let header = DatasetHeader::new(BoardGeometry::STANDARD, Fleet::standard(), true);
+        let options = EncodeOptions { chunk_size: 100, restart_delta: true, zstd_frames, header: Some(header) };
+
+        let mut encoded = Vec::new();
+        let index = encode_indexed(&mut raw.as_slice(), &mut encoded, &options).unwrap();
+        std::fs::write(&data_path, &encoded).unwrap();
+        index.save(&index_path).unwrap();
+
+        for (hit, miss) in [(0, 0), (1 << 80, 0), (1 << 80 | 1 << 79, 1), (0, 1 << 80 | 1 << 3)] {
+            let records = boards.iter().map(|&b| Ok(b));
+            let expected = filter_and_count(records, hit, miss).unwrap();
+            assert_eq!(filter_and_count_indexed(&data_path, &index_path, hit, miss).unwrap(), expected);
+        }
+
+        // An index for different data is rejected
+        std::fs::write(&data_path, &encoded[..encoded.len() - 1]).unwrap();
+        assert!(filter_and_count_indexed(&data_path, &index_path, 0, 0).is_err());
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_chunk_checksums_catch_corruption() {
+    use battleship::core::checksum::{xxh64, Xxh64};
+    use battleship::core::encoder::{encode_indexed, EncodeOptions};
+    use battleship::core::filter::{filter_and_accumulate_indexed, QueryOptions};
+    use battleship::core::accumulator::CellCounts;
+    use battleship::core::index::DatasetIndex;
+
+    // Reference values from the xxHash specification
+    assert_eq!(xxh64(b"", 0), 0xEF46_DB37_51D8_E999);
+    assert_eq!(xxh64(b"a", 0), 0xD24E_C4F1_A98C_6E5B);
+    assert_eq!(xxh64(b"abc", 0), 0x44BC_2CF5_AD77_0999);
+    let long: Vec<u8> = (0..1000u32).map(|i| (i * 7) as u8).collect();
+    let mut hasher = Xxh64::new(42);
+    for piece in long.chunks(13) {
+        hasher.update(piece);
+    }
+    assert_eq!(hasher.finish(), xxh64(&long, 42));
+
+    let boards: Vec<u128> = (0..300u128).map(|i| (i * 0x9E37_79B9) & ((1 << 81) - 1)).collect();
+    let raw: Vec<u8> = boards.iter().flat_map(|b| b.to_le_bytes()).collect();
+    let dir = std::env::temp_dir().join(format!("battleship-checksums-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("boards.bin");
+
+    for (restart_delta, zstd_frames) in [(true, Some(1)), (true, None), (false, None)] {
+        let options = EncodeOptions { chunk_size: 64, restart_delta, zstd_frames, ..EncodeOptions::default() };
+        let mut encoded = Vec::new();
+        let index = encode_indexed(&mut raw.as_slice(), &mut encoded, &options).unwrap();
+        for (i, chunk) in index.chunks.iter().enumerate() {
+            let range = index.chunk_range(i);
+            assert_eq!(chunk.checksum, Some(xxh64(&encoded[range.start as usize..range.end as usize], 0)));
+        }
+
+        let mut bytes = Vec::new();
+        index.write_to(&mut bytes).unwrap();
+        assert_eq!(DatasetIndex::read_from(&mut bytes.as_slice()).unwrap(), index);
+
+        let strict = QueryOptions { strict: true, ..QueryOptions::default() };
+        let count = |options: &QueryOptions| filter_and_accumulate_indexed(&path, &index, 0, 0, options, CellCounts::new);
+        std::fs::write(&path, &encoded).unwrap();
+        assert_eq!(count(&strict).unwrap().1, 300);
+
+        // A flipped byte in chunk 2 is reported by number in strict mode
+        encoded[index.chunk_range(2).start as usize + 5] ^= 0x10;
+        std::fs::write(&path, &encoded).unwrap();
+        let error = count(&strict).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("chunk 2 "), "{}", error);
+    }
+
+    // Indexes written before checksums existed still load, but can't be checked
+    let options = EncodeOptions { chunk_size: 64, restart_delta: true, ..EncodeOptions::default() };
+    let mut encoded = Vec::new();
+    let mut index = encode_indexed(&mut raw.as_slice(), &mut encoded, &options).unwrap();
+    index.chunks.iter_mut().for_each(|chunk| chunk.checksum = None);
+    let mut bytes = Vec::new();
+    index.write_to(&mut bytes).unwrap();
+    assert_eq!(u16::from_le_bytes([bytes[4], bytes[5]]), 1);
+    assert_eq!(DatasetIndex::read_from(&mut bytes.as_slice()).unwrap(), index);
+
+    std::fs::write(&path, &encoded).unwrap();
+    let strict = QueryOptions { strict: true, ..QueryOptions::default() };
+    assert!(filter_and_accumulate_indexed(&path, &index, 0, 0, &strict, CellCounts::new).is_err());
+    assert_eq!(filter_and_accumulate_indexed(&path, &index, 0, 0, &QueryOptions::default(), CellCounts::new).unwrap().1, 300);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_read_records_from_seeks_to_chunk() {
+    use battleship::core::encoder::{encode_indexed, EncodeOptions};
+    use battleship::core::reader::read_records_from;
+
+    let boards: Vec<u128> = (0..250u128).map(|i| i * 0x9e37_79b9 + (i << 70)).collect();
+    let raw: Vec<u8> = boards.iter().flat_map(|b| b.to_le_bytes()).collect();
+
+    let dir = std::env::temp_dir().join(format!("battleship-seek-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let data_path = dir.join("boards.bin");
+
+    let options = EncodeOptions { chunk_size: 64, restart_delta: true, zstd_frames: Some(1), ..EncodeOptions::default() };
+    let mut encoded = Vec::new();
+    let index = encode_indexed(&mut raw.as_slice(), &mut encoded, &options).unwrap();
+    std::fs::write(&data_path, &encoded).unwrap();
+
+    assert_eq!(index.locate(0), Some((0, 0)));
+    assert_eq!(index.locate(130), Some((2, 2)));
+    assert_eq!(index.locate(250), None);
+
+    for start in [0, 63, 64, 130, 249] {
+        let records: Vec<u128> = read_records_from(&data_path, &index, start).unwrap()
+            .take(70)
+            .collect::<std::io::Result<_>>()
+            .unwrap();
+        let end = (start as usize + 70).min(boards.len());
+        assert_eq!(records, boards[start as usize..end]);
+    }
+    assert_eq!(read_records_from(&data_path, &index, 250).unwrap().count(), 0);
+
+    // Chunks that share a delta chain can't be opened on their own
+    let shared = EncodeOptions { restart_delta: false, ..options };
+    let index = encode_indexed(&mut raw.as_slice(), &mut Vec::new(), &shared).unwrap();
+    assert!(read_records_from(&data_path, &index, 100).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_record_range() {
+    use battleship::core::reader::{record_range, DeltaDecodingReader};
+    use battleship::core::writer::DeltaEncodingWriter;
+
+    let boards: Vec<u128> = (0..100u128).map(|i| i * 0x9e37_79b9 + (i << 70)).collect();
+    let mut writer = DeltaEncodingWriter::new(Vec::new());
+    writer.write_all_records(boards.iter().copied()).unwrap();
+    let encoded = writer.into_inner().unwrap();
+
+    let range = |skip, limit| {
+        record_range(DeltaDecodingReader::new(encoded.as_slice()), skip, limit).collect::<std::io::Result<Vec<_>>>().unwrap()
+    };
+    assert_eq!(range(0, None), boards);
+    assert_eq!(range(10, Some(5)), boards[10..15]);
+    assert_eq!(range(95, Some(10)), boards[95..]);
+    assert!(range(0, Some(0)).is_empty());
+    assert!(range(100, None).is_empty());
+    assert!(range(500, Some(3)).is_empty());
+
+    // Errors in skipped records aren't swallowed
+    let records = vec![Ok(1), Err(std::io::Error::other("corrupt")), Ok(3), Ok(4)];
+    let mut ranged = record_range(records, 3, None);
+    assert!(ranged.next().unwrap().is_err());
+    assert!(ranged.next().is_none());
+}
+
+#[test]
+fn test_matching_boards_adds_up_to_scan_matches() {
+    use battleship::core::filter::{filter_and_count_with_options, matching_boards, PopcountRange, QueryOptions};
+
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let boards: Vec<u128> = (0..2000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state as u128 | (state.rotate_left(23) as u128) << 64) & ((1u128 << 81) - 1)
+        })
+        .collect();
+
+    for options in [
+        QueryOptions::default(),
+        QueryOptions { popcount: Some(PopcountRange { min: 35, max: 45 }), ..QueryOptions::default() },
+        QueryOptions { expand_symmetries: true, ..QueryOptions::default() },
+    ] {
+        let (hit_mask, miss_mask) = (1 << 40, 1 << 3);
+        let matched: u64 = boards.iter().map(|&board| matching_boards(board, hit_mask, miss_mask, &options)).sum();
+        let (_, expected) = filter_and_count_with_options(boards.iter().map(|&b| Ok(b)), hit_mask, miss_mask, &options).unwrap();
+        assert_eq!(matched, expected);
+    }
+}
+
+#[test]
+fn test_dataset_header_round_trip_and_detection() {
+    use battleship::core::encoder::{encode_chunks_with_options, EncodeOptions};
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::core::header::{read_optional_header, DatasetHeader, HEADER_LEN};
+    use battleship::core::reader::open_records;
+    use battleship::generator::Fleet;
+    use std::io::Read;
+
+    let mut header = DatasetHeader::new(BoardGeometry::CLASSIC, Fleet::new(&[(3, 4), (4, 2)]), false);
+    header.record_count = Some(3);
+    assert_eq!(DatasetHeader::from_bytes(&header.to_bytes()).unwrap(), header);
+    assert_eq!(header.to_string(), "format 1, 10x10 board, fleet 3:4,4:2, raw, 3 records");
+
+    // Headerless streams are passed through untouched
+    let (found, mut rest) = read_optional_header(&b"\x01\x02"[..]).unwrap();
+    let mut bytes = Vec::new();
+    rest.read_to_end(&mut bytes).unwrap();
+    assert_eq!((found, bytes), (None, vec![1, 2]));
+
+    let boards: Vec<u128> = vec![0b0111, 0b0110, 1 << 99];
+    let raw: Vec<u8> = boards.iter().flat_map(|b| b.to_le_bytes()).collect();
+
+    let dir = std::env::temp_dir().join(format!("battleship-header-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("boards.bin");
+
+    // Raw records with a header are read without delta decoding
+    let mut file = header.to_bytes().to_vec();
+    file.extend_from_slice(&raw);
+    std::fs::write(&path, &file).unwrap();
+    let (found, records) = open_records(&path).unwrap();
+    assert_eq!(found, Some(header.clone()));
+    assert_eq!(records.collect::<std::io::Result<Vec<_>>>().unwrap(), boards);
+
+    // The encoder marks its header as delta-encoded, and compression wraps only the records
+    let options = EncodeOptions { zstd_frames: Some(1), header: Some(header.clone()), ..EncodeOptions::default() };
+    let mut encoded = Vec::new();
+    encode_chunks_with_options(&mut raw.as_slice(), &mut encoded, &options).unwrap();
+    assert_eq!(&encoded[..4], b"BSDS");
+    std::fs::write(&path, &encoded).unwrap();
+    let (found, records) = open_records(&path).unwrap();
+    assert!(found.unwrap().delta_encoded);
+    assert_eq!(records.collect::<std::io::Result<Vec<_>>>().unwrap(), boards);
+
+    // Restarted chains decode sequentially when the header records the interval
+    let options = EncodeOptions { chunk_size: 2, restart_delta: true, ..options };
+    let mut encoded = Vec::new();
+    encode_chunks_with_options(&mut raw.as_slice(), &mut encoded, &options).unwrap();
+    std::fs::write(&path, &encoded).unwrap();
+    let (found, records) = open_records(&path).unwrap();
+    assert_eq!(found.unwrap().restart_interval, Some(2));
+    assert_eq!(records.collect::<std::io::Result<Vec<_>>>().unwrap(), boards);
+
+    // A header inside a compressed file is found too
+    std::fs::write(&path, zstd::encode_all(&encoded[..HEADER_LEN], 1).unwrap()).unwrap();
+    assert!(open_records(&path).unwrap().0.is_some());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_headerless_encoding_detection() {
+    use battleship::core::reader::{detect_encoding, open_records, RecordEncoding};
+    use battleship::core::writer::DeltaEncodingWriter;
+
+    // Boards with the same number of ship cells, like any real dataset
+    let boards: Vec<u128> = vec![0b0111, 0b1110 << 9, 0b1011 << 20, 0b0111 << 40];
+    let raw: Vec<u8> = boards.iter().flat_map(|b| b.to_le_bytes()).collect();
+    let mut writer = DeltaEncodingWriter::new(Vec::new());
+    writer.write_all_records(boards.iter().copied()).unwrap();
+    let delta = writer.into_inner().unwrap();
+
+    let records = |bytes: &[u8]| -> Vec<u128> {
+        bytes.chunks_exact(16).map(|chunk| u128::from_le_bytes(chunk.try_into().unwrap())).collect()
+    };
+    assert_eq!(detect_encoding(&records(&raw)), Some(RecordEncoding::Raw));
+    assert_eq!(detect_encoding(&records(&delta)), Some(RecordEncoding::Delta));
+    assert_eq!(detect_encoding(&records(&raw[..16])), None);
+
+    let dir = std::env::temp_dir().join(format!("battleship-detect-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("boards.bin");
+
+    for bytes in [&raw, &delta] {
+        std::fs::write(&path, bytes).unwrap();
+        let (header, reader) = open_records(&path).unwrap();
+        assert!(header.is_none());
+        assert_eq!(reader.collect::<std::io::Result<Vec<_>>>().unwrap(), boards);
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_compressed_inputs_are_detected() {
+    use battleship::core::reader::open_records;
+    use battleship::core::writer::DeltaEncodingWriter;
+
+    let boards: Vec<u128> = vec![0b0111, 0b1110 << 9, 0b1011 << 20];
+    let mut writer = DeltaEncodingWriter::new(Vec::new());
+    writer.write_all_records(boards.iter().copied()).unwrap();
+    let encoded = writer.into_inner().unwrap();
+
+    let dir = std::env::temp_dir().join(format!("battleship-compressed-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("boards.bin");
+    let read_all = |bytes: &[u8]| -> std::io::Result<Vec<u128>> {
+        std::fs::write(&path, bytes).unwrap();
+        open_records(&path)?.1.collect()
+    };
+
+    let gzip = [0x1F, 0x8B, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00];
+    #[cfg(not(feature = "gzip"))]
+    assert_eq!(read_all(&gzip).unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+
+    #[cfg(feature = "gzip")]
+    {
+        use std::io::Write;
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(&encoded).unwrap();
+        assert_eq!(read_all(&encoder.finish().unwrap()).unwrap(), boards);
+        assert!(read_all(&gzip).is_err());
+    }
+
+    #[cfg(feature = "xz")]
+    {
+        use std::io::Write;
+        let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 1);
+        encoder.write_all(&encoded).unwrap();
+        assert_eq!(read_all(&encoder.finish().unwrap()).unwrap(), boards);
+    }
+
+    #[cfg(feature = "lz4")]
+    {
+        use std::io::Write;
+        let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+        encoder.write_all(&encoded).unwrap();
+        assert_eq!(read_all(&encoder.finish().unwrap()).unwrap(), boards);
+    }
+
+    assert_eq!(read_all(&zstd::encode_all(encoded.as_slice(), 1).unwrap()).unwrap(), boards);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_whole_stream_compression_keeps_header_uncompressed() {
+    use battleship::core::encoder::{encode_indexed, zstd_writer, EncodeOptions, ZstdOptions};
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::core::header::{DatasetHeader, HEADER_LEN, HEADER_MAGIC};
+    use battleship::core::reader::open_records;
+    use battleship::generator::Fleet;
+
+    let boards: Vec<u128> = (0..300u128).map(|i| (0b111 << (i % 70)) | (0b1111 << 72)).collect();
+    let raw: Vec<u8> = boards.iter().flat_map(|b| b.to_le_bytes()).collect();
+
+    let header = DatasetHeader::new(BoardGeometry::STANDARD, Fleet::standard(), false);
+    let options = EncodeOptions { chunk_size: 64, restart_delta: true, header: Some(header), ..EncodeOptions::default() };
+
+    let mut output = Vec::new();
+    options.output_header().unwrap().write_to(&mut output).unwrap();
+    let mut encoder = zstd_writer(&mut output, &ZstdOptions::default()).unwrap();
+    encode_indexed(&mut raw.as_slice(), &mut encoder, &EncodeOptions { header: None, ..options }).unwrap();
+    encoder.finish().unwrap();
+
+    assert_eq!(output[..4], HEADER_MAGIC);
+    assert_eq!(output[HEADER_LEN..HEADER_LEN + 4], [0x28, 0xB5, 0x2F, 0xFD]);
+
+    let path = std::env::temp_dir().join(format!("battleship-compress-{}.bin", std::process::id()));
+    std::fs::write(&path, &output).unwrap();
+    let (header, records) = open_records(&path).unwrap();
+    assert_eq!(header.unwrap().restart_interval, Some(64));
+    assert_eq!(records.collect::<std::io::Result<Vec<_>>>().unwrap(), boards);
+    std::fs::remove_file(&path).unwrap();
+
+    let threaded = ZstdOptions { threads: 2, ..ZstdOptions::default() };
+    assert_eq!(zstd_writer(Vec::new(), &threaded).is_ok(), cfg!(feature = "zstdmt"));
+}
+
+#[test]
+fn test_parallel_frame_decompression_matches_sequential() {
+    use battleship::core::encoder::{encode_indexed, EncodeOptions};
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::core::header::{DatasetHeader, HEADER_LEN};
+    use battleship::core::reader::{open_records, open_records_with_threads, zstd_frame_ranges};
+    use battleship::generator::Fleet;
+
+    let boards: Vec<u128> = (0..1_000u128).map(|i| (0b111 << (i % 70)) | (0b1111 << (i % 50 + 72))).collect();
+    let raw: Vec<u8> = boards.iter().flat_map(|b| b.to_le_bytes()).collect();
+
+    let dir = std::env::temp_dir().join(format!("battleship-frames-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("boards.bin");
+
+    // Frames share one delta chain unless restarted; both must decode the same
+    for restart_delta in [false, true] {
+        let header = DatasetHeader::new(BoardGeometry::STANDARD, Fleet::standard(), false);
+        let options = EncodeOptions { chunk_size: 97, restart_delta, zstd_frames: Some(1), header: Some(header) };
+        let mut encoded = Vec::new();
+        let index = encode_indexed(&mut raw.as_slice(), &mut encoded, &options).unwrap();
+        std::fs::write(&path, &encoded).unwrap();
+
+        let frames = zstd_frame_ranges(&mut std::fs::File::open(&path).unwrap(), HEADER_LEN as u64).unwrap().unwrap();
+        let offsets: Vec<u64> = frames.iter().map(|frame| frame.start).collect();
+        assert_eq!(offsets, index.chunks.iter().map(|chunk| chunk.offset).collect::<Vec<_>>());
+        assert_eq!(frames.last().unwrap().end, encoded.len() as u64);
+
+        for threads in [1, 3, 16] {
+            let (_, records) = open_records_with_threads(&path, threads).unwrap();
+            assert_eq!(records.collect::<std::io::Result<Vec<_>>>().unwrap(), boards);
+        }
+
+        // Readers can stop early while frames are still being decompressed
+        let (_, records) = open_records_with_threads(&path, 3).unwrap();
+        assert_eq!(records.take(5).count(), 5);
+
+        // A corrupt frame is reported after the records of the frames before it, wherever it
+        // falls in a batch of 3 frames
+        for corrupt_frame in [3, 4, 5] {
+            // Garble the first block, leaving the frame and block headers intact so the frames
+            // are still found and decompressed in parallel. The encoder's frames start with the
+            // magic, a descriptor of 0 and a window size.
+            let mut corrupt = encoded.clone();
+            let block = frames[corrupt_frame].start as usize + 6;
+            assert_eq!(corrupt[block - 2], 0);
+            let size = u32::from_le_bytes([corrupt[block], corrupt[block + 1], corrupt[block + 2], 0]) >> 3;
+            corrupt[block + 3..block + 3 + size as usize].fill(0xFF);
+            std::fs::write(&path, &corrupt).unwrap();
+            assert_eq!(zstd_frame_ranges(&mut std::fs::File::open(&path).unwrap(), HEADER_LEN as u64).unwrap().as_ref(), Some(&frames));
+            let (_, records) = open_records_with_threads(&path, 3).unwrap();
+            let read: Vec<_> = records.take(boards.len()).collect();
+            assert!(read[..corrupt_frame * 97].iter().all(Result::is_ok), "frame {}", corrupt_frame);
+            assert!(read[corrupt_frame * 97].is_err());
+        }
+    }
+
+    // Uncompressed data isn't a sequence of frames
+    std::fs::write(&path, &raw).unwrap();
+    assert!(zstd_frame_ranges(&mut std::fs::File::open(&path).unwrap(), 0).unwrap().is_none());
+    let (_, records) = open_records(&path).unwrap();
+    assert_eq!(records.count(), boards.len());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_split_records_into_shards() {
+    use battleship::core::reader::DeltaDecodingReader;
+    use battleship::core::split::{split_records, SplitMode};
+
+    let boards: Vec<u128> = (0..10u128).map(|i| (i + 1) * 0x1_0001).collect();
+    let decode = |shard: &Vec<u8>| -> Vec<u128> {
+        DeltaDecodingReader::new(shard.as_slice()).collect::<std::io::Result<_>>().unwrap()
+    };
+
+    let mut shards = vec![Vec::new(); 3];
+    let counts = split_records(boards.iter().map(|&b| Ok(b)), &mut shards, SplitMode::RoundRobin).unwrap();
+    assert_eq!(counts, vec![4, 3, 3]);
+    assert_eq!(decode(&shards[1]), vec![boards[1], boards[4], boards[7]]);
+
+    let mut shards = vec![Vec::new(); 3];
+    let counts = split_records(boards.iter().map(|&b| Ok(b)), &mut shards, SplitMode::Ranges { total: 10 }).unwrap();
+    assert_eq!(counts, vec![4, 3, 3]);
+    let rejoined: Vec<u128> = shards.iter().flat_map(decode).collect();
+    assert_eq!(rejoined, boards);
+}
+
+#[test]
+fn test_merge_records_sorts_and_dedups() {
+    use battleship::core::encoder::{encode_records, EncodeOptions};
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::core::header::DatasetHeader;
+    use battleship::core::merge::{merge_headers, merge_records, MergeOptions};
+    use battleship::core::reader::DeltaDecodingReader;
+    use battleship::generator::symmetries::canonicalize;
+    use battleship::generator::Fleet;
+
+    let ok = |boards: &[u128]| boards.iter().map(|&b| Ok(b)).collect::<Vec<std::io::Result<u128>>>();
+    let corner = 0b111u128;
+    let mirrored = 0b111u128 << 6; // Top-right corner, a reflection of `corner`
+
+    let merged: Vec<u128> = merge_records(vec![ok(&[5, 1]), ok(&[3])], &MergeOptions::default()).unwrap()
+        .collect::<std::io::Result<_>>()
+        .unwrap();
+    assert_eq!(merged, vec![5, 1, 3]);
+
+    let dedup = MergeOptions { dedup: true, ..MergeOptions::default() };
+    let merged: Vec<u128> = merge_records(vec![ok(&[corner, 9]), ok(&[mirrored, 9])], &dedup).unwrap()
+        .collect::<std::io::Result<_>>()
+        .unwrap();
+    assert_eq!(merged, vec![corner, 9, mirrored]);
+
+    let canonical = MergeOptions { canonicalize: true, ..dedup };
+    let merged: Vec<u128> = merge_records(vec![ok(&[corner]), ok(&[mirrored])], &canonical).unwrap()
+        .collect::<std::io::Result<_>>()
+        .unwrap();
+    assert_eq!(merged, vec![canonicalize(corner)]);
+
+    // Records from an iterator encode the same as from raw bytes
+    let mut encoded = Vec::new();
+    let options = EncodeOptions { chunk_size: 2, ..EncodeOptions::default() };
+    let index = encode_records(ok(&[5, 1, 3]), &mut encoded, &options).unwrap();
+    assert_eq!(index.chunks.len(), 2);
+    let decoded: Vec<u128> = DeltaDecodingReader::new(encoded.as_slice()).collect::<std::io::Result<_>>().unwrap();
+    assert_eq!(decoded, vec![5, 1, 3]);
+
+    let standard = DatasetHeader::new(BoardGeometry::STANDARD, Fleet::standard(), true);
+    let classic = DatasetHeader::new(BoardGeometry::CLASSIC, Fleet::standard(), false);
+    assert_eq!(merge_headers(&[None, Some(standard.clone())]).unwrap(), Some(standard.clone()));
+    assert!(merge_headers(&[Some(standard.clone()), Some(classic)]).is_err());
+}
+
+#[test]
+fn test_sort_records_spills_sorted_runs() {
+    use battleship::core::random::SplitMix64;
+    use battleship::core::sort::sort_records;
+
+    let mut rng = SplitMix64::new(3);
+    let records: Vec<u128> = (0..1000).map(|_| rng.next_u64() as u128 | ((rng.next_u64() as u128) << 64)).collect();
+    let mut expected = records.clone();
+    expected.sort_unstable();
+
+    // In memory, spilled into uneven runs, and spilled into runs that exactly divide the input
+    for max_in_memory in [10_000, 64, 100] {
+        let mut output = Vec::new();
+        let count = sort_records(records.iter().map(|&r| Ok(r)), &mut output, max_in_memory, &std::env::temp_dir()).unwrap();
+        assert_eq!(count, 1000);
+        let sorted: Vec<u128> = output.chunks(16).map(|c| u128::from_le_bytes(c.try_into().unwrap())).collect();
+        assert_eq!(sorted, expected);
+    }
+
+    let mut output = Vec::new();
+    assert_eq!(sort_records(std::iter::empty(), &mut output, 10, &std::env::temp_dir()).unwrap(), 0);
+    assert!(output.is_empty());
+}
+
+#[test]
+fn test_find_duplicates_reports_exact_and_canonical() {
+    use battleship::core::duplicates::{find_duplicates, Duplicate};
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::generator::symmetries::canonicalize;
+
+    let corner = 0b111u128;
+    let mirrored = 0b111u128 << 6;
+    let records = [corner, 9, corner, mirrored, 9 << 20];
+
+    let report = find_duplicates(records.iter().map(|&b| Ok(b)), BoardGeometry::STANDARD, 1).unwrap();
+    assert_eq!(report.records, 5);
+    assert_eq!(report.exact.distinct, 4);
+    assert_eq!(report.exact.duplicates, 1);
+    assert_eq!(report.exact.examples, vec![Duplicate { board: corner, first: 0, repeat: 2 }]);
+    assert_eq!(report.exact.examples[0].repeat_offset(), 32);
+
+    assert_eq!(report.canonical.duplicates, 2);
+    assert_eq!(report.canonical.examples.len(), 1, "Examples are capped");
+    assert_eq!(report.canonical.examples[0].board, canonicalize(corner));
+    assert!(report.has_duplicates());
+
+    let unique = find_duplicates([1u128, 2, 4].into_iter().map(Ok), BoardGeometry::STANDARD, 10).unwrap();
+    assert!(!unique.has_duplicates());
+}
+
+#[test]
+fn test_verify_board_checks_fleet_and_spacing() {
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::core::reader::RawRecordReader;
+    use battleship::generator::enumerate_boards;
+    use battleship::generator::verify::{verify_board, InvalidBoard};
+    use battleship::generator::Fleet;
+
+    let geometry = BoardGeometry::STANDARD;
+    let fleet = Fleet::new(&[(3, 2), (4, 1)]);
+
+    let mut boards = Vec::new();
+    enumerate_boards(&fleet, |state| boards.push(state.hit_mask().raw_value()));
+    assert!(!boards.is_empty());
+    for &board in boards.iter().step_by(97) {
+        assert_eq!(verify_board(board, geometry, &fleet), Ok(()));
+    }
+
+    let row = |y: usize, x: usize, length: usize| ((1u128 << length) - 1) << (y * 9 + x);
+    let spaced = row(0, 0, 3) | row(2, 0, 3) | row(4, 0, 4);
+    assert_eq!(verify_board(spaced, geometry, &fleet), Ok(()));
+    assert_eq!(verify_board(spaced, geometry, &Fleet::new(&[(3, 1), (4, 1)])), Err(InvalidBoard::WrongFleet(fleet.clone())));
+
+    // Ships touching diagonally, ships of other lengths, one longer than any fleet has, and cells past the board
+    let diagonal = row(0, 0, 3) | row(1, 3, 3) | row(4, 0, 4);
+    assert_eq!(verify_board(diagonal, geometry, &fleet), Err(InvalidBoard::StrayCell(0)));
+    assert_eq!(verify_board(row(0, 0, 5), geometry, &fleet), Err(InvalidBoard::WrongFleet(Fleet::new(&[(5, 1)]))));
+    assert_eq!(verify_board(row(0, 0, 9), geometry, &fleet), Err(InvalidBoard::StrayCell(0)));
+    assert_eq!(verify_board(spaced | 1 << 81, geometry, &fleet), Err(InvalidBoard::OutsideBoard));
+
+    // A truncated record is an error rather than being dropped
+    let bytes = [spaced.to_le_bytes().as_slice(), &[0u8; 5]].concat();
+    let records: Vec<_> = RawRecordReader::new(bytes.as_slice()).collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[1].as_ref().unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_read_records_from_memory() {
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::core::header::DatasetHeader;
+    use battleship::core::reader::read_records;
+    use battleship::core::writer::DeltaEncodingWriter;
+    use battleship::generator::Fleet;
+
+    let boards: Vec<u128> = vec![0b0111, 0b1110 << 9, 0b1011 << 20];
+    let mut encoded = Vec::new();
+    DatasetHeader::new(BoardGeometry::STANDARD, Fleet::standard(), true).write_to(&mut encoded).unwrap();
+    let mut writer = DeltaEncodingWriter::new(encoded);
+    writer.write_all_records(boards.iter().copied()).unwrap();
+    let encoded = writer.into_inner().unwrap();
+
+    let (header, records) = read_records(std::io::Cursor::new(encoded.clone())).unwrap();
+    assert_eq!(header.unwrap().geometry, BoardGeometry::STANDARD);
+    assert_eq!(records.collect::<std::io::Result<Vec<_>>>().unwrap(), boards);
+
+    // Compressed buffers are decompressed like files
+    let compressed = zstd::encode_all(encoded.as_slice(), 3).unwrap();
+    let (header, records) = read_records(std::io::Cursor::new(compressed)).unwrap();
+    assert!(header.is_some());
+    assert_eq!(records.collect::<std::io::Result<Vec<_>>>().unwrap(), boards);
+}
+
+#[test]
+fn test_filter_and_count_batch_matches_individual_queries() {
+    use battleship::core::filter::{filter_and_count, filter_and_count_batch};
+    use battleship::generator::{enumerate_boards, Fleet};
+
+    let mut boards = Vec::new();
+    enumerate_boards(&Fleet::new(&[(3, 2), (4, 1)]), |state| boards.push(state.hit_mask().raw_value()));
+
+    let queries = [(0, 0), (1 << 40, 0), (1 << 40, 1 << 41), (1 << 0 | 1 << 1, 1 << 9), (1 << 80, 1 << 79)];
+    let batch = filter_and_count_batch(boards.iter().copied().map(Ok), &queries).unwrap();
+
+    assert_eq!(batch.len(), queries.len());
+    for (&(hit, miss), result) in queries.iter().zip(&batch) {
+        assert_eq!(*result, filter_and_count(boards.iter().copied().map(Ok), hit, miss).unwrap());
+    }
+    assert_eq!(batch[0].1, boards.len() as u64);
+
+    assert!(filter_and_count_batch(boards.iter().copied().map(Ok), &[]).unwrap().is_empty());
+}
+
+#[test]
+fn test_cell_probabilities_masks_shot_and_impossible_cells() {
+    use battleship::core::dataset::Dataset;
+    use battleship::generator::point::Point;
+    use battleship::generator::{enumerate_boards, Fleet};
+    use battleship::solver::cell_probabilities;
+    use battleship::solver::game_state::{GameState, ShotResult};
+
+    let mut boards = Vec::new();
+    enumerate_boards(&Fleet::new(&[(3, 2), (4, 1)]), |state| boards.push(state.hit_mask().raw_value()));
+    let dataset = Dataset::new(boards);
+
+    let mut game = GameState::new();
+    game.record_shot(Point::new(4, 4), ShotResult::Hit).unwrap();
+    game.record_shot(Point::new(0, 0), ShotResult::Miss).unwrap();
+
+    let probabilities = cell_probabilities(&dataset, &game);
+    let (hits, misses) = game.to_masks();
+    let expected = dataset.filter_and_count(hits, misses).probabilities();
+
+    for (index, &probability) in probabilities.iter().enumerate() {
+        if index == 40 || index == 0 {
+            assert_eq!(probability, 0.0);
+        } else {
+            assert_eq!(probability, expected[index]);
+        }
+    }
+    // Diagonal neighbours of a hit can't hold a ship
+    assert_eq!(probabilities[30], 0.0);
+    assert!(probabilities[41] > 0.0);
+
+    // No board is consistent with two hits touching diagonally
+    game.record_shot(Point::new(5, 5), ShotResult::Hit).unwrap();
+    assert!(cell_probabilities(&dataset, &game).iter().all(|&probability| probability == 0.0));
+}
+
+#[test]
+fn test_symmetry_classes_count_orbit_sizes() {
+    use battleship::core::accumulator::SymmetryClasses;
+    use battleship::core::filter::{filter_and_accumulate, QueryOptions};
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::generator::symmetries::is_canonical;
+    use battleship::generator::{enumerate_boards, Fleet};
+
+    let mut boards = Vec::new();
+    enumerate_boards(&Fleet::new(&[(3, 1), (4, 1)]), |state| boards.push(state.hit_mask().raw_value()));
+    let canonical: Vec<u128> = boards.iter().copied().filter(|&board| is_canonical(board)).collect();
+
+    let options = QueryOptions::default();
+    let classes = || SymmetryClasses::new(BoardGeometry::STANDARD);
+    let all = filter_and_accumulate(boards.iter().copied().map(Ok), 0, 0, &options, classes).unwrap();
+    let reduced = filter_and_accumulate(canonical.iter().copied().map(Ok), 0, 0, &options, classes).unwrap();
+
+    assert_eq!(all.records, boards.len() as u64);
+    assert_eq!(all.orbit_sizes.iter().sum::<u64>(), all.records);
+    assert!(!all.all_canonical());
+    assert!(reduced.all_canonical());
+
+    // Expanding the canonical boards gives back the full enumeration
+    assert_eq!(all.canonical, canonical.len() as u64);
+    assert_eq!(reduced.expanded_boards, boards.len() as u64);
+    assert_eq!(all.expanded_boards, boards.len() as u64);
+    for size in [1, 2, 4, 8] {
+        assert_eq!(reduced.orbit_sizes[size] * size as u64, all.orbit_sizes[size]);
+    }
+}
+
+#[test]
+fn test_board_history_undo_redo() {
+    use battleship::generator::board_history::{BoardEdit, BoardHistory};
+    use battleship::generator::board_state::{BoardState, CellState};
+    use battleship::generator::point::{Direction, Point};
+
+    let mut history = BoardHistory::new(BoardState::EMPTY);
+    assert!(!history.can_undo() && !history.can_redo());
+
+    assert!(history.place_ship(3, Point::new(0, 0), Direction::Horizontal));
+    let after_ship = *history.state();
+    history.set(Point::new(8, 8), CellState::Miss);
+
+    // A ship touching the first one doesn't fit and isn't recorded
+    assert!(!history.place_ship(4, Point::new(0, 1), Direction::Horizontal));
+    assert_eq!(history.edits().count(), 2);
+
+    assert_eq!(history.undo(), Some(BoardEdit::Mark { point: Point::new(8, 8), state: CellState::Miss }));
+    assert_eq!(*history.state(), after_ship);
+    assert!(matches!(history.undo(), Some(BoardEdit::PlaceShip { length: 3, .. })));
+    assert_eq!(*history.state(), BoardState::EMPTY);
+    assert_eq!(history.undo(), None);
+
+    assert!(history.redo().is_some());
+    assert_eq!(*history.state(), after_ship);
+    assert_eq!(history.state().remaining(3), 4);
+
+    // A new edit discards what was undone
+    history.set(Point::new(4, 4), CellState::Hit);
+    assert!(!history.can_redo());
+    assert_eq!(history.redo(), None);
+    assert_eq!(history.state().get(Point::new(4, 4)), CellState::Hit);
+}
+
+#[test]
+fn test_decompose_ships() {
+    use battleship::analysis::{decompose_ships, decompose_ships_with_geometry, Ship};
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::generator::board_mask::BoardMask;
+    use battleship::generator::point::{Direction, Point};
+
+    let board = BoardMask::from_coordinates("A1,A2,A3,C9,D9,E9,F9,I5").unwrap().raw_value();
+    let ships = decompose_ships(board);
+    assert_eq!(ships.len(), 3);
+    assert_eq!(ships[0], Ship { start: Point::new(0, 0), length: 3, direction: Some(Direction::Horizontal), cells: 0b111 });
+    assert_eq!((ships[1].start, ships[1].length, ships[1].direction), (Point::new(8, 2), 4, Some(Direction::Vertical)));
+    assert_eq!((ships[2].length, ships[2].direction), (1, None));
+    assert!(ships.iter().all(Ship::is_straight));
+    assert_eq!(ships.iter().fold(0, |cells, ship| cells | ship.cells), board);
+
+    // Cells at the end of one row and the start of the next aren't neighbours
+    assert_eq!(decompose_ships(BoardMask::from_coordinates("A9,B1").unwrap().raw_value()).len(), 2);
+
+    // Ships touching diagonally or bending form one group that isn't straight
+    let touching = decompose_ships(BoardMask::from_coordinates("A1,A2,B3,B4").unwrap().raw_value());
+    assert_eq!(touching.len(), 1);
+    assert_eq!((touching[0].length, touching[0].is_straight()), (4, false));
+
+    let classic = BoardGeometry::CLASSIC;
+    let column = BoardMask::column_with_geometry(9, classic).raw_value();
+    let ships = decompose_ships_with_geometry(column, classic);
+    assert_eq!((ships.len(), ships[0].length, ships[0].direction), (1, 10, Some(Direction::Vertical)));
+    assert!(decompose_ships(0).is_empty());
+}
+
+#[test]
+fn test_is_valid_board_accepts_generated_boards_only() {
+    use battleship::analysis::{is_valid_board, is_valid_board_with_geometry};
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::core::random::SplitMix64;
+    use battleship::generator::enumerate::enumerate_boards_with_geometry;
+    use battleship::generator::Fleet;
+
+    let fleet = Fleet::new(&[(2, 1), (3, 2), (4, 1)]);
+    let small = BoardGeometry::new(6, 6);
+    let mut boards = Vec::new();
+    enumerate_boards_with_geometry(small, &fleet, |state| boards.push(state.hit_mask().raw_value()));
+    assert!(!boards.is_empty());
+
+    // Every generated board is valid, and flipping any one cell of it never is
+    let mut random = SplitMix64::new(7);
+    for &board in boards.iter().step_by(7) {
+        assert!(is_valid_board_with_geometry(board, small, &fleet));
+        assert!(!is_valid_board_with_geometry(board, small, &Fleet::standard()));
+
+        let cell = random.next_u64() % 36;
+        assert!(!is_valid_board_with_geometry(board ^ 1 << cell, small, &fleet), "{:#x} with cell {} flipped", board, cell);
+    }
+
+    let spaced = 0b111 | 0b111 << 18 | 0b1111 << 36;
+    assert!(is_valid_board(spaced, &Fleet::new(&[(3, 2), (4, 1)])));
+
+    let classic = BoardGeometry::CLASSIC;
+    let carrier = 0b11111u128 | 0b11 << 30;
+    assert!(is_valid_board_with_geometry(carrier, classic, &Fleet::new(&[(2, 1), (5, 1)])));
+    assert!(!is_valid_board_with_geometry(carrier, classic, &Fleet::new(&[(2, 2)])));
+}
+
+#[test]
+fn test_cooccurrence_counts_pairs_of_ship_cells() {
+    use battleship::analysis::{cooccurrence, cooccurrence_parallel};
+    use battleship::core::random::SplitMix64;
+
+    let mut random = SplitMix64::new(3);
+    let boards: Vec<u128> = (0..5000)
+        .map(|_| ((random.next_u64() as u128) << 64 | random.next_u64() as u128) & ((1u128 << 81) - 1))
+        .collect();
+
+    let matrix = cooccurrence(boards.iter().map(|&b| Ok(b))).unwrap();
+    assert_eq!(cooccurrence_parallel(boards.iter().map(|&b| Ok(b))).unwrap(), matrix);
+
+    for (a, b) in [(0, 0), (0, 1), (40, 41), (80, 3), (17, 62)] {
+        let both = (1u128 << a) | (1u128 << b);
+        let expected = boards.iter().filter(|&&board| board & both == both).count() as u64;
+        assert_eq!(matrix[a][b], expected);
+        assert_eq!(matrix[b][a], expected);
+    }
+
+    let small = [0b011u128, 0b110, 0b111];
+    let matrix = cooccurrence(small.iter().map(|&b| Ok(b))).unwrap();
+    assert_eq!((matrix[0][0], matrix[0][1], matrix[0][2], matrix[1][1], matrix[3][3]), (2, 2, 1, 3, 0));
+    assert!(cooccurrence([Ok(1), Err(std::io::Error::other("truncated"))]).is_err());
+}
+
+#[test]
+fn test_board_state_render_options() {
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::generator::board_state::{BoardState, CellState, RenderOptions};
+    use battleship::generator::point::Point;
+
+    let mut board = BoardState::empty(BoardGeometry::new(3, 2));
+    board.set(Point::new(0, 0), CellState::Hit);
+    board.set(Point::new(2, 1), CellState::Miss);
+
+    assert_eq!(board.render(RenderOptions::default()), board.debug_description());
+    assert_eq!(board.debug_description(), "┌───────┐\n│ X     │\n│     • │\n└───────┘");
+
+    let compact = RenderOptions { compact: true, ..RenderOptions::default() };
+    assert_eq!(board.render(compact), "X··\n··•");
+
+    let labeled = RenderOptions { labels: true, compact: true, ..RenderOptions::default() };
+    assert_eq!(board.render(labeled), "  123\nA X··\nB ··•");
+
+    let wide = RenderOptions { labels: true, ..RenderOptions::default() };
+    assert_eq!(board.render(wide), "    1 2 3\n  ┌───────┐\nA │ X     │\nB │     • │\n  └───────┘");
+
+    let colored = board.render(RenderOptions { color: true, ..compact });
+    assert!(colored.contains("\x1b[1;31mX\x1b[0m"));
+    assert_eq!(colored.replace("\x1b[0m", "").matches('\x1b').count(), 6);
+}
+
+#[test]
+fn test_board_state_from_ascii_round_trips_renders() {
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::generator::board_state::{BoardState, CellState, RenderOptions};
+    use battleship::generator::point::Point;
+
+    let board = BoardState::from_ascii("
+        ┌───────────┐
+        │ X X X     │
+        │       •   │
+        │ X       X │
+        └───────────┘
+    ").unwrap();
+    assert_eq!(board.geometry(), BoardGeometry::new(5, 3));
+    assert_eq!(board.hit_mask().count(), 5);
+    assert_eq!(board.get(Point::new(3, 1)), CellState::Miss);
+    assert_eq!(board.get(Point::new(4, 0)), CellState::Open);
+
+    for labels in [false, true] {
+        for compact in [false, true] {
+            let options = RenderOptions { labels, compact, ..RenderOptions::default() };
+            assert_eq!(BoardState::from_ascii(&board.render(options)), Ok(board));
+        }
+    }
+
+    assert_eq!(BoardState::from_ascii("X..\n.•.\n...").unwrap().geometry(), BoardGeometry::new(3, 3));
+    assert!(BoardState::from_ascii("X..\n..").is_err());
+    assert!(BoardState::from_ascii("X?.").is_err());
+    assert!(BoardState::from_ascii("").is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_round_trips_through_json() {
+    use battleship::core::filter::FilterResult;
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::core::query::Query;
+    use battleship::generator::board_history::BoardHistory;
+    use battleship::generator::board_state::BoardState;
+    use battleship::generator::point::{Direction, Point};
+    use battleship::solver::game_state::{GameState, ShotResult};
+
+    let mut history = BoardHistory::new(BoardState::empty(BoardGeometry::CLASSIC));
+    history.place_ship(4, Point::new(2, 3), Direction::Vertical);
+    let json = serde_json::to_string(&history).unwrap();
+    assert!(json.contains("\"10x10\""));
+    assert_eq!(serde_json::from_str::<BoardHistory>(&json).unwrap(), history);
+
+    let mut counts = [0u32; 81];
+    counts[80] = 7;
+    let result = FilterResult::new(counts, 12);
+    let json = serde_json::to_string(&result).unwrap();
+    assert_eq!(serde_json::from_str::<FilterResult>(&json).unwrap(), result);
+    assert!(serde_json::from_str::<FilterResult>(r#"{"counts":[1,2,3],"matched":1}"#).is_err());
+
+    // Queries and games are validated when deserialized
+    let query = Query::compile(1 << 40, 1 << 41, BoardGeometry::STANDARD).unwrap();
+    assert_eq!(serde_json::from_str::<Query>(&serde_json::to_string(&query).unwrap()).unwrap(), query);
+    assert!(serde_json::from_str::<Query>(r#"{"hit_mask":1,"miss_mask":1,"geometry":"9x9"}"#).is_err());
+    assert!(serde_json::from_str::<Query>(r#"{"hit_mask":1,"miss_mask":0,"geometry":"20x20"}"#).is_err());
+
+    let mut game = GameState::new();
+    game.record_shot(Point::new(1, 1), ShotResult::Hit).unwrap();
+    game.record_shot(Point::new(5, 5), ShotResult::Miss).unwrap();
+    let json = serde_json::to_string(&game).unwrap();
+    assert_eq!(serde_json::from_str::<GameState>(&json).unwrap(), game);
+
+    let repeated = json.replace("\"x\":5,\"y\":5", "\"x\":1,\"y\":1");
+    assert!(serde_json::from_str::<GameState>(&repeated).is_err());
+}
+
+#[test]
+fn test_board_mask_points() {
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::generator::board_mask::BoardMask;
+    use battleship::generator::point::Point;
+
+    let points = [Point::new(0, 0), Point::new(8, 0), Point::new(4, 4), Point::new(8, 8)];
+    let mask = BoardMask::from_points(points.iter().rev().copied());
+    assert_eq!(mask.raw_value(), 1 | 1 << 8 | 1 << 40 | 1 << 80);
+    assert_eq!(mask.iter_points().collect::<Vec<_>>(), points);
+
+    assert_eq!(BoardMask::EMPTY.iter_points().count(), 0);
+    assert_eq!(BoardMask::full(BoardGeometry::CLASSIC).iter_points().count(), 100);
+
+    let classic = BoardMask::from_points_with_geometry([Point::new(9, 9)], BoardGeometry::CLASSIC);
+    assert_eq!(classic.iter_points().collect::<Vec<_>>(), [Point::new(9, 9)]);
+}
+
+#[test]
+fn test_board_mask_set_algebra() {
+    use battleship::generator::board_mask::BoardMask;
+
+    let a = BoardMask::new(0b0111);
+    let b = BoardMask::new(0b0110);
+    let c = BoardMask::new(0b1000);
+
+    assert_eq!(a.count(), 3);
+    assert!(BoardMask::EMPTY.is_empty() && !a.is_empty());
+    assert!(a.intersects(b) && !a.intersects(c));
+    assert!(a.contains_all(b) && !b.contains_all(a) && a.contains_all(BoardMask::EMPTY));
+    assert_eq!((a ^ c).raw_value(), 0b1111);
+    assert_eq!(a ^ a, BoardMask::EMPTY);
+
+    let mut mask = a;
+    mask &= b;
+    assert_eq!(mask, b);
+    mask |= c;
+    assert_eq!(mask.raw_value(), 0b1110);
+}
+
+#[test]
+fn test_board_mask_rows_columns_and_rects() {
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::generator::board_mask::BoardMask;
+    use battleship::generator::point::Point;
+
+    assert_eq!(BoardMask::row(0).raw_value(), 0x1FF);
+    assert_eq!(BoardMask::row(8).raw_value(), 0x1FF << 72);
+    assert_eq!(BoardMask::column(0).iter_points().map(|p| (p.x, p.y)).collect::<Vec<_>>(), (0..9).map(|y| (0, y)).collect::<Vec<_>>());
+    assert_eq!(BoardMask::row_with_geometry(9, BoardGeometry::CLASSIC).count(), 10);
+    assert_eq!(BoardMask::row_with_geometry(0, BoardGeometry::new(128, 1)).raw_value(), u128::MAX);
+    assert_eq!(BoardMask::column_with_geometry(9, BoardGeometry::CLASSIC).count(), 10);
+
+    let rect = BoardMask::rect(Point::new(3, 2), Point::new(1, 1));
+    assert_eq!(rect.count(), 6);
+    assert!(rect.contains_all(BoardMask::from_points([Point::new(1, 1), Point::new(3, 2)])));
+
+    // Rectangles are clipped to the board
+    assert_eq!(BoardMask::rect(Point::new(-1, -1), Point::new(1, 1)).count(), 4);
+    assert_eq!(BoardMask::rect(Point::new(-5, -5), Point::new(20, 20)), BoardMask::FULL);
+
+    // Every row and every column together cover the board
+    let rows = (0..9).fold(BoardMask::EMPTY, |mask, y| mask | BoardMask::row(y));
+    let columns = (0..9).fold(BoardMask::EMPTY, |mask, x| mask | BoardMask::column(x));
+    assert_eq!(rows, BoardMask::FULL);
+    assert_eq!(columns, BoardMask::FULL);
+}
+
+#[test]
+fn test_coordinate_parsing() {
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::generator::board_mask::BoardMask;
+    use battleship::generator::point::Point;
+
+    assert_eq!(Point::from_coordinate("B5"), Ok(Point::new(4, 1)));
+    assert_eq!(Point::from_coordinate("a1"), Ok(Point::new(0, 0)));
+    assert_eq!("J10".parse::<Point>(), Ok(Point::new(9, 9)));
+    for invalid in ["", "B", "5B", "B0", "B-1", "BB5", "B5x"] {
+        assert!(Point::from_coordinate(invalid).is_err(), "{} should not parse", invalid);
+    }
+
+    assert_eq!(Point::new(4, 1).to_string(), "B5");
+    assert_eq!(Point::new(-1, 0).to_string(), "(-1, 0)");
+    for index in 0..81 {
+        let point = BoardMask::point_of(index);
+        assert_eq!(point.to_string().parse::<Point>(), Ok(point));
+    }
+
+    let mask = BoardMask::from_coordinates("A3 b3,C3 ,  I9").unwrap();
+    assert_eq!(mask.raw_value(), 1 << 2 | 1 << 11 | 1 << 20 | 1 << 80);
+    assert_eq!(BoardMask::from_coordinates("").unwrap(), BoardMask::EMPTY);
+    assert!(BoardMask::from_coordinates("A3 J1").is_err());
+    assert!(BoardMask::from_coordinates("A3 A10").is_err());
+    assert!(BoardMask::from_coordinates_with_geometry("J10", BoardGeometry::CLASSIC).is_ok());
+}
+
+#[test]
+fn test_compression_and_encoding_are_reported() {
+    use battleship::core::reader::{detect_compression, read_records, RecordEncoding};
+    use battleship::core::writer::DeltaEncodingWriter;
+    use std::io::Cursor;
+
+    assert_eq!(detect_compression(&[0x28, 0xB5, 0x2F, 0xFD, 0, 0]), Some("zstd"));
+    assert_eq!(detect_compression(&[0x1F, 0x8B, 8]), Some("gzip"));
+    assert_eq!(detect_compression(&[0x07, 0, 0, 0]), None);
+
+    let boards: Vec<u128> = vec![0b0111, 0b1110 << 9, 0b1011 << 20, 0b0111 << 40];
+    let raw: Vec<u8> = boards.iter().flat_map(|b| b.to_le_bytes()).collect();
+    let mut writer = DeltaEncodingWriter::new(Vec::new());
+    writer.write_all_records(boards.iter().copied()).unwrap();
+    let delta = writer.into_inner().unwrap();
+
+    let (_, records) = read_records(Cursor::new(raw)).unwrap();
+    assert_eq!(records.encoding(), RecordEncoding::Raw);
+    let (_, records) = read_records(Cursor::new(delta)).unwrap();
+    assert_eq!(records.encoding(), RecordEncoding::Delta);
+}
+
+#[test]
+fn test_sample_matching() {
+    use battleship::core::filter::sample_matching;
+
+    let boards: Vec<u128> = (0..1000u128).map(|i| (i << 2) | 1).collect();
+    let records = || boards.iter().map(|&b| Ok(b));
+
+    let sample = sample_matching(records(), 0b100, 0, 10, 7).unwrap();
+    assert_eq!(sample.len(), 10);
+    assert!(sample.iter().all(|&b| b & 0b100 != 0 && boards.contains(&b)));
+    assert!(sample.windows(2).all(|pair| pair[0] < pair[1]), "sample keeps dataset order");
+    assert_eq!(sample, sample_matching(records(), 0b100, 0, 10, 7).unwrap());
+    assert_ne!(sample, sample_matching(records(), 0b100, 0, 10, 8).unwrap());
+
+    // Fewer matches than requested returns all of them
+    let all = sample_matching(records(), 0b1_1000, 0, 1000, 0).unwrap();
+    assert_eq!(all.len(), 250);
+
+    // Every matching board is equally likely to be picked
+    let mut picks = vec![0u32; 4];
+    for seed in 0..4000 {
+        for board in sample_matching(boards[..4].iter().map(|&b| Ok(b)), 0, 0, 1, seed).unwrap() {
+            picks[(board >> 2) as usize] += 1;
+        }
+    }
+    assert!(picks.iter().all(|&count| (800..1200).contains(&count)), "{:?}", picks);
+}
+
+#[test]
+fn test_for_each_matching() {
+    use battleship::core::filter::{filter_and_count, for_each_matching, for_each_matching_with_options, PopcountRange, QueryOptions};
+
+    let boards: Vec<u128> = (0..2000u128).map(|i| (i * 0x9E37_79B9) & ((1 << 81) - 1)).collect();
+    let records = || boards.iter().map(|&b| Ok(b));
+
+    // A custom reduction over the visited boards agrees with the built-in counts
+    let mut counts = [0u32; 81];
+    let mut pairs = 0u64;
+    let matched = for_each_matching(records(), 0b10, 0b1, |board| {
+        for (cell, count) in counts.iter_mut().enumerate() {
+            *count += (board >> cell & 1) as u32;
+        }
+        pairs += (board & 0b1100 == 0b1100) as u64;
+    }).unwrap();
+
+    assert_eq!((counts, matched), filter_and_count(records(), 0b10, 0b1).unwrap());
+    assert_eq!(pairs, boards.iter().filter(|&&b| b & 0b1111 == 0b1110).count() as u64);
+
+    let mut visited = Vec::new();
+    let options = QueryOptions { popcount: Some(PopcountRange { min: 0, max: 12 }), ..QueryOptions::default() };
+    for_each_matching_with_options(records(), 0, 0, &options, |board| visited.push(board)).unwrap();
+    let expected: Vec<u128> = boards.iter().copied().filter(|b| b.count_ones() <= 12).collect();
+    assert_eq!(visited, expected);
+}
+
+#[test]
+fn test_filter_and_write() {
+    use battleship::core::encoder::EncodeOptions;
+    use battleship::core::filter::{filter_and_count, filter_and_write, QueryOptions};
+    use battleship::core::reader::read_records;
+    use std::io::Cursor;
+
+    let boards: Vec<u128> = (0..500u128).map(|i| (i * 0x9E37) & ((1 << 81) - 1)).collect();
+    let records = || boards.iter().map(|&b| Ok(b));
+    let (hit, miss) = (1 << 3, 1 << 5);
+
+    let options = EncodeOptions { chunk_size: 64, zstd_frames: Some(3), ..EncodeOptions::default() };
+    let mut output = Vec::new();
+    let index = filter_and_write(records(), hit, miss, &QueryOptions::default(), &mut output, &options).unwrap();
+
+    let expected: Vec<u128> = boards.iter().copied().filter(|b| b & hit == hit && b & miss == 0).collect();
+    assert_eq!(index.record_count(), expected.len() as u64);
+
+    let (_, written) = read_records(Cursor::new(output.clone())).unwrap();
+    assert_eq!(written.collect::<std::io::Result<Vec<_>>>().unwrap(), expected);
+
+    // Counting from the narrowed dataset gives the same answer as the original
+    let (_, written) = read_records(Cursor::new(output)).unwrap();
+    assert_eq!(filter_and_count(written, hit, miss).unwrap(), filter_and_count(records(), hit, miss).unwrap());
+}
+
+#[test]
+fn test_strategies() {
+    use battleship::core::dataset::Dataset;
+    use battleship::generator::point::Point;
+    use battleship::solver::game_state::{GameState, ShotResult};
+    use battleship::solver::strategy::{Entropy, HuntTarget, MaxProbability, Random, Strategy};
+
+    let cells = |indices: &[u32]| indices.iter().fold(0u128, |mask, &i| mask | (1 << i));
+    let dataset = Dataset::new(vec![
+        cells(&[0, 1, 2]),
+        cells(&[0, 9, 18]),
+        cells(&[0, 10, 20]),
+        cells(&[0, 50, 51]),
+        cells(&[50, 60, 70]),
+    ]);
+    let empty = GameState::new();
+
+    // A1 is most likely (4 of 5); F6 (2 of 5) is the least predictable
+    assert_eq!(MaxProbability::new(&dataset).next_shot(&empty), Some(Point::new(0, 0)));
+    assert_eq!(Entropy::new(&dataset).next_shot(&empty), Some(Point::new(5, 5)));
+
+    // Random fires at every cell once, then runs out
+    let mut random = Random::new(1);
+    let mut game = GameState::new();
+    while let Some(point) = random.next_shot(&game) {
+        game.record_shot(point, ShotResult::Miss).unwrap();
+    }
+    assert_eq!(game.shots().len(), 81);
+
+    // Hunting stays on the lattice; a hit switches to targeting its neighbors
+    let mut hunter = HuntTarget::new(1);
+    let point = hunter.next_shot(&empty).unwrap();
+    assert_eq!((point.x + point.y) % 3, 0);
+
+    let mut game = GameState::new();
+    game.record_shot(Point::new(4, 4), ShotResult::Hit).unwrap();
+    let next = hunter.next_shot(&game).unwrap();
+    assert_eq!((next.x - 4).abs() + (next.y - 4).abs(), 1);
+
+    // A line of hits is extended along its direction
+    game.record_shot(Point::new(5, 4), ShotResult::Hit).unwrap();
+    assert_eq!(hunter.next_shot(&game), Some(Point::new(3, 4)));
+    game.record_shot(Point::new(3, 4), ShotResult::Miss).unwrap();
+    assert_eq!(hunter.next_shot(&game), Some(Point::new(6, 4)));
+}
+
+#[test]
+fn test_monte_carlo_estimate_converges() {
+    use battleship::generator::enumerate_boards;
+    use battleship::generator::Fleet;
+    use battleship::solver::monte_carlo::estimate_probabilities;
+
+    let fleet = Fleet::new(&[(3, 1), (4, 1)]);
+    let (hit, miss) = (1u128 << 40, (1u128 << 41) | (1u128 << 0));
+
+    let mut counts = [0u64; 81];
+    let mut matched = 0u64;
+    enumerate_boards(&fleet, |state| {
+        let board = state.hit_mask().raw_value();
+        if board & hit == hit && board & miss == 0 {
+            matched += 1;
+            for (cell, count) in counts.iter_mut().enumerate() {
+                *count += (board >> cell & 1) as u64;
+            }
+        }
+    });
+
+    let estimate = estimate_probabilities(hit, miss, &fleet, 20_000, 1);
+    assert_eq!(estimate.samples, 20_000);
+    assert!(estimate.boards > 0);
+    assert_eq!(estimate, estimate_probabilities(hit, miss, &fleet, 20_000, 1));
+
+    for (cell, &count) in counts.iter().enumerate() {
+        let exact = count as f64 / matched as f64;
+        let error = (estimate.probabilities[cell] - exact).abs();
+        assert!(error < 0.03, "cell {}: estimated {}, exact {}", cell, estimate.probabilities[cell], exact);
+    }
+    assert_eq!(estimate.probabilities[40], 1.0);
+    assert_eq!(estimate.probabilities[41], 0.0);
+}
+
+#[test]
+fn test_exact_counts_match_enumeration() {
+    use battleship::generator::enumerate_boards;
+    use battleship::generator::Fleet;
+    use battleship::solver::exact::exact_counts;
+
+    let fleet = Fleet::new(&[(3, 1), (4, 1)]);
+    let mut boards = Vec::new();
+    enumerate_boards(&fleet, |state| boards.push(state.hit_mask().raw_value()));
+
+    let queries = [(0, 0), (1u128 << 40, 1u128 << 41), ((1u128 << 40) | (1u128 << 49), 1 | (1u128 << 80)), (1, 1 << 1 | 1 << 9)];
+    for (hit, miss) in queries {
+        let mut expected = vec![0u64; 81];
+        let mut matched = 0u64;
+        for board in boards.iter().filter(|&&board| board & hit == hit && board & miss == 0) {
+            matched += 1;
+            for (cell, count) in expected.iter_mut().enumerate() {
+                *count += (board >> cell & 1) as u64;
+            }
+        }
+
+        assert_eq!(exact_counts(hit, miss, &fleet), (expected, matched), "hit {:#x}, miss {:#x}", hit, miss);
+    }
+}
+
+#[test]
+fn test_query_cache() {
+    use battleship::core::cache::{CacheKey, QueryCache};
+    use battleship::core::filter::QueryOptions;
+
+    let dir = std::env::temp_dir().join(format!("battleship-cache-{}", std::process::id()));
+    let dataset = dir.join("boards.bin");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(&dataset, [7u8; 64]).unwrap();
+
+    let cache = QueryCache::new(dir.join("cache")).unwrap();
+    let options = QueryOptions::default();
+    let key = CacheKey::new(&dataset, 0b1, 0b10, &options).unwrap();
+    assert_eq!(cache.get(&key).unwrap(), None);
+
+    let counts: Vec<u32> = (0..81).collect();
+    cache.put(&key, &counts, 1234).unwrap();
+    assert_eq!(cache.get(&key).unwrap(), Some((counts, 1234)));
+
+    // Other queries, options and datasets have their own entries
+    assert_eq!(cache.get(&CacheKey::new(&dataset, 0b1, 0b100, &options).unwrap()).unwrap(), None);
+    let expanded = QueryOptions { expand_symmetries: true, ..options };
+    assert_eq!(cache.get(&CacheKey::new(&dataset, 0b1, 0b10, &expanded).unwrap()).unwrap(), None);
+    std::fs::write(&dataset, [8u8; 64]).unwrap();
+    assert_ne!(CacheKey::new(&dataset, 0b1, 0b10, &options).unwrap(), key);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_partial_results_merge_like_one_scan() {
+    use battleship::core::filter::{filter_and_count_cells, QueryOptions};
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::core::partial::PartialResult;
+
+    let boards: Vec<u128> = (0..3000u128).map(|i| (i * 0x9E3779B97F4A7C15) & ((1u128 << 81) - 1)).collect();
+    let options = QueryOptions::default();
+    let (hit, miss) = (1 << 3, 1 << 70);
+    let partial = |shard: &[u128]| {
+        let (counts, matched) = filter_and_count_cells(shard.iter().map(|&b| Ok(b)), hit, miss, &options).unwrap();
+        PartialResult::new(hit, miss, &options, &counts, matched)
+    };
+
+    let whole = partial(&boards);
+    let shards: Vec<PartialResult> = boards.chunks(700).map(partial).collect();
+
+    // Any merge order gives the same counts as one scan
+    let mut forward = PartialResult::empty(hit, miss, &options);
+    for shard in &shards {
+        forward.merge(shard).unwrap();
+    }
+    let mut backward = PartialResult::empty(hit, miss, &options);
+    for shard in shards.iter().rev() {
+        backward.merge(shard).unwrap();
+    }
+    assert_eq!(forward, whole);
+    assert_eq!(backward, whole);
+
+    let mut bytes = Vec::new();
+    whole.write_to(&mut bytes).unwrap();
+    assert!(bytes.len() < 300, "{} bytes", bytes.len());
+    assert_eq!(PartialResult::read_from(&mut bytes.as_slice()).unwrap(), whole);
+
+    // Counts past u32 survive, as merges of many shards can produce them
+    let mut big = whole.clone();
+    big.matched = u64::MAX;
+    big.counts[80] = 1 << 40;
+    let mut bytes = Vec::new();
+    big.write_to(&mut bytes).unwrap();
+    assert_eq!(PartialResult::read_from(&mut bytes.as_slice()).unwrap(), big);
+    assert!(PartialResult::read_from(&mut &bytes[..bytes.len() - 1]).is_err());
+
+    // Board sizes that can't be stored are errors, not panics
+    for size in [[0, 9], [9, 0], [12, 12]] {
+        let mut bytes = bytes.clone();
+        bytes[6..8].copy_from_slice(&size);
+        assert_eq!(PartialResult::read_from(&mut bytes.as_slice()).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    // Parts of other queries are refused
+    let mut merged = whole.clone();
+    for other in [
+        PartialResult::empty(hit, 1 << 71, &options),
+        PartialResult::empty(hit, miss, &QueryOptions { expand_symmetries: true, ..options }),
+        PartialResult::empty(hit, miss, &QueryOptions { geometry: BoardGeometry::CLASSIC, ..options }),
+    ] {
+        assert_eq!(merged.merge(&other).unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+    }
+    assert_eq!(merged, whole);
+}
+
+#[test]
+fn test_session_for_each_match() {
+    use battleship::core::dataset::Dataset;
+    use battleship::core::encoder::{encode_indexed, EncodeOptions};
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::core::query::Query;
+    use battleship::core::random::SplitMix64;
+    use battleship::core::session::Session;
+
+    let mut rng = SplitMix64::new(11);
+    let mut boards: Vec<u128> = (0..1_000).map(|_| (rng.next_u64() as u128 | ((rng.next_u64() as u128) << 64)) & ((1 << 81) - 1)).collect();
+    boards.sort_unstable();
+    let raw: Vec<u8> = boards.iter().flat_map(|b| b.to_le_bytes()).collect();
+
+    let dir = std::env::temp_dir().join(format!("battleship-session-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (data_path, index_path) = (dir.join("boards.bin"), dir.join("boards.idx"));
+    let options = EncodeOptions { chunk_size: 100, restart_delta: true, zstd_frames: Some(1), header: None };
+    let mut encoded = Vec::new();
+    encode_indexed(&mut raw.as_slice(), &mut encoded, &options).unwrap().save(&index_path).unwrap();
+    std::fs::write(&data_path, &encoded).unwrap();
+
+    let sessions = [Session::Decoded(Dataset::new(boards.clone())), Session::open_indexed(&data_path, &index_path).unwrap()];
+    let query = Query::compile(1 << 80 | 1 << 2, 1 << 5, BoardGeometry::STANDARD).unwrap();
+    let expected: Vec<u128> = boards.iter().copied().filter(|&b| b & (1 << 80 | 1 << 2) == 1 << 80 | 1 << 2 && b & 1 << 5 == 0).collect();
+    assert!(expected.len() > 2);
+
+    for session in &sessions {
+        let mut matches = Vec::new();
+        session.for_each_match(&query, |board| { matches.push(board); true }).unwrap();
+        assert_eq!(matches, expected);
+
+        // Returning false stops the scan
+        let mut first = Vec::new();
+        session.for_each_match(&query, |board| { first.push(board); first.len() < 2 }).unwrap();
+        assert_eq!(first, expected[..2]);
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_daemon_protocol() {
+    use battleship::core::dataset::Dataset;
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::core::protocol::{read_response, serve_connection, write_frame, write_request, MAX_FRAME_LEN};
+    use battleship::core::query::Query;
+    use battleship::core::session::Session;
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+
+    let session = Session::Decoded(Dataset::new(vec![0b011, 0b110, 1 << 80 | 0b010, 0b1_0000]));
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+    std::thread::scope(|scope| {
+        let server = scope.spawn(|| serve_connection(&session, listener.accept().unwrap().0));
+
+        // Several requests on one connection, answered in order
+        write_request(&mut client, &Query::compile(0b010, 0, BoardGeometry::STANDARD).unwrap()).unwrap();
+        write_request(&mut client, &Query::compile(0b010, 0b100, BoardGeometry::STANDARD).unwrap()).unwrap();
+        let result = read_response(&mut client).unwrap();
+        assert_eq!(result.matched, 3);
+        assert_eq!(&result.counts[..4], &[1, 3, 1, 0]);
+        assert_eq!(result.counts[80], 1);
+        assert_eq!(read_response(&mut client).unwrap().matched, 2);
+
+        // Invalid requests are answered with an error, and the connection stays open
+        write_frame(&mut client, &[1u8; 32]).unwrap();
+        let error = read_response(&mut client).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        write_frame(&mut client, &[0u8; 5]).unwrap();
+        assert!(read_response(&mut client).unwrap_err().to_string().contains("32 bytes"));
+        write_request(&mut client, &Query::compile(0, 0, BoardGeometry::STANDARD).unwrap()).unwrap();
+        assert_eq!(read_response(&mut client).unwrap().matched, 4);
+
+        // A frame longer than the limit ends the connection
+        client.write_all(&(MAX_FRAME_LEN + 1).to_le_bytes()).unwrap();
+        assert!(server.join().unwrap().is_err());
+    });
+}
+
+#[cfg(feature = "grpc")]
+#[test]
+fn test_grpc_service() {
+    use battleship::core::dataset::Dataset;
+    use battleship::core::grpc::proto::battleship_server::Battleship;
+    use battleship::core::grpc::proto::QueryRequest;
+    use battleship::core::grpc::BattleshipService;
+    use battleship::core::session::Session;
+    use tokio_stream::StreamExt;
+
+    let boards = vec![0b011, 0b110, 1 << 80 | 0b010, 0b1_0000];
+    let service = BattleshipService::new(Session::Decoded(Dataset::new(boards)));
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    runtime.block_on(async {
+        let request = QueryRequest { hit_low: 0b010, ..Default::default() };
+        let heatmap = service.query(tonic::Request::new(request)).await.unwrap().into_inner();
+        assert_eq!(heatmap.matched, 3);
+        assert_eq!(&heatmap.counts[..4], &[1, 3, 1, 0]);
+        assert_eq!(heatmap.counts[80], 1);
+
+        let stream = service.match_boards(tonic::Request::new(request)).await.unwrap().into_inner();
+        let boards: Vec<(u64, u64)> = stream.map(|board| board.map(|b| (b.low, b.high)).unwrap()).collect().await;
+        assert_eq!(boards, [(0b011, 0), (0b110, 0), (0b010, 1 << 16)]);
+
+        let conflicting = QueryRequest { hit_low: 1, miss_low: 1, ..Default::default() };
+        let error = service.query(tonic::Request::new(conflicting)).await.unwrap_err();
+        assert_eq!(error.code(), tonic::Code::InvalidArgument);
+    });
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_async_board_reader() {
+    use battleship::core::async_reader::AsyncBoardReader;
+    use battleship::core::encoder::{encode_records, EncodeOptions};
+    use battleship::core::filter::{filter_and_count, filter_and_count_async};
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::core::header::DatasetHeader;
+    use battleship::core::reader::RecordEncoding;
+    use battleship::generator::Fleet;
+    use std::io::Cursor;
+
+    let boards: Vec<u128> = (0..1000u128).map(|i| (i * 0x9E37_79B9) & ((1 << 81) - 1)).collect();
+    let options = EncodeOptions {
+        chunk_size: 64,
+        restart_delta: true,
+        zstd_frames: Some(3),
+        header: Some(DatasetHeader::new(BoardGeometry::STANDARD, Fleet::standard(), true)),
+    };
+    let mut encoded = Vec::new();
+    encode_records(boards.iter().map(|&b| Ok(b)), &mut encoded, &options).unwrap();
+    // Every board of a dataset has the same popcount, which is how headerless encodings are detected
+    let uniform: Vec<u128> = (0..1000u128).map(|i| 0b1011 << (i % 78)).collect();
+    let raw: Vec<u8> = uniform.iter().flat_map(|b| b.to_le_bytes()).collect();
+
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    runtime.block_on(async {
+        // Restarted delta chains across several zstd frames decode like the sync reader
+        let mut reader = AsyncBoardReader::new(Cursor::new(encoded)).await.unwrap();
+        assert_eq!(reader.header().unwrap().restart_interval, Some(64));
+        let mut read = Vec::new();
+        while let Some(board) = reader.next_board().await {
+            read.push(board.unwrap());
+        }
+        assert_eq!(read, boards);
+
+        // Headerless raw records are detected, and counts match the blocking scan
+        let mut reader = AsyncBoardReader::new(Cursor::new(zstd::encode_all(raw.as_slice(), 3).unwrap())).await.unwrap();
+        assert_eq!(reader.encoding(), RecordEncoding::Raw);
+        let expected = filter_and_count(uniform.iter().map(|&b| Ok(b)), 0b1000, 0b1).unwrap();
+        assert_eq!(filter_and_count_async(&mut reader, 0b1000, 0b1).await.unwrap(), expected);
+        assert!(expected.1 > 0);
+
+        let mut truncated = AsyncBoardReader::new(Cursor::new(raw[..40].to_vec())).await.unwrap();
+        assert!(truncated.next_board().await.unwrap().is_ok());
+        assert!(truncated.next_board().await.unwrap().is_ok());
+        assert_eq!(truncated.next_board().await.unwrap().unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+    });
+}
+
+#[cfg(feature = "remote")]
+#[test]
+fn test_remote_datasets() {
+    use battleship::core::encoder::{encode_indexed, EncodeOptions};
+    use battleship::core::filter::filter_and_count_indexed;
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::core::header::DatasetHeader;
+    use battleship::core::reader::create_reader;
+    use battleship::core::remote::resolve_url;
+    use battleship::generator::Fleet;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    let boards: Vec<u128> = (0..500u128).map(|i| (i * 0x9E37_79B9) & ((1 << 81) - 1)).collect();
+    let raw: Vec<u8> = boards.iter().flat_map(|b| b.to_le_bytes()).collect();
+    let header = DatasetHeader::new(BoardGeometry::STANDARD, Fleet::standard(), true);
+    let options = EncodeOptions { chunk_size: 50, restart_delta: true, zstd_frames: Some(1), header: Some(header) };
+    let mut data = Vec::new();
+    let index = encode_indexed(&mut raw.as_slice(), &mut data, &options).unwrap();
+    let mut index_bytes = Vec::new();
+    index.write_to(&mut index_bytes).unwrap();
+
+    // A minimal HTTP server that honors single range requests and counts them
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let ranges = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let served = ranges.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut lines = BufReader::new(stream.try_clone().unwrap()).lines().map(Result::unwrap);
+            let request = lines.next().unwrap();
+            let headers: Vec<String> = lines.take_while(|line| !line.is_empty()).collect();
+
+            let body = if request.contains("/boards.idx") { &index_bytes } else { &data };
+            let range = headers.iter()
+                .find_map(|h| h.to_ascii_lowercase().strip_prefix("range: bytes=").map(str::to_string))
+                .and_then(|r| r.split_once('-').map(|(a, b)| (a.parse::<usize>().unwrap(), b.parse::<usize>().unwrap() + 1)));
+
+            let (status, bytes) = match range {
+                Some((start, end)) => {
+                    served.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    ("206 Partial Content", &body[start..end])
+                }
+                None => ("200 OK", &body[..]),
+            };
+            let head = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, bytes.len());
+            stream.write_all(head.as_bytes()).unwrap();
+            if !request.starts_with("HEAD") {
+                stream.write_all(bytes).unwrap();
+            }
+        }
+    });
+
+    let data_url = format!("{}/boards.zst", base);
+    let records: Vec<u128> = create_reader(&data_url).unwrap().into_iter().map(Result::unwrap).collect();
+    assert_eq!(records, boards);
+
+    // Indexed queries only fetch the chunks that may match
+    let hit = boards[123] | 1;
+    let expected = filter_and_count(boards.iter().map(|&b| Ok(b)), hit, 0).unwrap();
+    assert_eq!(filter_and_count_indexed(&data_url, format!("{}/boards.idx", base), hit, 0).unwrap(), expected);
+    let fetched = ranges.load(std::sync::atomic::Ordering::SeqCst);
+    assert!(fetched < index.chunks.len(), "fetched {} of {} chunks", fetched, index.chunks.len());
+
+    assert_eq!(
+        resolve_url("s3://bucket/data/boards.zst").unwrap(),
+        format!("https://bucket.s3.{}.amazonaws.com/data/boards.zst", std::env::var("AWS_REGION").or_else(|_| std::env::var("AWS_DEFAULT_REGION")).unwrap_or("us-east-1".into())),
+    );
+    assert!(resolve_url("s3://bucket").is_err());
+    assert_eq!(resolve_url(&data_url).unwrap(), data_url);
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn test_parquet_export() {
+    use arrow_array::{Array, BooleanArray, FixedSizeBinaryArray};
+    use battleship::core::filter::QueryOptions;
+    use battleship::core::parquet::write_matches_parquet;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    let boards: Vec<u128> = vec![0b011, 0b110, 1 << 80 | 0b010, 0b1_0000];
+    let path = std::env::temp_dir().join(format!("battleship-export-{}.parquet", std::process::id()));
+
+    let file = std::fs::File::create(&path).unwrap();
+    let written = write_matches_parquet(boards.iter().map(|&b| Ok(b)), 0b010, 0b100, &QueryOptions::default(), file).unwrap();
+    assert_eq!(written, 2);
+
+    let reader = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap()).unwrap().build().unwrap();
+    let batches: Vec<_> = reader.map(Result::unwrap).collect();
+    assert_eq!(batches.len(), 1);
+    let batch = &batches[0];
+    assert_eq!(batch.num_columns(), 82);
+    assert_eq!(batch.num_rows(), 2);
+
+    let masks = batch.column_by_name("board").unwrap().as_any().downcast_ref::<FixedSizeBinaryArray>().unwrap();
+    assert_eq!(masks.value(0), 0b011u128.to_le_bytes());
+    assert_eq!(masks.value(1), (1u128 << 80 | 0b010).to_le_bytes());
+
+    let cell = |name: &str| batch.column_by_name(name).unwrap().as_any().downcast_ref::<BooleanArray>().unwrap().clone();
+    assert_eq!(cell("A1").iter().collect::<Vec<_>>(), [Some(true), Some(false)]);
+    assert_eq!(cell("A2").iter().collect::<Vec<_>>(), [Some(true), Some(true)]);
+    assert_eq!(cell("I9").iter().collect::<Vec<_>>(), [Some(false), Some(true)]);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_sqlite_store() {
+    use battleship::core::filter::{filter_and_count_cells, PopcountRange, QueryOptions};
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::core::random::SplitMix64;
+    use battleship::core::sqlite::SqliteStore;
+
+    let mut rng = SplitMix64::new(5);
+    let boards: Vec<u128> = (0..500).map(|_| (rng.next_u64() as u128 | ((rng.next_u64() as u128) << 64)) & ((1 << 81) - 1)).collect();
+    let path = std::env::temp_dir().join(format!("battleship-sqlite-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut store = SqliteStore::create(&path, BoardGeometry::STANDARD).unwrap();
+    assert_eq!(store.import(boards.iter().map(|&b| Ok(b))).unwrap(), 500);
+    drop(store);
+
+    let store = SqliteStore::open(&path).unwrap();
+    assert_eq!(store.len().unwrap(), 500);
+
+    let mut exported = Vec::new();
+    assert_eq!(store.export(&mut exported).unwrap(), 500);
+    assert_eq!(exported, boards.iter().flat_map(|b| b.to_le_bytes()).collect::<Vec<u8>>());
+
+    let queries = [(0, 0), (1 << 80, 0), (1 << 3 | 1 << 70, 1 << 80 | 1 << 79), (0, (1 << 81) - (1 << 40))];
+    for options in [
+        QueryOptions::default(),
+        QueryOptions { popcount: Some(PopcountRange { min: 38, max: 42 }), ..QueryOptions::default() },
+        QueryOptions { expand_symmetries: true, ..QueryOptions::default() },
+    ] {
+        for (hit, miss) in queries {
+            let expected = filter_and_count_cells(boards.iter().map(|&b| Ok(b)), hit, miss, &options).unwrap();
+            assert_eq!(store.filter_and_count(hit, miss, &options).unwrap(), expected, "hit {:#x}, miss {:#x}", hit, miss);
+        }
+    }
+
+    // The geometry is fixed when the database is created
+    drop(store);
+    assert!(SqliteStore::create(&path, BoardGeometry::CLASSIC).is_err());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_canonicalize_records() {
+    use battleship::core::canonicalize::canonicalize_records;
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::core::random::SplitMix64;
+    use battleship::generator::symmetries::{canonicalize, generate_symmetries};
+
+    let mut rng = SplitMix64::new(3);
+    let boards: Vec<u128> = (0..200).map(|_| (rng.next_u64() as u128 | ((rng.next_u64() as u128) << 64)) & ((1 << 81) - 1)).collect();
+
+    // Every board appears as several of its variants, some more than once
+    let mut records: Vec<u128> = boards.iter().flat_map(|&board| generate_symmetries(board)).collect();
+    records.extend_from_slice(&records.clone()[..300]);
+    rng.shuffle(&mut records);
+
+    let mut expected: Vec<u128> = boards.iter().map(|&board| canonicalize(board)).collect();
+    expected.sort_unstable();
+
+    let dir = std::env::temp_dir().join(format!("battleship-canonicalize-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    // In memory, and spilled to buckets (recursively, with a single board of memory)
+    for max_records_in_memory in [usize::MAX, 50, 1] {
+        let mut output = Vec::new();
+        let stats = canonicalize_records(records.iter().map(|&b| Ok(b)), &mut output, BoardGeometry::STANDARD, max_records_in_memory, &dir).unwrap();
+        assert_eq!(stats.records, records.len() as u64);
+        assert_eq!(stats.canonical, 200);
+        assert!((stats.reduction_ratio() - records.len() as f64 / 200.0).abs() < 1e-9);
+
+        let mut written: Vec<u128> = output.chunks(16).map(|chunk| u128::from_le_bytes(chunk.try_into().unwrap())).collect();
+        written.sort_unstable();
+        assert_eq!(written, expected);
+    }
+
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0, "temporary buckets are removed");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_is_canonical_matches_canonicalize() {
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::core::random::SplitMix64;
+    use battleship::generator::symmetries::{canonicalize_with_geometry, generate_symmetries_with_geometry, is_canonical_with_geometry};
+
+    let mut rng = SplitMix64::new(9);
+    for geometry in [BoardGeometry::STANDARD, BoardGeometry::CLASSIC, BoardGeometry::new(8, 6)] {
+        let cells = geometry.cell_mask();
+        for _ in 0..200 {
+            let board = (rng.next_u64() as u128 | ((rng.next_u64() as u128) << 64)) & cells;
+            let variants = generate_symmetries_with_geometry(board, geometry);
+
+            // Every variant, plus a board that maps onto itself under a flip
+            for variant in variants.iter().copied().chain([board | variants[1]]) {
+                let canonical = canonicalize_with_geometry(variant, geometry) == variant;
+                assert_eq!(is_canonical_with_geometry(variant, geometry), canonical, "{} board {:#x}", geometry, variant);
+            }
+        }
+
+        assert!(is_canonical_with_geometry(0, geometry));
+        assert!(is_canonical_with_geometry(cells, geometry));
+    }
+}
+
+#[test]
+fn test_apply_symmetry() {
+    use battleship::core::geometry::BoardGeometry;
+    use battleship::generator::symmetries::{apply_symmetry, apply_symmetry_with_geometry, distinct_symmetries_with_geometry, generate_symmetries_with_geometry, Sym};
+
+    // The top-left cell, (0, 0), and its neighbor to the right, (1, 0)
+    let board = 0b11;
+    let cell = |x: usize, y: usize| 1u128 << (y * 9 + x);
+    assert_eq!(apply_symmetry(board, Sym::Identity), board);
+    assert_eq!(apply_symmetry(board, Sym::FlipHorizontal), cell(8, 0) | cell(7, 0));
+    assert_eq!(apply_symmetry(board, Sym::FlipVertical), cell(0, 8) | cell(1, 8));
+    assert_eq!(apply_symmetry(board, Sym::Rotate180), cell(8, 8) | cell(7, 8));
+    assert_eq!(apply_symmetry(board, Sym::Transpose), cell(0, 0) | cell(0, 1));
+    assert_eq!(apply_symmetry(board, Sym::Rotate90), cell(8, 0) | cell(8, 1));
+    assert_eq!(apply_symmetry(board, Sym::Rotate270), cell(0, 8) | cell(0, 7));
+    assert_eq!(apply_symmetry(board, Sym::AntiTranspose), cell(8, 8) | cell(8, 7));
+
+    for geometry in [BoardGeometry::STANDARD, BoardGeometry::CLASSIC, BoardGeometry::new(8, 6)] {
+        let board = 0x1234_5678_9abc_def0_1357_9bdf & geometry.cell_mask();
+        assert_eq!(Sym::of(geometry).len(), if geometry.is_square() { 8 } else { 4 });
+
+        for &sym in Sym::of(geometry) {
+            let variant = apply_symmetry_with_geometry(board, sym, geometry);
+            assert_eq!(variant.count_ones(), board.count_ones());
+            assert_eq!(apply_symmetry_with_geometry(variant, sym.inverse(), geometry), board);
+        }
+
+        let mut expected = generate_symmetries_with_geometry(board, geometry);
+        expected.sort_unstable();
+        expected.dedup();
+        assert_eq!(distinct_symmetries_with_geometry(board, geometry).collect::<Vec<_>>(), expected);
+
+        // A board symmetric under every flip has a single distinct variant
+        assert_eq!(distinct_symmetries_with_geometry(geometry.cell_mask(), geometry).count(), 1);
+    }
+}
@@ -7,6 +7,50 @@
 extern "C" {
 #endif
 
+/**
+ * Describes this build as a JSON object with the crate version, ABI version,
+ * supported dataset format versions, enabled features and kernel backends.
+ *
+ * @return A null-terminated UTF-8 string owned by the library. Do not free it.
+ */
+const char *battleship_version(void);
+
+/**
+ * Returns the version of this C ABI. It is incremented whenever an exported
+ * function signature or struct layout changes incompatibly.
+ */
+uint32_t battleship_abi_version(void);
+
+/**
+ * Returns the crate version, e.g. "0.1.0".
+ *
+ * @return A null-terminated string owned by the library. Do not free it.
+ */
+const char *bs_version(void);
+
+/** Status codes returned by battleship_filter_and_count and the bs_* queries. */
+#define BATTLESHIP_OK                  0
+#define BATTLESHIP_ERR_NULL_POINTER   -1
+#define BATTLESHIP_ERR_INVALID_UTF8   -2
+#define BATTLESHIP_ERR_BAD_PATH       -3
+#define BATTLESHIP_ERR_DECODE         -4
+#define BATTLESHIP_ERR_INVALID_QUERY  -5
+
+/** Hit and miss masks of a query, as 64-bit halves. */
+typedef struct BsQuery {
+    uint64_t hit_mask_low;
+    uint64_t hit_mask_high;
+    uint64_t miss_mask_low;
+    uint64_t miss_mask_high;
+} BsQuery;
+
+/** Outcome of a query: a status code, and on success the matched count and hits per cell. */
+typedef struct BsResult {
+    int32_t status;
+    uint64_t matched;
+    uint32_t counts[81];
+} BsResult;
+
 /**
  * Filters and counts boards based on hit/miss bitmasks.
  *
@@ -16,7 +60,8 @@ extern "C" {
  * @param miss_mask_low  Lower 64 bits of the miss mask.
  * @param miss_mask_high Upper 64 bits of the miss mask.
  * @param out_counts     Pointer to a 81-element u32 array for cell counts.
- * @return               The number of matching boards.
+ * @return               The number of matching boards, or 0 on any error.
+ *                       Use battleship_filter_and_count to tell errors apart.
  */
 uint64_t filter_and_count_ffi(
     const char *path_ptr,
@@ -27,6 +72,192 @@ uint64_t filter_and_count_ffi(
     uint32_t *out_counts
 );
 
+/**
+ * Filters and counts boards based on hit/miss bitmasks, reporting errors.
+ *
+ * @param path_ptr       A pointer to a null-terminated UTF-8 path string.
+ * @param hit_mask_low   Lower 64 bits of the hit mask.
+ * @param hit_mask_high  Upper 64 bits of the hit mask.
+ * @param miss_mask_low  Lower 64 bits of the miss mask.
+ * @param miss_mask_high Upper 64 bits of the miss mask.
+ * @param out_counts     Pointer to a 81-element u32 array for cell counts.
+ * @param out_matched    Receives the number of matching boards.
+ * @return               BATTLESHIP_OK, or a BATTLESHIP_ERR_* code for a null
+ *                       argument, a non-UTF-8 path, a file that can't be opened,
+ *                       data that can't be decoded, or invalid masks. Outputs are
+ *                       only written on success.
+ */
+int32_t battleship_filter_and_count(
+    const char *path_ptr,
+    uint64_t hit_mask_low,
+    uint64_t hit_mask_high,
+    uint64_t miss_mask_low,
+    uint64_t miss_mask_high,
+    uint32_t *out_counts,
+    uint64_t *out_matched
+);
+
+/**
+ * Filters and counts boards like battleship_filter_and_count, also reporting
+ * how many records were read, matching or not.
+ *
+ * @param out_scanned    Receives the number of records read from the file.
+ *                       Compare it with the dataset's size to show progress
+ *                       or detect a truncated file. Required: NULL returns
+ *                       BATTLESHIP_ERR_NULL_POINTER, like the other outputs.
+ * @return               The same status codes as battleship_filter_and_count.
+ *                       Outputs are only written on success.
+ */
+int32_t battleship_filter_and_count_scanned(
+    const char *path_ptr,
+    uint64_t hit_mask_low,
+    uint64_t hit_mask_high,
+    uint64_t miss_mask_low,
+    uint64_t miss_mask_high,
+    uint32_t *out_counts,
+    uint64_t *out_matched,
+    uint64_t *out_scanned
+);
+
+/**
+ * Runs a query against a dataset file, filling in result.
+ *
+ * @return  The status, also stored in result->status.
+ */
+int32_t bs_filter(const char *path_ptr, const BsQuery *query, BsResult *result);
+
+/** Opaque dataset kept open across queries. */
+typedef struct BsSession BsSession;
+
+/**
+ * Decodes a whole dataset into memory so it can be queried repeatedly.
+ *
+ * @param path_ptr  A pointer to a null-terminated UTF-8 path string.
+ * @return          A session to free with bs_session_close, or NULL if the
+ *                  file can't be read.
+ */
+BsSession *bs_session_open(const char *path_ptr);
+
+/**
+ * Loads the index written by the encoder for a dataset encoded with
+ * restarted deltas. Each query decodes only chunks that may contain matches.
+ *
+ * @return  A session to free with bs_session_close, or NULL on failure.
+ */
+BsSession *bs_session_open_indexed(const char *data_path_ptr, const char *index_path_ptr);
+
+/**
+ * Counts hits per cell over the session's boards matching the masks.
+ *
+ * @param out_counts   Pointer to a 81-element u32 array for cell counts.
+ * @param out_matched  Receives the number of matching boards.
+ * @return             The same status codes as battleship_filter_and_count.
+ */
+int32_t bs_session_query(
+    const BsSession *session,
+    uint64_t hit_mask_low,
+    uint64_t hit_mask_high,
+    uint64_t miss_mask_low,
+    uint64_t miss_mask_high,
+    uint32_t *out_counts,
+    uint64_t *out_matched
+);
+
+/**
+ * Frees a session. Passing NULL is a no-op.
+ */
+void bs_session_close(BsSession *session);
+
+/**
+ * Opaque, reference-counted dataset that may be shared across threads.
+ *
+ * Every bs_context_* function may be called concurrently with the same
+ * context. Each thread keeping a context should hold its own reference from
+ * bs_context_retain and drop it with bs_context_release; the dataset is freed
+ * when the last reference is released.
+ */
+typedef struct BsContext BsContext;
+
+/** Like bs_session_open, returning a context with one reference, or NULL. */
+const BsContext *bs_context_open(const char *path_ptr);
+
+/** Like bs_session_open_indexed, returning a context with one reference, or NULL. */
+const BsContext *bs_context_open_indexed(const char *data_path_ptr, const char *index_path_ptr);
+
+/** Takes another reference to a context and returns it. */
+const BsContext *bs_context_retain(const BsContext *context);
+
+/** Drops a reference, freeing the context with the last one. NULL is a no-op. */
+void bs_context_release(const BsContext *context);
+
+/** Same as bs_session_query; safe to call concurrently on one context. */
+int32_t bs_context_query(
+    const BsContext *context,
+    uint64_t hit_mask_low,
+    uint64_t hit_mask_high,
+    uint64_t miss_mask_low,
+    uint64_t miss_mask_high,
+    uint32_t *out_counts,
+    uint64_t *out_matched
+);
+
+/** Same as bs_filter, for a context; safe to call concurrently on one context. */
+int32_t bs_context_filter(const BsContext *context, const BsQuery *query, BsResult *result);
+
+/** Opaque game state tracking shots, results and sunk ships. */
+typedef struct GameState GameState;
+
+/** Result codes for battleship_game_record_shot. */
+#define BATTLESHIP_SHOT_MISS 0
+#define BATTLESHIP_SHOT_HIT  1
+#define BATTLESHIP_SHOT_SUNK 2
+
+/**
+ * Creates an empty game. Free it with battleship_game_free.
+ */
+GameState *battleship_game_new(void);
+
+/**
+ * Frees a game created by battleship_game_new. Passing NULL is a no-op.
+ */
+void battleship_game_free(GameState *game);
+
+/**
+ * Records a shot at column x, row y (both 0-based).
+ *
+ * @param result  One of BATTLESHIP_SHOT_MISS, BATTLESHIP_SHOT_HIT or BATTLESHIP_SHOT_SUNK.
+ * @return        0 on success, -1 for an unknown result code, -2 if the shot is
+ *                out of bounds, already taken, or contradicts earlier results.
+ */
+int32_t battleship_game_record_shot(GameState *game, uint32_t x, uint32_t y, uint32_t result);
+
+/**
+ * Removes the most recent shot.
+ *
+ * @return 1 if a shot was removed, 0 if there were no shots.
+ */
+int32_t battleship_game_undo(GameState *game);
+
+/**
+ * Writes the game's hit and miss masks, including misses inferred from sunk ships.
+ */
+void battleship_game_masks(
+    const GameState *game,
+    uint64_t *hit_mask_low,
+    uint64_t *hit_mask_high,
+    uint64_t *miss_mask_low,
+    uint64_t *miss_mask_high
+);
+
+/**
+ * Runs filter_and_count_ffi with the game's current hit and miss masks.
+ *
+ * @param path_ptr    A pointer to a null-terminated UTF-8 path string.
+ * @param out_counts  Pointer to a 81-element u32 array for cell counts.
+ * @return            The number of matching boards.
+ */
+uint64_t battleship_game_filter_and_count(const GameState *game, const char *path_ptr, uint32_t *out_counts);
+
 #ifdef __cplusplus
 }
 #endif